@@ -0,0 +1,881 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use huffman_format::HuffmanError;
+
+// Minimalny parser argumentów w stylu clap: podkomendy, flagi długie/krótkie,
+// wartości jako `--flag=v` lub `--flag v`, automatyczny tekst pomocy.
+
+pub struct FlagSpec {
+    pub long: &'static str,
+    pub short: Option<char>,
+    // Nazwa wartości w pomocy; None oznacza przełącznik bez wartości
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub about: &'static str,
+    pub flags: &'static [&'static FlagSpec],
+}
+
+pub const VERBOSE: FlagSpec = FlagSpec {
+    long: "verbose",
+    short: Some('v'),
+    value: None,
+    help: "Szczegółowe logi i statystyki",
+};
+
+pub const HELP: FlagSpec = FlagSpec {
+    long: "help",
+    short: Some('h'),
+    value: None,
+    help: "Wyświetla tę pomoc",
+};
+
+pub const ORDER: FlagSpec = FlagSpec {
+    long: "order",
+    short: None,
+    value: Some("N"),
+    help: "Rząd modelu kontekstowego (0-255, domyślnie 0)",
+};
+
+pub const MAX_LEN: FlagSpec = FlagSpec {
+    long: "max-len",
+    short: None,
+    value: Some("N"),
+    help: "Maksymalna długość kodu w bitach (1-64, domyślnie 15)",
+};
+
+pub const MAX_SYMBOLS: FlagSpec = FlagSpec {
+    long: "max-symbols",
+    short: None,
+    value: Some("N"),
+    help: "Najwyżej N par (kontekst, symbol) w modelu; rzadsze symbole idą wprost za kodem ucieczki",
+};
+
+pub const USE_TABLE: FlagSpec = FlagSpec {
+    long: "use-table",
+    short: None,
+    value: Some("PLIK"),
+    help: "Koduj tabelą kodów z nagłówka innego pliku .huff (symbole spoza niej idą za kodem ucieczki)",
+};
+
+pub const FREQ: FlagSpec = FlagSpec {
+    long: "freq",
+    short: None,
+    value: Some("PLIK"),
+    help: "Koduj modelem z pliku częstotliwości (huff stats --export-freq, JSON albo HFRQ)",
+};
+
+pub const DICT: FlagSpec = FlagSpec {
+    long: "dict",
+    short: None,
+    value: Some("PLIK"),
+    help: "Koduj/dekoduj słownikiem z huff dict train: tabela kodów nie trafia do wyniku",
+};
+
+pub const ALPHABET: FlagSpec = FlagSpec {
+    long: "alphabet",
+    short: None,
+    value: Some("NAZWA"),
+    help: "Dozwolone bajty: ascii|printable|text|digits|hex|base64|set:<znaki>",
+};
+
+pub const UNIT: FlagSpec = FlagSpec {
+    long: "unit",
+    short: None,
+    value: Some("N"),
+    help: "Szerokość symbolu: auto|1-8 bajtów albo nibble - 4 bity (auto: 2 dla tekstu z BOM UTF-16)",
+};
+
+pub const MAX_RATIO: FlagSpec = FlagSpec {
+    long: "max-ratio",
+    short: None,
+    value: Some("N"),
+    help: "Przerwij, gdy wynik przekroczy N-krotność rozmiaru archiwum",
+};
+
+pub const MAX_OUTPUT_SIZE: FlagSpec = FlagSpec {
+    long: "max-output-size",
+    short: None,
+    value: Some("ROZMIAR"),
+    help: "Odrzuć plik, który rozpakowałby się do więcej niż ROZMIAR bajtów (np. 1G)",
+};
+
+pub const CHECKSUM: FlagSpec = FlagSpec {
+    long: "checksum",
+    short: None,
+    value: Some("RODZAJ"),
+    help: "Suma kontrolna oryginału: crc32 (domyślnie) lub none",
+};
+
+pub const FILTER: FlagSpec = FlagSpec {
+    long: "filter",
+    short: None,
+    value: Some("NAZWA"),
+    help: "Przekształć dane przed kodowaniem: rle (serie), bwt (tekst), delta[:1|2|4] (próbki), timestamps[:N] (logi)",
+};
+
+pub const FILTERS: FlagSpec = FlagSpec {
+    long: "filters",
+    short: None,
+    value: Some("LISTA"),
+    help: "Łańcuch filtrów po przecinku, stosowanych po kolei, np. delta:2,rle albo bwt,rle (najwyżej 4)",
+};
+
+pub const NO_STORE: FlagSpec = FlagSpec {
+    long: "no-store",
+    short: None,
+    value: None,
+    help: "Zawsze koduj z tablicą kodów, także gdy ramka z danymi zapisanymi wprost byłaby krótsza",
+};
+
+pub const SKIP_INCOMPRESSIBLE: FlagSpec = FlagSpec {
+    long: "skip-incompressible",
+    short: None,
+    value: None,
+    help: "Oceń entropię z kilku próbek pliku i dane bliskie 8 bitów/bajt zapisz wprost, bez dwóch przebiegów",
+};
+
+pub const DEADLINE: FlagSpec = FlagSpec {
+    long: "deadline",
+    short: None,
+    value: Some("CZAS"),
+    help: "Limit czasu kodowania, np. 30s, 500ms, 2m; spóźnione bloki dostają rząd 0",
+};
+
+pub const OUTPUT: FlagSpec = FlagSpec {
+    long: "output",
+    short: Some('o'),
+    value: Some("PLIK"),
+    help: "Plik wyjściowy (zamiast drugiego argumentu); przy --rotate-size wzorzec z %d",
+};
+
+pub const STDOUT: FlagSpec = FlagSpec {
+    long: "stdout",
+    short: Some('c'),
+    value: None,
+    help: "Zapisuje wynik na standardowe wyjście (jak wyjście `-`)",
+};
+
+pub const ROTATE_SIZE: FlagSpec = FlagSpec {
+    long: "rotate-size",
+    short: None,
+    value: Some("ROZMIAR"),
+    help: "Zaczynaj nowy plik wyjściowy po przekroczeniu rozmiaru, np. 1G, 100M",
+};
+
+pub const PRESET: FlagSpec = FlagSpec {
+    long: "preset",
+    short: None,
+    value: Some("NAZWA"),
+    help: "logs (rząd 2, timestamps, słownik do 1 MiB, dalej bloki linii) lub model: english|json|csv|base64|dna",
+};
+
+pub const STREAM: FlagSpec = FlagSpec {
+    long: "stream",
+    short: None,
+    value: None,
+    help: "Dwa przebiegi po pliku zamiast wczytywania go do pamięci",
+};
+
+pub const LINES: FlagSpec = FlagSpec {
+    long: "lines",
+    short: Some('n'),
+    value: Some("N"),
+    help: "Liczba wypisywanych linii (domyślnie 10)",
+};
+
+pub const THREADS: FlagSpec = FlagSpec {
+    long: "threads",
+    short: Some('j'),
+    value: Some("N"),
+    help: "Koduj/dekoduj bloki po 1 MiB na N wątkach (0 = wybór z --autotune albo wszystkie rdzenie)",
+};
+
+pub const AUTOTUNE: FlagSpec = FlagSpec {
+    long: "autotune",
+    short: None,
+    value: None,
+    help: "Najpierw przez ~1 s dobierz tablice dekodera i liczbę wątków i zapisz wybór w konfiguracji",
+};
+
+pub const LINE_INDEX: FlagSpec = FlagSpec {
+    long: "line-index",
+    short: None,
+    value: Some("N"),
+    help: "Nowa ramka co N linii i indeks linii w <output>.idx (dla huff lines)",
+};
+
+pub const SEEK_TABLE: FlagSpec = FlagSpec {
+    long: "seek-table",
+    short: None,
+    value: None,
+    help: "Koduj blokami i dopisz tablicę skoków (dla decode --range)",
+};
+
+pub const RANGE: FlagSpec = FlagSpec {
+    long: "range",
+    short: None,
+    value: Some("OD:DŁ"),
+    help: "Dekoduj tylko DŁ bajtów od offsetu OD, np. 1G:100M",
+};
+
+pub const OPTIMAL_SPLIT: FlagSpec = FlagSpec {
+    long: "optimal-split",
+    short: None,
+    value: None,
+    help: "Dobierz granice bloków tak, by plik był najmniejszy (wolniej)",
+};
+
+pub const ADAPTIVE: FlagSpec = FlagSpec {
+    long: "adaptive",
+    short: None,
+    value: None,
+    help: "Adaptacyjny kod Huffmana (FGK): jeden przebieg, bez tablicy kodów w nagłówku",
+};
+
+pub const LZ77: FlagSpec = FlagSpec {
+    long: "lz77",
+    short: None,
+    value: None,
+    help: "Dopasowania LZ77 w oknie 32 KiB kodowane tabelami Huffmana, jak deflate (tekst, kod źródłowy)",
+};
+
+pub const WARM_START: FlagSpec = FlagSpec {
+    long: "warm-start",
+    short: None,
+    value: Some("PLIK"),
+    help: "Model startowy --adaptive z liczności bajtów próbki (przy decode ten sam plik)",
+};
+
+pub const SOLID: FlagSpec = FlagSpec {
+    long: "solid",
+    short: None,
+    value: None,
+    help: "Archiwum z jednym modelem dla wszystkich plików (lepsze dla wielu małych plików)",
+};
+
+pub const DEDUP: FlagSpec = FlagSpec {
+    long: "dedup",
+    short: None,
+    value: None,
+    help: "W archiwum zapisz tylko raz powtórzone bloki plików (po 1 MiB, także całe małe pliki)",
+};
+
+pub const BATCH_SMALL: FlagSpec = FlagSpec {
+    long: "batch-small",
+    short: None,
+    value: Some("ROZMIAR"),
+    help: "W archiwum koduj pliki mniejsze niż ROZMIAR partiami z jedną tabelą, np. 64K",
+};
+
+pub const VERIFY: FlagSpec = FlagSpec {
+    long: "verify",
+    short: None,
+    value: None,
+    help: "Po zapisie zdekoduj wynik i porównaj z oryginałem; przy różnicy usuń plik",
+};
+
+pub const DRY_RUN: FlagSpec = FlagSpec {
+    long: "dry-run",
+    short: None,
+    value: None,
+    help: "Tylko policz tabelę kodów i przewidywany rozmiar (jedna ramka), niczego nie zapisuj",
+};
+
+pub const EXCLUDE: FlagSpec = FlagSpec {
+    long: "exclude",
+    short: None,
+    value: Some("WZORZEC"),
+    help: "Pomiń w archiwum pliki i katalogi pasujące do wzorca (* i ?, można powtarzać)",
+};
+
+pub const IO_CHUNK: FlagSpec = FlagSpec {
+    long: "io-chunk",
+    short: None,
+    value: Some("ROZMIAR"),
+    help: "Porcja czytana z pliku jednym odczytem (domyślnie 8K), np. 256K na dyskach sieciowych",
+};
+
+pub const VERIFY_BLOCKS: FlagSpec = FlagSpec {
+    long: "verify-blocks",
+    short: None,
+    value: None,
+    help: "Wypisuj blok dopiero po sprawdzeniu jego sumy kontrolnej (uszkodzony blok nie trafia do wyjścia)",
+};
+
+pub const REPORT_APPEND: FlagSpec = FlagSpec {
+    long: "report-append",
+    short: None,
+    value: Some("PLIK"),
+    help: "Dopisz do PLIKU linię JSON z ustawieniami, rozmiarami, czasem i hostem przebiegu",
+};
+
+pub const CONSTANT_TIME: FlagSpec = FlagSpec {
+    long: "constant-time",
+    short: None,
+    value: None,
+    help: "Dekoduj bez dostępów do pamięci zależnych od danych (dla danych tajnych, dużo wolniej)",
+};
+
+pub const FIRST: FlagSpec = FlagSpec {
+    long: "first",
+    short: None,
+    value: Some("ROZMIAR"),
+    help: "Zakończ po zdekodowaniu pierwszych N bajtów (podgląd początku), np. 4K",
+};
+
+pub const ONLY: FlagSpec = FlagSpec {
+    long: "only",
+    short: None,
+    value: Some("WZORZEC"),
+    help: "Rozpakuj tylko pliki pasujące do wzorca (*, ?, **/; można powtarzać; włącza --extract)",
+};
+
+pub const EXTRACT: FlagSpec = FlagSpec {
+    long: "extract",
+    short: Some('x'),
+    value: None,
+    help: "Rozpakuj archiwum wielu plików do katalogu (drugi argument, domyślnie .)",
+};
+
+pub const COUNT: FlagSpec = FlagSpec {
+    long: "count",
+    short: Some('c'),
+    value: None,
+    help: "Wypisuje tylko liczbę pasujących linii",
+};
+
+pub const SIZE: FlagSpec = FlagSpec {
+    long: "size",
+    short: None,
+    value: Some("ROZMIAR"),
+    help: "Rozmiar każdego korpusu, np. 4M (domyślnie 1M)",
+};
+
+pub const CORPUS: FlagSpec = FlagSpec {
+    long: "corpus",
+    short: None,
+    value: Some("NAZWA"),
+    help: "Tylko wybrany korpus: zipf|markov|runs|random",
+};
+
+pub const SEED: FlagSpec = FlagSpec {
+    long: "seed",
+    short: None,
+    value: Some("N"),
+    help: "Ziarno generatora (domyślne daje wyniki porównywalne między maszynami)",
+};
+
+pub const DECOMPRESS: FlagSpec = FlagSpec {
+    long: "decompress",
+    short: Some('d'),
+    value: None,
+    help: "Dekompresuje pliki .huff",
+};
+
+pub const KEEP: FlagSpec = FlagSpec {
+    long: "keep",
+    short: Some('k'),
+    value: None,
+    help: "Zostawia plik wejściowy (domyślnie jest usuwany po udanej operacji)",
+};
+
+pub const FORCE: FlagSpec = FlagSpec {
+    long: "force",
+    short: Some('f'),
+    value: None,
+    help: "Nadpisuje istniejące pliki i pisze dane binarne na terminal",
+};
+
+pub const TEST: FlagSpec = FlagSpec {
+    long: "test",
+    short: Some('t'),
+    value: None,
+    help: "Sprawdza, czy pliki dają się zdekodować, niczego nie zapisując",
+};
+
+pub const LEVEL: FlagSpec = FlagSpec {
+    long: "level",
+    short: None,
+    value: Some("1-9"),
+    help: "Poziom kompresji, też jako -1..-9 (domyślnie 6): wyższy to większy rząd i bloki",
+};
+
+pub const ENCODE: CommandSpec = CommandSpec {
+    name: "encode",
+    args: "[input|-] [output|-] | <plik|katalog>... -o <archiwum>",
+    about: "Kompresuje plik, standardowe wejście albo kilka plików do jednego archiwum (domyślnie output.huff)",
+    flags: &[
+        &OUTPUT,
+        &STDOUT,
+        &PRESET,
+        &ORDER,
+        &MAX_LEN,
+        &MAX_SYMBOLS,
+        &USE_TABLE,
+        &FREQ,
+        &DICT,
+        &ALPHABET,
+        &UNIT,
+        &CHECKSUM,
+        &NO_STORE,
+        &FILTER,
+        &FILTERS,
+        &SKIP_INCOMPRESSIBLE,
+        &STREAM,
+        &ADAPTIVE,
+        &LZ77,
+        &WARM_START,
+        &THREADS,
+        &OPTIMAL_SPLIT,
+        &LINE_INDEX,
+        &SEEK_TABLE,
+        &EXCLUDE,
+        &SOLID,
+        &DEDUP,
+        &BATCH_SMALL,
+        &DEADLINE,
+        &ROTATE_SIZE,
+        &DRY_RUN,
+        &VERIFY,
+        &REPORT_APPEND,
+        &AUTOTUNE,
+        &VERBOSE,
+        &HELP,
+    ],
+};
+
+pub const DECODE: CommandSpec = CommandSpec {
+    name: "decode",
+    args: "[input.huff|-] [output|-] | <archiwum> --extract [katalog]",
+    about: "Dekompresuje plik .huff (domyślnie na standardowe wyjście) albo rozpakowuje archiwum wielu plików",
+    flags: &[
+        &STDOUT,
+        &EXTRACT,
+        &ONLY,
+        &MAX_RATIO,
+        &MAX_OUTPUT_SIZE,
+        &THREADS,
+        &RANGE,
+        &FIRST,
+        &WARM_START,
+        &DICT,
+        &IO_CHUNK,
+        &VERIFY_BLOCKS,
+        &CONSTANT_TIME,
+        &REPORT_APPEND,
+        &AUTOTUNE,
+        &VERBOSE,
+        &HELP,
+    ],
+};
+
+pub const TAIL: CommandSpec = CommandSpec {
+    name: "tail",
+    args: "<input.huff>",
+    about: "Wypisuje ostatnie linie archiwum, dekodując tylko końcowe ramki",
+    flags: &[&LINES, &VERBOSE, &HELP],
+};
+
+pub const GREP: CommandSpec = CommandSpec {
+    name: "grep",
+    args: "<wzorzec> <input.huff>...",
+    about: "Szuka wyrażenia regularnego w archiwach, dekodując je strumieniowo",
+    flags: &[&COUNT, &MAX_RATIO, &MAX_OUTPUT_SIZE, &VERBOSE, &HELP],
+};
+
+pub const TEST_CMD: CommandSpec = CommandSpec {
+    name: "test",
+    args: "[plik.huff|-]...",
+    about: "Sprawdza pliki .huff i archiwa jak gzip -t: dekoduje bez zapisu, kontroluje sumy i długości",
+    flags: &[&WARM_START, &VERBOSE, &HELP],
+};
+
+pub const LINES_CMD: CommandSpec = CommandSpec {
+    name: "lines",
+    args: "<input.huff> <od[,do]>",
+    about: "Wypisuje zakres linii (jak sed -n 'od,dop'), korzystając z indeksu <input.huff>.idx",
+    flags: &[&VERBOSE, &HELP],
+};
+
+pub const LIST: CommandSpec = CommandSpec {
+    name: "list",
+    args: "<archiwum>",
+    about: "Wypisuje pliki archiwum z rozmiarami i stopniem kompresji (jak unzip -l)",
+    flags: &[&VERBOSE, &HELP],
+};
+
+pub const BENCH: CommandSpec = CommandSpec {
+    name: "bench",
+    args: "",
+    about: "Mierzy stopień i szybkość kompresji na powtarzalnych danych syntetycznych",
+    flags: &[&SIZE, &CORPUS, &SEED, &ORDER, &VERBOSE, &HELP],
+};
+
+pub const AUTOMATON: FlagSpec = FlagSpec {
+    long: "automaton",
+    short: None,
+    value: None,
+    help: "Wypisz automat dekodera pierwszej ramki jako tabelę TSV (kontekst, stan, bit, cel)",
+};
+
+pub const INFO: CommandSpec = CommandSpec {
+    name: "info",
+    args: "<input.huff>",
+    about: "Opisuje plik .huff na podstawie nagłówków, bez dekompresji danych",
+    flags: &[&AUTOMATON, &VERBOSE, &HELP],
+};
+
+pub const COMMENT: FlagSpec = FlagSpec {
+    long: "comment",
+    short: None,
+    value: Some("TEKST"),
+    help: "Komentarz zapisany w pliku (pusty tekst usuwa komentarz)",
+};
+
+pub const META_NAME: FlagSpec = FlagSpec {
+    long: "name",
+    short: None,
+    value: Some("NAZWA"),
+    help: "Nazwa pliku przed kompresją (pusty tekst usuwa nazwę)",
+};
+
+pub const SET_META: CommandSpec = CommandSpec {
+    name: "set-meta",
+    args: "<input.huff>",
+    about: "Zmienia metadane pliku .huff w miejscu, bez przepisywania skompresowanych ramek",
+    flags: &[&COMMENT, &META_NAME, &VERBOSE, &HELP],
+};
+
+pub const CONVERT_ORDER: FlagSpec = FlagSpec {
+    long: "order",
+    short: None,
+    value: Some("N"),
+    help: "Rząd modelu wyniku (domyślnie rząd modelu pliku wejściowego)",
+};
+
+pub const CONVERT: CommandSpec = CommandSpec {
+    name: "convert",
+    args: "<input.huff> <output.huff>",
+    about: "Przepisuje plik starszego formatu (także sprzed v0) do bieżącego, z sumą kontrolną",
+    flags: &[&CONVERT_ORDER, &CHECKSUM, &VERBOSE, &HELP],
+};
+
+pub const STATS_ORDER: FlagSpec = FlagSpec {
+    long: "order",
+    short: None,
+    value: Some("N"),
+    help: "Najwyższy analizowany rząd modelu (domyślnie 2)",
+};
+
+pub const CHART: FlagSpec = FlagSpec {
+    long: "chart",
+    short: None,
+    value: None,
+    help: "Rysuj histogram bajtów i długości kodów jako wykres słupkowy",
+};
+
+pub const EXPORT_FREQ: FlagSpec = FlagSpec {
+    long: "export-freq",
+    short: None,
+    value: Some("PLIK"),
+    help: "Zapisz częstotliwości modelu rzędu --order: JSON dla *.json, inaczej binarnie (HFRQ)",
+};
+
+pub const TOP: FlagSpec = FlagSpec {
+    long: "top",
+    short: None,
+    value: Some("N"),
+    help: "Liczba bajtów w histogramie (domyślnie 16, -v pokazuje wszystkie)",
+};
+
+pub const STATS: CommandSpec = CommandSpec {
+    name: "stats",
+    args: "<plik|->",
+    about: "Liczy entropię rzędów 0..N i szacuje zysk z kompresji bez tworzenia pliku",
+    flags: &[&STATS_ORDER, &CHART, &TOP, &EXPORT_FREQ, &VERBOSE, &HELP],
+};
+
+pub const DICT_ORDER: FlagSpec = FlagSpec {
+    long: "order",
+    short: None,
+    value: Some("N"),
+    help: "Rząd modelu słownika (0-255, domyślnie 2)",
+};
+
+pub const DICT_CMD: CommandSpec = CommandSpec {
+    name: "dict",
+    args: "train <plik|katalog>... -o <słownik.hufdict>",
+    about: "Trenuje słownik na wielu podobnych plikach (dla encode/decode --dict)",
+    flags: &[&OUTPUT, &DICT_ORDER, &MAX_LEN, &MAX_SYMBOLS, &UNIT, &EXCLUDE, &VERBOSE, &HELP],
+};
+
+pub const GZIP: CommandSpec = CommandSpec {
+    name: "gzip",
+    args: "[plik...]",
+    about: "Tryb zgodny z gzip (plik -> plik.huff); wywołanie bez komendy, np. huff -d plik.huff, trafia tutaj",
+    flags: &[&DECOMPRESS, &STDOUT, &KEEP, &FORCE, &TEST, &LEVEL, &VERBOSE, &HELP],
+};
+
+pub const COMMANDS: &[&CommandSpec] = &[
+    &ENCODE, &DECODE, &TAIL, &GREP, &LINES_CMD, &LIST, &INFO, &SET_META, &TEST_CMD, &CONVERT, &STATS, &DICT_CMD, &BENCH,
+    &GZIP,
+];
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Huffman(HuffmanError),
+    /// Polecenie zgłosiło już szczegóły (np. `huff test` dla każdego pliku); zostaje podsumowanie.
+    Failed(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}", msg),
+            CliError::Huffman(e) => write!(f, "{}", e),
+            CliError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<HuffmanError> for CliError {
+    fn from(e: HuffmanError) -> Self {
+        CliError::Huffman(e)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        CliError::Huffman(HuffmanError::Io(e))
+    }
+}
+
+pub fn usage(msg: impl Into<String>) -> CliError {
+    CliError::Usage(msg.into())
+}
+
+/// Czas w postaci `30s`, `500ms`, `2m` albo samej liczby sekund.
+pub fn parse_duration(raw: &str) -> Result<Duration, CliError> {
+    let invalid = || usage(format!("niepoprawny czas '{}' (np. 30s, 500ms, 2m)", raw));
+    let (number, scale) = if let Some(n) = raw.strip_suffix("ms") {
+        (n, 0.001)
+    } else if let Some(n) = raw.strip_suffix('s') {
+        (n, 1.0)
+    } else if let Some(n) = raw.strip_suffix('m') {
+        (n, 60.0)
+    } else {
+        (raw, 1.0)
+    };
+    let value: f64 = number.parse().map_err(|_| invalid())?;
+    Duration::try_from_secs_f64(value * scale).map_err(|_| invalid())
+}
+
+/// Rozmiar w bajtach z opcjonalnym przyrostkiem binarnym: `64K`, `100M`, `1G`.
+pub fn parse_size(raw: &str) -> Result<u64, CliError> {
+    let invalid = || usage(format!("niepoprawny rozmiar '{}' (np. 64K, 100M, 1G)", raw));
+    let upper = raw.to_ascii_uppercase();
+    let (number, shift) = match upper.strip_suffix('B').unwrap_or(&upper) {
+        n if n.ends_with('K') => (&n[..n.len() - 1], 10),
+        n if n.ends_with('M') => (&n[..n.len() - 1], 20),
+        n if n.ends_with('G') => (&n[..n.len() - 1], 30),
+        n => (n, 0),
+    };
+    let value: u64 = number.parse().map_err(|_| invalid())?;
+    value.checked_mul(1 << shift).filter(|&v| v > 0).ok_or_else(invalid)
+}
+
+/// Zakres bajtów `OD:DŁ`; obie wartości jak w [`parse_size`], ale początek może być zerem.
+pub fn parse_byte_range(raw: &str) -> Result<(u64, u64), CliError> {
+    let (start, len) = raw
+        .split_once(':')
+        .ok_or_else(|| usage(format!("niepoprawny zakres '{}' (oczekiwano OD:DŁ, np. 1G:100M)", raw)))?;
+    let start = if start == "0" { 0 } else { parse_size(start)? };
+    Ok((start, parse_size(len)?))
+}
+
+/// Zakres linii w stylu sed: `N` albo `OD,DO` (od 1, włącznie); `$` oznacza ostatnią linię.
+pub fn parse_line_range(raw: &str) -> Result<(u64, u64), CliError> {
+    let invalid = || usage(format!("niepoprawny zakres linii '{}' (np. 1000,2000 albo 5,$)", raw));
+    let (first, last) = raw.split_once(',').unwrap_or((raw, raw));
+    let first: u64 = first.trim().parse().map_err(|_| invalid())?;
+    let last = match last.trim() {
+        "$" => u64::MAX,
+        n => n.parse().map_err(|_| invalid())?,
+    };
+    if first == 0 || last < first {
+        return Err(invalid());
+    }
+    Ok((first, last))
+}
+
+/// Liczba plików z poprawną formą: 1 plik, 2-4 pliki (ale 12-14 plików), 5 plików.
+pub fn pliki(n: usize) -> String {
+    let word = match (n % 10, n % 100) {
+        _ if n == 1 => "plik",
+        (2..=4, tens) if !(12..=14).contains(&tens) => "pliki",
+        _ => "plików",
+    };
+    format!("{} {}", n, word)
+}
+
+/// Podstawia numer części pod `%d` lub `%0Nd` we wzorcu nazwy pliku.
+pub fn format_part(pattern: &str, part: u32) -> Option<String> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d')?;
+    let spec = &rest[..end];
+    let width: usize = if spec.is_empty() { 0 } else { spec.parse().ok()? };
+    Some(format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        part,
+        &rest[end + 1..],
+        width = width
+    ))
+}
+
+#[derive(Debug, Default)]
+pub struct Matches {
+    pub positionals: Vec<String>,
+    values: HashMap<&'static str, Vec<String>>,
+}
+
+impl Matches {
+    pub fn flag(&self, long: &str) -> bool {
+        self.values.contains_key(long)
+    }
+
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values.get(long).and_then(|v| v.last()).map(String::as_str)
+    }
+
+    /// Wszystkie wartości flagi podanej kilka razy, w kolejności z linii poleceń.
+    pub fn values(&self, long: &str) -> &[String] {
+        self.values.get(long).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn parse_value<T: FromStr>(&self, long: &str) -> Result<Option<T>, CliError> {
+        match self.value(long) {
+            None => Ok(None),
+            Some(raw) => raw
+                .parse()
+                .map(Some)
+                .map_err(|_| usage(format!("niepoprawna wartość '{}' dla --{}", raw, long))),
+        }
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positionals.get(index).map(String::as_str)
+    }
+}
+
+pub fn parse(spec: &CommandSpec, args: &[String]) -> Result<Matches, CliError> {
+    let mut matches = Matches::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = if let Some(rest) = arg.strip_prefix("--") {
+            if rest.is_empty() {
+                matches.positionals.extend(iter.by_ref().cloned());
+                break;
+            }
+            let (name, inline) = match rest.split_once('=') {
+                Some((n, v)) => (n, Some(v.to_string())),
+                None => (rest, None),
+            };
+            let flag = spec.flags.iter().find(|f| f.long == name);
+            (flag.ok_or_else(|| usage(format!("nieznana opcja --{}", name)))?, inline)
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // Przełączniki można sklejać jak w gzip (-dc); pierwsza opcja z wartością kończy grupę
+            let mut chars = arg[1..].chars();
+            loop {
+                let c = chars.next().unwrap();
+                let flag = spec.flags.iter().find(|f| f.short == Some(c));
+                let flag = flag.ok_or_else(|| usage(format!("nieznana opcja -{}", c)))?;
+                let rest = chars.as_str();
+                if flag.value.is_some() || rest.is_empty() {
+                    break (flag, (!rest.is_empty()).then(|| rest.to_string()));
+                }
+                matches.values.entry(flag.long).or_default().push(String::new());
+            }
+        } else {
+            matches.positionals.push(arg.clone());
+            continue;
+        };
+
+        let value = match (flag.value, inline) {
+            (None, None) => String::new(),
+            (None, Some(_)) => return Err(usage(format!("opcja --{} nie przyjmuje wartości", flag.long))),
+            (Some(_), Some(v)) => v,
+            (Some(_), None) => iter
+                .next()
+                .cloned()
+                .ok_or_else(|| usage(format!("opcja --{} wymaga wartości", flag.long)))?,
+        };
+        matches.values.entry(flag.long).or_default().push(value);
+    }
+    Ok(matches)
+}
+
+/// Zamienia gzipowe `-1`..`-9` (także sklejone, jak `-9kv`) na `--level=N`,
+/// bo parser nie zna cyfr jako opcji.
+pub fn expand_levels(args: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(args.len());
+    for arg in args {
+        let Some(group) = arg.strip_prefix('-').filter(|g| !g.starts_with('-') && g.chars().any(|c| c.is_ascii_digit()))
+        else {
+            out.push(arg.clone());
+            continue;
+        };
+        let switches: String = group.chars().filter(|c| !c.is_ascii_digit()).collect();
+        let level: String = group.chars().filter(|c| c.is_ascii_digit()).collect();
+        out.push(format!("--level={}", level));
+        if !switches.is_empty() {
+            out.push(format!("-{}", switches));
+        }
+    }
+    out
+}
+
+// Kolumna opisów zaczyna się za najdłuższą nazwą i co najmniej dwiema spacjami
+fn column_width<'a>(names: impl Iterator<Item = &'a str>, min: usize) -> usize {
+    names.map(|name| name.chars().count() + 2).fold(min, usize::max)
+}
+
+pub fn command_help(spec: &CommandSpec) -> String {
+    let mut out = format!("{}\n\nUżycie: huff {} [opcje] {}\n\nOpcje:\n", spec.about, spec.name, spec.args);
+    let longs: Vec<String> = spec
+        .flags
+        .iter()
+        .map(|flag| match flag.value {
+            Some(v) => format!("--{} <{}>", flag.long, v),
+            None => format!("--{}", flag.long),
+        })
+        .collect();
+    let width = column_width(longs.iter().map(String::as_str), 22);
+    for (flag, long) in spec.flags.iter().zip(&longs) {
+        let short = flag.short.map(|c| format!("-{}, ", c)).unwrap_or_default();
+        out.push_str(&format!("  {:<4}{:<width$}{}\n", short, long, flag.help));
+    }
+    out
+}
+
+pub fn main_help() -> String {
+    let mut out = String::from("huff - koder/dekoder Huffmana\n\nUżycie: huff <komenda> [opcje]\n\nKomendy:\n");
+    let width = column_width(COMMANDS.iter().map(|cmd| cmd.name), 10);
+    for cmd in COMMANDS {
+        out.push_str(&format!("  {:<width$}{}\n", cmd.name, cmd.about));
+    }
+    out.push_str("\nSzczegóły: huff <komenda> --help\n");
+    out.push_str("Przerwanie (Ctrl-C) usuwa niepełne pliki wyjściowe i kończy z kodem 130.\n");
+    out
+}
@@ -0,0 +1,160 @@
+// Tryb zgodny z gzip: `plik` -> `plik.huff` z usunięciem oryginału, -d w drugą stronę,
+// -c na standardowe wyjście, -t tylko sprawdza dane
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Write};
+
+use log::debug;
+
+use huffman_format::{
+    ADAPTIVE_MAGIC, AdaptiveReader, Archive, DecodeOptions, HuffmanError, HuffmanReader, HuffmanWriter, LZ77_MAGIC,
+    Options, decompress_lz77,
+};
+
+use crate::cli::{CliError, Matches, usage};
+use crate::{Counted, LogEvents, STDIO, create_output, open_input};
+
+const SUFFIX: &str = ".huff";
+const DEFAULT_LEVEL: u8 = 6;
+
+// Rząd modelu i rozmiar bloku dla poziomów 1-9: wyższy rząd zwykle kompresuje lepiej,
+// ale ma większą tabelę kodów, więc dostaje też większe bloki
+fn level_options(level: u8) -> (usize, usize) {
+    const MIB: usize = 1024 * 1024;
+    match level {
+        1 => (0, MIB),
+        2 => (0, 4 * MIB),
+        3 => (1, MIB),
+        4 => (1, 4 * MIB),
+        5 => (2, MIB),
+        6 => (2, 4 * MIB),
+        7 => (2, 16 * MIB),
+        8 => (3, 4 * MIB),
+        _ => (3, 16 * MIB),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Compress,
+    Decompress,
+    Test,
+}
+
+pub fn cmd_gzip(m: &Matches) -> Result<(), CliError> {
+    let mode = match (m.flag("test"), m.flag("decompress")) {
+        (true, _) => Mode::Test,
+        (false, true) => Mode::Decompress,
+        (false, false) => Mode::Compress,
+    };
+    let level = m.parse_value::<u8>("level")?.unwrap_or(DEFAULT_LEVEL);
+    if !(1..=9).contains(&level) {
+        return Err(usage(format!("--level: oczekiwano 1-9, podano {}", level)));
+    }
+    let stdin_only = [STDIO.to_string()];
+    let inputs = if m.positionals.is_empty() { &stdin_only[..] } else { &m.positionals[..] };
+
+    for input in inputs {
+        let to_stdout = m.flag("stdout") || input == STDIO;
+        let output = match mode {
+            Mode::Test => None,
+            _ if to_stdout => Some(STDIO.to_string()),
+            Mode::Compress => Some(format!("{}{}", input, SUFFIX)),
+            Mode::Decompress => match input.strip_suffix(SUFFIX) {
+                Some(stem) if !stem.is_empty() => Some(stem.to_string()),
+                _ => return Err(usage(format!("{}: nieznany przyrostek (oczekiwano {})", input, SUFFIX))),
+            },
+        };
+        if matches!(mode, Mode::Compress) && to_stdout && io::stdout().is_terminal() && !m.flag("force") {
+            return Err(usage("nie zapisuję danych binarnych na terminal (-f wymusza)"));
+        }
+        if let Some(output) = output.as_deref().filter(|&o| o != STDIO)
+            && !m.flag("force")
+            && fs::exists(output)?
+        {
+            return Err(usage(format!("{} już istnieje (-f nadpisuje)", output)));
+        }
+
+        let result = match mode {
+            Mode::Compress => compress(input, output.as_deref().expect("wyjście ustalone"), level),
+            Mode::Decompress => decompress(input, &mut create_output(output.as_deref().expect("wyjście ustalone"))?),
+            Mode::Test => decompress(input, &mut io::sink()),
+        };
+        let (read, written) = match result {
+            Ok(sizes) => sizes,
+            Err(e) => {
+                if let Some(output) = output.as_deref().filter(|&o| o != STDIO) {
+                    let _ = fs::remove_file(output);
+                }
+                return Err(e.into());
+            }
+        };
+
+        match output.as_deref() {
+            None => debug!("{}: OK", input),
+            Some(STDIO) => {}
+            Some(output) => {
+                copy_metadata(input, output)?;
+                if !m.flag("keep") && input != STDIO {
+                    fs::remove_file(input)?;
+                }
+                let (original, packed) = if matches!(mode, Mode::Compress) { (read, written) } else { (written, read) };
+                let saved = if original == 0 { 0.0 } else { 100.0 - packed as f64 * 100.0 / original as f64 };
+                debug!("{}: {:.1}% -> {}", input, saved, output);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Zwraca liczbę bajtów przeczytanych i zapisanych
+fn compress(input: &str, output: &str, level: u8) -> huffman_format::Result<(u64, u64)> {
+    let (order, block_size) = level_options(level);
+    let options = Options { order, ..Options::default() };
+    let mut input = open_input(input)?;
+    let mut writer = HuffmanWriter::with_block_size(create_output(output)?, options, block_size).with_events(LogEvents);
+    io::copy(&mut input, &mut writer)?;
+    // flush zapisuje ostatni blok, więc liczniki obejmują już całe wyjście
+    writer.flush()?;
+    let (read, written) = (writer.total_in(), writer.total_out());
+    writer.finish()?;
+    Ok((read, written))
+}
+
+fn decompress(input: &str, output: &mut dyn Write) -> huffman_format::Result<(u64, u64)> {
+    let mut source = open_input(input)?;
+    let mut prefix = Vec::with_capacity(ADAPTIVE_MAGIC.len());
+    (&mut source).take(ADAPTIVE_MAGIC.len() as u64).read_to_end(&mut prefix)?;
+    if Archive::is_archive(&prefix) {
+        return Err(HuffmanError::InvalidOptions(format!(
+            "{}: archiwum wielu plików - użyj huff decode --extract",
+            input
+        )));
+    }
+    let mut counted = Counted { inner: io::Cursor::new(prefix.clone()).chain(source), count: 0 };
+    // Pusty plik to puste wejście ze starszej wersji, która nie zapisywała wtedy żadnej ramki
+    let written = if prefix.is_empty() {
+        0
+    } else if prefix == ADAPTIVE_MAGIC {
+        io::copy(&mut AdaptiveReader::new(&mut counted)?, output)?
+    } else if prefix == LZ77_MAGIC {
+        let mut frame = Vec::new();
+        counted.read_to_end(&mut frame)?;
+        let decoded = decompress_lz77(&frame, &DecodeOptions::default())?;
+        output.write_all(&decoded)?;
+        decoded.len() as u64
+    } else {
+        io::copy(&mut HuffmanReader::new(&mut counted)?, output)?
+    };
+    output.flush()?;
+    Ok((counted.count, written))
+}
+
+// Jak gzip: wynik dostaje uprawnienia i czas modyfikacji oryginału
+fn copy_metadata(input: &str, output: &str) -> io::Result<()> {
+    if input == STDIO {
+        return Ok(());
+    }
+    let metadata = fs::metadata(input)?;
+    fs::set_permissions(output, metadata.permissions())?;
+    File::options().write(true).open(output)?.set_modified(metadata.modified()?)
+}
@@ -0,0 +1,131 @@
+// Obsługa Ctrl-C: handler sygnału tylko ustawia flagę, a resztę (usunięcie niepełnych
+// plików, podsumowanie, kod wyjścia) robi osobny wątek, bo w handlerze wolno niewiele
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Kod wyjścia po przerwaniu, jak w powłokach: 128 + numer sygnału SIGINT.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static WRITTEN: AtomicU64 = AtomicU64::new(0);
+static BLOCKS: AtomicU64 = AtomicU64::new(0);
+// Pliki wyjściowe otwarte do zapisu; po przerwaniu są niepełne
+static PARTIAL: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+#[cfg(unix)]
+mod sys {
+    pub const SIGINT: i32 = 2;
+
+    unsafe extern "C" {
+        pub fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: i32) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Przechwytuje Ctrl-C; poza Uniksem nic nie robi (proces kończy się jak dotąd).
+pub fn install() {
+    #[cfg(unix)]
+    {
+        // SAFETY: handler tylko zapisuje do zmiennej atomowej, co jest bezpieczne w kontekście sygnału
+        unsafe {
+            sys::signal(sys::SIGINT, on_sigint);
+        }
+        let start = Instant::now();
+        thread::spawn(move || {
+            while !INTERRUPTED.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(50));
+            }
+            cleanup(start.elapsed());
+            std::process::exit(EXIT_INTERRUPTED);
+        });
+    }
+}
+
+fn cleanup(elapsed: Duration) {
+    log::logger().flush();
+    let partial = PARTIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let mut summary = format!(
+        "\n⚠️  Przerwano (Ctrl-C) po {:.1?}: zapisano {} bajtów",
+        elapsed,
+        WRITTEN.load(Ordering::SeqCst)
+    );
+    match BLOCKS.load(Ordering::SeqCst) {
+        0 => {}
+        blocks => summary.push_str(&format!(", zakodowano {} ramek", blocks)),
+    }
+    eprintln!("{}", summary);
+    for path in partial.iter() {
+        match fs::remove_file(path) {
+            Ok(()) => eprintln!("🗑️  Usunięto niepełny plik {}", path.display()),
+            Err(e) => eprintln!("❌ Nie udało się usunąć niepełnego pliku {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Liczy ramkę do podsumowania po przerwaniu.
+pub fn record_block() {
+    BLOCKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Plik wyjściowy usuwany po Ctrl-C, dopóki nie zostanie zamknięty.
+pub struct PartialFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl PartialFile {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut partial = PARTIAL.lock().unwrap_or_else(|e| e.into_inner());
+        // Po przerwaniu nie zaczynamy nowych plików, które nie zostałyby już posprzątane
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "przerwano (Ctrl-C)"));
+        }
+        // Także do odczytu: archiwum z --dedup porównuje powtórzone bloki z zapisanymi
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        partial.push(path.clone());
+        Ok(PartialFile { file, path })
+    }
+}
+
+impl Write for PartialFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        WRITTEN.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Read for PartialFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for PartialFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        let mut partial = PARTIAL.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = partial.iter().position(|p| *p == self.path) {
+            partial.swap_remove(i);
+        }
+    }
+}
@@ -0,0 +1,2319 @@
+mod cli;
+mod gzip;
+mod interrupt;
+mod report;
+mod tune;
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use regex::bytes::Regex;
+
+use huffman_format::checksum::Crc32;
+use huffman_format::huffman::{DEFAULT_MAX_CODE_LEN, MAX_CODE_LEN_LIMIT};
+use huffman_format::testdata::{Corpus, DEFAULT_SEED};
+use huffman_format::{
+    ADAPTIVE_MAGIC, ARCHIVE_MAGIC, AdaptivePreset, AdaptiveReader, AdaptiveWriter, Alphabet, Archive, ArchiveWriter,
+    ChecksumKind, Coverage, DEFAULT_BLOCK_SIZE, DEFAULT_GRANULE, DEFAULT_LOOKUP_BITS, DICT_FRAME_MAGIC,
+    DecodeAutomaton, DecodeOptions, Dedup, Dictionary, ESCAPE, Event, EventSink, FORMAT_VERSION, FilterChain,
+    FixedTable, FreqModel, Header, HuffmanError, HuffmanReader, HuffmanWriter, INCOMPRESSIBLE_ENTROPY, LZ77_MAGIC,
+    LineCheckpoint, LineIndex, Lz77Options, MAGIC, MAX_UNIT, Member, MemoryEstimate, Model, Options, Resource,
+    SeekTable, SolidArchiveWriter, Stats, build_code_lengths, collect_frequencies, compress_lz77_with_stats,
+    compress_optimal_split, compress_parallel, compress_stream_with_events, compress_with_stats, conditional_entropy,
+    decompress, decompress_lz77, decompress_parallel, decompress_range, default_threads, detect_unit, estimate,
+    estimate_decode_memory, frame_index, payload_bits, read_metadata, sample_entropy, set_metadata, store_stream,
+};
+
+use crate::cli::{CliError, Matches, usage};
+use crate::interrupt::PartialFile;
+use crate::report::Report;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    interrupt::install();
+
+    let code = match run(&args) {
+        Ok(()) => 0,
+        Err(CliError::Usage(msg)) => {
+            eprintln!("❌ {}\nPomoc: huff --help", msg);
+            2
+        }
+        Err(CliError::Huffman(e)) => {
+            eprintln!("❌ Błąd: {}", e);
+            1
+        }
+        Err(CliError::Failed(msg)) => {
+            eprintln!("❌ {}", msg);
+            1
+        }
+    };
+    std::process::exit(code);
+}
+
+fn init_logger(verbose: bool) {
+    let level = if verbose { "debug" } else { "info" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+}
+
+fn run(args: &[String]) -> Result<(), CliError> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", cli::main_help());
+        return Ok(());
+    };
+
+    let (spec, rest) = match command.as_str() {
+        "-h" | "--help" | "help" => {
+            println!("{}", cli::main_help());
+            return Ok(());
+        }
+        name => match cli::COMMANDS.iter().find(|c| c.name == name) {
+            Some(spec) => (*spec, rest),
+            // Skrypty pisane pod gzip podają od razu opcje albo pliki
+            None if name.starts_with('-') || Path::new(name).is_file() => (&cli::GZIP, args),
+            None => return Err(usage(format!("nieznana komenda '{}'", name))),
+        },
+    };
+    let rest = if spec.name == cli::GZIP.name { cli::expand_levels(rest) } else { rest.to_vec() };
+
+    let matches = cli::parse(spec, &rest)?;
+    if matches.flag("help") {
+        println!("{}", cli::command_help(spec));
+        return Ok(());
+    }
+    init_logger(matches.flag("verbose"));
+
+    match spec.name {
+        "encode" => cmd_encode(&matches),
+        "decode" => cmd_decode(&matches),
+        "tail" => cmd_tail(&matches),
+        "grep" => cmd_grep(&matches),
+        "lines" => cmd_lines(&matches),
+        "list" => cmd_list(&matches),
+        "info" => cmd_info(&matches),
+        "set-meta" => cmd_set_meta(&matches),
+        "test" => cmd_test(&matches),
+        "convert" => cmd_convert(&matches),
+        "stats" => cmd_stats(&matches),
+        "dict" => cmd_dict(&matches),
+        "bench" => cmd_bench(&matches),
+        "gzip" => gzip::cmd_gzip(&matches),
+        _ => unreachable!(),
+    }
+}
+
+fn cmd_encode(m: &Matches) -> Result<(), CliError> {
+    // Zegar raportu rusza od razu, żeby czas obejmował też czytanie wejścia
+    let report = m.value("report-append").map(|path| (path, Report::new("encode")));
+    // Bez argumentów dane płyną ze standardowego wejścia
+    let stdin_only = [STDIO.to_string()];
+    let positionals = if m.positionals.is_empty() { &stdin_only[..] } else { &m.positionals[..] };
+    let input_filepath = positionals[0].as_str();
+    // Drugi argument jest wyjściem tylko w klasycznej postaci `encode <input> <output>`;
+    // więcej wejść albo katalog dają archiwum, którego nazwę podaje się przez -o
+    let legacy_output = m.value("output").is_none() && positionals.len() == 2 && !Path::new(&positionals[1]).is_dir();
+    let inputs = if legacy_output { &positionals[..1] } else { positionals };
+    let archive = inputs.len() > 1 || Path::new(input_filepath).is_dir();
+    let from_stdin = input_filepath == STDIO;
+    // Jak gzip: potok bez nazwy wyjścia trafia dalej potokiem
+    let output_filepath = match m.value("output").or(legacy_output.then(|| positionals[1].as_str())) {
+        _ if m.flag("stdout") => STDIO,
+        Some(output) => output,
+        None if from_stdin => STDIO,
+        None => "output.huff",
+    };
+    let dry_run = m.flag("dry-run");
+    // Próba niczego nie zapisuje, więc komunikaty mogą iść na standardowe wyjście
+    let to_stdout = output_filepath == STDIO && !dry_run;
+    if to_stdout && io::stdout().is_terminal() {
+        return Err(usage("encode: nie zapisuję danych binarnych na terminal (przekieruj wyjście albo użyj -o)"));
+    }
+    run_autotune(m, to_stdout)?;
+
+    // Nazwy zestawów to wbudowane słowniki: ramka bez tabeli kodów, jak przy --dict;
+    // słownik logs dostaje tylko wejście z jednego bloku, a dłuższe idzie blokami linii
+    let builtin = match m.value("preset") {
+        None => None,
+        Some(name) => Some(Dictionary::preset(name).ok_or_else(|| {
+            let names: Vec<_> = Dictionary::presets().collect();
+            usage(format!("--preset: nieznany zestaw '{}' (dostępne: {})", name, names.join(", ")))
+        })?),
+    };
+    let (builtin, logs_dict) = match builtin {
+        Some(dict) if m.value("preset") == Some("logs") => (None, Some(dict)),
+        builtin => (builtin, None),
+    };
+    let preset = m.value("preset").filter(|_| builtin.is_none());
+    // Kontekst dwóch bajtów dobrze łapie powtarzalne słowa kluczowe i znaczniki czasu
+    let default_order = if preset.is_some() { 2 } else { 0 };
+    let mut order: usize = m.parse_value("order")?.unwrap_or(default_order);
+    let max_code_len = m
+        .parse_value::<u8>("max-len")?
+        .unwrap_or(DEFAULT_MAX_CODE_LEN)
+        .clamp(1, MAX_CODE_LEN_LIMIT);
+    let alphabet = m
+        .value("alphabet")
+        .map(|name| name.parse::<Alphabet>().map_err(usage))
+        .transpose()?;
+
+    // Ograniczenie rzędu, żeby nie przepełnić bufora w nagłówku (format zakłada 1 bajt na rząd)
+    if order > 255 {
+        LogEvents.event(&Event::Warning { message: "Maksymalny rząd to 255. Ustawiono na 255.".to_string() });
+        order = 255;
+    }
+
+    // Rząd i szerokość symbolu podane wprost muszą zgadzać się z tabelą, a domyślnie są z niej brane
+    let sources = ["use-table", "freq", "dict"].iter().filter(|&&name| m.value(name).is_some()).count();
+    if sources + builtin.is_some() as usize > 1 {
+        return Err(usage(
+            "--use-table, --freq, --dict i wbudowany --preset wykluczają się (każde z nich daje gotową tabelę kodów)",
+        ));
+    }
+    let dict = match builtin {
+        Some(dict) => Some(dict),
+        None => m.value("dict").map(read_dict).transpose()?,
+    };
+    let table = match &dict {
+        Some(dict) => Some(dict.table().clone()),
+        None if let Some(path) = m.value("freq") => Some(read_freq_table(path, max_code_len)?),
+        None => m.value("use-table").map(read_table).transpose()?,
+    };
+    if let Some(table) = &table
+        && m.value("order").is_none()
+    {
+        order = table.order();
+    }
+    let unit = match m.value("unit").unwrap_or("auto") {
+        "auto" if table.is_some() => table.as_ref().map_or(1, FixedTable::unit),
+        // Ze standardowego wejścia nie da się podejrzeć BOM bez jego konsumowania;
+        // w archiwum szerokość jest wykrywana osobno dla każdego pliku
+        "auto" if from_stdin || archive => 1,
+        "auto" => detect_file_unit(input_filepath)?,
+        "nibble" => 1,
+        other => match other.parse::<u8>() {
+            Ok(unit @ 1..=MAX_UNIT) => unit,
+            _ => {
+                return Err(usage(format!("--unit: oczekiwano auto, 1-{} lub nibble, podano '{}'", MAX_UNIT, other)));
+            }
+        },
+    };
+    let nibble = m.value("unit") == Some("nibble");
+
+    let checksum = m
+        .value("checksum")
+        .map(|name| name.parse::<ChecksumKind>().map_err(usage))
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut max_symbols = m.parse_value::<usize>("max-symbols")?;
+    if unit > 2 && max_symbols.is_none() {
+        max_symbols = Some(WIDE_UNIT_MAX_SYMBOLS);
+        debug!("Symbole {}-bajtowe: model przycięty do {} par (--max-symbols)", unit, WIDE_UNIT_MAX_SYMBOLS);
+    }
+    let store_fallback = !m.flag("no-store");
+    let filters = match (m.value("filter"), m.value("filters")) {
+        (Some(_), Some(_)) => return Err(usage("--filter to jeden filtr, a --filters łańcuch: podaj tylko jedno")),
+        (filter, list) => filter.or(list).map(|list| list.parse::<FilterChain>().map_err(usage)).transpose()?,
+    };
+    let filters = match filters {
+        Some(_) if preset.is_some() => {
+            return Err(usage(format!("--preset logs ma własny filtr {}: bez --filter i --filters", LOGS_FILTERS)));
+        }
+        Some(filters) => filters,
+        None if preset.is_some() => LOGS_FILTERS.parse().expect("poprawny łańcuch filtrów"),
+        None => FilterChain::default(),
+    };
+    let options =
+        Options { order, max_code_len, alphabet, unit, checksum, max_symbols, nibble, store_fallback, filters };
+    if let Some(table) = &table {
+        table.check_options(&options)?;
+    }
+    // Słownik ma rząd 2 i bajty, więc inny --order albo --unit zostawia same bloki linii
+    let logs_dict = logs_dict.filter(|dict| dict.table().check_options(&options).is_ok());
+
+    let deadline = m.value("deadline").map(cli::parse_duration).transpose()?;
+    let threads = parse_threads(m)?;
+    let line_index: Option<u64> = m.parse_value("line-index")?;
+    if line_index == Some(0) {
+        return Err(usage("--line-index: odstęp musi być większy od 0"));
+    }
+    let rotate_size = m.value("rotate-size").map(cli::parse_size).transpose()?;
+    if rotate_size.is_some() && cli::format_part(output_filepath, 0).is_none() {
+        return Err(usage("--rotate-size wymaga wzorca nazwy z %d, np. -o part-%03d.huff"));
+    }
+
+    let seek_table = m.flag("seek-table");
+    if to_stdout && (archive || rotate_size.is_some() || line_index.is_some()) {
+        return Err(usage("encode: archiwum, --rotate-size i --line-index wymagają pliku wyjściowego"));
+    }
+
+    let adaptive = m.flag("adaptive");
+    if adaptive && (archive || m.value("order").is_some_and(|o| o != "0") || preset.is_some()) {
+        return Err(usage("--adaptive koduje pojedynczy plik modelem rzędu 0"));
+    }
+    if !filters.is_empty() && (adaptive || m.flag("stream")) {
+        return Err(usage("--filters przekształcają całe ramki w pamięci: bez --adaptive i --stream"));
+    }
+    if m.value("warm-start").is_some() && !adaptive {
+        return Err(usage("--warm-start dotyczy trybu --adaptive"));
+    }
+    let warm_start = m.value("warm-start").map(read_preset).transpose()?;
+    let solid = m.flag("solid");
+    if solid && !archive {
+        return Err(usage("--solid dotyczy archiwum wielu plików"));
+    }
+    let dedup = m.flag("dedup");
+    if dedup && (!archive || solid) {
+        return Err(usage("--dedup dotyczy archiwum wielu plików bez --solid"));
+    }
+    let batch_small = m.value("batch-small").map(cli::parse_size).transpose()?;
+    if batch_small.is_some() && (!archive || solid) {
+        return Err(usage("--batch-small dotyczy archiwum wielu plików bez --solid"));
+    }
+    let blocks = seek_table || deadline.is_some() || threads.is_some() || m.flag("optimal-split") || m.flag("stream");
+    let one_frame = !(archive || adaptive || preset.is_some() || line_index.is_some() || rotate_size.is_some());
+    if table.is_some() && (blocks || !one_frame) {
+        return Err(usage(
+            "--use-table, --freq i --dict kodują jeden plik jedną ramką: bez archiwum, --adaptive, --preset i bloków",
+        ));
+    }
+    let skip_incompressible = m.flag("skip-incompressible");
+    if skip_incompressible && (from_stdin || !one_frame || table.is_some() || seek_table) {
+        return Err(usage(
+            "--skip-incompressible próbkuje jeden plik: bez standardowego wejścia, archiwum, tabel i --seek-table",
+        ));
+    }
+    let lz77 = m.flag("lz77");
+    let plain = !(table.is_some() || preset.is_some() || !filters.is_empty() || blocks || skip_incompressible);
+    let byte_model = m.value("order").is_none_or(|o| o == "0") && m.value("unit").is_none_or(|u| u == "1");
+    if lz77 && (!one_frame || !plain || !byte_model || dry_run) {
+        return Err(usage(
+            "--lz77 koduje jeden plik bajtami w jednej ramce HLZ7: bez archiwum, --adaptive, tabel, filtrów i bloków",
+        ));
+    }
+    // LZ77 ma własne alfabety literałów i odległości z kodami do 15 bitów
+    if lz77 && ["max-len", "alphabet", "max-symbols"].iter().any(|name| m.value(name).is_some()) {
+        return Err(usage("--lz77 ma stałe alfabety i limit kodów 15 bitów: bez --max-len, --alphabet i --max-symbols"));
+    }
+    if dry_run && dict.is_some() {
+        return Err(usage("--dry-run szacuje ramkę z tabelą w nagłówku, więc nie działa z --dict"));
+    }
+    if dry_run {
+        if archive || adaptive || rotate_size.is_some() || line_index.is_some() {
+            return Err(usage("--dry-run szacuje jeden plik: bez archiwum, --adaptive, --rotate-size i --line-index"));
+        }
+        return Ok(encode_dry_run(input_filepath, &options, table.as_ref())?);
+    }
+    let verify = m.flag("verify");
+    if verify && (archive || rotate_size.is_some() || from_stdin || to_stdout) {
+        return Err(usage("--verify czyta wejście i wynik ponownie: wymaga plików, bez archiwum i --rotate-size"));
+    }
+
+    // Słownik, którego ramkę zapisano: --dict, wbudowany --preset albo logs dla krótkiego wejścia
+    let mut written_dict = dict.as_ref();
+    let mut coverage = None;
+    let result = if archive {
+        // W archiwum solid jest jeden model, więc i jedna szerokość symbolu
+        let auto_unit = !solid && m.value("unit").is_none_or(|u| u == "auto");
+        let excludes = m.values("exclude");
+        let layout = ArchiveLayout { auto_unit, solid, dedup, batch_small };
+        encode_archive(inputs, excludes, output_filepath, &options, layout).map(|(stats, members, shared)| {
+            println!("📦 Plików w archiwum: {}{}", members, if solid { " (solid)" } else { "" });
+            if shared.blocks > 0 {
+                println!(
+                    "♻️  Powtórzone bloki:  {} w {} (zaoszczędzono {} bajtów)",
+                    shared.blocks,
+                    if shared.members == 1 { "1 pliku".to_string() } else { format!("{} plikach", shared.members) },
+                    shared.saved
+                );
+            }
+            stats
+        })
+    } else if adaptive {
+        encode_adaptive(open_input(input_filepath)?, output_filepath, warm_start.as_ref())
+    } else if lz77 {
+        encode_lz77(open_input(input_filepath)?, output_filepath, checksum)
+    } else if let Some(dict) = &dict {
+        encode_with_dict(open_input(input_filepath)?, output_filepath, &options, dict).map(|(stats, covered)| {
+            coverage = Some(covered);
+            stats
+        })
+    } else if let Some(table) = &table {
+        encode_with_table(open_input(input_filepath)?, output_filepath, &options, table)
+    } else if let Some(dict) = logs_dict.as_ref().filter(|_| line_index.is_none() && !seek_table) {
+        encode_logs(open_input(input_filepath)?, output_filepath, &options, dict).map(|(stats, framed)| {
+            if framed.is_some() {
+                written_dict = Some(dict);
+            }
+            coverage = framed;
+            stats
+        })
+    } else if preset.is_some() || line_index.is_some() {
+        let input = open_input(input_filepath)?;
+        encode_lines(input, output_filepath, &options, line_index, seek_table).and_then(|(stats, index)| {
+            if line_index.is_some() {
+                fs::write(index_path(output_filepath), index.to_bytes())?;
+                println!("📑 Indeks linii:     {} punktów", index.checkpoints.len());
+            }
+            Ok(stats)
+        })
+    } else if from_stdin || rotate_size.is_some() || seek_table {
+        let input = open_input(input_filepath)?;
+        encode_rotating(input, output_filepath, rotate_size, &options, seek_table).map(|(stats, parts)| {
+            if rotate_size.is_some() {
+                println!("📦 Zapisano części: {}", parts);
+            }
+            stats
+        })
+    } else if skip_incompressible && let Some(entropy) = incompressible_sample(input_filepath)? {
+        println!("🎲 Próbka ma {:.2} bitów/bajt: plik zapisany wprost, bez kodowania", entropy);
+        encode_stored(input_filepath, output_filepath, &options)
+    } else if let Some(deadline) = deadline {
+        encode_with_deadline(input_filepath, output_filepath, &options, deadline)
+    } else if m.flag("optimal-split") {
+        encode_optimal_split(input_filepath, output_filepath, &options)
+    } else if let Some(threads) = threads {
+        encode_parallel(input_filepath, output_filepath, &options, threads)
+    } else if m.flag("stream") {
+        encode_streaming(input_filepath, output_filepath, &options)
+    } else {
+        encode_in_memory(input_filepath, output_filepath, &options)
+    };
+    let stats = result?;
+    // Ramka słownika ma stały nagłówek bez tabeli kodów, więc rady z ostrzeżenia jej nie dotyczą
+    if written_dict.is_none() {
+        warn_header_dominates(&stats);
+    }
+    if verify && let Err(e) = verify_output(input_filepath, output_filepath, warm_start.as_ref(), written_dict) {
+        let _ = fs::remove_file(output_filepath);
+        return Err(e.into());
+    }
+
+    let summary = format!(
+        "\r\n✅ Kodowanie rzędu {} zakończone.\n\
+         📂 Rozmiar nagłówka:  {} bajtów\n\
+         💾 Rozmiar strumienia: {} bajtów\n\
+         📊 Entropia H(X|C):   {:.4} bitów/bajt\n\
+         📐 Narzut kodera:     {:.2}% ponad minimum {:.0} bajtów\n\
+         🗜️  Kompresja:        {:.2}%",
+        order,
+        stats.header_len,
+        stats.payload_len,
+        stats.entropy,
+        stats.coding_overhead_percent(),
+        stats.ideal_payload_len(),
+        stats.compression_percent(),
+    );
+    status(to_stdout, &summary);
+    if unit > 1 {
+        let bytes = if unit < 5 { "bajty" } else { "bajtów" };
+        status(to_stdout, &format!("🔤 Symbol:           {} {}", unit, bytes));
+    }
+    if let Some(coverage) = &coverage {
+        report_coverage(to_stdout, coverage, m.value("preset"));
+    }
+    if verify {
+        status(to_stdout, "🔍 Weryfikacja:      zdekodowane dane zgodne z oryginałem");
+    }
+    if let Some((path, report)) = report {
+        let unit = if nibble { "nibble".to_string() } else { unit.to_string() };
+        report
+            .text("input", input_filepath)
+            .text("output", output_filepath)
+            .number("order", order as f64)
+            .text("unit", &unit)
+            .number("max_code_len", max_code_len as f64)
+            .text("checksum", &format!("{:?}", checksum).to_lowercase())
+            .number("input_len", stats.original_len as f64)
+            .number("output_len", (stats.header_len + stats.payload_len + stats.footer_len) as f64)
+            .number("header_len", stats.header_len as f64)
+            .number("payload_len", stats.payload_len as f64)
+            .number("compression_percent", stats.compression_percent())
+            .number("entropy", stats.entropy)
+            .append(path, stats.original_len as u64)?;
+    }
+    Ok(())
+}
+
+// Dekoduje zapisany plik od nowa i porównuje długość i CRC-32 wyniku z oryginałem,
+// niezależnie od sumy w stopce ramek (ta sprawdza tylko zgodność z tym, co zobaczył koder)
+fn verify_output(
+    input: &str,
+    output: &str,
+    preset: Option<&AdaptivePreset>,
+    dict: Option<&Dictionary>,
+) -> huffman_format::Result<()> {
+    let (expected_len, expected) = crc_of(open_input(input)?)?;
+    let mut file = BufReader::new(File::open(output)?);
+    let reader: Box<dyn Read> = if file.fill_buf()?.starts_with(&ADAPTIVE_MAGIC) {
+        match preset {
+            Some(preset) => Box::new(AdaptiveReader::with_preset(file, preset)?),
+            None => Box::new(AdaptiveReader::new(file)?),
+        }
+    } else if file.fill_buf()?.starts_with(&LZ77_MAGIC) {
+        let mut frame = Vec::new();
+        file.read_to_end(&mut frame)?;
+        Box::new(io::Cursor::new(decompress_lz77(&frame, &DecodeOptions::default())?))
+    } else if let Some(dict) = dict {
+        let mut frame = Vec::new();
+        file.read_to_end(&mut frame)?;
+        Box::new(io::Cursor::new(dict.decompress(&frame, &DecodeOptions::default())?))
+    } else {
+        Box::new(HuffmanReader::new(file)?)
+    };
+    let (decoded, actual) = crc_of(reader)?;
+    if decoded != expected_len {
+        return Err(HuffmanError::TruncatedStream { decoded, expected: expected_len });
+    }
+    if actual != expected {
+        return Err(HuffmanError::ChecksumMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+fn crc_of(mut input: impl Read) -> io::Result<(u64, u32)> {
+    let mut crc = Crc32::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut len = 0;
+    loop {
+        match input.read(&mut buf) {
+            Ok(0) => return Ok((len, crc.finish())),
+            Ok(n) => {
+                crc.update(&buf[..n]);
+                len += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Liczy tabelę kodów i rozmiar wyniku jak kodowanie do jednej ramki, ale niczego nie zapisuje
+fn encode_dry_run(input: &str, options: &Options, table: Option<&FixedTable>) -> huffman_format::Result<()> {
+    let mut data = Vec::new();
+    open_input(input)?.read_to_end(&mut data)?;
+    let stats = match table {
+        Some(table) => {
+            let covering = covering_table(table, &data)?;
+            if covering.has_escapes() {
+                println!("🧩 Symbole spoza tabeli pójdą za kodem ucieczki ({} par kontekst-symbol)", table.symbols());
+            } else {
+                println!("🧩 Tabela pokrywa wszystkie symbole wejścia ({} par kontekst-symbol)", table.symbols());
+            }
+            let stats = covering.estimate(&data, options)?;
+            warn_table_cost(&covering, &data, options, &stats)?;
+            stats
+        }
+        None => estimate(&data, options)?,
+    };
+    warn_header_dominates(&stats);
+    println!(
+        "🧪 Próba rzędu {} (--dry-run), nic nie zapisano.\n\
+         📂 Rozmiar nagłówka:  {} bajtów\n\
+         💾 Rozmiar strumienia: {} bajtów\n\
+         📦 Przewidywany plik: {} bajtów (z {})\n\
+         📊 Entropia H(X|C):   {:.4} bitów/bajt\n\
+         📐 Narzut kodera:     {:.2}% ponad minimum {:.0} bajtów\n\
+         🗜️  Kompresja:        {:.2}%",
+        options.order,
+        stats.header_len,
+        stats.payload_len,
+        stats.header_len + stats.payload_len + stats.footer_len,
+        stats.original_len,
+        stats.entropy,
+        stats.coding_overhead_percent(),
+        stats.ideal_payload_len(),
+        stats.compression_percent(),
+    );
+    Ok(())
+}
+
+// `-` zamiast nazwy pliku to standardowe wejście albo wyjście
+const STDIO: &str = "-";
+
+// Domyślny --max-symbols dla symboli szerszych niż 2 bajty: liczba różnych symboli rośnie
+// wtedy z długością danych, a każdy to wpis nagłówka i pamięć dekodera
+const WIDE_UNIT_MAX_SYMBOLS: usize = 1 << 16;
+
+// Poniżej takiego pokrycia symboli kodami słownika encode radzi wytrenować nowy; słownik
+// rzędu 2 z próbki kilku kB pokrywa zwykle ~90% podobnych danych, bo nie widział wszystkich kontekstów
+const RETRAIN_COVERAGE: f64 = 0.8;
+
+// Filtr --preset logs: różnice cyfr znacznika czasu na początku linii, jak w próbce słownika logs
+const LOGS_FILTERS: &str = "timestamps:32";
+
+// Nagłówek większy od zakodowanych danych zwykle oznacza zbyt bogaty model
+fn warn_header_dominates(stats: &Stats) {
+    if stats.header_len > stats.payload_len {
+        warn!(
+            "Nagłówek ({} bajtów) jest większy niż zakodowane dane ({} bajtów); \
+             spróbuj mniejszego --unit, --order albo --max-symbols",
+            stats.header_len, stats.payload_len
+        );
+    }
+}
+
+fn open_input(path: &str) -> io::Result<Box<dyn BufRead>> {
+    Ok(if path == STDIO { Box::new(io::stdin().lock()) } else { Box::new(BufReader::new(File::open(path)?)) })
+}
+
+fn create_output(path: &str) -> io::Result<Box<dyn Write>> {
+    Ok(if path == STDIO {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(BufWriter::new(PartialFile::create(path)?))
+    })
+}
+
+fn write_output(path: &str, data: &[u8]) -> io::Result<()> {
+    let mut out = create_output(path)?;
+    out.write_all(data)?;
+    out.flush()
+}
+
+// Zlicza bajty czytane przez dekoder, do podsumowań i komunikatów o błędach
+struct Counted<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+// Gdy dane idą na standardowe wyjście, komunikaty trafiają na stderr, żeby nie zepsuć potoku
+fn status(on_stderr: bool, message: &str) {
+    if on_stderr {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+// Patrzy tylko na pierwsze bajty pliku, żeby nie wczytywać go w trybie --stream
+fn detect_file_unit(input: &str) -> huffman_format::Result<u8> {
+    let mut prefix = Vec::with_capacity(2);
+    File::open(input)?.take(2).read_to_end(&mut prefix)?;
+    Ok(detect_unit(&prefix))
+}
+
+fn encode_in_memory(input: &str, output: &str, options: &Options) -> huffman_format::Result<Stats> {
+    let raw_data = fs::read(input)?;
+    let (encoded, stats) = compress_with_stats(&raw_data, options)?;
+
+    write_output(output, &encoded)?;
+    Ok(stats)
+}
+
+// Entropia próbki pliku, jeśli jest tak wysoka, że --skip-incompressible zapisze go wprost
+fn incompressible_sample(input: &str) -> huffman_format::Result<Option<f64>> {
+    let entropy = sample_entropy(&mut BufReader::new(File::open(input)?))?;
+    debug!("Entropia próbki: {:.3} bitów/bajt", entropy);
+    Ok((entropy >= INCOMPRESSIBLE_ENTROPY).then_some(entropy))
+}
+
+fn encode_stored(input: &str, output: &str, options: &Options) -> huffman_format::Result<Stats> {
+    let mut writer = create_output(output)?;
+    // Dane bez redundancji zapisujemy wprost bez filtrów: filtry tylko by je wydłużyły
+    let unfiltered = Options { filters: FilterChain::default(), ..*options };
+    store_stream(&mut BufReader::new(File::open(input)?), &mut writer, &unfiltered)
+}
+
+fn encode_streaming(input: &str, output: &str, options: &Options) -> huffman_format::Result<Stats> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = create_output(output)?;
+    compress_stream_with_events(&mut reader, &mut writer, options, &mut LogEvents)
+}
+
+// Zdarzenia kodera jako komunikaty logu: postęp przy --verbose, ostrzeżenia zawsze
+struct LogEvents;
+
+impl EventSink for LogEvents {
+    fn event(&mut self, event: &Event) {
+        match event {
+            Event::PhaseStarted(phase) => debug!("Etap: {:?}", phase),
+            Event::BlockEncoded { n, original_len, compressed_len, ratio, payload_len, ideal_payload_len } => {
+                interrupt::record_block();
+                let overhead = if *ideal_payload_len > 0.0 {
+                    (*payload_len as f64 / ideal_payload_len - 1.0) * 100.0
+                } else {
+                    0.0
+                };
+                debug!(
+                    "Ramka {}: {} -> {} bajtów ({:.1}%), strumień {} bajtów przy minimum {:.0} (narzut {:.1}%)",
+                    n,
+                    original_len,
+                    compressed_len,
+                    ratio * 100.0,
+                    payload_len,
+                    ideal_payload_len,
+                    overhead
+                )
+            }
+            Event::Warning { message } => warn!("{}", message),
+        }
+    }
+}
+
+// --autotune mierzy przed właściwą pracą, więc ten sam przebieg używa już nowego wyboru
+fn run_autotune(m: &Matches, on_stderr: bool) -> Result<(), CliError> {
+    if !m.flag("autotune") {
+        return Ok(());
+    }
+    let tuning = tune::autotune(tune::BUDGET)?;
+    let path = tune::save(&tuning)?;
+    status(
+        on_stderr,
+        &format!(
+            "⚙️  Autotune: tablice dekodera po {} bitów, wątki: {} (zapisano w {})",
+            tuning.lookup_bits.unwrap_or(DEFAULT_LOOKUP_BITS),
+            tuning.threads.unwrap_or(1),
+            path.display()
+        ),
+    );
+    Ok(())
+}
+
+fn parse_threads(m: &Matches) -> Result<Option<usize>, CliError> {
+    let auto = || tune::load().threads.unwrap_or_else(default_threads);
+    Ok(m.parse_value::<usize>("threads")?.map(|n| if n == 0 { auto() } else { n }))
+}
+
+fn encode_parallel(input: &str, output: &str, options: &Options, threads: usize) -> huffman_format::Result<Stats> {
+    let raw_data = fs::read(input)?;
+    let (encoded, stats) = compress_parallel(&raw_data, options, DEFAULT_BLOCK_SIZE, threads)?;
+    debug!("Zakodowano {} bloków na {} wątkach", raw_data.len().div_ceil(DEFAULT_BLOCK_SIZE), threads);
+
+    write_output(output, &encoded)?;
+    Ok(stats)
+}
+
+// Bloki nie przekraczają domyślnego rozmiaru, żeby pamięć dekodera była ta sama co przy --stream
+fn encode_optimal_split(input: &str, output: &str, options: &Options) -> huffman_format::Result<Stats> {
+    let raw_data = fs::read(input)?;
+    let (encoded, stats) =
+        compress_optimal_split(&raw_data, options, DEFAULT_GRANULE, DEFAULT_BLOCK_SIZE / DEFAULT_GRANULE)?;
+    debug!("Zakodowano {} bajtów w {} ramkach", raw_data.len(), frame_index(&mut io::Cursor::new(&encoded))?.len());
+
+    write_output(output, &encoded)?;
+    Ok(stats)
+}
+
+// Koduje blokami; gdy przy obecnym tempie reszta nie zmieści się w czasie,
+// pozostałe bloki dostają rząd 0 (najtańszy model)
+fn encode_with_deadline(
+    input: &str,
+    output: &str,
+    options: &Options,
+    deadline: Duration,
+) -> huffman_format::Result<Stats> {
+    let start = Instant::now();
+    let total = fs::metadata(input)?.len();
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = HuffmanWriter::new(create_output(output)?, *options).with_events(LogEvents);
+
+    let mut block = Vec::with_capacity(DEFAULT_BLOCK_SIZE);
+    let mut degraded_from = None;
+    loop {
+        block.clear();
+        (&mut reader).take(DEFAULT_BLOCK_SIZE as u64).read_to_end(&mut block)?;
+        if block.is_empty() {
+            break;
+        }
+        writer.write_all(&block)?;
+        writer.flush()?;
+
+        let done = writer.total_in();
+        if degraded_from.is_none() && writer.options().order > 0 && done < total {
+            let elapsed = start.elapsed().as_secs_f64();
+            let projected = elapsed + elapsed / done as f64 * (total - done) as f64;
+            if projected > deadline.as_secs_f64() {
+                degraded_from = Some(writer.blocks());
+                writer.set_options(Options { order: 0, ..*options });
+            }
+        }
+    }
+    let blocks = writer.blocks();
+    let (_, stats) = writer.finish_with_stats()?;
+
+    if let Some(first) = degraded_from {
+        println!("⏱️  Limit czasu: bloki {}-{} z {} zakodowano rzędem 0", first + 1, blocks, blocks);
+    }
+    if start.elapsed() > deadline {
+        println!("⏱️  Limit czasu przekroczony o {:.1?}", start.elapsed() - deadline);
+    }
+    Ok(stats)
+}
+
+// Jeden przebieg bez buforowania bloków, więc działa też dla potoków bez końca
+fn encode_adaptive(
+    mut input: impl Read,
+    output: &str,
+    preset: Option<&AdaptivePreset>,
+) -> huffman_format::Result<Stats> {
+    let mut writer = match preset {
+        Some(preset) => AdaptiveWriter::with_preset(create_output(output)?, preset)?,
+        None => AdaptiveWriter::new(create_output(output)?)?,
+    };
+    io::copy(&mut input, &mut writer)?;
+    Ok(writer.finish()?.1)
+}
+
+fn encode_lz77(mut input: impl Read, output: &str, checksum: ChecksumKind) -> huffman_format::Result<Stats> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    let (encoded, stats) = compress_lz77_with_stats(&data, &Lz77Options { checksum, ..Lz77Options::default() })?;
+    write_output(output, &encoded)?;
+    Ok(stats)
+}
+
+// Tabela to nagłówek pierwszej ramki pliku; dekoder jej nie potrzebuje, bo ramki niosą ją same
+fn read_table(path: &str) -> huffman_format::Result<FixedTable> {
+    let header = Header::read_from(&mut BufReader::new(File::open(path)?))?;
+    FixedTable::from_header(&header).map_err(|e| HuffmanError::InvalidOptions(format!("--use-table {}: {}", path, e)))
+}
+
+// Model z pliku daje te same kody przy każdym uruchomieniu, niezależnie od kodowanych danych
+fn read_freq_table(path: &str, max_code_len: u8) -> huffman_format::Result<FixedTable> {
+    FreqModel::parse(&fs::read(path)?)
+        .and_then(|model| model.table(max_code_len))
+        .map_err(|e| HuffmanError::InvalidOptions(format!("--freq {}: {}", path, e)))
+}
+
+fn encode_with_table(
+    mut input: impl Read,
+    output: &str,
+    options: &Options,
+    table: &FixedTable,
+) -> huffman_format::Result<Stats> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    let table = covering_table(table, &data)?;
+    let (encoded, stats) = table.compress_with_stats(&data, options)?;
+    warn_table_cost(&table, &data, options, &stats)?;
+    write_output(output, &encoded)?;
+    Ok(stats)
+}
+
+// Pokrycie nie mówi nic o koszcie: tabela z innych danych może wydłużyć wejście,
+// a wtedy ramka idzie wprost (albo, z --no-store, wychodzi większa od oryginału)
+fn warn_table_cost(table: &FixedTable, data: &[u8], options: &Options, stats: &Stats) -> huffman_format::Result<()> {
+    let coded = table.estimate(data, &Options { store_fallback: false, ..*options })?;
+    let coded_len = coded.header_len + coded.payload_len + coded.footer_len;
+    if coded_len as u64 <= coded.original_len {
+        return Ok(());
+    }
+    if stats.header_len + stats.payload_len + stats.footer_len < coded_len {
+        warn!(
+            "gotowa tabela kodów wydłużyłaby dane ({} bajtów z {}); dane idą wprost, bez kodowania",
+            coded_len, coded.original_len
+        );
+    } else {
+        warn!("gotowa tabela kodów wydłuża dane ({} bajtów z {})", coded_len, coded.original_len);
+    }
+    Ok(())
+}
+
+// Tabela bez kodów części symboli wejścia (np. z plików sprzed zmiany danych) dostaje
+// kody ucieczki zamiast odrzucać wejście; symbole spoza niej idą wtedy wprost
+fn covering_table<'a>(table: &'a FixedTable, data: &[u8]) -> huffman_format::Result<Cow<'a, FixedTable>> {
+    match table.check_sample(data) {
+        Ok(()) => Ok(Cow::Borrowed(table)),
+        Err(e) if !table.has_escapes() => {
+            warn!("{}; dodano kody ucieczki", e);
+            Ok(Cow::Owned(table.with_escapes()?))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn read_dict(path: &str) -> huffman_format::Result<Dictionary> {
+    Dictionary::from_bytes(&fs::read(path)?)
+}
+
+fn encode_with_dict(
+    mut input: impl Read,
+    output: &str,
+    options: &Options,
+    dict: &Dictionary,
+) -> huffman_format::Result<(Stats, Coverage)> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    let (encoded, stats, coverage) = dict.compress_with_coverage(&data, options)?;
+    write_output(output, &encoded)?;
+    Ok((stats, coverage))
+}
+
+// Pokrycie danych przez słownik; poniżej RETRAIN_COVERAGE słownik nie pasuje już do danych
+fn report_coverage(to_stdout: bool, coverage: &Coverage, preset: Option<&str>) {
+    status(
+        to_stdout,
+        &format!(
+            "📚 Pokrycie słownika: {:.2}% symboli (ucieczki: {} symboli, {} bajtów, {:.2}% strumienia)",
+            100.0 * coverage.ratio(),
+            coverage.escaped,
+            coverage.escape_bits.div_ceil(8),
+            100.0 * coverage.escape_share()
+        ),
+    );
+    if coverage.ratio() < RETRAIN_COVERAGE {
+        let source = preset.map_or_else(|| "słownik".to_string(), |name| format!("wbudowany słownik {}", name));
+        warn!(
+            "{} ma kody tylko dla {:.1}% symboli, a ucieczki zajmują {:.1}% strumienia; \
+             wytrenuj słownik na podobnych danych: huff dict train <próbki> -o nowy.hufdict, potem --dict nowy.hufdict",
+            source,
+            100.0 * coverage.ratio(),
+            100.0 * coverage.escape_share()
+        );
+    }
+}
+
+// Wejście z jednego bloku koduje słownik logs, jeśli wychodzi krócej niż z własnym modelem:
+// krótki log nie płaci za tabelę kodów w nagłówku, a od kilkudziesięciu kB model z pliku
+// wygrywa ze słownikiem z próbki. Dłuższe dostaje bloki na końcach linii; pokrycie jest
+// tylko przy zapisanej ramce słownika.
+fn encode_logs(
+    mut input: impl BufRead,
+    output: &str,
+    options: &Options,
+    dict: &Dictionary,
+) -> huffman_format::Result<(Stats, Option<Coverage>)> {
+    let mut head = Vec::new();
+    (&mut input).take(DEFAULT_BLOCK_SIZE as u64 + 1).read_to_end(&mut head)?;
+    if head.len() <= DEFAULT_BLOCK_SIZE {
+        let (encoded, stats, coverage) = dict.compress_with_coverage(&head, options)?;
+        let own = estimate(&head, options)?;
+        if encoded.len() < own.header_len + own.payload_len + own.footer_len {
+            write_output(output, &encoded)?;
+            return Ok((stats, Some(coverage)));
+        }
+    }
+    let (stats, _) = encode_lines(io::Cursor::new(head).chain(input), output, options, None, false)?;
+    Ok((stats, None))
+}
+
+// Preset to liczności bajtów próbki; koder i dekoder muszą dostać ten sam plik
+fn read_preset(path: &str) -> huffman_format::Result<AdaptivePreset> {
+    Ok(AdaptivePreset::from_sample(&fs::read(path)?))
+}
+
+// Koduje blokami do kolejnych plików; nowy plik zaczyna się, gdy kolejny blok
+// podobnej wielkości przekroczyłby `rotate_size`. Każda część jest samodzielnym archiwum.
+fn encode_rotating(
+    mut input: impl Read,
+    output: &str,
+    rotate_size: Option<u64>,
+    options: &Options,
+    seek_table: bool,
+) -> huffman_format::Result<(Stats, u32)> {
+    // Przy małych częściach mniejsze bloki, żeby części trzymały się limitu
+    let block_size = rotate_size.map_or(DEFAULT_BLOCK_SIZE, |size| {
+        (size / 4).clamp(1, DEFAULT_BLOCK_SIZE as u64) as usize
+    });
+    let open_part = |parts| -> huffman_format::Result<HuffmanWriter<Box<dyn Write>>> {
+        let path = match rotate_size {
+            Some(_) => cli::format_part(output, parts).expect("wzorzec sprawdzony wcześniej"),
+            None => output.to_string(),
+        };
+        let w = HuffmanWriter::with_block_size(create_output(&path)?, *options, block_size).with_events(LogEvents);
+        Ok(if seek_table { w.with_seek_table() } else { w })
+    };
+    let mut stats = Stats::default();
+    let mut parts = 0;
+    let mut writer: Option<HuffmanWriter<Box<dyn Write>>> = None;
+
+    let mut block = Vec::with_capacity(block_size);
+    loop {
+        block.clear();
+        (&mut input).take(block_size as u64).read_to_end(&mut block)?;
+        if block.is_empty() {
+            break;
+        }
+
+        let w = match &mut writer {
+            Some(w) => w,
+            None => writer.insert(open_part(parts)?),
+        };
+        let before = w.total_out();
+        w.write_all(&block)?;
+        // Po każdym bloku dane trafiają na dysk - proces może działać tygodniami
+        w.flush()?;
+        let last_frame = w.total_out() - before;
+
+        if rotate_size.is_some_and(|size| w.total_out() + last_frame > size) {
+            let w = writer.take().expect("writer istnieje");
+            stats.merge(&w.finish_with_stats()?.1);
+            parts += 1;
+        }
+    }
+    // Puste wejście też daje plik: z jedną ramką bez danych
+    if parts == 0 && writer.is_none() {
+        writer = Some(open_part(0)?);
+    }
+    if let Some(w) = writer {
+        stats.merge(&w.finish_with_stats()?.1);
+        parts += 1;
+    }
+    Ok((stats, parts))
+}
+
+// Koduje blokami kończącymi się na granicy linii, żeby żaden wpis logu nie był
+// rozcięty między ramki; tylko linia dłuższa niż blok trafia do kilku ramek.
+// Z `interval` co tyle linii zaczyna się nowa ramka, a jej położenie trafia do indeksu.
+fn encode_lines(
+    mut input: impl BufRead,
+    output: &str,
+    options: &Options,
+    interval: Option<u64>,
+    seek_table: bool,
+) -> huffman_format::Result<(Stats, LineIndex)> {
+    let mut writer = HuffmanWriter::new(create_output(output)?, *options).with_events(LogEvents);
+    if seek_table {
+        writer = writer.with_seek_table();
+    }
+    let mut index = LineIndex { interval: interval.unwrap_or(0), checkpoints: Vec::new() };
+    let mut line = Vec::new();
+    let mut line_no = 0;
+    let mut pending = 0;
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let checkpoint = interval.is_some_and(|n| line_no % n == 0);
+        if pending > 0 && (checkpoint || pending + line.len() > DEFAULT_BLOCK_SIZE) {
+            writer.flush()?;
+            pending = 0;
+        }
+        if checkpoint {
+            index.checkpoints.push(LineCheckpoint {
+                line: line_no,
+                frame_offset: writer.total_out(),
+                data_offset: writer.total_in(),
+            });
+        }
+        writer.write_all(&line)?;
+        pending = (pending + line.len()) % DEFAULT_BLOCK_SIZE;
+        line_no += 1;
+    }
+    let (_, stats) = writer.finish_with_stats()?;
+    Ok((stats, index))
+}
+
+// Pliki z argumentów (katalogi rekurencyjnie) z nazwami, pod którymi trafią do archiwum
+fn archive_members(inputs: &[String], excludes: &[String]) -> huffman_format::Result<Vec<(PathBuf, String)>> {
+    let mut members = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| HuffmanError::InvalidOptions(format!("'{}' nie ma nazwy pliku", input)))?;
+        if path.is_dir() {
+            collect_dir(path, &name, excludes, &mut members)?;
+        } else if !matches_glob(&name, excludes) {
+            members.push((path.to_path_buf(), name));
+        }
+    }
+
+    let mut names: Vec<&str> = members.iter().map(|(_, name)| name.as_str()).collect();
+    names.sort_unstable();
+    if let Some(pair) = names.windows(2).find(|w| w[0] == w[1]) {
+        return Err(HuffmanError::InvalidOptions(format!("plik '{}' występuje w archiwum dwa razy", pair[0])));
+    }
+    Ok(members)
+}
+
+// Dopisuje pliki z `dir` w kolejności nazw; `prefix` to ścieżka katalogu w archiwum
+fn collect_dir(
+    dir: &Path,
+    prefix: &str,
+    excludes: &[String],
+    members: &mut Vec<(PathBuf, String)>,
+) -> huffman_format::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        if matches_glob(&name, excludes) {
+            debug!("Wykluczono {}", name);
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_dir(&path, &name, excludes, members)?;
+        } else if file_type.is_symlink() && path.is_dir() {
+            // Dowiązania do katalogów mogłyby zapętlić przechodzenie drzewa
+            warn!("Pominięto dowiązanie do katalogu {}", path.display());
+        } else {
+            members.push((path, name));
+        }
+    }
+    Ok(())
+}
+
+// Wzorzec bez `/` dotyczy samej nazwy, wzorzec z `/` - całej ścieżki w archiwum
+fn matches_glob(path: &str, patterns: &[String]) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    patterns.iter().any(|pattern| {
+        let subject = if pattern.contains('/') { path } else { file_name };
+        glob_match(pattern.as_bytes(), subject.as_bytes())
+    })
+}
+
+// `*` to dowolny ciąg znaków bez `/`, `?` to jeden znak, `**/` to zero lub więcej katalogów,
+// a `**` w innym miejscu - dowolny ciąg znaków
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    if let Some(rest) = pattern.strip_prefix(b"**/") {
+        return (0..=text.len())
+            .filter(|&i| i == 0 || text[i - 1] == b'/')
+            .any(|i| glob_match(rest, &text[i..]));
+    }
+    if let Some(rest) = pattern.strip_prefix(b"**") {
+        return (0..=text.len()).any(|i| glob_match(rest, &text[i..]));
+    }
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => {
+            (0..=text.len()).take_while(|&i| i == 0 || text[i - 1] != b'/').any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((&p, rest)) => match text.split_first() {
+            Some((&t, tail)) if p == t || (p == b'?' && t != b'/') => glob_match(rest, tail),
+            _ => false,
+        },
+    }
+}
+
+// Jak zapisać archiwum: szerokość symbolu wykrywana dla każdego pliku, jeden model, deduplikacja,
+// partie małych plików
+struct ArchiveLayout {
+    auto_unit: bool,
+    solid: bool,
+    dedup: bool,
+    batch_small: Option<u64>,
+}
+
+fn encode_archive(
+    inputs: &[String],
+    excludes: &[String],
+    output: &str,
+    options: &Options,
+    layout: ArchiveLayout,
+) -> huffman_format::Result<(Stats, usize, Dedup)> {
+    let ArchiveLayout { auto_unit, solid, dedup, batch_small } = layout;
+    let members = archive_members(inputs, excludes)?;
+    let paths = members.iter().map(|(_, name)| name.clone()).collect();
+    let count = members.len();
+    if solid {
+        let mut writer = SolidArchiveWriter::new(BufWriter::new(PartialFile::create(output)?), paths, *options)?;
+        for (path, name) in &members {
+            let len = writer.add_member(&mut BufReader::new(File::open(path)?))?;
+            debug!("{}: {} bajtów", name, len);
+        }
+        return Ok((writer.finish()?.1, count, Dedup::default()));
+    }
+    let (stats, shared) = if dedup {
+        // Powtórzone bloki są porównywane z odczytanymi z pliku, więc zapis idzie bez bufora
+        let writer = ArchiveWriter::new(PartialFile::create(output)?, paths)?.with_dedup(DEFAULT_BLOCK_SIZE)?;
+        add_archive_members(writer, &members, options, auto_unit, batch_small)?
+    } else {
+        let writer = ArchiveWriter::new(BufWriter::new(PartialFile::create(output)?), paths)?;
+        add_archive_members(writer, &members, options, auto_unit, batch_small)?
+    };
+    Ok((stats, count, shared))
+}
+
+fn add_archive_members<W: Write + Seek>(
+    mut writer: ArchiveWriter<W>,
+    members: &[(PathBuf, String)],
+    options: &Options,
+    auto_unit: bool,
+    batch_small: Option<u64>,
+) -> huffman_format::Result<(Stats, Dedup)> {
+    if let Some(limit) = batch_small {
+        writer = writer.with_batches(limit)?;
+    }
+    for (path, name) in members {
+        let unit = if auto_unit { detect_file_unit(&path.to_string_lossy())? } else { options.unit };
+        let stats = writer.add_member(&mut BufReader::new(File::open(path)?), &Options { unit, ..*options })?;
+        debug!("{}: {} -> {} bajtów", name, stats.original_len, stats.header_len + stats.payload_len);
+    }
+    let shared = writer.dedup();
+    Ok((writer.finish()?.1, shared))
+}
+
+fn cmd_decode(m: &Matches) -> Result<(), CliError> {
+    let report = m.value("report-append").map(|path| (path, Report::new("decode")));
+    let input = m.positional(0).unwrap_or(STDIO);
+    if m.flag("extract") || m.flag("only") {
+        if input == STDIO {
+            return Err(usage("decode: --extract wymaga pliku archiwum"));
+        }
+        let dest = m.positional(1).unwrap_or(".");
+        run_autotune(m, false)?;
+        let options = DecodeOptions {
+            input_chunk: parse_io_chunk(m)?,
+            max_output_len: parse_max_output(m)?,
+            lookup_bits: tune::load().lookup_bits,
+            verify_blocks: m.flag("verify-blocks"),
+            ..DecodeOptions::default()
+        };
+        let (members, bytes) = extract_archive(input, Path::new(dest), m.values("only"), options)?;
+        println!("✅ Rozpakowano {} ({} bajtów) do {}.", cli::pliki(members), bytes, dest);
+        if let Some((path, report)) = report {
+            report
+                .text("input", input)
+                .text("output", dest)
+                .number("members", members as f64)
+                .number("input_len", fs::metadata(input)?.len() as f64)
+                .number("output_len", bytes as f64)
+                .append(path, bytes)?;
+        }
+        return Ok(());
+    }
+    let output = if m.flag("stdout") { STDIO } else { m.positional(1).unwrap_or(STDIO) };
+    let to_stdout = output == STDIO;
+    run_autotune(m, to_stdout)?;
+
+    // Sygnaturę czytamy z już otwartego wejścia, bo standardowego wejścia nie da się otworzyć drugi raz
+    let mut source = open_input(input)?;
+    let mut prefix = Vec::with_capacity(DICT_PREFIX_LEN);
+    (&mut source).take(DICT_PREFIX_LEN as u64).read_to_end(&mut prefix)?;
+    if Archive::is_archive(&prefix) {
+        return Err(usage("decode: to archiwum wielu plików - użyj --extract [katalog]"));
+    }
+    let adaptive = prefix.starts_with(&ADAPTIVE_MAGIC);
+    let lz77 = prefix.starts_with(&LZ77_MAGIC);
+    // Ramki wbudowanych słowników (--preset english itd.) dekodują się bez --dict
+    let dict = match (Dictionary::required_by(&prefix), m.value("dict")) {
+        (Some(_), Some(path)) => Some(read_dict(path)?),
+        (Some(id), None) => match Dictionary::find_preset(id) {
+            Some((_, dict)) => Some(dict),
+            None => return Err(usage(format!("decode: plik wymaga słownika {:08x} (--dict)", id))),
+        },
+        (None, Some(_)) => return Err(usage("decode: --dict dotyczy plików z encode --dict")),
+        (None, None) => None,
+    };
+    let source = io::Cursor::new(prefix).chain(source);
+
+    let io_chunk = parse_io_chunk(m)?;
+    let options = DecodeOptions {
+        max_expansion_ratio: m.parse_value("max-ratio")?,
+        input_chunk: io_chunk,
+        constant_time: m.flag("constant-time"),
+        max_output_len: parse_max_output(m)?,
+        lookup_bits: tune::load().lookup_bits,
+        verify_blocks: m.flag("verify-blocks"),
+        ..DecodeOptions::default()
+    };
+
+    let range = m.value("range").map(cli::parse_byte_range).transpose()?;
+    let first = m.value("first").map(cli::parse_size).transpose()?;
+    if adaptive && (range.is_some() || m.flag("threads")) {
+        return Err(usage("decode: strumień adaptacyjny nie ma ramek - --range i --threads nie działają"));
+    }
+    if range.is_some() && first.is_some() {
+        return Err(usage("decode: --first i --range wykluczają się (--range 0:N to to samo)"));
+    }
+    let warm_start = m.value("warm-start").map(read_preset).transpose()?;
+    if warm_start.is_some() && !adaptive {
+        return Err(usage("decode: --warm-start dotyczy strumieni z encode --adaptive"));
+    }
+    if range.is_some() && input == STDIO {
+        return Err(usage("decode: --range skacze po pliku, więc nie działa na standardowym wejściu"));
+    }
+
+    if dict.is_some() && (range.is_some() || first.is_some() || m.flag("threads")) {
+        return Err(usage("decode: ramka słownika jest dekodowana w całości - bez --range, --first i --threads"));
+    }
+    if lz77 && (range.is_some() || first.is_some() || m.flag("threads")) {
+        return Err(usage("decode: ramka LZ77 jest dekodowana w całości - bez --range, --first i --threads"));
+    }
+
+    let result = match (range, parse_threads(m)?) {
+        _ if let Some(dict) = &dict => decode_with_dict(source, output, options, dict),
+        _ if lz77 => decode_lz77(source, output, options),
+        (Some((start, len)), _) => decode_range(input, output, start, len, io_chunk),
+        // Podgląd dekoduje tylko początek, więc nie ma czego zrównoleglać
+        _ if first.is_some() || adaptive => {
+            decode_streaming(source, adaptive, warm_start.as_ref(), output, options, first)
+        }
+        (None, Some(threads)) => decode_parallel(source, output, options, threads),
+        (None, None) => decode_streaming(source, adaptive, None, output, options, None),
+    };
+    let decoded = match result {
+        Ok(n) => n,
+        Err(e) => {
+            // Nie zostawiamy połowicznie zapisanego pliku
+            if !to_stdout {
+                let _ = fs::remove_file(output);
+            }
+            return Err(e.into());
+        }
+    };
+    status(to_stdout, &format!("✅ Zdekodowano {} bajtów.", decoded));
+    if let Some((path, report)) = report {
+        let input_len = if input == STDIO { None } else { fs::metadata(input).ok().map(|meta| meta.len()) };
+        report
+            .text("input", input)
+            .text("output", output)
+            .number("input_len", input_len.map_or(f64::NAN, |len| len as f64))
+            .number("output_len", decoded as f64)
+            .append(path, decoded as u64)?;
+    }
+    Ok(())
+}
+
+// Limit długości wyniku dekodowania z --max-output-size
+fn parse_max_output(m: &Matches) -> Result<Option<u64>, CliError> {
+    m.value("max-output-size").map(cli::parse_size).transpose()
+}
+
+// To samo sprawdzenie co w dekoderze, dla długości znanych przed dekodowaniem (katalog archiwum)
+fn check_output_limit(options: &DecodeOptions, output: u64) -> huffman_format::Result<()> {
+    match options.max_output_len {
+        Some(limit) if output > limit => {
+            Err(HuffmanError::ResourceLimit { resource: Resource::Output, limit, requested: output })
+        }
+        _ => Ok(()),
+    }
+}
+
+// Rozmiar porcji odczytu dla dekodera i buforów plików; bez flagi zostają domyślne
+fn parse_io_chunk(m: &Matches) -> Result<Option<usize>, CliError> {
+    Ok(m.value("io-chunk").map(cli::parse_size).transpose()?.map(|size| size as usize))
+}
+
+fn open_buffered(input: &str, io_chunk: Option<usize>) -> io::Result<BufReader<File>> {
+    let file = File::open(input)?;
+    Ok(match io_chunk {
+        Some(capacity) => BufReader::with_capacity(capacity, file),
+        None => BufReader::new(file),
+    })
+}
+
+fn is_archive_file(input: &str) -> huffman_format::Result<bool> {
+    has_prefix(input, &ARCHIVE_MAGIC)
+}
+
+fn has_prefix(input: &str, magic: &[u8]) -> huffman_format::Result<bool> {
+    let mut prefix = Vec::with_capacity(magic.len());
+    File::open(input)?.take(magic.len() as u64).read_to_end(&mut prefix)?;
+    Ok(prefix == magic)
+}
+
+// Odtwarza pliki archiwum pod `dest`; katalog archiwum odrzucił już ścieżki wychodzące poza `dest`
+// Z `only` rozpakowuje tylko pasujące pliki, przeskakując pozostałe bez dekodowania
+fn extract_archive(
+    input: &str,
+    dest: &Path,
+    only: &[String],
+    options: DecodeOptions,
+) -> huffman_format::Result<(usize, u64)> {
+    let mut file = open_buffered(input, options.input_chunk)?;
+    let archive = Archive::read_from(&mut file)?;
+    let selected: Vec<&Member> = archive
+        .members
+        .iter()
+        .filter(|member| only.is_empty() || matches_glob(&member.path, only))
+        .collect();
+    if selected.is_empty() && !archive.members.is_empty() {
+        return Err(HuffmanError::InvalidOptions(format!("żaden plik archiwum nie pasuje do {:?}", only)));
+    }
+    check_output_limit(&options, selected.iter().fold(0u64, |total, m| total.saturating_add(m.original_len)))?;
+    let mut total = 0;
+    if archive.solid {
+        // Jeden wspólny strumień: pliki leżą w nim po kolei, więc niewybrane trzeba
+        // zdekodować w próżnię, ale za ostatnim wybranym można przerwać
+        let last = archive.members.iter().rposition(|m| selected.contains(&m)).map_or(0, |i| i + 1);
+        let has_data = archive.members.iter().any(|m| m.original_len > 0);
+        let mut stream = if has_data { Some(HuffmanReader::with_options(file, options)?) } else { None };
+        for member in &archive.members[..last] {
+            let wanted = selected.contains(&member);
+            match stream.as_mut() {
+                Some(stream) if wanted => total += extract_member(member, dest, stream.take(member.original_len))?,
+                Some(stream) => {
+                    io::copy(&mut stream.take(member.original_len), &mut io::sink())?;
+                }
+                None => total += extract_member(member, dest, io::empty())?,
+            }
+        }
+    } else {
+        for member in &selected {
+            total += if member.compressed_len > 0 {
+                let mut content = HuffmanReader::with_options(archive.member_frames(&mut file, member)?, options)?;
+                if archive.batched {
+                    // Ramka partii: pomijamy wcześniejsze pliki i czytamy tylko ten
+                    io::copy(&mut (&mut content).take(member.batch_offset), &mut io::sink())?;
+                    extract_member(member, dest, content.take(member.original_len))?
+                } else {
+                    extract_member(member, dest, content)?
+                }
+            } else {
+                extract_member(member, dest, io::empty())?
+            };
+        }
+    }
+    Ok((selected.len(), total))
+}
+
+fn extract_member(member: &Member, dest: &Path, mut content: impl Read) -> huffman_format::Result<u64> {
+    let target = dest.join(&member.path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = BufWriter::new(PartialFile::create(&target)?);
+    let written = io::copy(&mut content, &mut out)?;
+    out.flush()?;
+    if written != member.original_len {
+        return Err(HuffmanError::TruncatedStream { decoded: written, expected: member.original_len });
+    }
+    debug!("{} ({} bajtów)", member.path, written);
+    Ok(written)
+}
+
+fn decode_range(
+    input: &str,
+    output: &str,
+    start: u64,
+    len: u64,
+    io_chunk: Option<usize>,
+) -> huffman_format::Result<u64> {
+    let decoded = decompress_range(&mut open_buffered(input, io_chunk)?, start, len)?;
+    write_output(output, &decoded)?;
+    Ok(decoded.len() as u64)
+}
+
+fn decode_parallel(
+    mut input: impl Read,
+    output: &str,
+    options: DecodeOptions,
+    threads: usize,
+) -> huffman_format::Result<u64> {
+    let mut content = Vec::new();
+    input.read_to_end(&mut content)?;
+    let decoded = decompress_parallel(&content, &options, threads)?;
+    write_output(output, &decoded)?;
+    Ok(decoded.len() as u64)
+}
+
+// Sygnatura z wersją i identyfikatorem słownika; starczy też na sygnatury archiwum i HADP
+const DICT_PREFIX_LEN: usize = DICT_FRAME_MAGIC.len() + 5;
+
+fn decode_with_dict(
+    mut input: impl Read,
+    output: &str,
+    options: DecodeOptions,
+    dict: &Dictionary,
+) -> huffman_format::Result<u64> {
+    let mut frame = Vec::new();
+    input.read_to_end(&mut frame)?;
+    let decoded = dict.decompress(&frame, &options)?;
+    write_output(output, &decoded)?;
+    Ok(decoded.len() as u64)
+}
+
+// Odległości dopasowań sięgają 32 KiB wstecz, więc ramka jest dekodowana w pamięci
+fn decode_lz77(mut input: impl Read, output: &str, options: DecodeOptions) -> huffman_format::Result<u64> {
+    let mut frame = Vec::new();
+    input.read_to_end(&mut frame)?;
+    let decoded = decompress_lz77(&frame, &options)?;
+    write_output(output, &decoded)?;
+    Ok(decoded.len() as u64)
+}
+
+// Dane trafiają do wyjścia w kolejności i na bieżąco; z `first` reszta pliku nie jest dekodowana
+fn decode_streaming(
+    input: impl Read + 'static,
+    adaptive: bool,
+    preset: Option<&AdaptivePreset>,
+    output: &str,
+    options: DecodeOptions,
+    first: Option<u64>,
+) -> huffman_format::Result<u64> {
+    let reader: Box<dyn Read> = if adaptive {
+        // Dekoder adaptacyjny pobiera bity po bajcie, więc porcje odczytu ustala bufor
+        let input = BufReader::with_capacity(options.input_chunk.unwrap_or(8 * 1024), input);
+        match preset {
+            Some(preset) => Box::new(AdaptiveReader::with_preset(input, preset)?),
+            None => Box::new(AdaptiveReader::new(input)?),
+        }
+    } else {
+        Box::new(HuffmanReader::with_options(input, options)?)
+    };
+    // Strumień adaptacyjny nie deklaruje długości: bajt ponad limit oznacza jego przekroczenie
+    let limit = match (adaptive, options.max_output_len) {
+        (true, Some(limit)) => limit.saturating_add(1),
+        _ => u64::MAX,
+    };
+    let mut writer = create_output(output)?;
+    let decoded = io::copy(&mut reader.take(first.unwrap_or(u64::MAX).min(limit)), &mut writer)?;
+    writer.flush()?;
+    check_output_limit(&options, decoded)?;
+    Ok(decoded)
+}
+
+fn cmd_list(m: &Matches) -> Result<(), CliError> {
+    let input = m.positional(0).ok_or_else(|| usage("list: brak pliku wejściowego"))?;
+    if !is_archive_file(input)? {
+        return Err(usage("list: to nie jest archiwum wielu plików"));
+    }
+    let file = File::open(input)?;
+    let file_len = file.metadata()?.len();
+    let archive = Archive::read_from(&mut BufReader::new(file))?;
+
+    let mut stdout = io::stdout().lock();
+    let result = print_members(&mut stdout, &archive, file_len);
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+// W archiwum solid dane plików nie są rozdzielone, więc rozmiar skompresowany jest tylko w sumie
+fn print_members(out: &mut impl Write, archive: &Archive, file_len: u64) -> io::Result<()> {
+    let members = &archive.members;
+    // Stopień kompresji jak w podsumowaniu encode: ile procent oryginału zaoszczędzono
+    let ratio = |original: u64, compressed: u64| match original {
+        0 => "-".to_string(),
+        _ => format!("{:.1}%", 100.0 - compressed as f64 * 100.0 / original as f64),
+    };
+    writeln!(out, "{:>12} {:>12} {:>8}  Nazwa", "Oryginał", "Skompr.", "Kompr.")?;
+    writeln!(out, "{:->12} {:->12} {:->8}  {:-<20}", "", "", "", "")?;
+    // Pliki jednej partii (--batch-small) dzielą ramkę, więc nie mają własnej długości danych
+    let mut frames: HashMap<(u64, u64), usize> = HashMap::new();
+    for member in members.iter().filter(|_| archive.batched) {
+        *frames.entry((member.offset, member.compressed_len)).or_default() += 1;
+    }
+    for member in members {
+        let in_batch = frames.get(&(member.offset, member.compressed_len)).is_some_and(|&n| n > 1);
+        // Plik z listą bloków (--dedup) może dzielić każdy z nich z innymi plikami
+        let (compressed, member_ratio) = if archive.solid || in_batch || member.blocks > 0 {
+            ("-".to_string(), "-".to_string())
+        } else {
+            (member.compressed_len.to_string(), ratio(member.original_len, member.compressed_len))
+        };
+        writeln!(out, "{:>12} {:>12} {:>8}  {}", member.original_len, compressed, member_ratio, member.path)?;
+    }
+    let original: u64 = members.iter().map(|m| m.original_len).sum();
+    // Ramki bloków z --dedup wskazują tylko listy bloków, więc liczy się wszystko za katalogiem
+    let compressed = if archive.solid || archive.deduped {
+        file_len - archive.byte_len() as u64
+    } else {
+        // Dane wspólne dla kilku plików (--dedup, --batch-small) liczą się raz
+        let shared: HashSet<(u64, u64)> = members.iter().map(|m| (m.offset, m.compressed_len)).collect();
+        shared.iter().map(|&(_, len)| len).sum()
+    };
+    writeln!(out, "{:->12} {:->12} {:->8}  {:-<20}", "", "", "", "")?;
+    writeln!(
+        out,
+        "{:>12} {:>12} {:>8}  {}",
+        original,
+        compressed,
+        ratio(original, compressed),
+        cli::pliki(members.len())
+    )?;
+    out.flush()
+}
+
+fn cmd_info(m: &Matches) -> Result<(), CliError> {
+    let input = m.positional(0).ok_or_else(|| usage("info: brak pliku wejściowego"))?;
+    let mut stdout = io::stdout().lock();
+    if m.flag("automaton") {
+        if is_archive_file(input)? || has_prefix(input, &ADAPTIVE_MAGIC)? || has_prefix(input, &LZ77_MAGIC)? {
+            return Err(usage("info: --automaton opisuje ramki .huff, nie archiwum, strumień adaptacyjny i ramki LZ77"));
+        }
+        let header = Header::read_from(&mut BufReader::new(File::open(input)?))?;
+        write!(stdout, "{}", DecodeAutomaton::from_header(&header)?)?;
+        return Ok(());
+    }
+    print_info(&mut stdout, input)?;
+    Ok(())
+}
+
+// Sygnatura, wersja i suma kontrolna ramki LZ77, a za nimi u64 długość oryginału
+const LZ77_PREFIX_LEN: usize = LZ77_MAGIC.len() + 10;
+
+// Czyta tylko nagłówki: długość danych pierwszej ramki zna z `payload_bits`, a kolejne
+// ramki przeskakuje; w plikach sprzed v3 nie da się policzyć ramek bez dekodowania
+fn print_info(out: &mut impl Write, input: &str) -> huffman_format::Result<()> {
+    let mut file = BufReader::new(File::open(input)?);
+    let file_len = file.get_ref().metadata()?.len();
+    let mut prefix = Vec::with_capacity(LZ77_PREFIX_LEN);
+    (&mut file).take(LZ77_PREFIX_LEN as u64).read_to_end(&mut prefix)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    writeln!(out, "Plik:              {} ({} bajtów)", input, file_len)?;
+    if Archive::is_archive(&prefix) {
+        let archive = Archive::read_from(&mut file)?;
+        let original: u64 = archive.members.iter().map(|m| m.original_len).sum();
+        let layout = match (archive.solid, archive.batched, archive.deduped) {
+            (true, _, _) => " (solid)",
+            (false, true, true) => " (partie małych plików, deduplikacja bloków)",
+            (false, false, true) => " (deduplikacja bloków)",
+            (false, true, false) => " (partie małych plików)",
+            (false, false, false) => "",
+        };
+        writeln!(out, "Format:            archiwum HARC{}", layout)?;
+        writeln!(out, "Pliki:             {} (lista: huff list)", archive.members.len())?;
+        writeln!(out, "Długość oryginału: {} bajtów", original)?;
+        writeln!(out, "Katalog:           {} bajtów", archive.byte_len())?;
+        return Ok(());
+    }
+    if prefix.starts_with(&ADAPTIVE_MAGIC) {
+        writeln!(out, "Format:            strumień adaptacyjny HADP v{}", prefix.get(4).copied().unwrap_or(0))?;
+        if let (Some(2), Some(id)) = (prefix.get(4), prefix.get(5..9)) {
+            writeln!(out, "Model startowy:    {}", id.iter().map(|b| format!("{:02x}", b)).collect::<String>())?;
+        }
+        writeln!(out, "Długość oryginału: nieznana bez dekodowania (brak nagłówka z długością)")?;
+        return Ok(());
+    }
+    if prefix.starts_with(&LZ77_MAGIC) {
+        writeln!(out, "Format:            ramka LZ77 HLZ7 v{} (okno 32 KiB, tabele Huffmana w blokach)", prefix[4])?;
+        let original = prefix.get(6..LZ77_PREFIX_LEN).ok_or_else(|| {
+            let len = prefix.len();
+            HuffmanError::MalformedHeader(format!("ramka LZ77 ma {} bajtów, nagłówek {}", len, LZ77_PREFIX_LEN))
+        })?;
+        writeln!(out, "Długość oryginału: {} bajtów", u64::from_be_bytes(original.try_into().expect("8 bajtów")))?;
+        return Ok(());
+    }
+    if let Some(id) = Dictionary::required_by(&prefix) {
+        match Dictionary::find_preset(id) {
+            Some((name, _)) => {
+                writeln!(out, "Format:            ramka słownika HDCT (wbudowany model, huff decode go rozpozna)")?;
+                writeln!(out, "Słownik:           {:08x} (--preset {})", id, name)?;
+            }
+            None => {
+                writeln!(out, "Format:            ramka słownika HDCT (dekodowanie: huff decode --dict)")?;
+                writeln!(out, "Słownik:           {:08x}", id)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let header = Header::read_from(&mut file)?;
+    let header_len = file.stream_position()?;
+    let version = Header::peek_version(&prefix).unwrap_or_else(|| header.version());
+    let contexts = match &header.model {
+        Model::Legacy(tables) => tables.len(),
+        Model::Frequencies(tables) => tables.len(),
+        Model::CodeLengths(tables) => tables.len(),
+    };
+    match header.model {
+        Model::Legacy(_) => writeln!(out, "Format:            .huff sprzed v0 (bez limitu kodu; huff convert)")?,
+        _ => writeln!(out, "Format:            .huff v{}", version)?,
+    }
+    writeln!(out, "Rząd modelu:       {}", header.order)?;
+    if header.nibble {
+        writeln!(out, "Szerokość symbolu: 4 bity")?;
+    } else {
+        writeln!(out, "Szerokość symbolu: {} B", header.unit)?;
+    }
+    for (filter, coded_len) in &header.filters {
+        writeln!(out, "Filtr:             {} ({} bajtów po filtrze)", filter, coded_len)?;
+    }
+    match header.model {
+        Model::Legacy(_) => writeln!(out, "Limit kodu:        brak (kody prosto z drzewa Huffmana)")?,
+        _ => writeln!(out, "Limit kodu:        {} bitów", header.max_code_len)?,
+    }
+    writeln!(out, "Konteksty:         {}", contexts)?;
+    writeln!(out, "Wpisy tabeli:      {}", header.table_entries())?;
+    if let (true, Model::CodeLengths(tables)) = (header.escape, &header.model) {
+        let escapes = tables.values().filter(|t| t.contains_key(ESCAPE)).count();
+        if tables.is_empty() {
+            writeln!(out, "Kody ucieczki:     brak tablic, wszystkie symbole zapisane wprost")?;
+        } else {
+            writeln!(out, "Kody ucieczki:     {} kontekstów (rzadkie symbole zapisane wprost)", escapes)?;
+        }
+    }
+    writeln!(out, "Nagłówek:          {} bajtów", header_len)?;
+
+    let Some(bits) = header.payload_bits else {
+        writeln!(out, "Długość oryginału: {} bajtów (pierwsza ramka)", header.original_len)?;
+        writeln!(out, "Dane:              nieznane bez dekodowania (plik sprzed v3)")?;
+        write_memory(out, &estimate_decode_memory(&header))?;
+        return Ok(());
+    };
+    writeln!(out, "Dane:              {} bajtów ({} bitów)", bits.div_ceil(8), bits)?;
+    match header.checksum {
+        ChecksumKind::Crc32 => {
+            file.seek(SeekFrom::Start(header_len + bits.div_ceil(8)))?;
+            let mut footer = [0u8; 4];
+            file.read_exact(&mut footer)?;
+            writeln!(out, "Suma kontrolna:    CRC-32 {:08x}", u32::from_be_bytes(footer))?;
+        }
+        ChecksumKind::None => writeln!(out, "Suma kontrolna:    brak")?,
+        other => writeln!(out, "Suma kontrolna:    {:?}", other)?,
+    }
+
+    let frames = frame_index(&mut file)?;
+    let original: u64 = frames.iter().map(|f| f.original_len).sum();
+    writeln!(out, "Długość oryginału: {} bajtów", original)?;
+    if frames.len() > 1 {
+        // Kolejne ramki mogą mieć inny model, więc nie udajemy, że opis wyżej dotyczy całości
+        let line =
+            format!("{} po {} bajtów (nagłówek i dane wyżej - pierwsza ramka)", frames.len(), header.original_len);
+        writeln!(out, "Ramki:             {}", line)?;
+    }
+    // Tablice pierwszej ramki, ale wynik wszystkich, bo decompress trzyma go w jednym buforze
+    write_memory(out, &MemoryEstimate { output: original, ..estimate_decode_memory(&header) })?;
+    if let Some((table, _)) = SeekTable::read_from_end(&mut file)? {
+        writeln!(out, "Tablica skoków:    {} wpisów", table.entries.len())?;
+    }
+    let metadata = read_metadata(&mut file)?;
+    if let Some(name) = &metadata.name {
+        writeln!(out, "Nazwa oryginału:   {}", name)?;
+    }
+    if let Some(comment) = &metadata.comment {
+        writeln!(out, "Komentarz:         {}", comment)?;
+    }
+    writeln!(out, "Kompresja:         {:.2}%", 100.0 - file_len as f64 * 100.0 / original.max(1) as f64)?;
+    Ok(())
+}
+
+fn write_memory(out: &mut impl Write, memory: &MemoryEstimate) -> io::Result<()> {
+    writeln!(
+        out,
+        "Pamięć dekodera:   {} bajtów strumieniowo (tablice {}, bufory {}), {} z wynikiem w pamięci",
+        memory.streaming(),
+        memory.tables,
+        memory.buffers,
+        memory.total()
+    )
+}
+
+// Blok metadanych leży za ramkami, więc plik zmienia się tylko od końca ostatniej ramki
+fn cmd_set_meta(m: &Matches) -> Result<(), CliError> {
+    let input = m.positional(0).ok_or_else(|| usage("set-meta: brak pliku wejściowego"))?;
+    let (comment, name) = (m.value("comment"), m.value("name"));
+    if comment.is_none() && name.is_none() {
+        return Err(usage("set-meta: podaj --comment lub --name"));
+    }
+    if !has_prefix(input, &MAGIC)? {
+        return Err(usage("set-meta: metadane mają tylko pliki z ramkami .huff (nie archiwa, HDCT, HLZ7, HADP)"));
+    }
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(input)?;
+    let mut metadata = read_metadata(&mut file)?;
+    // Pusty tekst usuwa wpis
+    let set = |field: &mut Option<String>, value: Option<&str>| {
+        if let Some(value) = value {
+            *field = (!value.is_empty()).then(|| value.to_string());
+        }
+    };
+    set(&mut metadata.comment, comment);
+    set(&mut metadata.name, name);
+    let len = set_metadata(&mut file, &metadata)?;
+    file.set_len(len)?;
+    println!("✅ Zapisano metadane {} ({} bajtów).", input, len);
+    Ok(())
+}
+
+// Histogram bez --verbose pokazuje tylko najczęstsze bajty
+const HISTOGRAM_TOP: usize = 16;
+
+fn cmd_test(m: &Matches) -> Result<(), CliError> {
+    let stdin_only = [STDIO.to_string()];
+    let inputs = if m.positionals.is_empty() { &stdin_only[..] } else { &m.positionals[..] };
+    let preset = m.value("warm-start").map(read_preset).transpose()?;
+
+    let mut failed = 0;
+    for input in inputs {
+        let mut source = match open_input(input) {
+            Ok(inner) => Counted { inner, count: 0 },
+            Err(e) => {
+                failed += 1;
+                eprintln!("❌ {}: {}", input, e);
+                continue;
+            }
+        };
+        match test_stream(&mut source, preset.as_ref()) {
+            Ok(decoded) => println!("✅ {}: OK ({} bajtów po dekompresji)", input, decoded),
+            Err(e) => {
+                failed += 1;
+                eprintln!("❌ {}: błąd po przeczytaniu {} bajtów pliku: {}", input, source.count, e);
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        n => Err(CliError::Failed(format!("uszkodzone pliki: {} z {}", n, inputs.len()))),
+    }
+}
+
+// Dekoduje plik w próżnię: dekodery sprawdzają sumy kontrolne ramek i długości zapisane
+// w nagłówkach, a przy archiwum także długości z katalogu
+fn test_stream(source: &mut impl Read, preset: Option<&AdaptivePreset>) -> huffman_format::Result<u64> {
+    let mut prefix = Vec::with_capacity(ARCHIVE_MAGIC.len());
+    source.take(ARCHIVE_MAGIC.len() as u64).read_to_end(&mut prefix)?;
+    let empty = prefix.is_empty();
+    let (archive, adaptive) = (Archive::is_archive(&prefix), prefix == ADAPTIVE_MAGIC);
+    let lz77 = prefix == LZ77_MAGIC;
+    let mut data = io::Cursor::new(prefix).chain(source);
+    // Pusty plik to zakodowane puste wejście
+    Ok(if empty {
+        0
+    } else if archive {
+        test_archive(&mut data)?
+    } else if adaptive {
+        match preset {
+            Some(preset) => io::copy(&mut AdaptiveReader::with_preset(data, preset)?, &mut io::sink())?,
+            None => io::copy(&mut AdaptiveReader::new(data)?, &mut io::sink())?,
+        }
+    } else if lz77 {
+        let mut frame = Vec::new();
+        data.read_to_end(&mut frame)?;
+        decompress_lz77(&frame, &DecodeOptions::default())?.len() as u64
+    } else {
+        io::copy(&mut HuffmanReader::new(data)?, &mut io::sink())?
+    })
+}
+
+// Czyta archiwum po kolei, bez skoków, więc działa też na standardowym wejściu
+fn test_archive(input: &mut impl Read) -> huffman_format::Result<u64> {
+    let archive = Archive::read_from(input)?;
+    let expected: u64 = archive.members.iter().map(|m| m.original_len).sum();
+    if archive.solid {
+        let decoded = if expected > 0 { io::copy(&mut HuffmanReader::new(input)?, &mut io::sink())? } else { 0 };
+        if decoded != expected {
+            return Err(HuffmanError::TruncatedStream { decoded, expected });
+        }
+        return Ok(decoded);
+    }
+    let mut members: Vec<&Member> = archive.members.iter().filter(|m| m.compressed_len > 0).collect();
+    members.sort_by_key(|m| m.offset);
+    let mut pos = archive.byte_len() as u64;
+    // Plik mieści się w zdekodowanych danych: cały je wypełnia albo, w partii, leży w nich od `batch_offset`
+    let end = |member: &Member| member.batch_offset.saturating_add(member.original_len);
+    let fits = |member: &Member, decoded: u64| match archive.batched {
+        true => end(member) <= decoded,
+        false => member.original_len == decoded,
+    };
+    // Zdekodowane już odcinki danych -> plik, który je zapisał, i długość po dekodowaniu
+    let mut decoded_runs: HashMap<(u64, u64), (&str, u64)> = HashMap::new();
+    for member in members {
+        let gap = member.offset.checked_sub(pos).ok_or_else(|| {
+            HuffmanError::MalformedHeader(format!("dane pliku '{}' nachodzą na poprzedni", member.path))
+        });
+        if member.blocks > 0 {
+            io::copy(&mut input.take(gap?), &mut io::sink())?;
+            let blocks = member.read_blocks(input)?;
+            pos = member.offset + member.compressed_len;
+            // Nowe ramki leżą zaraz za listą, a powtórzone wskazują zdekodowane wcześniej
+            let mut decoded = 0u64;
+            for block in blocks {
+                decoded += match decoded_runs.get(&block) {
+                    Some(&(_, len)) => len,
+                    None if block.0 == pos => {
+                        let len = io::copy(&mut HuffmanReader::new(input.take(block.1))?, &mut io::sink())?;
+                        pos += block.1;
+                        decoded_runs.insert(block, (&member.path, len));
+                        len
+                    }
+                    None => {
+                        return Err(HuffmanError::MalformedHeader(format!(
+                            "blok pliku '{}' z offsetem {} nie wskazuje zapisanej ramki",
+                            member.path, block.0
+                        )));
+                    }
+                };
+            }
+            if decoded != member.original_len {
+                return Err(HuffmanError::TruncatedStream { decoded, expected: member.original_len });
+            }
+            debug!("{}: OK", member.path);
+            continue;
+        }
+        // Plik z --dedup wskazuje dane wcześniejszego: te same bajty dają tę samą długość
+        let data = (member.offset, member.compressed_len);
+        if let Some((shared, decoded)) = decoded_runs.get(&data).copied() {
+            if archive.batched && !fits(member, decoded) {
+                return Err(HuffmanError::MalformedHeader(format!(
+                    "plik '{}' wychodzi poza dane partii z '{}'",
+                    member.path, shared
+                )));
+            }
+            if !fits(member, decoded) {
+                return Err(HuffmanError::MalformedHeader(format!(
+                    "pliki '{}' i '{}' mają wspólne dane, ale różne długości",
+                    shared, member.path
+                )));
+            }
+            continue;
+        }
+        io::copy(&mut input.take(gap?), &mut io::sink())?;
+        let decoded = io::copy(&mut HuffmanReader::new(input.take(member.compressed_len))?, &mut io::sink())?;
+        if !fits(member, decoded) {
+            return Err(HuffmanError::TruncatedStream { decoded, expected: end(member) });
+        }
+        debug!("{}: OK", member.path);
+        pos = member.offset + member.compressed_len;
+        decoded_runs.insert(data, (&member.path, decoded));
+    }
+    Ok(expected)
+}
+
+fn cmd_convert(m: &Matches) -> Result<(), CliError> {
+    let (Some(input), Some(output)) = (m.positional(0), m.positional(1)) else {
+        return Err(usage("convert: podaj plik wejściowy i wyjściowy"));
+    };
+    if input == output {
+        return Err(usage("convert: plik wyjściowy musi być inny niż wejściowy"));
+    }
+    let mut source = BufReader::new(File::open(input)?);
+    let header = Header::read_from(&mut source)?;
+    source.seek(SeekFrom::Start(0))?;
+    let version = match header.model {
+        Model::Legacy(_) => "sprzed v0".to_string(),
+        _ => format!("v{}", Header::peek_version(source.fill_buf()?).unwrap_or_else(|| header.version())),
+    };
+
+    // Bez --order zostaje rząd modelu z pliku wejściowego
+    let order = m.parse_value::<usize>("order")?.unwrap_or(header.order).min(255);
+    let checksum = m
+        .value("checksum")
+        .map(|name| name.parse::<ChecksumKind>().map_err(usage))
+        .transpose()?
+        .unwrap_or_default();
+    let options = Options { order, checksum, ..Options::default() };
+
+    let sizes = convert_stream(source, output, options);
+    let (read, written) = match sizes {
+        Ok(sizes) => sizes,
+        Err(e) => {
+            let _ = fs::remove_file(output);
+            return Err(e.into());
+        }
+    };
+    println!(
+        "✅ Przepisano {} bajtów danych ({} -> v{}): {} -> {} bajtów.",
+        read,
+        version,
+        FORMAT_VERSION,
+        fs::metadata(input)?.len(),
+        written
+    );
+    Ok(())
+}
+
+// Dekoduje wszystkie ramki wejścia i koduje je od nowa; zwraca długość danych i wyniku
+fn convert_stream(source: impl Read, output: &str, options: Options) -> huffman_format::Result<(u64, u64)> {
+    let mut reader = HuffmanReader::new(source)?;
+    let mut writer = HuffmanWriter::new(create_output(output)?, options).with_events(LogEvents);
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    let (read, written) = (writer.total_in(), writer.total_out());
+    writer.finish()?;
+    Ok((read, written))
+}
+
+fn cmd_stats(m: &Matches) -> Result<(), CliError> {
+    let input = m.positional(0).ok_or_else(|| usage("stats: brak pliku wejściowego"))?;
+    let max_order: usize = m.parse_value("order")?.unwrap_or(2);
+    if max_order > 255 {
+        return Err(usage("stats: maksymalny rząd to 255"));
+    }
+    let mut data = Vec::new();
+    open_input(input)?.read_to_end(&mut data)?;
+    if data.is_empty() {
+        println!("Plik jest pusty.");
+        return Ok(());
+    }
+    let mut stdout = io::stdout().lock();
+    let top = if m.flag("verbose") { None } else { Some(m.parse_value("top")?.unwrap_or(HISTOGRAM_TOP)) };
+    let result = print_stats(&mut stdout, &data, max_order)
+        .and_then(|()| print_histogram(&mut stdout, &data, top, m.flag("chart")))
+        .and_then(|()| match m.value("export-freq") {
+            Some(path) => export_freq(&mut stdout, &data, max_order, path),
+            None => Ok(()),
+        });
+    match result {
+        Err(HuffmanError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+// Model najwyższego analizowanego rzędu; rozszerzenie .json wybiera postać do czytania
+fn export_freq(out: &mut impl Write, data: &[u8], order: usize, path: &str) -> huffman_format::Result<()> {
+    let model = FreqModel::count(data, order, 1)?;
+    let json = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    fs::write(path, if json { model.to_json().into_bytes() } else { model.to_bytes() })?;
+    writeln!(out, "\n💾 Zapisano częstotliwości rzędu {} ({} kontekstów) do {}", order, model.tables.len(), path)?;
+    Ok(())
+}
+
+// Dla każdego rzędu: entropia warunkowa, minimum wynikające z entropii, dokładna długość
+// danych Huffmana z tymi samymi długościami kodów co encode i rozmiar nagłówka z tabelą
+fn print_stats(out: &mut impl Write, data: &[u8], max_order: usize) -> huffman_format::Result<()> {
+    let len = data.len() as u64;
+    let distinct = collect_frequencies(data, 0).values().map(|freqs| freqs.len()).sum::<usize>();
+    writeln!(out, "Długość: {} bajtów, różnych bajtów: {}\n", len, distinct)?;
+
+    writeln!(
+        out,
+        "{:>4} {:>9} {:>12} {:>12} {:>7} {:>10} {:>12} {:>9}",
+        "Rząd", "Entropia", "Minimum", "Huffman", "Narzut", "Nagłówek", "Plik .huff", "Kompr."
+    )?;
+    let mut best: Option<(usize, u64)> = None;
+    for order in 0..=max_order {
+        let frequencies = collect_frequencies(data, order);
+        let entropy = conditional_entropy(&frequencies, len);
+        let lengths = build_code_lengths(&frequencies, DEFAULT_MAX_CODE_LEN)?;
+        let bits = payload_bits(&frequencies, &lengths);
+        let header = Header {
+            original_len: len,
+            order,
+            max_code_len: DEFAULT_MAX_CODE_LEN,
+            unit: 1,
+            payload_bits: Some(bits),
+            checksum: ChecksumKind::default(),
+            escape: false,
+            nibble: false,
+            filters: Vec::new(),
+            model: Model::CodeLengths(lengths),
+        };
+        let header_len = header.to_bytes().len() as u64;
+        let minimum = entropy * len as f64 / 8.0;
+        let file_len = header_len + bits.div_ceil(8) + ChecksumKind::default().footer_len() as u64;
+        // Narzut Huffmana względem entropii: kody mają całkowitą liczbę bitów
+        let overhead = if minimum > 0.0 { (bits as f64 / 8.0 / minimum - 1.0) * 100.0 } else { 0.0 };
+        writeln!(
+            out,
+            "{:>4} {:>9.4} {:>10.0} B {:>10} B {:>6.2}% {:>8} B {:>10} B {:>8.2}%",
+            order,
+            entropy,
+            minimum,
+            bits.div_ceil(8),
+            overhead,
+            header_len,
+            file_len,
+            100.0 - file_len as f64 * 100.0 / len as f64
+        )?;
+        if best.is_none_or(|(_, size)| file_len < size) {
+            best = Some((order, file_len));
+        }
+    }
+    match best {
+        Some((_, size)) if size >= len => {
+            writeln!(out, "\nKompresja się nie opłaca: każdy rząd daje plik większy niż oryginał.")?
+        }
+        Some((order, size)) => writeln!(out, "\nNajlepiej: --order {} ({} -> {} bajtów)", order, len, size)?,
+        None => {}
+    }
+    Ok(())
+}
+
+// Najczęstsze bajty z długościami kodów rzędu 0; z `chart` także jako wykres słupkowy.
+// Częste bajty dostają krótkie kody - im bardziej nierówne słupki, tym lepsza kompresja.
+fn print_histogram(out: &mut impl Write, data: &[u8], top: Option<usize>, chart: bool) -> huffman_format::Result<()> {
+    let frequencies = collect_frequencies(data, 0);
+    let lengths = build_code_lengths(&frequencies, DEFAULT_MAX_CODE_LEN)?;
+    let (Some(counts), Some(lengths)) = (frequencies.get(&[][..]), lengths.get(&[][..])) else {
+        return Ok(());
+    };
+    let mut histogram: Vec<(u8, u64, u8)> =
+        counts.iter().map(|(symbol, &count)| (symbol[0], count, lengths[symbol])).collect();
+    histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let shown = top.map_or(histogram.len(), |n| n.min(histogram.len()));
+    writeln!(out, "\nNajczęstsze bajty ({} z {}) i długości ich kodów rzędu 0:", shown, histogram.len())?;
+
+    // Słupek zajmuje to, co zostaje z szerokości terminala po kolumnach z liczbami
+    let columns = std::env::var("COLUMNS").ok().and_then(|c| c.parse::<usize>().ok()).unwrap_or(80);
+    let bar_width = columns.saturating_sub(48).max(10) as u64;
+    let largest = histogram[0].1;
+    for &(byte, count, code_len) in &histogram[..shown] {
+        let label = match byte {
+            b' '..=b'~' => format!("'{}'", byte as char),
+            _ => format!("0x{:02x}", byte),
+        };
+        let percent = count as f64 * 100.0 / data.len() as f64;
+        write!(out, "{:>6} {:>12} {:>6.2}% {:>3} b", label, count, percent, code_len)?;
+        if chart {
+            let bar = "█".repeat((count * bar_width).div_ceil(largest) as usize);
+            let code = "▪".repeat(code_len as usize);
+            write!(out, "  {:<width$}  {}", bar, code, width = bar_width as usize)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Słownik koduje wiele plików, więc większy model niż domyślny rząd 0 zwraca się szybko
+const DICT_DEFAULT_ORDER: usize = 2;
+
+fn cmd_dict(m: &Matches) -> Result<(), CliError> {
+    match m.positional(0) {
+        Some("train") => {}
+        Some(other) => return Err(usage(format!("dict: nieznana operacja '{}' (dostępne: train)", other))),
+        None => return Err(usage("dict: brak operacji (huff dict train <plik>... -o <słownik>)")),
+    }
+    let corpus = &m.positionals[1..];
+    if corpus.is_empty() {
+        return Err(usage("dict train: brak plików do treningu"));
+    }
+    let output = m.value("output").ok_or_else(|| usage("dict train: podaj plik słownika przez -o"))?;
+    let order: usize = m.parse_value("order")?.unwrap_or(DICT_DEFAULT_ORDER);
+    if order > 255 {
+        return Err(usage("dict train: maksymalny rząd to 255"));
+    }
+    let unit = match m.value("unit").unwrap_or("auto") {
+        "auto" => 1,
+        other => match other.parse::<u8>() {
+            Ok(unit @ 1..=MAX_UNIT) => unit,
+            _ => return Err(usage(format!("--unit: oczekiwano auto albo 1-{}, podano '{}'", MAX_UNIT, other))),
+        },
+    };
+    let max_code_len = m
+        .parse_value::<u8>("max-len")?
+        .unwrap_or(DEFAULT_MAX_CODE_LEN)
+        .clamp(1, MAX_CODE_LEN_LIMIT);
+    let max_symbols = m.parse_value::<usize>("max-symbols")?.or((unit > 2).then_some(WIDE_UNIT_MAX_SYMBOLS));
+    let options = Options { order, max_code_len, unit, max_symbols, ..Options::default() };
+
+    let excludes = m.values("exclude");
+    let mut files = Vec::new();
+    for input in corpus {
+        let path = Path::new(input);
+        if path.is_dir() {
+            collect_dir(path, input, excludes, &mut files)?;
+        } else {
+            files.push((path.to_path_buf(), input.clone()));
+        }
+    }
+    let samples = files.iter().map(|(path, _)| fs::read(path)).collect::<io::Result<Vec<_>>>()?;
+    let dict = Dictionary::train(samples.iter().map(Vec::as_slice), &options)?;
+    let bytes = dict.to_bytes();
+    write_output(output, &bytes)?;
+    println!(
+        "📚 Słownik {:08x}: {} ({} bajtów), rząd {}, {} par kontekst-symbol\n💾 Zapisano {} ({} bajtów)",
+        dict.id(),
+        cli::pliki(samples.len()),
+        samples.iter().map(Vec::len).sum::<usize>(),
+        order,
+        dict.table().symbols(),
+        output,
+        bytes.len()
+    );
+    Ok(())
+}
+
+fn cmd_tail(m: &Matches) -> Result<(), CliError> {
+    let input = m.positional(0).ok_or_else(|| usage("tail: brak pliku wejściowego"))?;
+    let lines: usize = m.parse_value("lines")?.unwrap_or(10);
+
+    let tail = tail_lines(input, lines)?;
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&tail)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// Dekoduje ramki od końca, aż zbierze się dość linii; ramki są samodzielne,
+// więc każdą można odczytać osobno
+fn tail_lines(input: &str, lines: usize) -> huffman_format::Result<Vec<u8>> {
+    let mut file = BufReader::new(File::open(input)?);
+    let frames = frame_index(&mut file)?;
+
+    let mut data = Vec::new();
+    let mut decoded = 0;
+    for frame in frames.iter().rev() {
+        // Końcowy znak nowej linii nie zaczyna kolejnej linii
+        let needed = lines + usize::from(data.last().is_some_and(|&b| b == b'\n'));
+        if data.iter().filter(|&&b| b == b'\n').count() >= needed {
+            break;
+        }
+        file.seek(SeekFrom::Start(frame.offset))?;
+        let mut chunk = Vec::with_capacity(frame.original_len as usize);
+        HuffmanReader::new((&mut file).take(frame.len))?.read_to_end(&mut chunk)?;
+        chunk.append(&mut data);
+        data = chunk;
+        decoded += 1;
+    }
+    debug!("tail: zdekodowano {} z {} ramek", decoded, frames.len());
+
+    let body = data.strip_suffix(b"\n").unwrap_or(&data);
+    let start = match lines {
+        0 => data.len(),
+        n => body
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|&(_, &b)| b == b'\n')
+            .nth(n - 1)
+            .map_or(0, |(i, _)| i + 1),
+    };
+    Ok(data.split_off(start))
+}
+
+fn cmd_grep(m: &Matches) -> Result<(), CliError> {
+    let [pattern, archives @ ..] = m.positionals.as_slice() else {
+        return Err(usage("grep: brak wzorca"));
+    };
+    if archives.is_empty() {
+        return Err(usage("grep: brak archiwum do przeszukania"));
+    }
+    let regex = Regex::new(pattern).map_err(|e| usage(format!("grep: niepoprawny wzorzec: {}", e)))?;
+    let options = DecodeOptions {
+        max_expansion_ratio: m.parse_value("max-ratio")?,
+        max_output_len: parse_max_output(m)?,
+        ..DecodeOptions::default()
+    };
+
+    let mut stdout = io::stdout().lock();
+    for archive in archives {
+        // Przy kilku archiwach każda linia wyniku zaczyna się od nazwy pliku, jak w grep
+        let prefix = if archives.len() > 1 { format!("{}:", archive) } else { String::new() };
+        let result = grep_archive(archive, &regex, options, |line_no, line| {
+            if m.flag("count") {
+                return Ok(());
+            }
+            write!(stdout, "{}{}:", prefix, line_no)?;
+            stdout.write_all(line)?;
+            stdout.write_all(b"\n")
+        });
+        let count = match result {
+            // Odbiorca zamknął potok (np. `| head`) - dalsze szukanie nie ma sensu
+            Err(HuffmanError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        };
+        if m.flag("count") {
+            writeln!(stdout, "{}{}", prefix, count)?;
+        }
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+// Dekoduje archiwum ramka po ramce i przekazuje pasujące linie (bez końca linii);
+// zwraca liczbę dopasowań
+fn grep_archive(
+    input: &str,
+    regex: &Regex,
+    options: DecodeOptions,
+    mut on_match: impl FnMut(u64, &[u8]) -> io::Result<()>,
+) -> huffman_format::Result<u64> {
+    let reader = HuffmanReader::with_options(BufReader::new(File::open(input)?), options)?;
+    let mut lines = BufReader::new(reader);
+    let mut line = Vec::new();
+    let mut line_no = 0;
+    let mut count = 0;
+    loop {
+        line.clear();
+        if lines.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_no += 1;
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        if regex.is_match(text) {
+            count += 1;
+            on_match(line_no, text)?;
+        }
+    }
+    Ok(count)
+}
+
+// Indeks linii leży obok archiwum
+fn index_path(archive: &str) -> String {
+    format!("{}.idx", archive)
+}
+
+fn cmd_lines(m: &Matches) -> Result<(), CliError> {
+    let (Some(input), Some(range)) = (m.positional(0), m.positional(1)) else {
+        return Err(usage("lines: wymagane <input.huff> <od[,do]>"));
+    };
+    let (first, last) = cli::parse_line_range(range)?;
+
+    let mut stdout = io::stdout().lock();
+    match print_lines(input, first, last, &mut stdout) {
+        Err(HuffmanError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+        result => result?,
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+// Wypisuje linie first..=last (liczone od 1); z indeksem zaczyna od najbliższej ramki
+fn print_lines(input: &str, first: u64, last: u64, out: &mut impl Write) -> huffman_format::Result<()> {
+    let mut file = BufReader::new(File::open(input)?);
+    let start = match File::open(index_path(input)) {
+        Ok(index) => LineIndex::read_from(&mut BufReader::new(index))?.checkpoint_for(first - 1).copied(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut line_no = match start {
+        Some(point) => {
+            debug!("lines: start od linii {} (ramka na offsecie {})", point.line + 1, point.frame_offset);
+            file.seek(SeekFrom::Start(point.frame_offset))?;
+            point.line
+        }
+        None => 0,
+    };
+
+    let mut lines = BufReader::new(HuffmanReader::new(file)?);
+    let mut line = Vec::new();
+    while line_no < last {
+        line.clear();
+        if lines.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_no += 1;
+        if line_no >= first {
+            out.write_all(&line)?;
+        }
+    }
+    Ok(())
+}
+
+// Każdy pomiar to najlepszy z kilku przebiegów, żeby ograniczyć szum planisty
+const BENCH_RUNS: usize = 3;
+
+fn cmd_bench(m: &Matches) -> Result<(), CliError> {
+    let size = m.value("size").map(cli::parse_size).transpose()?.unwrap_or(1024 * 1024) as usize;
+    let seed = m.parse_value("seed")?.unwrap_or(DEFAULT_SEED);
+    let order: usize = m.parse_value("order")?.unwrap_or(0);
+    let corpora = match m.value("corpus") {
+        Some(name) => vec![name.parse::<Corpus>().map_err(usage)?],
+        None => Corpus::ALL.to_vec(),
+    };
+    let options = Options { order: order.min(255), ..Options::default() };
+
+    println!("Rząd {}, {} bajtów na korpus, ziarno {}", options.order, size, seed);
+    println!("{:<8} {:>8} {:>12} {:>12}", "Korpus", "Kompr.", "Kodowanie", "Dekodowanie");
+    for corpus in corpora {
+        let data = corpus.generate(size, seed);
+        let (mut encode, mut decode) = (Duration::MAX, Duration::MAX);
+        let mut packed = Vec::new();
+        for _ in 0..BENCH_RUNS {
+            let start = Instant::now();
+            packed = compress_with_stats(&data, &options)?.0;
+            encode = encode.min(start.elapsed());
+
+            let start = Instant::now();
+            let decoded = decompress(&packed)?;
+            decode = decode.min(start.elapsed());
+            if decoded != data {
+                let message = format!("bench: {} nie przeszedł roundtripu", corpus);
+                return Err(HuffmanError::Io(io::Error::other(message)).into());
+            }
+        }
+        let throughput = |time: Duration| format!("{:.1} MB/s", size as f64 / 1e6 / time.as_secs_f64().max(1e-9));
+        println!(
+            "{:<8} {:>7.2}% {:>12} {:>12}",
+            corpus.name(),
+            100.0 - packed.len() as f64 * 100.0 / size.max(1) as f64,
+            throughput(encode),
+            throughput(decode)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// Raporty przebiegów (--report-append): jedna linia JSON na wywołanie, dopisywana na koniec
+// pliku, żeby porównywać skuteczność ustawień w czasie i między zbiorami danych
+use std::env;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub struct Report {
+    started: Instant,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Report {
+    // Wspólne pola: czas, host, wersja, polecenie i jego argumenty
+    pub fn new(command: &str) -> Report {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_secs());
+        let args: Vec<String> = env::args().skip(1).map(|arg| quote(&arg)).collect();
+        let report = Report { started: Instant::now(), fields: Vec::new() };
+        report
+            .number("time", time as f64)
+            .text("host", &hostname())
+            .text("version", env!("CARGO_PKG_VERSION"))
+            .text("command", command)
+            .raw("args", format!("[{}]", args.join(",")))
+    }
+
+    pub fn text(self, key: &'static str, value: &str) -> Report {
+        self.raw(key, quote(value))
+    }
+
+    pub fn number(self, key: &'static str, value: f64) -> Report {
+        // JSON nie ma NaN ani nieskończoności
+        let value = if value.is_finite() { format!("{}", (value * 1000.0).round() / 1000.0) } else { "null".into() };
+        self.raw(key, value)
+    }
+
+    fn raw(mut self, key: &'static str, value: String) -> Report {
+        self.fields.push((key, value));
+        self
+    }
+
+    // Dopisuje linię z czasem od utworzenia raportu; cała linia idzie jednym zapisem,
+    // więc równoległe przebiegi nie przeplatają swoich pól
+    pub fn append(self, path: &str, bytes: u64) -> io::Result<()> {
+        let seconds = self.started.elapsed().as_secs_f64();
+        let report = self.number("elapsed_ms", seconds * 1000.0);
+        let report = report.number("mb_per_s", bytes as f64 / 1e6 / seconds.max(1e-9));
+        let mut line = String::from("{");
+        for (i, (key, value)) in report.fields.iter().enumerate() {
+            let _ = write!(line, "{}{}:{}", if i > 0 { "," } else { "" }, quote(key), value);
+        }
+        line.push_str("}\n");
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())
+    }
+}
+
+// Nazwa maszyny ze zmiennej środowiska albo z jądra (Linux), bez wywołań systemowych
+fn hostname() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "?".to_string())
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
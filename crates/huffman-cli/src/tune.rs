@@ -0,0 +1,157 @@
+// Kalibracja wydajności (--autotune) i plik konfiguracji użytkownika: krótki pomiar na
+// tej maszynie wybiera rozmiar tablic dekodera i liczbę wątków, a kolejne uruchomienia
+// czytają zapisany wybór
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+
+use huffman_format::testdata::{Corpus, DEFAULT_SEED};
+use huffman_format::{
+    DEFAULT_LOOKUP_BITS, DecodeOptions, HuffmanError, MAX_LOOKUP_BITS, Options, compress_parallel, decompress_parallel,
+    decompress_with, default_threads,
+};
+
+/// Łączny czas pomiarów --autotune.
+pub const BUDGET: Duration = Duration::from_secs(1);
+
+const LOOKUP_CANDIDATES: [u8; 5] = [6, 8, DEFAULT_LOOKUP_BITS, 12, 14];
+
+// Dane pomiaru: tekst z modelem rzędu 1 ma i krótkie, i długie kody
+const SAMPLE_LEN: usize = 512 * 1024;
+const SAMPLE_ORDER: usize = 1;
+
+/// Parametry wydajności zapisane w pliku konfiguracji; `None` to wartość domyślna.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tuning {
+    pub lookup_bits: Option<u8>,
+    pub threads: Option<usize>,
+}
+
+/// Plik konfiguracji: `$HUFF_CONFIG`, a bez niego `huff/config` w katalogu
+/// konfiguracji użytkownika (`$XDG_CONFIG_HOME`, `~/.config`, `%APPDATA%`).
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("HUFF_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(dir.join("huff").join("config"))
+}
+
+/// Czyta plik konfiguracji; brak pliku to same wartości domyślne, a nieznane
+/// albo błędne wiersze są pomijane z ostrzeżeniem.
+pub fn load() -> Tuning {
+    let mut tuning = Tuning::default();
+    let Some(path) = config_path() else { return tuning };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return tuning,
+        Err(e) => {
+            warn!("Pominięto plik konfiguracji {}: {}", path.display(), e);
+            return tuning;
+        }
+    };
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("lookup_bits", value)) => value
+                .parse()
+                .ok()
+                .filter(|bits| (1..=MAX_LOOKUP_BITS).contains(bits))
+                .map(|bits| tuning.lookup_bits = Some(bits)),
+            Some(("threads", value)) => {
+                value.parse().ok().filter(|&threads| threads > 0).map(|threads| tuning.threads = Some(threads))
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            warn!("{}:{}: pominięto błędny wiersz '{}'", path.display(), n + 1, line);
+        }
+    }
+    tuning
+}
+
+/// Zapisuje `tuning` do pliku konfiguracji (zastępując go) i zwraca jego ścieżkę.
+pub fn save(tuning: &Tuning) -> io::Result<PathBuf> {
+    let path = config_path()
+        .ok_or_else(|| io::Error::other("nie znaleziono katalogu konfiguracji (ustaw HUFF_CONFIG)"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = String::from("# Wybrane przez huff --autotune dla tej maszyny\n");
+    if let Some(bits) = tuning.lookup_bits {
+        text.push_str(&format!("lookup_bits = {}\n", bits));
+    }
+    if let Some(threads) = tuning.threads {
+        text.push_str(&format!("threads = {}\n", threads));
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Mierzy kandydatów przez około `budget`: połowę czasu dekodowanie z różnymi
+/// rozmiarami tablic, połowę kodowanie i dekodowanie blokami na różnej liczbie wątków.
+pub fn autotune(budget: Duration) -> huffman_format::Result<Tuning> {
+    let data = Corpus::Markov.generate(SAMPLE_LEN, DEFAULT_SEED);
+    let options = Options { order: SAMPLE_ORDER, ..Options::default() };
+    let mut threads = vec![1];
+    while threads.last().is_some_and(|&n| n * 2 <= default_threads()) {
+        threads.push(threads.last().unwrap() * 2);
+    }
+    if threads.last() != Some(&default_threads()) {
+        threads.push(default_threads());
+    }
+    // Co najmniej dwa bloki na wątek, żeby zrównoleglenie miało z czego korzystać
+    let block_size = SAMPLE_LEN / (2 * default_threads());
+
+    let (packed, _) = compress_parallel(&data, &options, block_size, 1)?;
+    let slice = budget / 2 / LOOKUP_CANDIDATES.len() as u32;
+    let mut best_bits = (Duration::MAX, DEFAULT_LOOKUP_BITS);
+    for bits in LOOKUP_CANDIDATES {
+        let decode = DecodeOptions { lookup_bits: Some(bits), ..DecodeOptions::default() };
+        let time = fastest(slice, || check(decompress_with(&packed, &decode)?, &data))?;
+        debug!("autotune: tablice {} bitów - {:?}", bits, time);
+        best_bits = best_bits.min((time, bits));
+    }
+
+    let slice = budget / 2 / threads.len() as u32;
+    let decode = DecodeOptions { lookup_bits: Some(best_bits.1), ..DecodeOptions::default() };
+    let mut best_threads = (Duration::MAX, 1);
+    for n in threads {
+        let time = fastest(slice, || {
+            let (packed, _) = compress_parallel(&data, &options, block_size, n)?;
+            check(decompress_parallel(&packed, &decode, n)?, &data)
+        })?;
+        debug!("autotune: wątki: {} - {:?}", n, time);
+        best_threads = best_threads.min((time, n));
+    }
+    Ok(Tuning { lookup_bits: Some(best_bits.1), threads: Some(best_threads.1) })
+}
+
+// Najkrótszy czas `run` z powtórzeń mieszczących się w `slice` (co najmniej jednego)
+fn fastest(slice: Duration, mut run: impl FnMut() -> huffman_format::Result<()>) -> huffman_format::Result<Duration> {
+    let start = Instant::now();
+    let mut best = Duration::MAX;
+    while best == Duration::MAX || start.elapsed() < slice {
+        let run_start = Instant::now();
+        run()?;
+        best = best.min(run_start.elapsed());
+    }
+    Ok(best)
+}
+
+fn check(decoded: Vec<u8>, data: &[u8]) -> huffman_format::Result<()> {
+    if decoded != data {
+        return Err(HuffmanError::Io(io::Error::other("autotune: dane po dekodowaniu różnią się od próbki")));
+    }
+    Ok(())
+}
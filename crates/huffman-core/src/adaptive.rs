@@ -0,0 +1,217 @@
+// Adaptacyjny kod Huffmana (algorytm FGK): drzewo zmienia się po każdym symbolu,
+// więc koder i dekoder nie potrzebują tablicy częstotliwości z góry
+use std::io::{self, Read, Write};
+
+use crate::bitio::{BitReader, BitWriter};
+use crate::huffman::{Code, FreqTable};
+
+/// Symbol końca danych; bajty to symbole 0..=255.
+pub const EOF_SYMBOL: u16 = 256;
+
+const SYMBOLS: usize = 257;
+// Nowy symbol jest zapisywany wprost na 9 bitach, zaraz po kodzie liścia NYT
+const RAW_BITS: u8 = 9;
+const NONE: usize = usize::MAX;
+// Znacznik liścia NYT ("not yet transmitted") w polu `symbol`
+const NYT: u16 = u16::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    weight: u64,
+    parent: usize,
+    // Dzieci węzła wewnętrznego; w liściu oba są NONE
+    left: usize,
+    right: usize,
+    symbol: u16,
+}
+
+/// Model adaptacyjny rzędu 0 wspólny dla kodera i dekodera.
+///
+/// Węzły leżą w tablicy w kolejności numerów FGK: wagi nie maleją wraz z indeksem,
+/// a korzeń ma indeks największy. Przed aktualizacją wagi węzeł jest zamieniany
+/// z ostatnim węzłem o tej samej wadze, co zachowuje własność rodzeństwa.
+///
+/// ```
+/// use huffman_core::adaptive::{AdaptiveModel, EOF_SYMBOL};
+/// use huffman_core::bitio::{BitReader, BitWriter};
+///
+/// let mut encoder = AdaptiveModel::new();
+/// let mut bits = BitWriter::new(Vec::new());
+/// for &byte in b"abracadabra" {
+///     encoder.encode(byte as u16, &mut bits)?;
+/// }
+/// encoder.encode(EOF_SYMBOL, &mut bits)?;
+/// let packed = bits.finish()?;
+///
+/// let mut decoder = AdaptiveModel::new();
+/// let mut bits = BitReader::new(&packed[..]);
+/// let mut decoded = Vec::new();
+/// while let Some(symbol) = decoder.decode(&mut bits)?.filter(|&s| s != EOF_SYMBOL) {
+///     decoded.push(symbol as u8);
+/// }
+/// assert_eq!(decoded, b"abracadabra");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdaptiveModel {
+    nodes: Vec<Node>,
+    leaves: [usize; SYMBOLS],
+    nyt: usize,
+    // Bufor na ścieżkę od liścia do korzenia
+    path: Vec<bool>,
+}
+
+impl Default for AdaptiveModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdaptiveModel {
+    pub fn new() -> Self {
+        // Pełne drzewo ma 257 liści symboli i liść NYT
+        let root = 2 * SYMBOLS;
+        let nodes = vec![Node { weight: 0, parent: NONE, left: NONE, right: NONE, symbol: NYT }; root + 1];
+        AdaptiveModel { nodes, leaves: [NONE; SYMBOLS], nyt: root, path: Vec::new() }
+    }
+
+    /// Model "rozgrzany": bajt `b` jest wstawiany `counts[b]` razy, zanim
+    /// cokolwiek zostanie zakodowane.
+    ///
+    /// Krótki strumień od razu dostaje rozsądne kody zamiast płacić 9 bitów
+    /// za pierwsze wystąpienie każdego bajtu, a model dalej się dostosowuje.
+    /// Im mniejsze liczności, tym szybciej dane przeważą nad modelem startowym.
+    pub fn with_counts(counts: &[u64; 256]) -> Self {
+        let mut model = Self::new();
+        for (byte, &count) in counts.iter().enumerate() {
+            for _ in 0..count {
+                model.update(byte as u16);
+            }
+        }
+        model
+    }
+
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Zapisuje kod symbolu (0..=256) i aktualizuje model.
+    pub fn encode<W: Write>(&mut self, symbol: u16, bits: &mut BitWriter<W>) -> io::Result<()> {
+        let leaf = self.leaves[symbol as usize];
+        let known = leaf != NONE;
+        self.write_path(if known { leaf } else { self.nyt }, bits)?;
+        if !known {
+            bits.write_code(Code { bits: symbol as u64, len: RAW_BITS })?;
+        }
+        self.update(symbol);
+        Ok(())
+    }
+
+    /// Czyta kolejny symbol i aktualizuje model; `None` na końcu danych.
+    pub fn decode<R: Read>(&mut self, bits: &mut BitReader<R>) -> io::Result<Option<u16>> {
+        let mut node = self.root();
+        while self.nodes[node].left != NONE {
+            let Some(bit) = bits.read_bit()? else { return Ok(None) };
+            node = if bit { self.nodes[node].right } else { self.nodes[node].left };
+        }
+        let symbol = match self.nodes[node].symbol {
+            NYT => {
+                let mut raw = 0u16;
+                for _ in 0..RAW_BITS {
+                    let Some(bit) = bits.read_bit()? else { return Ok(None) };
+                    raw = (raw << 1) | bit as u16;
+                }
+                if raw as usize >= SYMBOLS || self.leaves[raw as usize] != NONE {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("niepoprawny nowy symbol {}", raw)));
+                }
+                raw
+            }
+            symbol => symbol,
+        };
+        self.update(symbol);
+        Ok(Some(symbol))
+    }
+
+    /// Liczności bajtów widzianych do tej pory (wagi liści, bez symbolu końca).
+    pub fn frequencies(&self) -> FreqTable {
+        (0..=255u8)
+            .filter(|&b| self.leaves[b as usize] != NONE)
+            .map(|b| (vec![b], self.nodes[self.leaves[b as usize]].weight))
+            .collect()
+    }
+
+    // Kod węzła to ścieżka od korzenia; zbieramy ją od liścia w górę
+    fn write_path<W: Write>(&mut self, mut node: usize, bits: &mut BitWriter<W>) -> io::Result<()> {
+        self.path.clear();
+        while self.nodes[node].parent != NONE {
+            let parent = self.nodes[node].parent;
+            self.path.push(self.nodes[parent].right == node);
+            node = parent;
+        }
+        // Ścieżka może być dłuższa niż 64 bity, więc piszemy ją kawałkami
+        for chunk in self.path.rchunks(64) {
+            let code = chunk.iter().rev().fold(Code::default(), |code, &bit| code.push(bit));
+            bits.write_code(code)?;
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, symbol: u16) {
+        let mut node = self.leaves[symbol as usize];
+        if node == NONE {
+            // Liść NYT dostaje dwoje dzieci: nowy NYT i liść nowego symbolu
+            let old = self.nyt;
+            let (nyt, leaf) = (old - 2, old - 1);
+            self.nodes[nyt] = Node { weight: 0, parent: old, left: NONE, right: NONE, symbol: NYT };
+            self.nodes[leaf] = Node { weight: 0, parent: old, left: NONE, right: NONE, symbol };
+            self.nodes[old].left = nyt;
+            self.nodes[old].right = leaf;
+            self.nyt = nyt;
+            self.leaves[symbol as usize] = leaf;
+            node = leaf;
+        }
+        loop {
+            let leader = self.block_leader(node);
+            if leader != node && leader != self.nodes[node].parent {
+                self.swap(node, leader);
+                node = leader;
+            }
+            self.nodes[node].weight += 1;
+            if node == self.root() {
+                break;
+            }
+            node = self.nodes[node].parent;
+        }
+    }
+
+    // Węzeł o największym numerze z tą samą wagą co `node`
+    fn block_leader(&self, node: usize) -> usize {
+        let weight = self.nodes[node].weight;
+        let mut leader = node;
+        while leader + 1 < self.nodes.len() && self.nodes[leader + 1].weight == weight {
+            leader += 1;
+        }
+        leader
+    }
+
+    // Zamienia miejscami poddrzewa zaczepione w `a` i `b` (rodzice zostają na miejscu)
+    fn swap(&mut self, a: usize, b: usize) {
+        let (parent_a, parent_b) = (self.nodes[a].parent, self.nodes[b].parent);
+        self.nodes.swap(a, b);
+        self.nodes[a].parent = parent_a;
+        self.nodes[b].parent = parent_b;
+        for node in [a, b] {
+            let Node { left, right, symbol, .. } = self.nodes[node];
+            if left == NONE {
+                if symbol == NYT {
+                    self.nyt = node;
+                } else {
+                    self.leaves[symbol as usize] = node;
+                }
+            } else {
+                self.nodes[left].parent = node;
+                self.nodes[right].parent = node;
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+use crate::error::{HuffmanError, Result};
+
+/// Zbiór dozwolonych bajtów wejścia (bitmapa 256 bitów).
+///
+/// Kodowanie z ustawionym alfabetem kończy się błędem
+/// [`HuffmanError::SymbolOutsideAlphabet`] na pierwszym bajcie spoza zbioru,
+/// zanim zostanie zbudowany model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alphabet {
+    bits: [u64; 4],
+}
+
+impl Alphabet {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut alphabet = Alphabet { bits: [0; 4] };
+        for &b in bytes {
+            alphabet.bits[(b >> 6) as usize] |= 1 << (b & 63);
+        }
+        alphabet
+    }
+
+    pub fn from_range(lo: u8, hi: u8) -> Self {
+        Self::from_bytes(&(lo..=hi).collect::<Vec<u8>>())
+    }
+
+    /// Pełny zakres ASCII (0x00-0x7F).
+    pub fn ascii() -> Self {
+        Self::from_range(0x00, 0x7F)
+    }
+
+    /// Drukowalne ASCII (0x20-0x7E).
+    pub fn ascii_printable() -> Self {
+        Self::from_range(0x20, 0x7E)
+    }
+
+    /// Drukowalne ASCII oraz `\t`, `\n`, `\r`.
+    pub fn text() -> Self {
+        Self::ascii_printable().union(&Self::from_bytes(b"\t\n\r"))
+    }
+
+    pub fn union(&self, other: &Alphabet) -> Self {
+        let mut bits = self.bits;
+        for (b, o) in bits.iter_mut().zip(other.bits) {
+            *b |= o;
+        }
+        Alphabet { bits }
+    }
+
+    pub fn contains(&self, byte: u8) -> bool {
+        self.bits[(byte >> 6) as usize] & (1 << (byte & 63)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sprawdza dane i zwraca błąd z offsetem pierwszego niedozwolonego bajtu.
+    pub fn check(&self, data: &[u8]) -> Result<()> {
+        self.check_at(data, 0)
+    }
+
+    /// Jak [`Alphabet::check`], dla porcji zaczynającej się na offsecie `base` całego wejścia.
+    pub fn check_at(&self, data: &[u8], base: u64) -> Result<()> {
+        match data.iter().position(|&b| !self.contains(b)) {
+            None => Ok(()),
+            Some(offset) => Err(HuffmanError::SymbolOutsideAlphabet {
+                offset: base + offset as u64,
+                symbol: data[offset],
+            }),
+        }
+    }
+}
+
+/// Nazwy przyjmowane przez `--alphabet`: `ascii`, `printable`, `text`, `digits`,
+/// `hex`, `base64` albo `set:<znaki>` z jawną listą bajtów.
+impl FromStr for Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if let Some(chars) = s.strip_prefix("set:") {
+            return Ok(Alphabet::from_bytes(chars.as_bytes()));
+        }
+        match s {
+            "ascii" => Ok(Alphabet::ascii()),
+            "printable" => Ok(Alphabet::ascii_printable()),
+            "text" => Ok(Alphabet::text()),
+            "digits" => Ok(Alphabet::from_range(b'0', b'9')),
+            "hex" => Ok(Alphabet::from_bytes(b"0123456789abcdefABCDEF")),
+            "base64" => Ok(Alphabet::from_bytes(
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=",
+            )),
+            other => Err(format!("nieznany alfabet '{}'", other)),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Strumieniowy zapis i odczyt bitów (MSB-first), buforowany słowami 64-bitowymi
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::huffman::Code;
+
+/// Zapisuje kody bit po bicie do `W`, oddając je pełnymi słowami po 8 bajtów.
+///
+/// Przy niebuforowanym `W` (np. `File`) warto go opakować w `BufWriter`.
+///
+/// ```
+/// use huffman_core::bitio::{BitReader, BitWriter};
+/// use huffman_core::huffman::Code;
+///
+/// let mut writer = BitWriter::new(Vec::new());
+/// writer.write_code(Code { bits: 0b101, len: 3 })?;
+/// let bytes = writer.finish()?;
+/// assert_eq!(bytes, [0b1010_0000]);
+///
+/// let mut reader = BitReader::with_limit(&bytes[..], 3);
+/// assert_eq!(reader.read_bit()?, Some(true));
+/// assert_eq!(reader.read_bit()?, Some(false));
+/// assert_eq!(reader.read_bit()?, Some(true));
+/// assert_eq!(reader.read_bit()?, None);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct BitWriter<W: Write> {
+    inner: W,
+    // Bity czekające na zapis; najmłodsze `len` bitów, najstarszy pierwszy
+    acc: u128,
+    len: u32,
+    bits_written: u64,
+}
+
+impl<W: Write> BitWriter<W> {
+    pub fn new(inner: W) -> Self {
+        BitWriter { inner, acc: 0, len: 0, bits_written: 0 }
+    }
+
+    /// Dopisuje kod (do 64 bitów).
+    pub fn write_code(&mut self, code: Code) -> io::Result<()> {
+        // W akumulatorze zostaje < 64 bitów, więc kod do 64 bitów zawsze się mieści
+        self.acc = (self.acc << code.len) | code.bits as u128;
+        self.len += code.len as u32;
+        self.bits_written += code.len as u64;
+        if self.len >= 64 {
+            self.len -= 64;
+            self.inner.write_all(&((self.acc >> self.len) as u64).to_be_bytes())?;
+            self.acc &= (1u128 << self.len) - 1;
+        }
+        Ok(())
+    }
+
+    /// Liczba zapisanych bitów, bez dopełnienia.
+    pub fn bits_written(&self) -> u64 {
+        self.bits_written
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Dopełnia ostatni bajt zerami, zapisuje resztę i zwraca `W`.
+    pub fn finish(mut self) -> io::Result<W> {
+        let padded = self.len.div_ceil(8) * 8;
+        let bits = self.acc << (padded - self.len);
+        let bytes = (bits as u64).to_be_bytes();
+        self.inner.write_all(&bytes[8 - padded as usize / 8..])?;
+        Ok(self.inner)
+    }
+}
+
+/// Czyta bity z `R` w kolejności, w jakiej zapisał je [`BitWriter`].
+///
+/// Z limitem ([`BitReader::with_limit`]) czyta z `R` najwyżej tyle bajtów, ile
+/// zajmuje `limit` bitów, więc po ostatnim bicie `R` stoi tuż za strumieniem.
+/// Bez limitu długość strumienia nie jest znana i `R` jest czytane po bajcie.
+#[derive(Debug)]
+pub struct BitReader<R: Read> {
+    inner: R,
+    acc: u64,
+    len: u32,
+    bits_read: u64,
+    limit: Option<u64>,
+    // Bajty pobrane z `R`, razem z tymi, które jeszcze czekają w akumulatorze
+    bytes_in: u64,
+}
+
+impl<R: Read> BitReader<R> {
+    pub fn new(inner: R) -> Self {
+        BitReader { inner, acc: 0, len: 0, bits_read: 0, limit: None, bytes_in: 0 }
+    }
+
+    /// Czytnik, który po `limit` bitach zgłasza koniec danych.
+    pub fn with_limit(inner: R, limit: u64) -> Self {
+        BitReader { limit: Some(limit), ..Self::new(inner) }
+    }
+
+    /// Kolejny bit albo `None` na końcu danych (lub po osiągnięciu limitu).
+    pub fn read_bit(&mut self) -> io::Result<Option<bool>> {
+        if self.limit == Some(self.bits_read) {
+            return Ok(None);
+        }
+        if self.len == 0 && !self.refill()? {
+            return Ok(None);
+        }
+        self.len -= 1;
+        self.bits_read += 1;
+        Ok(Some((self.acc >> self.len) & 1 == 1))
+    }
+
+    /// Najwyżej `count` (do 32) kolejnych bitów jako liczba, pierwszy bit najstarszy,
+    /// razem z liczbą przeczytanych bitów; mniej niż `count` tylko na końcu danych.
+    pub fn read_bits(&mut self, count: u8) -> io::Result<(u64, u8)> {
+        assert!(count <= 32, "najwyżej 32 bity naraz");
+        let (mut value, mut got) = (0u64, 0u8);
+        while got < count {
+            if self.limit == Some(self.bits_read) || (self.len == 0 && !self.refill()?) {
+                break;
+            }
+            let mut take = ((count - got) as u32).min(self.len);
+            if let Some(limit) = self.limit {
+                take = take.min((limit - self.bits_read).min(32) as u32);
+            }
+            self.len -= take;
+            value = (value << take) | ((self.acc >> self.len) & ((1 << take) - 1));
+            self.bits_read += take as u64;
+            got += take as u8;
+        }
+        Ok((value, got))
+    }
+
+    /// Liczba przeczytanych bitów.
+    pub fn bits_read(&self) -> u64 {
+        self.bits_read
+    }
+
+    /// Dostęp do `R`. Bez limitu akumulator trzyma najwyżej resztę bieżącego bajtu,
+    /// więc `R` stoi tuż za ostatnim bajtem, z którego pochodzą przeczytane bity.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn refill(&mut self) -> io::Result<bool> {
+        let want = match self.limit {
+            Some(limit) => (limit.div_ceil(8) - self.bytes_in).min(8) as usize,
+            None => 1,
+        };
+        let mut buf = [0u8; 8];
+        let mut filled = 0;
+        while filled < want {
+            match self.inner.read(&mut buf[filled..want]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        for &byte in &buf[..filled] {
+            self.acc = (self.acc << 8) | byte as u64;
+        }
+        self.len = filled as u32 * 8;
+        self.bytes_in += filled as u64;
+        Ok(filled > 0)
+    }
+}
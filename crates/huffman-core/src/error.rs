@@ -0,0 +1,246 @@
+use std::fmt;
+use std::io;
+
+/// Zasób ograniczany limitami dekodera, zob. [`HuffmanError::ResourceLimit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Resource {
+    /// Bajty odtworzonych danych.
+    Output,
+    /// Pary (kontekst, symbol) w tablicach kodów jednej ramki.
+    TableEntries,
+    /// Szacowana pamięć dekodera w bajtach.
+    Memory,
+}
+
+/// Błędy zwracane przez koder i dekoder.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HuffmanError {
+    /// Błąd wejścia/wyjścia.
+    Io(io::Error),
+    /// Brak danych do zbudowania modelu.
+    EmptyInput,
+    /// Nagłówek jest urwany lub zawiera niespójne wartości.
+    MalformedHeader(String),
+    /// Strumień bitów skończył się przed odtworzeniem wszystkich symboli.
+    TruncatedStream { decoded: u64, expected: u64 },
+    /// Symbol nie ma kodu w tabeli danego kontekstu.
+    SymbolNotInTable { context: Vec<u8>, symbol: Vec<u8> },
+    /// Kontekst nie występuje w modelu.
+    UnknownContext(Vec<u8>),
+    /// Ciąg bitów nie odpowiada żadnemu kodowi.
+    InvalidCode { context: Vec<u8>, bits: String },
+    /// Alfabet nie mieści się w zadanym limicie długości kodu.
+    CodeLengthLimit { max_len: u8, symbols: usize },
+    /// Tablica częstotliwości nie nadaje się do budowy drzewa.
+    InvalidFrequencies(String),
+    /// Bajt wejścia spoza dozwolonego alfabetu.
+    SymbolOutsideAlphabet { offset: u64, symbol: u8 },
+    /// Nieprawidłowe parametry kompresji.
+    InvalidOptions(String),
+    /// Suma kontrolna odtworzonych danych nie zgadza się z zapisaną w pliku.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// Dane po dekompresji przekroczyłyby dozwoloną krotność wejścia.
+    ExpansionLimit { ratio: u64, input: u64, output: u64 },
+    /// Dekodowanie przekroczyłoby limit zasobu z opcji dekodera.
+    ResourceLimit { resource: Resource, limit: u64, requested: u64 },
+    /// Błąd dekodowania z informacją, gdzie w strumieniu wystąpił, i podpowiedzią.
+    Context {
+        source: Box<HuffmanError>,
+        position: Position,
+        hint: Option<&'static str>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, HuffmanError>;
+
+/// Miejsce w strumieniu, w którym wystąpił błąd dekodowania.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub block: Option<u64>,
+    /// Offset bitowy w strumieniu danych (za nagłówkiem).
+    pub bit_offset: Option<u64>,
+    /// Liczba bajtów odtworzonych przed błędem.
+    pub decoded: Option<u64>,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(block) = self.block {
+            parts.push(format!("blok {}", block));
+        }
+        if let Some(bit) = self.bit_offset {
+            parts.push(format!("bit {} (bajt {})", bit, bit / 8));
+        }
+        if let Some(decoded) = self.decoded {
+            parts.push(format!("po {} odtworzonych danych", format_size(decoded)));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Formatuje rozmiar w jednostkach binarnych, np. `1.2 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+impl HuffmanError {
+    /// Błąd źródłowy bez warstw kontekstu - do dopasowywania po rodzaju.
+    pub fn root(&self) -> &HuffmanError {
+        match self {
+            HuffmanError::Context { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Dokleja pozycję w strumieniu i podpowiedź właściwą dla rodzaju błędu.
+    pub fn at(self, position: Position) -> HuffmanError {
+        let hint = match self.root() {
+            HuffmanError::TruncatedStream { .. } => {
+                Some("plik jest prawdopodobnie obcięty - sprawdź, czy został skopiowany w całości")
+            }
+            HuffmanError::InvalidCode { .. } | HuffmanError::UnknownContext(_) => {
+                Some("strumień bitów jest uszkodzony albo nie pasuje do nagłówka")
+            }
+            HuffmanError::MalformedHeader(_) => {
+                Some("plik nie jest archiwum .huff albo zapisała go inna wersja programu")
+            }
+            HuffmanError::ChecksumMismatch { .. } => {
+                Some("archiwum jest uszkodzone - odtworzone dane różnią się od oryginału")
+            }
+            HuffmanError::ExpansionLimit { .. } | HuffmanError::ResourceLimit { .. } => {
+                Some("nagłówek deklaruje podejrzanie dużo danych - plik może być spreparowany")
+            }
+            _ => None,
+        };
+        HuffmanError::Context {
+            source: Box::new(self),
+            position,
+            hint,
+        }
+    }
+}
+
+impl fmt::Display for HuffmanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HuffmanError::Io(e) => write!(f, "błąd wejścia/wyjścia: {}", e),
+            HuffmanError::EmptyInput => write!(f, "puste dane wejściowe"),
+            HuffmanError::MalformedHeader(msg) => write!(f, "uszkodzony nagłówek: {}", msg),
+            HuffmanError::TruncatedStream { decoded, expected } => write!(
+                f,
+                "strumień urwany: odtworzono {} z {} bajtów",
+                decoded, expected
+            ),
+            HuffmanError::SymbolNotInTable { context, symbol } => write!(
+                f,
+                "symbol {:?} nie ma kodu w kontekście {:?}",
+                symbol, context
+            ),
+            HuffmanError::UnknownContext(context) => write!(f, "nieznany kontekst {:?}", context),
+            HuffmanError::InvalidCode { context, bits } => write!(
+                f,
+                "nie znaleziono kodu w kontekście {:?} (bity: {})",
+                context, bits
+            ),
+            HuffmanError::CodeLengthLimit { max_len, symbols } => write!(
+                f,
+                "limit {} bitów nie pomieści {} symboli",
+                max_len, symbols
+            ),
+            HuffmanError::InvalidFrequencies(msg) => {
+                write!(f, "niepoprawna tablica częstotliwości: {}", msg)
+            }
+            HuffmanError::SymbolOutsideAlphabet { offset, symbol } => write!(
+                f,
+                "bajt 0x{:02x} na offsecie {} jest spoza dozwolonego alfabetu",
+                symbol, offset
+            ),
+            HuffmanError::InvalidOptions(msg) => write!(f, "nieprawidłowe opcje: {}", msg),
+            HuffmanError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "niezgodna suma kontrolna: zapisano {:08x}, odtworzone dane dają {:08x}",
+                expected, actual
+            ),
+            HuffmanError::ExpansionLimit { ratio, input, output } => write!(
+                f,
+                "przekroczono limit rozszerzenia {}x: {} na wyjściu z {} na wejściu",
+                ratio,
+                format_size(*output),
+                format_size(*input)
+            ),
+            HuffmanError::ResourceLimit { resource, limit, requested } => match resource {
+                // Dokładne liczby: strumień bez zadeklarowanej długości przekracza limit o bajt
+                Resource::Output => write!(
+                    f,
+                    "przekroczono limit długości wyniku {} B: dane mają co najmniej {} B",
+                    limit, requested
+                ),
+                Resource::TableEntries => write!(
+                    f,
+                    "przekroczono limit tablicy kodów: {} wpisów przy dozwolonych {}",
+                    requested, limit
+                ),
+                Resource::Memory => write!(
+                    f,
+                    "przekroczono limit pamięci dekodera: potrzeba około {}, dozwolone {}",
+                    format_size(*requested),
+                    format_size(*limit)
+                ),
+            },
+            HuffmanError::Context { source, position, hint } => {
+                write!(f, "{}", source)?;
+                if *position != Position::default() {
+                    write!(f, " [{}]", position)?;
+                }
+                if let Some(hint) = hint {
+                    write!(f, "\n  wskazówka: {}", hint)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for HuffmanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HuffmanError::Io(e) => Some(e),
+            HuffmanError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HuffmanError {
+    fn from(e: io::Error) -> Self {
+        // Błędy dekodera przeniesione przez io::Read wracają do swojej postaci
+        if e.get_ref().is_some_and(|inner| inner.is::<HuffmanError>()) {
+            let inner = e.into_inner().and_then(|inner| inner.downcast::<HuffmanError>().ok());
+            return *inner.expect("typ sprawdzony wyżej");
+        }
+        HuffmanError::Io(e)
+    }
+}
+
+impl From<HuffmanError> for io::Error {
+    fn from(e: HuffmanError) -> Self {
+        match e {
+            HuffmanError::Io(e) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
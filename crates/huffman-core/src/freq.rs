@@ -0,0 +1,93 @@
+use crate::error::{HuffmanError, Result};
+use crate::huffman::FreqTable;
+
+/// Arytmetyka na tablicach częstotliwości do składania modeli przed budową drzewa.
+///
+/// Operacje pilnują, żeby w tablicy nie zostały wagi zerowe (drzewo traktuje liść
+/// o wadze 0 jak węzeł sztuczny) i żeby model nie stał się pusty.
+///
+/// ```
+/// use huffman_core::freq::FreqTableExt;
+/// use huffman_core::huffman::FreqTable;
+///
+/// let mut corpus = FreqTable::from([(b"a".to_vec(), 10), (b"b".to_vec(), 4), (b" ".to_vec(), 6)]);
+/// let stop = FreqTable::from([(b" ".to_vec(), 6)]);
+/// corpus.subtract(&stop)?;
+/// corpus.scale(0.5)?;
+/// assert_eq!(corpus.top_n(1), FreqTable::from([(b"a".to_vec(), 5)]));
+/// # Ok::<(), huffman_core::HuffmanError>(())
+/// ```
+pub trait FreqTableExt {
+    /// Odejmuje wagi `other`; symbole, których waga spadnie do zera, są usuwane.
+    fn subtract(&mut self, other: &FreqTable) -> Result<()>;
+    /// Mnoży wagi przez `factor` z zaokrągleniem, ale nie mniej niż do 1.
+    fn scale(&mut self, factor: f64) -> Result<()>;
+    /// Zwraca `n` najcięższych symboli (remisy rozstrzyga kolejność symboli).
+    fn top_n(&self, n: usize) -> FreqTable;
+    /// Sprawdza, że tablica nie jest pusta i nie zawiera wag zerowych.
+    fn validate(&self) -> Result<()>;
+}
+
+impl FreqTableExt for FreqTable {
+    fn subtract(&mut self, other: &FreqTable) -> Result<()> {
+        for (symbol, &weight) in other {
+            if let Some(current) = self.get_mut(symbol) {
+                *current = current.saturating_sub(weight);
+            }
+        }
+        self.retain(|_, weight| *weight > 0);
+
+        if self.is_empty() {
+            return Err(HuffmanError::InvalidFrequencies(
+                "po odjęciu nie został żaden symbol".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn scale(&mut self, factor: f64) -> Result<()> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(HuffmanError::InvalidFrequencies(format!(
+                "niepoprawny współczynnik skalowania {}",
+                factor
+            )));
+        }
+
+        let mut scaled = Vec::with_capacity(self.len());
+        for (symbol, &weight) in self.iter() {
+            let value = (weight as f64 * factor).round();
+            if value >= u64::MAX as f64 {
+                return Err(HuffmanError::InvalidFrequencies(format!(
+                    "waga symbolu {:?} przekroczy zakres u64",
+                    symbol
+                )));
+            }
+            scaled.push((symbol.clone(), (value as u64).max(1)));
+        }
+        self.extend(scaled);
+        Ok(())
+    }
+
+    fn top_n(&self, n: usize) -> FreqTable {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|(sa, wa), (sb, wb)| wb.cmp(wa).then_with(|| sa.cmp(sb)));
+        entries
+            .into_iter()
+            .take(n)
+            .map(|(symbol, &weight)| (symbol.clone(), weight))
+            .collect()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.is_empty() {
+            return Err(HuffmanError::EmptyInput);
+        }
+        if let Some((symbol, _)) = self.iter().find(|&(_, &weight)| weight == 0) {
+            return Err(HuffmanError::InvalidFrequencies(format!(
+                "symbol {:?} ma wagę 0",
+                symbol
+            )));
+        }
+        Ok(())
+    }
+}
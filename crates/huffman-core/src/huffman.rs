@@ -0,0 +1,652 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+use log::warn;
+
+use crate::error::{HuffmanError, Result};
+
+pub type Symbol = Vec<u8>;
+pub type CodeTable = HashMap<Symbol, Code>;
+pub type FreqTable = HashMap<Symbol, u64>;
+pub type CodeLengths = HashMap<Symbol, u8>;
+// Modele rzędu N: osobna tablica dla każdego kontekstu (N poprzednich bajtów)
+pub type MarkovFreqTable = HashMap<Vec<u8>, FreqTable>;
+pub type MarkovCodeTable = HashMap<Vec<u8>, CodeTable>;
+pub type MarkovCodeLengths = HashMap<Vec<u8>, CodeLengths>;
+
+/// Kod Huffmana: `len` najmłodszych bitów `bits`, od najstarszego do najmłodszego.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Code {
+    pub bits: u64,
+    pub len: u8,
+}
+
+impl Code {
+    /// Kod wydłużony o jeden bit na końcu.
+    pub fn push(self, bit: bool) -> Code {
+        Code {
+            bits: (self.bits << 1) | bit as u64,
+            len: self.len + 1,
+        }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.len == 0 {
+            return Ok(());
+        }
+        write!(f, "{:0width$b}", self.bits, width = self.len as usize)
+    }
+}
+
+// Domyślny limit długości kodu (w bitach); format zapisuje go w nagłówku. Przy 15 bitach
+// dekoder tablicowy rozwiązuje każdy kod korzeniem i najwyżej jedną podtablicą
+pub const DEFAULT_MAX_CODE_LEN: u8 = 15;
+pub const MAX_CODE_LEN_LIMIT: u8 = 64;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Node {
+    Leaf {
+        symbol: Symbol,
+        freq: u64,
+    },
+    Internal {
+        freq: u64,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+}
+
+impl Node {
+    fn freq(&self) -> u64 {
+        match self {
+            Node::Leaf { freq, .. } => *freq,
+            Node::Internal { freq, .. } => *freq,
+        }
+    }
+}
+
+// Implementacja Ord dla Node zapewnia determinizm przy porównywaniu węzłów o tej samej wadze
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let freq_cmp = other.freq().cmp(&self.freq());
+        if freq_cmp != Ordering::Equal {
+            return freq_cmp;
+        }
+
+        match (self, other) {
+            // Przy równych wagach, sortujemy leksykograficznie po symbolu
+            (Node::Leaf { symbol: a, .. }, Node::Leaf { symbol: b, .. }) => a.cmp(b),
+            // Liście mają pierwszeństwo przed węzłami wewnętrznymi (konwencja dla determinizmu)
+            (Node::Leaf { .. }, Node::Internal { .. }) => Ordering::Less,
+            (Node::Internal { .. }, Node::Leaf { .. }) => Ordering::Greater,
+            // Dwa węzły wewnętrzne o tej samej wadze są "równe"
+            (Node::Internal { .. }, Node::Internal { .. }) => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub type HuffmanTree = Node;
+
+#[derive(Eq, PartialEq)]
+pub(crate) struct HeapNode {
+    freq: u64,
+    node: Box<Node>,
+}
+
+// KLUCZOWA POPRAWKA: Determinizm sterty
+impl Ord for HeapNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap to MaxHeap, więc odwracamy kolejność częstotliwości (najmniejsze najpierw)
+        other.freq.cmp(&self.freq)
+            // JEŚLI CZĘSTOTLIWOŚCI SĄ RÓWNE: używamy porównania Node (leksykograficznie),
+            // aby enkoder i dekoder zawsze podejmowały tę samą decyzję co do kolejności łączenia.
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for HeapNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub fn entropy_from_freq(freq: &FreqTable) -> f64 {
+    let total: u64 = freq.values().sum();
+    if total == 0 { return 0.0; }
+    let total_f = total as f64;
+
+    freq.values()
+        .map(|&count| {
+            if count == 0 { return 0.0; }
+            let p = count as f64 / total_f;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+pub fn build_huffman_tree(frequencies: &FreqTable) -> Result<Box<HuffmanTree>> {
+    // Kolejność wstawiania musi być stała (HashMap iteruje losowo), inaczej przy remisach
+    // wag węzłów wewnętrznych enkoder i dekoder mogą zbudować różne drzewa.
+    let mut entries: Vec<(Symbol, u64)> = frequencies.iter().map(|(symbol, &freq)| (symbol.clone(), freq)).collect();
+    entries.sort();
+    build_huffman_tree_in_order(&entries)
+}
+
+// Drzewo z par wstawianych na stertę w podanej kolejności; przy remisach wag węzłów
+// wewnętrznych od tej kolejności zależy kształt drzewa
+pub fn build_huffman_tree_in_order(entries: &[(Symbol, u64)]) -> Result<Box<HuffmanTree>> {
+    if entries.is_empty() { return Err(HuffmanError::EmptyInput); }
+
+    let mut heap = BinaryHeap::new();
+
+    for (symbol, freq) in entries {
+        heap.push(HeapNode {
+            freq: *freq,
+            node: Box::new(Node::Leaf {
+                symbol: symbol.clone(),
+                freq: *freq,
+            }),
+        });
+    }
+
+    // POPRAWKA: Jeśli jest tylko jeden symbol, tworzymy sztuczny węzeł.
+    // Używamy pustego wektora vec![], aby nie kolidował z prawdziwym symbolem [0] (null byte).
+    if heap.len() == 1 {
+        let only_node = heap.pop().unwrap();
+        return Ok(Box::new(Node::Internal {
+            freq: only_node.freq,
+            left: only_node.node,
+            right: Box::new(Node::Leaf { symbol: vec![], freq: 0 }), 
+        }));
+    }
+
+    while heap.len() > 1 {
+        let left = heap.pop().unwrap();
+        let right = heap.pop().unwrap();
+        // Częstotliwości z nagłówka v0 mogą pochodzić z uszkodzonego pliku
+        let freq = left
+            .freq
+            .checked_add(right.freq)
+            .ok_or_else(|| HuffmanError::InvalidFrequencies("suma częstotliwości przekracza u64".to_string()))?;
+        heap.push(HeapNode {
+            freq,
+            node: Box::new(Node::Internal {
+                freq,
+                left: left.node,
+                right: right.node,
+            }),
+        });
+    }
+
+    heap.pop().map(|n| n.node).ok_or(HuffmanError::EmptyInput)
+}
+
+// Prawdziwe symbole drzewa; sztuczny liść drzewa jednego symbolu (freq 0, pusty wektor) jest pomijany
+fn is_symbol(symbol: &Symbol, freq: u64) -> bool {
+    freq > 0 || !symbol.is_empty()
+}
+
+// Głębokości liści drzewa; liczone osobno, bo nienaprawione drzewo może być głębsze niż 64 bity.
+// Drzewo jest przechodzone jawnym stosem, bez rekurencji
+fn leaf_depths(root: &Node) -> HashMap<Symbol, u32> {
+    let mut depths = HashMap::new();
+    let mut stack = vec![(root, 0u32)];
+    while let Some((node, depth)) = stack.pop() {
+        match node {
+            Node::Leaf { symbol, freq } => {
+                if is_symbol(symbol, *freq) {
+                    depths.insert(symbol.clone(), depth);
+                }
+            }
+            Node::Internal { left, right, .. } => {
+                stack.push((right, depth + 1));
+                stack.push((left, depth + 1));
+            }
+        }
+    }
+    depths
+}
+
+// Wymaga drzewa o głębokości najwyżej 64 (sprawdzonej przez validate_code_lengths).
+// Przechodzi drzewo jawnym stosem, więc głębokość drzewa nie zależy od rozmiaru stosu wątku
+pub fn build_code_table(root: &Node, prefix: Code, table: &mut CodeTable) {
+    let mut stack = vec![(root, prefix)];
+    while let Some((node, code)) = stack.pop() {
+        match node {
+            Node::Leaf { symbol, freq } => {
+                if is_symbol(symbol, *freq) {
+                    table.insert(symbol.clone(), code);
+                }
+            }
+            Node::Internal { left, right, .. } => {
+                stack.push((right, code.push(true)));
+                stack.push((left, code.push(false)));
+            }
+        }
+    }
+}
+
+// Wpis drzewa FlatTree: indeks węzła, a z ustawionym FLAT_LEAF indeks symbolu
+const FLAT_LEAF: u32 = 1 << 31;
+const FLAT_NONE: u32 = u32::MAX;
+
+/// Krok po drzewie [`FlatTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    /// Kod trwa: kolejny bit czyta węzeł o tym numerze.
+    Node(u32),
+    /// Kod się skończył na symbolu o tym numerze ([`FlatTree::symbol`]).
+    Leaf(u32),
+    /// Bity nie zaczynają żadnego kodu.
+    Missing,
+}
+
+/// Drzewo kodów w jednym `Vec`, do dekodowania po bicie: węzeł to para indeksów
+/// dzieci (`u32`), a liście są oznaczone najstarszym bitem i wskazują symbol.
+/// Budowane raz z tabeli kodów (kanonicznych albo z drzewa v0); węzły są numerowane
+/// w kolejności leksykograficznej kodów, więc numeracja nie zależy od HashMap.
+///
+/// ```
+/// use huffman_core::huffman::{Code, CodeTable, FlatTree, Walk};
+///
+/// let mut codes: CodeTable =
+///     [(b"a".to_vec(), Code { bits: 0, len: 1 }), (b"b".to_vec(), Code { bits: 0b10, len: 2 })].into();
+/// let tree = FlatTree::from_codes(&codes).unwrap();
+/// let Walk::Node(root) = tree.start() else { unreachable!() };
+/// let Walk::Node(next) = tree.next(root, true) else { unreachable!() };
+/// let Walk::Leaf(b) = tree.next(next, false) else { unreachable!() };
+/// assert_eq!(tree.symbol(b), b"b");
+/// assert_eq!(tree.next(next, true), Walk::Missing);
+///
+/// // "1" jest początkiem kodu "10"
+/// codes.insert(b"c".to_vec(), Code { bits: 1, len: 1 });
+/// assert!(FlatTree::from_codes(&codes).is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlatTree {
+    root: u32,
+    nodes: Vec<[u32; 2]>,
+    symbols: Vec<Symbol>,
+}
+
+impl FlatTree {
+    /// Drzewo z tabeli kodów; kod będący początkiem innego (albo powtórzony) to błąd.
+    pub fn from_codes(codes: &CodeTable) -> std::result::Result<FlatTree, String> {
+        let mut sorted: Vec<(&Symbol, Code)> = codes.iter().map(|(symbol, &code)| (symbol, code)).collect();
+        sorted.sort_by_key(|&(_, code)| (code.bits.checked_shl(64 - code.len as u32).unwrap_or(0), code.len));
+
+        let symbols = Vec::with_capacity(sorted.len());
+        let mut tree = FlatTree { root: FLAT_NONE, nodes: Vec::new(), symbols };
+        for (symbol, code) in sorted {
+            let conflict = || format!("kod {} symbolu {:?} nie jest prefiksowy", code, symbol);
+            let leaf = FLAT_LEAF | tree.symbols.len() as u32;
+            tree.symbols.push(symbol.clone());
+            if code.len == 0 {
+                if tree.root != FLAT_NONE {
+                    return Err(conflict());
+                }
+                tree.root = leaf;
+                continue;
+            }
+            if tree.root == FLAT_NONE {
+                tree.root = tree.add_node();
+            }
+            let mut node = tree.root;
+            for i in (0..code.len).rev() {
+                if node & FLAT_LEAF != 0 {
+                    return Err(conflict());
+                }
+                let bit = ((code.bits >> i) & 1) as usize;
+                let child = match tree.nodes[node as usize][bit] {
+                    FLAT_NONE if i == 0 => leaf,
+                    FLAT_NONE => tree.add_node(),
+                    _ if i == 0 => return Err(conflict()),
+                    child => child,
+                };
+                tree.nodes[node as usize][bit] = child;
+                node = child;
+            }
+        }
+        Ok(tree)
+    }
+
+    fn add_node(&mut self) -> u32 {
+        self.nodes.push([FLAT_NONE; 2]);
+        (self.nodes.len() - 1) as u32
+    }
+
+    /// Krok przed przeczytaniem pierwszego bitu; liść od razu oznacza kod zerowej długości.
+    pub fn start(&self) -> Walk {
+        Self::walk(self.root)
+    }
+
+    /// Krok po bicie `bit` w węźle `node`.
+    pub fn next(&self, node: u32, bit: bool) -> Walk {
+        Self::walk(self.nodes[node as usize][bit as usize])
+    }
+
+    fn walk(entry: u32) -> Walk {
+        match entry {
+            FLAT_NONE => Walk::Missing,
+            _ if entry & FLAT_LEAF != 0 => Walk::Leaf(entry & !FLAT_LEAF),
+            _ => Walk::Node(entry),
+        }
+    }
+
+    pub fn symbol(&self, leaf: u32) -> &Symbol {
+        &self.symbols[leaf as usize]
+    }
+
+    /// Liczba węzłów wewnętrznych.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+/// Węzeł drzewa podawany wizytorom [`Tree`]; `code` to bity ścieżki od korzenia, a `weight`
+/// jest znana tylko w drzewach z [`Tree::from_frequencies`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TreeNode<'a> {
+    /// Węzeł wewnętrzny; waga to suma wag liści jego poddrzewa.
+    Internal { code: Code, weight: Option<u64> },
+    /// Liść z symbolem, którego kodem jest `code`.
+    Leaf { code: Code, symbol: &'a [u8], weight: Option<u64> },
+}
+
+/// Drzewo kodów tylko do odczytu dla narzędzi zewnętrznych (wizualizacje, eksport modeli).
+///
+/// Kształt drzewa wynika z tabeli kodów, więc [`Tree::from_frequencies`] daje drzewo kodów
+/// kanonicznych, którymi koduje format `.huff`, a nie drzewo z łączenia węzłów. Wizytory
+/// dostają [`TreeNode`] z kodem ścieżki; węzły odwiedzane są od lewej (bit 0), czyli
+/// w kolejności leksykograficznej kodów.
+///
+/// ```
+/// use huffman_core::huffman::{FreqTable, Tree, TreeNode};
+///
+/// let frequencies = FreqTable::from([(b"a".to_vec(), 5), (b"b".to_vec(), 2), (b"c".to_vec(), 1)]);
+/// let tree = Tree::from_frequencies(&frequencies, 15)?;
+/// let mut leaves = Vec::new();
+/// tree.visit_leaves(|symbol, code| leaves.push(format!("{}={}", symbol[0] as char, code)));
+/// assert_eq!(leaves, ["a=0", "b=10", "c=11"]);
+///
+/// let mut weights = Vec::new();
+/// tree.visit_preorder(|node| match node {
+///     TreeNode::Internal { weight, .. } | TreeNode::Leaf { weight, .. } => weights.push(weight.unwrap()),
+///     _ => {}
+/// });
+/// assert_eq!(weights, [8, 5, 3, 2, 1]);
+/// # Ok::<(), huffman_core::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree {
+    flat: FlatTree,
+    // Wagi liści (po numerze symbolu) i węzłów wewnętrznych (po numerze węzła)
+    weights: Option<(Vec<u64>, Vec<u64>)>,
+}
+
+impl Tree {
+    /// Drzewo z tabeli kodów; kod będący początkiem innego (albo powtórzony) to błąd.
+    pub fn from_codes(codes: &CodeTable) -> std::result::Result<Tree, String> {
+        Ok(Tree { flat: FlatTree::from_codes(codes)?, weights: None })
+    }
+
+    /// Drzewo kodów kanonicznych z [`assign_codes`] razem z wagami węzłów.
+    pub fn from_frequencies(frequencies: &FreqTable, max_len: u8) -> Result<Tree> {
+        let codes = assign_codes(frequencies, max_len)?;
+        let flat = FlatTree::from_codes(&codes).map_err(HuffmanError::InvalidFrequencies)?;
+        let leaves: Vec<u64> = flat.symbols.iter().map(|symbol| frequencies[symbol]).collect();
+        // Dzieci mają zawsze wyższe numery niż rodzic, więc wystarczy jedno przejście od końca
+        let mut nodes = vec![0u64; flat.nodes.len()];
+        for node in (0..flat.nodes.len()).rev() {
+            let weight = flat.nodes[node]
+                .iter()
+                .map(|&child| match FlatTree::walk(child) {
+                    Walk::Node(child) => nodes[child as usize],
+                    Walk::Leaf(leaf) => leaves[leaf as usize],
+                    Walk::Missing => 0,
+                })
+                .fold(0u64, u64::saturating_add);
+            nodes[node] = weight;
+        }
+        Ok(Tree { flat, weights: Some((leaves, nodes)) })
+    }
+
+    /// Odwiedza wszystkie węzły: najpierw węzeł, potem poddrzewo bitu 0, potem bitu 1.
+    pub fn visit_preorder<'a>(&'a self, mut visit: impl FnMut(TreeNode<'a>)) {
+        // Jawny stos: drzewo z kodami do 64 bitów nie zależy od rozmiaru stosu wątku
+        let mut stack = vec![(self.flat.start(), Code::default())];
+        while let Some((walk, code)) = stack.pop() {
+            match walk {
+                Walk::Node(node) => {
+                    let weight = self.weights.as_ref().map(|(_, nodes)| nodes[node as usize]);
+                    visit(TreeNode::Internal { code, weight });
+                    stack.push((self.flat.next(node, true), code.push(true)));
+                    stack.push((self.flat.next(node, false), code.push(false)));
+                }
+                Walk::Leaf(leaf) => {
+                    let weight = self.weights.as_ref().map(|(leaves, _)| leaves[leaf as usize]);
+                    visit(TreeNode::Leaf { code, symbol: self.flat.symbol(leaf), weight });
+                }
+                Walk::Missing => {}
+            }
+        }
+    }
+
+    /// Odwiedza liście w kolejności leksykograficznej kodów.
+    pub fn visit_leaves<'a>(&'a self, mut visit: impl FnMut(&'a [u8], Code)) {
+        self.visit_preorder(|node| {
+            if let TreeNode::Leaf { code, symbol, .. } = node {
+                visit(symbol, code);
+            }
+        });
+    }
+}
+
+// Sprawdza nierówność Krafta (suma 2^-len musi wynosić dokładnie 1) oraz limit długości kodu.
+// Wyjątkiem jest alfabet jednoelementowy, gdzie jedyny symbol dostaje kod 1-bitowy.
+pub fn validate_code_lengths(lengths: &[u32], max_len: u8) -> std::result::Result<(), String> {
+    if lengths.is_empty() {
+        return Ok(());
+    }
+
+    let longest = lengths.iter().copied().max().unwrap_or(0);
+    if longest > max_len as u32 {
+        return Err(format!("najdłuższy kod ma {} bitów, limit to {}", longest, max_len));
+    }
+
+    if lengths.len() == 1 {
+        return if longest == 1 { Ok(()) } else { Err(format!("jedyny symbol ma kod długości {}", longest)) };
+    }
+
+    // Liczymy w jednostkach 2^-longest, żeby uniknąć arytmetyki zmiennoprzecinkowej
+    let kraft: u128 = lengths.iter().map(|&l| 1u128 << (longest - l)).sum();
+    let full = 1u128 << longest;
+    if kraft != full {
+        return Err(format!("suma Krafta {}/{} zamiast 1", kraft, full));
+    }
+    Ok(())
+}
+
+enum PackageItem {
+    Leaf(usize),
+    Pair(usize, usize),
+}
+
+// Algorytm package-merge: optymalne długości kodów przy ograniczeniu max_len
+pub fn package_merge_lengths(frequencies: &FreqTable, max_len: u8) -> Result<CodeLengths> {
+    let mut leaves: Vec<(u64, &Symbol)> = frequencies.iter().map(|(s, &f)| (f, s)).collect();
+    leaves.sort();
+
+    let n = leaves.len();
+    let mut lengths = HashMap::new();
+    if n == 0 {
+        return Err(HuffmanError::EmptyInput);
+    }
+    if n == 1 {
+        lengths.insert(leaves[0].1.clone(), 1);
+        return Ok(lengths);
+    }
+    if max_len >= 64 || (n as u64) > (1u64 << max_len) {
+        return Err(HuffmanError::CodeLengthLimit { max_len, symbols: n });
+    }
+
+    let mut arena: Vec<(u64, PackageItem)> = leaves
+        .iter()
+        .enumerate()
+        .map(|(i, &(f, _))| (f, PackageItem::Leaf(i)))
+        .collect();
+    let leaf_ids: Vec<usize> = (0..n).collect();
+    let mut level = leaf_ids.clone();
+
+    for _ in 1..max_len {
+        let mut packages = Vec::with_capacity(level.len() / 2);
+        for pair in level.chunks_exact(2) {
+            let weight = arena[pair[0]].0 + arena[pair[1]].0;
+            arena.push((weight, PackageItem::Pair(pair[0], pair[1])));
+            packages.push(arena.len() - 1);
+        }
+
+        // Scalanie dwóch posortowanych list; przy remisie liście idą przed paczkami
+        let mut merged = Vec::with_capacity(n + packages.len());
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < packages.len() {
+            let take_leaf = j >= packages.len()
+                || (i < n && arena[leaf_ids[i]].0 <= arena[packages[j]].0);
+            if take_leaf {
+                merged.push(leaf_ids[i]);
+                i += 1;
+            } else {
+                merged.push(packages[j]);
+                j += 1;
+            }
+        }
+        level = merged;
+    }
+
+    // Długość kodu symbolu = liczba wystąpień jego liścia w 2n-2 najlżejszych elementach
+    let mut counts = vec![0u8; n];
+    let mut stack: Vec<usize> = level[..2 * n - 2].to_vec();
+    while let Some(id) = stack.pop() {
+        match arena[id].1 {
+            PackageItem::Leaf(leaf) => counts[leaf] += 1,
+            PackageItem::Pair(a, b) => {
+                stack.push(a);
+                stack.push(b);
+            }
+        }
+    }
+
+    for (i, &(_, symbol)) in leaves.iter().enumerate() {
+        lengths.insert(symbol.clone(), counts[i]);
+    }
+    Ok(lengths)
+}
+
+// Kody kanoniczne: sortujemy po (długość, symbol) i przydzielamy kolejne wartości
+pub fn canonical_code_table(lengths: &CodeLengths) -> CodeTable {
+    let mut sorted: Vec<(u8, &Symbol)> = lengths.iter().map(|(s, &l)| (l, s)).collect();
+    sorted.sort();
+
+    let mut table = CodeTable::new();
+    let mut code: u64 = 0;
+    let mut prev_len = sorted.first().map(|&(l, _)| l).unwrap_or(0);
+
+    for (len, symbol) in sorted {
+        code <<= len - prev_len;
+        prev_len = len;
+        table.insert(symbol.clone(), Code { bits: code, len });
+        // Po ostatnim kodzie 64-bitowym licznik może się przekręcić - nie jest już używany
+        code = code.wrapping_add(1);
+    }
+    table
+}
+
+// Sprawdza długości kodów odczytane z zewnątrz: zakres 1..=max_len i suma Krafta <= 1
+// (kod może być niepełny, np. pojedynczy symbol z kodem "0").
+pub fn check_code_lengths(lengths: &CodeLengths, max_len: u8) -> std::result::Result<(), String> {
+    let max_len = feasible_max_len(lengths.len(), max_len).min(MAX_CODE_LEN_LIMIT);
+    if let Some((symbol, &len)) = lengths.iter().find(|&(_, &l)| l == 0 || l > max_len) {
+        return Err(format!("symbol {:?} ma kod długości {}, dozwolone 1..={}", symbol, len, max_len));
+    }
+
+    let kraft: u128 = lengths.values().map(|&l| 1u128 << (MAX_CODE_LEN_LIMIT - l)).sum();
+    if kraft > 1u128 << MAX_CODE_LEN_LIMIT {
+        return Err("suma Krafta przekracza 1".to_string());
+    }
+    Ok(())
+}
+
+// Limit mniejszy niż ceil(log2 n) jest niewykonalny - enkoder go podnosi, a dekoder
+// musi przy sprawdzaniu nagłówka podnieść go tak samo
+fn feasible_max_len(symbols: usize, max_len: u8) -> u8 {
+    let min_len = (usize::BITS - symbols.saturating_sub(1).leading_zeros()).max(1) as u8;
+    max_len.max(min_len)
+}
+
+// Kody odczytane wprost z drzewa Huffmana (format v0). Przy naruszeniu ograniczeń
+// naprawia długości algorytmem package-merge. Enkoder i dekoder muszą wołać
+// tę samą funkcję z tym samym limitem, żeby dostać identyczne kody.
+pub fn assign_tree_codes(frequencies: &FreqTable, max_len: u8) -> Result<CodeTable> {
+    let tree = build_huffman_tree(frequencies)?;
+
+    let max_len = feasible_max_len(frequencies.len(), max_len);
+
+    let depths = leaf_depths(&tree);
+    let lengths: Vec<u32> = depths.values().copied().collect();
+
+    if let Err(reason) = validate_code_lengths(&lengths, max_len) {
+        let lengths = package_merge_lengths(frequencies, max_len)?;
+        let repaired = canonical_code_table(&lengths);
+        warn!(
+            "Naprawiono długości kodów ({}): {} symboli, maks. długość {} -> {}",
+            reason,
+            depths.len(),
+            depths.values().max().unwrap_or(&0),
+            repaired.values().map(|c| c.len).max().unwrap_or(0)
+        );
+        return Ok(repaired);
+    }
+
+    let mut table = CodeTable::new();
+    build_code_table(&tree, Code::default(), &mut table);
+    Ok(table)
+}
+
+// Kody z drzewa budowanego w kolejności par z nagłówka, jak w pierwszej wersji programu
+// (bez limitu długości kodu w nagłówku). Drzewa nie da się naprawić bez zmiany kodów,
+// więc kod dłuższy niż 64 bity jest błędem.
+pub fn assign_legacy_codes(entries: &[(Symbol, u64)]) -> Result<CodeTable> {
+    let tree = build_huffman_tree_in_order(entries)?;
+
+    let depths = leaf_depths(&tree);
+    let lengths: Vec<u32> = depths.values().copied().collect();
+    validate_code_lengths(&lengths, MAX_CODE_LEN_LIMIT).map_err(HuffmanError::InvalidFrequencies)?;
+
+    let mut table = CodeTable::new();
+    build_code_table(&tree, Code::default(), &mut table);
+    Ok(table)
+}
+
+// Długości kodów Huffmana z uwzględnieniem limitu max_len
+pub fn code_lengths(frequencies: &FreqTable, max_len: u8) -> Result<CodeLengths> {
+    Ok(assign_tree_codes(frequencies, max_len)?
+        .into_iter()
+        .map(|(symbol, code)| (symbol, code.len))
+        .collect())
+}
+
+// Kody kanoniczne zbudowane z długości - zależą tylko od długości, nie od kształtu drzewa,
+// więc dekoder odtwarza je z samego nagłówka.
+pub fn assign_codes(frequencies: &FreqTable, max_len: u8) -> Result<CodeTable> {
+    Ok(canonical_code_table(&code_lengths(frequencies, max_len)?))
+}
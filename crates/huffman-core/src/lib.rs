@@ -0,0 +1,16 @@
+//! Algorytmy kodowania Huffmana niezależne od formatu pliku: budowa drzewa,
+//! tabele kodów (z limitem długości przez package-merge), arytmetyka tablic
+//! częstotliwości, alfabety wejścia, zapis i odczyt bitów, sumy kontrolne
+//! i adaptacyjny model FGK, a także generatory powtarzalnych danych testowych.
+
+pub mod adaptive;
+pub mod alphabet;
+pub mod bitio;
+pub mod checksum;
+pub mod error;
+pub mod freq;
+pub mod huffman;
+pub mod testdata;
+
+pub use alphabet::Alphabet;
+pub use error::{HuffmanError, Position, Resource, Result};
@@ -0,0 +1,182 @@
+// Powtarzalne dane syntetyczne do testów, pomiarów i przykładów: ten sam `seed`
+// daje te same bajty na każdej maszynie (tylko arytmetyka całkowita i podstawowe
+// działania na f64, bez funkcji z libm)
+use std::fmt;
+use std::str::FromStr;
+
+/// Ziarno używane przez `huff bench`, żeby wyniki z różnych maszyn były porównywalne.
+pub const DEFAULT_SEED: u64 = 0x4855_4646;
+
+/// Generator SplitMix64: mały, szybki i z ustalonym przebiegiem dla danego ziarna.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Liczba z przedziału `0..n` (`n` > 0).
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// Liczba z przedziału `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Rodzaj syntetycznego korpusu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corpus {
+    /// Słowa ze stałego słownika o rozkładzie Zipfa, jak tekst naturalny.
+    Zipf,
+    /// Tekst z łańcucha Markowa rzędu 1: korzysta na modelu kontekstowym.
+    Markov,
+    /// Serie powtórzeń kilku bajtów.
+    Runs,
+    /// Jednostajnie losowe bajty (nieściśliwe).
+    Random,
+}
+
+impl Corpus {
+    pub const ALL: [Corpus; 4] = [Corpus::Zipf, Corpus::Markov, Corpus::Runs, Corpus::Random];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Corpus::Zipf => "zipf",
+            Corpus::Markov => "markov",
+            Corpus::Runs => "runs",
+            Corpus::Random => "random",
+        }
+    }
+
+    /// Generuje dokładnie `len` bajtów.
+    ///
+    /// ```
+    /// use huffman_core::testdata::Corpus;
+    ///
+    /// let text = Corpus::Zipf.generate(1000, 7);
+    /// assert_eq!(text.len(), 1000);
+    /// assert_eq!(text, Corpus::Zipf.generate(1000, 7));
+    /// ```
+    pub fn generate(self, len: usize, seed: u64) -> Vec<u8> {
+        match self {
+            Corpus::Zipf => zipf_text(len, seed),
+            Corpus::Markov => markov_text(len, seed),
+            Corpus::Runs => runs(len, seed),
+            Corpus::Random => random(len, seed),
+        }
+    }
+}
+
+impl fmt::Display for Corpus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Corpus {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Corpus::ALL
+            .into_iter()
+            .find(|corpus| corpus.name() == s)
+            .ok_or_else(|| format!("nieznany korpus '{}' (dostępne: zipf, markov, runs, random)", s))
+    }
+}
+
+const VOCABULARY: usize = 2048;
+const MARKOV_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz .,\n";
+
+// Losuje indeks zgodnie z dystrybuantą `cdf` (rosnącą, ostatni element to suma wag)
+fn sample(rng: &mut Rng, cdf: &[f64]) -> usize {
+    let target = rng.next_f64() * cdf[cdf.len() - 1];
+    cdf.partition_point(|&c| c <= target).min(cdf.len() - 1)
+}
+
+fn cumulative(weights: impl Iterator<Item = f64>) -> Vec<f64> {
+    weights
+        .scan(0.0, |sum, w| {
+            *sum += w;
+            Some(*sum)
+        })
+        .collect()
+}
+
+/// Słowa ze słownika 2048 losowych słów; słowo o randze `k` pada z wagą `1/(k+1)`.
+pub fn zipf_text(len: usize, seed: u64) -> Vec<u8> {
+    let mut rng = Rng::new(seed);
+    let words: Vec<Vec<u8>> = (0..VOCABULARY)
+        .map(|_| {
+            let word_len = 1 + rng.below(4) + rng.below(5);
+            (0..word_len).map(|_| b'a' + rng.below(26) as u8).collect()
+        })
+        .collect();
+    let cdf = cumulative((0..VOCABULARY).map(|k| 1.0 / (k + 1) as f64));
+
+    let mut out = Vec::with_capacity(len + 16);
+    while out.len() < len {
+        out.extend_from_slice(&words[sample(&mut rng, &cdf)]);
+        out.push(if rng.below(12) == 0 { b'\n' } else { b' ' });
+    }
+    out.truncate(len);
+    out
+}
+
+/// Tekst, w którym każdy znak ma kilka prawdopodobnych następników.
+pub fn markov_text(len: usize, seed: u64) -> Vec<u8> {
+    let mut rng = Rng::new(seed);
+    // Czwarta potęga rozkładu jednostajnego skupia masę na kilku przejściach
+    let transitions: Vec<Vec<f64>> = MARKOV_ALPHABET
+        .iter()
+        .map(|_| {
+            cumulative((0..MARKOV_ALPHABET.len()).map(|_| {
+                let u = rng.next_f64();
+                u * u * u * u
+            }))
+        })
+        .collect();
+
+    let mut state = 0;
+    let mut out = Vec::with_capacity(len);
+    while out.len() < len {
+        state = sample(&mut rng, &transitions[state]);
+        out.push(MARKOV_ALPHABET[state]);
+    }
+    out
+}
+
+/// Serie długości 1..=64 złożone z ośmiu losowo wybranych bajtów.
+pub fn runs(len: usize, seed: u64) -> Vec<u8> {
+    let mut rng = Rng::new(seed);
+    let symbols: Vec<u8> = (0..8).map(|_| rng.next_u64() as u8).collect();
+    let mut out = Vec::with_capacity(len + 64);
+    while out.len() < len {
+        let byte = symbols[rng.below(symbols.len() as u64) as usize];
+        let run = 1 + rng.below(64) as usize;
+        out.resize(out.len() + run, byte);
+    }
+    out.truncate(len);
+    out
+}
+
+pub fn random(len: usize, seed: u64) -> Vec<u8> {
+    let mut rng = Rng::new(seed);
+    let mut out = Vec::with_capacity(len + 8);
+    while out.len() < len {
+        out.extend_from_slice(&rng.next_u64().to_le_bytes());
+    }
+    out.truncate(len);
+    out
+}
@@ -0,0 +1,51 @@
+// Zlicza symbole próbek z presets/ i zapisuje je do OUT_DIR/presets.rs, skąd dict.rs buduje
+// wbudowane słowniki (--preset english itd.); same próbki nie trafiają do biblioteki
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+// Filtr znaczników czasu z biblioteki: słownik logs liczy próbkę tak, jak zobaczy ją koder
+#[path = "src/timestamps.rs"]
+#[allow(dead_code)]
+mod timestamps;
+
+// Nazwa, plik próbki, rząd modelu i kolumny filtra timestamps (0 - bez filtra);
+// dla zakodowanych bajtów i DNA kontekst niewiele daje
+const PRESETS: [(&str, &str, usize, usize); 6] = [
+    ("english", "english.txt", 1, 0),
+    ("json", "json.txt", 1, 0),
+    ("csv", "csv.txt", 1, 0),
+    ("base64", "base64.txt", 0, 0),
+    ("dna", "dna.txt", 0, 0),
+    ("logs", "logs.txt", 2, 32),
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=presets");
+    println!("cargo:rerun-if-changed=src/timestamps.rs");
+    let mut out = String::from("&[\n");
+    for (name, file, order, columns) in PRESETS {
+        let sample = fs::read(Path::new("presets").join(file)).unwrap_or_else(|e| panic!("presets/{}: {}", file, e));
+        let sample = if columns > 0 { timestamps::encode(&sample, columns) } else { sample };
+        // Jak koder: pierwszy kontekst to same zera
+        let mut context = vec![0u8; order];
+        let mut counts: BTreeMap<(Vec<u8>, u8), u64> = BTreeMap::new();
+        for &byte in &sample {
+            *counts.entry((context.clone(), byte)).or_insert(0) += 1;
+            if order > 0 {
+                context.remove(0);
+                context.push(byte);
+            }
+        }
+        let _ = writeln!(out, "    PresetCounts {{ name: {:?}, order: {}, counts: &[", name, order);
+        for ((context, symbol), count) in counts {
+            let _ = writeln!(out, "        (&{:?}, {}, {}),", context, symbol, count);
+        }
+        out.push_str("    ] },\n");
+    }
+    out.push(']');
+    let path = Path::new(&env::var("OUT_DIR").expect("OUT_DIR ustawia cargo")).join("presets.rs");
+    fs::write(path, out).expect("zapis presets.rs");
+}
@@ -0,0 +1,274 @@
+use std::io::{self, Read, Write};
+
+pub use huffman_core::adaptive::{AdaptiveModel, EOF_SYMBOL};
+
+use crate::bitio::{BitReader, BitWriter};
+use crate::checksum::{Crc32, crc32};
+use crate::codec::Stats;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::entropy_from_freq;
+
+/// Sygnatura strumienia adaptacyjnego.
+pub const ADAPTIVE_MAGIC: [u8; 4] = *b"HADP";
+
+const ADAPTIVE_VERSION: u8 = 1;
+// Strumień z modelem startowym: za wersją leży `u32` identyfikator presetu
+const PRESET_VERSION: u8 = 2;
+const PREFIX_LEN: usize = ADAPTIVE_MAGIC.len() + 1;
+// Suma liczności presetu po przeskalowaniu; większa spowalniałaby dostosowanie do danych
+const PRESET_TOTAL: u64 = 4096;
+
+/// Model startowy trybu adaptacyjnego: liczności bajtów, od których zaczyna
+/// [`AdaptiveWriter::with_preset`] i [`AdaptiveReader::with_preset`].
+///
+/// Liczności są skalowane do sumy najwyżej 4096, więc preset tylko nadaje
+/// kierunek, a po kilku kilobajtach przeważają już same dane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdaptivePreset {
+    counts: [u64; 256],
+}
+
+impl AdaptivePreset {
+    /// Preset z liczności bajtów próbki (np. typowego pliku danego rodzaju).
+    pub fn from_sample(sample: &[u8]) -> Self {
+        let mut counts = [0u64; 256];
+        for &byte in sample {
+            counts[byte as usize] += 1;
+        }
+        Self::from_counts(&counts)
+    }
+
+    pub fn from_counts(counts: &[u64; 256]) -> Self {
+        let total: u64 = counts.iter().sum();
+        let mut scaled = *counts;
+        if total > PRESET_TOTAL {
+            for count in scaled.iter_mut().filter(|c| **c > 0) {
+                *count = (*count as u128 * PRESET_TOTAL as u128 / total as u128).max(1) as u64;
+            }
+        }
+        AdaptivePreset { counts: scaled }
+    }
+
+    /// Przeskalowane liczności.
+    pub fn counts(&self) -> &[u64; 256] {
+        &self.counts
+    }
+
+    /// Identyfikator zapisywany w strumieniu: CRC-32 liczności (`u16` big-endian),
+    /// żeby dekoder z innym presetem zgłosił błąd zamiast zwracać śmieci.
+    pub fn id(&self) -> u32 {
+        let bytes: Vec<u8> = self.counts.iter().flat_map(|&c| (c as u16).to_be_bytes()).collect();
+        crc32(&bytes)
+    }
+}
+
+/// Koder jednoprzebiegowy: każdy bajt jest kodowany od razu modelem
+/// [`AdaptiveModel`], bez bufora bloku i bez tablicy kodów w nagłówku.
+///
+/// Układ strumienia: `HADP`, `u8` wersja, bity zakończone symbolem końca
+/// ([`EOF_SYMBOL`]) i dopełnione do bajtu, CRC-32 oryginału
+/// (4 bajty big-endian). Odczytuje go [`AdaptiveReader`] i [`crate::decompress`].
+/// Strumień z [`AdaptiveWriter::with_preset`] ma wersję 2 i za nią `u32` [`AdaptivePreset::id`].
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use huffman_format::{AdaptiveReader, AdaptiveWriter};
+///
+/// let mut writer = AdaptiveWriter::new(Vec::new())?;
+/// writer.write_all(b"abracadabra")?;
+/// let (packed, _) = writer.finish()?;
+///
+/// let mut decoded = Vec::new();
+/// AdaptiveReader::new(&packed[..])?.read_to_end(&mut decoded)?;
+/// assert_eq!(decoded, b"abracadabra");
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub struct AdaptiveWriter<W: Write> {
+    bits: BitWriter<W>,
+    model: AdaptiveModel,
+    crc: Crc32,
+    total_in: u64,
+    header_len: usize,
+    // Liczności presetu, odejmowane od wag modelu w statystykach
+    preset: [u64; 256],
+}
+
+impl<W: Write> AdaptiveWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner.write_all(&ADAPTIVE_MAGIC)?;
+        inner.write_all(&[ADAPTIVE_VERSION])?;
+        Ok(AdaptiveWriter {
+            bits: BitWriter::new(inner),
+            model: AdaptiveModel::new(),
+            crc: Crc32::new(),
+            total_in: 0,
+            header_len: PREFIX_LEN,
+            preset: [0; 256],
+        })
+    }
+
+    /// Koder startujący od modelu `preset` zamiast od pustego drzewa; dekoder
+    /// musi dostać ten sam preset.
+    pub fn with_preset(mut inner: W, preset: &AdaptivePreset) -> Result<Self> {
+        inner.write_all(&ADAPTIVE_MAGIC)?;
+        inner.write_all(&[PRESET_VERSION])?;
+        inner.write_all(&preset.id().to_be_bytes())?;
+        Ok(AdaptiveWriter {
+            bits: BitWriter::new(inner),
+            model: AdaptiveModel::with_counts(preset.counts()),
+            crc: Crc32::new(),
+            total_in: 0,
+            header_len: PREFIX_LEN + 4,
+            preset: preset.counts,
+        })
+    }
+
+    /// Liczba bajtów przyjętych do kompresji.
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// Zapisuje symbol końca i sumę kontrolną; zwraca `W` i statystyki
+    /// (entropia rzędu 0 z liczności zebranych przez model).
+    pub fn finish(mut self) -> Result<(W, Stats)> {
+        self.model.encode(EOF_SYMBOL, &mut self.bits)?;
+        let payload_len = self.bits.bits_written().div_ceil(8) as usize;
+        let mut inner = self.bits.finish()?;
+        inner.write_all(&self.crc.finish().to_be_bytes())?;
+        inner.flush()?;
+
+        let mut frequencies = self.model.frequencies();
+        for (symbol, count) in frequencies.iter_mut() {
+            *count -= self.preset[symbol[0] as usize];
+        }
+        frequencies.retain(|_, count| *count > 0);
+        let stats = Stats {
+            original_len: self.total_in,
+            header_len: self.header_len,
+            payload_len,
+            footer_len: 4,
+            entropy: if self.total_in > 0 { entropy_from_freq(&frequencies) } else { 0.0 },
+        };
+        Ok((inner, stats))
+    }
+}
+
+impl<W: Write> Write for AdaptiveWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.model.encode(byte as u16, &mut self.bits)?;
+        }
+        self.crc.update(buf);
+        self.total_in += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Niepełne słowo zostaje w akumulatorze do finish
+        Ok(())
+    }
+}
+
+/// Dekoder strumienia z [`AdaptiveWriter`]; przy niebuforowanym `R` warto go
+/// opakować w `BufReader`, bo bity są pobierane po bajcie.
+pub struct AdaptiveReader<R: Read> {
+    bits: BitReader<R>,
+    model: AdaptiveModel,
+    crc: Crc32,
+    finished: bool,
+    footer_checked: bool,
+}
+
+impl<R: Read> AdaptiveReader<R> {
+    pub fn new(inner: R) -> Result<Self> {
+        Self::open(inner, None)
+    }
+
+    /// Dekoder strumienia, który może używać modelu startowego; strumień
+    /// bez presetu jest dekodowany zwykłym, pustym modelem.
+    pub fn with_preset(inner: R, preset: &AdaptivePreset) -> Result<Self> {
+        Self::open(inner, Some(preset))
+    }
+
+    fn open(mut inner: R, preset: Option<&AdaptivePreset>) -> Result<Self> {
+        let mut prefix = [0u8; PREFIX_LEN];
+        inner.read_exact(&mut prefix)?;
+        if !prefix.starts_with(&ADAPTIVE_MAGIC) {
+            return Err(HuffmanError::MalformedHeader("brak sygnatury HADP".to_string()));
+        }
+        let model = match prefix[4] {
+            ADAPTIVE_VERSION => AdaptiveModel::new(),
+            PRESET_VERSION => {
+                let mut id = [0u8; 4];
+                inner.read_exact(&mut id)?;
+                let id = u32::from_be_bytes(id);
+                match preset {
+                    Some(preset) if preset.id() == id => AdaptiveModel::with_counts(preset.counts()),
+                    Some(preset) => {
+                        return Err(HuffmanError::InvalidOptions(format!(
+                            "model startowy {:08x} nie pasuje do strumienia (wymaga {:08x})",
+                            preset.id(),
+                            id
+                        )));
+                    }
+                    None => {
+                        return Err(HuffmanError::InvalidOptions(format!(
+                            "strumień adaptacyjny wymaga modelu startowego {:08x}",
+                            id
+                        )));
+                    }
+                }
+            }
+            version => {
+                return Err(HuffmanError::MalformedHeader(format!(
+                    "nieobsługiwana wersja strumienia adaptacyjnego {}",
+                    version
+                )));
+            }
+        };
+        Ok(AdaptiveReader {
+            bits: BitReader::new(inner),
+            model,
+            crc: Crc32::new(),
+            finished: false,
+            footer_checked: false,
+        })
+    }
+
+    fn check_footer(&mut self) -> Result<()> {
+        let mut footer = [0u8; 4];
+        self.bits.get_mut().read_exact(&mut footer)?;
+        let expected = u32::from_be_bytes(footer);
+        let actual = self.crc.finish();
+        if expected != actual {
+            return Err(HuffmanError::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for AdaptiveReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() && !self.finished {
+            match self.model.decode(&mut self.bits)? {
+                Some(EOF_SYMBOL) => self.finished = true,
+                Some(symbol) => {
+                    buf[n] = symbol as u8;
+                    n += 1;
+                }
+                None => {
+                    let message = "strumień adaptacyjny bez symbolu końca";
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+                }
+            }
+        }
+        self.crc.update(&buf[..n]);
+        if self.finished && !self.footer_checked && n < buf.len() {
+            // Suma obejmuje już wszystkie bajty; kolejne wywołania zwrócą 0
+            self.footer_checked = true;
+            self.check_footer()?;
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::checksum::crc32;
+use crate::codec::{DecodeOptions, EncoderScratch, Options, Stats, compress_into};
+use crate::error::{HuffmanError, Result};
+use crate::reader::HuffmanReader;
+use crate::stream::compress_stream;
+
+/// Sygnatura archiwum wielu plików.
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"HARC";
+
+const ARCHIVE_VERSION: u8 = 1;
+// Archiwum "solid": jeden strumień ramek dla wszystkich plików
+const SOLID_VERSION: u8 = 2;
+// Archiwum z partiami małych plików: katalog ma też offset pliku w ramce partii
+const BATCH_VERSION: u8 = 3;
+// Archiwum z deduplikacją bloków (bez partii i z nimi): katalog ma też liczbę bloków pliku
+const DEDUP_VERSION: u8 = 4;
+const DEDUP_BATCH_VERSION: u8 = 5;
+
+/// Jeden plik w archiwum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// Ścieżka względna z separatorem `/`.
+    pub path: String,
+    pub original_len: u64,
+    /// Offset skompresowanych danych liczony od początku archiwum, a w archiwum
+    /// [`Archive::solid`] - offset pliku we wspólnym zdekodowanym strumieniu.
+    pub offset: u64,
+    /// Długość skompresowanych danych; 0 dla pustego pliku i w archiwum solid.
+    pub compressed_len: u64,
+    /// Offset pliku w zdekodowanych danych spod `offset`; poza 0 tylko w archiwum
+    /// [`Archive::batched`], gdzie kilka małych plików dzieli jedną ramkę.
+    pub batch_offset: u64,
+    /// Liczba bloków pliku w archiwum [`Archive::deduped`]; przy 0 dane leżą w jednym odcinku,
+    /// a inaczej pod `offset` jest lista `blocks` par `u64` offset, `u64` długość jego ramek.
+    pub blocks: u32,
+}
+
+impl Member {
+    /// Lista `(offset, długość)` ramek pliku z `blocks > 0`, czytana z `reader` ustawionego na `offset`.
+    pub fn read_blocks<R: Read>(&self, reader: &mut R) -> Result<Vec<(u64, u64)>> {
+        let mut list = Vec::new();
+        reader.take(self.compressed_len).read_to_end(&mut list)?;
+        if list.len() as u64 != self.compressed_len {
+            return Err(HuffmanError::TruncatedStream { decoded: list.len() as u64, expected: self.compressed_len });
+        }
+        let field = |entry: &[u8], at: usize| u64::from_be_bytes(entry[at..at + 8].try_into().expect("8 bajtów"));
+        Ok(list.chunks_exact(16).map(|entry| (field(entry, 0), field(entry, 8))).collect())
+    }
+}
+
+/// Katalog archiwum: lista plików zapisana przed ich danymi.
+///
+/// Układ (big-endian): `HARC`, `u8` wersja, `u32` liczba plików, dla każdego
+/// `u16` długość ścieżki, ścieżka w UTF-8, `u64` długość oryginału, `u64` offset,
+/// `u64` długość danych. Dane każdego pliku to zwykły strumień ramek `.huff`.
+///
+/// Archiwum solid (wersja 2) ma ten sam katalog, ale za nim leży jeden strumień
+/// ramek z połączoną zawartością wszystkich plików, w kolejności katalogu.
+///
+/// Archiwum z partiami (wersja 3) ma za długością danych jeszcze `u64` offset pliku
+/// w zdekodowanej ramce: pliki jednej partii wskazują tę samą ramkę.
+///
+/// Archiwum z deduplikacją bloków (wersja 4, a z partiami 5) ma pola wersji 3 i jeszcze
+/// `u32` liczbę bloków. Plik z wieloma blokami wskazuje zamiast ramek ich listę
+/// (`u64` offset, `u64` długość każdej), leżącą w danych archiwum przed jego nowymi ramkami.
+///
+/// Kilka plików może wskazywać te same dane (ten sam offset i długość), a listy bloków
+/// te same ramki, gdy [`ArchiveWriter::with_dedup`] zapisał powtórzony blok tylko raz.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Archive {
+    pub members: Vec<Member>,
+    pub solid: bool,
+    /// Małe pliki są zebrane w partie przez [`ArchiveWriter::with_batches`].
+    pub batched: bool,
+    /// Pliki mogą mieć listy bloków z [`ArchiveWriter::with_dedup`].
+    pub deduped: bool,
+}
+
+impl Archive {
+    /// Czy dane zaczynają się od sygnatury archiwum.
+    pub fn is_archive(prefix: &[u8]) -> bool {
+        prefix.starts_with(&ARCHIVE_MAGIC)
+    }
+
+    pub fn byte_len(&self) -> usize {
+        let fields = match (self.batched || self.deduped, self.deduped) {
+            (_, true) => 36,
+            (true, false) => 32,
+            (false, false) => 24,
+        };
+        9 + self.members.iter().map(|m| 2 + m.path.len() + fields).sum::<usize>()
+    }
+
+    pub fn write_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&ARCHIVE_MAGIC);
+        out.push(match (self.solid, self.batched, self.deduped) {
+            (true, _, _) => SOLID_VERSION,
+            (false, true, true) => DEDUP_BATCH_VERSION,
+            (false, false, true) => DEDUP_VERSION,
+            (false, true, false) => BATCH_VERSION,
+            (false, false, false) => ARCHIVE_VERSION,
+        });
+        out.extend_from_slice(&(self.members.len() as u32).to_be_bytes());
+        for member in &self.members {
+            out.extend_from_slice(&(member.path.len() as u16).to_be_bytes());
+            out.extend_from_slice(member.path.as_bytes());
+            out.extend_from_slice(&member.original_len.to_be_bytes());
+            out.extend_from_slice(&member.offset.to_be_bytes());
+            out.extend_from_slice(&member.compressed_len.to_be_bytes());
+            if self.batched || self.deduped {
+                out.extend_from_slice(&member.batch_offset.to_be_bytes());
+            }
+            if self.deduped {
+                out.extend_from_slice(&member.blocks.to_be_bytes());
+            }
+        }
+    }
+
+    /// Czyta katalog z początku archiwum; ścieżki wychodzące poza katalog docelowy są odrzucane.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Archive> {
+        let malformed = |what: String| HuffmanError::MalformedHeader(format!("katalog archiwum: {}", what));
+        let mut head = [0u8; 9];
+        reader.read_exact(&mut head)?;
+        if !Archive::is_archive(&head) {
+            return Err(malformed("brak sygnatury HARC".to_string()));
+        }
+        let (solid, batched, deduped) = match head[4] {
+            ARCHIVE_VERSION => (false, false, false),
+            SOLID_VERSION => (true, false, false),
+            BATCH_VERSION => (false, true, false),
+            DEDUP_VERSION => (false, false, true),
+            DEDUP_BATCH_VERSION => (false, true, true),
+            version => return Err(malformed(format!("nieobsługiwana wersja {}", version))),
+        };
+        let count = u32::from_be_bytes(head[5..9].try_into().expect("4 bajty"));
+
+        let mut members = Vec::new();
+        let mut buf2 = [0u8; 2];
+        let mut buf8 = [0u8; 8];
+        for _ in 0..count {
+            reader.read_exact(&mut buf2)?;
+            let mut path = vec![0u8; u16::from_be_bytes(buf2) as usize];
+            reader.read_exact(&mut path)?;
+            let path = String::from_utf8(path).map_err(|_| malformed("ścieżka nie jest UTF-8".to_string()))?;
+            check_path(&path).map_err(malformed)?;
+
+            let mut fields = [0u64; 4];
+            for field in &mut fields[..if batched || deduped { 4 } else { 3 }] {
+                reader.read_exact(&mut buf8)?;
+                *field = u64::from_be_bytes(buf8);
+            }
+            let mut blocks = 0;
+            if deduped {
+                let mut buf4 = [0u8; 4];
+                reader.read_exact(&mut buf4)?;
+                blocks = u32::from_be_bytes(buf4);
+            }
+            if blocks > 0 && fields[2] != 16 * blocks as u64 {
+                return Err(malformed(format!("lista {} bloków pliku '{}' ma {} bajtów", blocks, path, fields[2])));
+            }
+            members.push(Member {
+                path,
+                original_len: fields[0],
+                offset: fields[1],
+                compressed_len: fields[2],
+                batch_offset: fields[3],
+                blocks,
+            });
+        }
+        if solid {
+            // Pliki leżą w strumieniu jeden za drugim, bez przerw
+            let mut next = 0u64;
+            for member in &members {
+                if member.offset != next {
+                    return Err(malformed(format!("plik '{}' nie zaczyna się na offsecie {}", member.path, next)));
+                }
+                next = next.checked_add(member.original_len).ok_or_else(|| malformed("za duże pliki".to_string()))?;
+            }
+        }
+        Ok(Archive { members, solid, batched, deduped })
+    }
+
+    /// Pliki archiwum w kolejności katalogu, bez szczegółów układu danych.
+    pub fn members(&self) -> impl Iterator<Item = MemberInfo<'_>> {
+        let mut frames: HashMap<(u64, u64), usize> = HashMap::new();
+        for member in &self.members {
+            *frames.entry((member.offset, member.compressed_len)).or_default() += 1;
+        }
+        self.members.iter().map(move |member| {
+            let shared = self.solid || member.blocks > 0 || frames[&(member.offset, member.compressed_len)] > 1;
+            MemberInfo {
+                path: &member.path,
+                original_len: member.original_len,
+                compressed_len: (!shared).then_some(member.compressed_len),
+            }
+        })
+    }
+
+    /// Otwiera plik `path` do czytania: dane są dekodowane porcjami w trakcie czytania,
+    /// więc plik nie musi mieścić się w pamięci. `reader` to całe archiwum, od którego
+    /// początku liczone są offsety katalogu.
+    ///
+    /// W archiwum solid i w partii małych plików wcześniejsze dane ramki są dekodowane
+    /// i pomijane. Dane krótsze niż długość z katalogu dają przy czytaniu błąd, a nie koniec pliku.
+    ///
+    /// ```
+    /// use std::io::{Cursor, Read};
+    /// use huffman_format::{Archive, ArchiveWriter, Options};
+    ///
+    /// let files: [(&str, &[u8]); 2] = [("a.txt", b"abracadabra"), ("b/c.txt", b"hokus pokus")];
+    /// let paths = files.iter().map(|(path, _)| path.to_string()).collect();
+    /// let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), paths)?;
+    /// for (_, data) in files {
+    ///     writer.add_member(&mut Cursor::new(data), &Options::default())?;
+    /// }
+    /// let mut packed = writer.finish()?.0;
+    ///
+    /// packed.set_position(0);
+    /// let archive = Archive::read_from(&mut packed)?;
+    /// let names: Vec<_> = archive.members().map(|member| member.path).collect();
+    /// assert_eq!(names, ["a.txt", "b/c.txt"]);
+    /// let mut content = String::new();
+    /// archive.read_member_streaming(&mut packed, "b/c.txt")?.read_to_string(&mut content)?;
+    /// assert_eq!(content, "hokus pokus");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn read_member_streaming<'r, R: Read + Seek>(
+        &self,
+        reader: &'r mut R,
+        path: &str,
+    ) -> Result<MemberReader<'r, R>> {
+        self.read_member_streaming_with(reader, path, DecodeOptions::default())
+    }
+
+    /// Jak [`Archive::read_member_streaming`], ale z limitami dekodera z `options`.
+    pub fn read_member_streaming_with<'r, R: Read + Seek>(
+        &self,
+        reader: &'r mut R,
+        path: &str,
+        options: DecodeOptions,
+    ) -> Result<MemberReader<'r, R>> {
+        let member = self
+            .members
+            .iter()
+            .find(|member| member.path == path)
+            .ok_or_else(|| HuffmanError::InvalidOptions(format!("brak pliku '{}' w archiwum", path)))?;
+        let expected = member.original_len;
+        if expected == 0 {
+            return Ok(MemberReader { frames: None, remaining: 0, expected });
+        }
+        if !self.solid && member.compressed_len == 0 {
+            return Ok(MemberReader { frames: None, remaining: expected, expected });
+        }
+        // Solid: jeden strumień za katalogiem, a plik leży w nim od `offset`
+        let (frames, skip) = match self.solid {
+            true => (MemberFrames::new(reader, vec![(self.byte_len() as u64, u64::MAX)]), member.offset),
+            false => (self.member_frames(reader, member)?, member.batch_offset),
+        };
+        let mut frames = HuffmanReader::with_options(frames, options)?;
+        let skipped = io::copy(&mut (&mut frames).take(skip), &mut io::sink())?;
+        if skipped < skip {
+            return Err(HuffmanError::TruncatedStream { decoded: skipped, expected: skip });
+        }
+        Ok(MemberReader { frames: Some(frames), remaining: expected, expected })
+    }
+
+    /// Skompresowane ramki pliku poza archiwum solid, do dekodowania np. [`HuffmanReader`];
+    /// plik z listą bloków daje po kolei ramki wszystkich swoich bloków.
+    pub fn member_frames<'r, R: Read + Seek>(&self, reader: &'r mut R, member: &Member) -> Result<MemberFrames<'r, R>> {
+        if self.solid {
+            return Err(HuffmanError::InvalidOptions("pliki archiwum solid nie mają osobnych ramek".to_string()));
+        }
+        if member.blocks == 0 {
+            return Ok(MemberFrames::new(reader, vec![(member.offset, member.compressed_len)]));
+        }
+        reader.seek(SeekFrom::Start(member.offset))?;
+        let blocks = member.read_blocks(reader)?;
+        Ok(MemberFrames::new(reader, blocks))
+    }
+}
+
+/// Ramki pliku z [`Archive::member_frames`]: kolejne odcinki archiwum `(offset, długość)`.
+pub struct MemberFrames<'r, R> {
+    reader: &'r mut R,
+    blocks: std::vec::IntoIter<(u64, u64)>,
+    remaining: u64,
+}
+
+impl<'r, R> MemberFrames<'r, R> {
+    fn new(reader: &'r mut R, blocks: Vec<(u64, u64)>) -> Self {
+        MemberFrames { reader, blocks: blocks.into_iter(), remaining: 0 }
+    }
+}
+
+impl<R: Read + Seek> Read for MemberFrames<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.remaining == 0 {
+            let Some((offset, len)) = self.blocks.next() else {
+                return Ok(0);
+            };
+            self.reader.seek(SeekFrom::Start(offset))?;
+            self.remaining = len;
+        }
+        let limit = buf.len().min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = self.reader.read(&mut buf[..limit])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Plik archiwum z [`Archive::members`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemberInfo<'a> {
+    pub path: &'a str,
+    pub original_len: u64,
+    /// Skompresowane bajty tylko tego pliku; `None`, gdy dzieli dane z innymi (archiwum solid,
+    /// partia małych plików, powtórzona zawartość albo lista bloków).
+    pub compressed_len: Option<u64>,
+}
+
+/// Zawartość jednego pliku archiwum z [`Archive::read_member_streaming`].
+pub struct MemberReader<'r, R: Read + Seek> {
+    frames: Option<HuffmanReader<MemberFrames<'r, R>>>,
+    remaining: u64,
+    expected: u64,
+}
+
+impl<R: Read + Seek> Read for MemberReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let limit = buf.len().min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = match &mut self.frames {
+            Some(frames) => frames.read(&mut buf[..limit])?,
+            None => 0,
+        };
+        if n == 0 {
+            let decoded = self.expected - self.remaining;
+            return Err(HuffmanError::TruncatedStream { decoded, expected: self.expected }.into());
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Katalog z początku bufora z całym archiwum (albo przynajmniej jego katalogiem),
+/// np. pliku w pamięci albo obiektu pobranego z magazynu.
+impl TryFrom<&[u8]> for Archive {
+    type Error = HuffmanError;
+
+    fn try_from(bytes: &[u8]) -> Result<Archive> {
+        Archive::read_from(&mut &bytes[..])
+    }
+}
+
+// Ścieżka musi być względna i nie może wychodzić w górę (../) - inaczej rozpakowanie
+// mogłoby nadpisać pliki poza katalogiem docelowym
+fn check_path(path: &str) -> std::result::Result<(), String> {
+    let unsafe_part = path.is_empty()
+        || path.len() > u16::MAX as usize
+        || path.starts_with('/')
+        || path.contains('\\')
+        || path.split('/').any(|part| part.is_empty() || part == "." || part == ".." || part.contains(':'));
+    if unsafe_part {
+        return Err(format!("niedozwolona ścieżka '{}'", path));
+    }
+    Ok(())
+}
+
+/// Zapisuje archiwum wielu plików do `W`.
+///
+/// Lista ścieżek jest znana z góry, więc katalog trafia na początek archiwum
+/// z zerowymi offsetami i jest nadpisywany w [`ArchiveWriter::finish`], gdy
+/// długości skompresowanych danych są już znane. Pliki trzeba dodawać w kolejności ścieżek.
+/// Każdy plik ma własny model; wspólny dla wszystkich daje [`SolidArchiveWriter`],
+/// a wspólny dla partii małych plików - [`ArchiveWriter::with_batches`].
+pub struct ArchiveWriter<W: Write + Seek> {
+    inner: W,
+    start: u64,
+    archive: Archive,
+    added: usize,
+    stats: Stats,
+    // Zapisane już ramki bloków; `None` bez deduplikacji
+    written: Option<Written<W>>,
+    dedup: Dedup,
+    batch: Option<Batch>,
+}
+
+// Małe pliki czekające na wspólną ramkę: ich zawartość i indeksy w katalogu
+struct Batch {
+    limit: u64,
+    options: Options,
+    data: Vec<u8>,
+    members: Vec<usize>,
+}
+
+// Skróty ramek zapisanych bloków -> offset pierwszej z tą zawartością. Odczyt z `W` potwierdza
+// trafienie porównaniem bajtów, a `with_dedup` wymaga od `W` czytania, więc ustawia go tutaj
+struct Written<W> {
+    block_size: usize,
+    frames: HashMap<DataKey, u64>,
+    read_at: fn(&mut W, u64, &mut [u8]) -> io::Result<()>,
+}
+
+// Długość, CRC-32 i SipHash ramki bloku
+type DataKey = (u64, u32, u64);
+
+/// Bloki, których ramki [`ArchiveWriter::with_dedup`] zastąpił odwołaniem do wcześniejszych.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Dedup {
+    /// Pliki z co najmniej jednym takim blokiem.
+    pub members: usize,
+    pub blocks: usize,
+    /// Skompresowane bajty, których nie trzeba było zapisywać.
+    pub saved: u64,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    pub fn new(mut inner: W, paths: Vec<String>) -> Result<Self> {
+        let archive = placeholder_archive(paths, false)?;
+        let start = write_directory(&mut inner, &archive)?;
+        Ok(ArchiveWriter {
+            inner,
+            start,
+            archive,
+            added: 0,
+            stats: Stats::default(),
+            written: None,
+            dedup: Dedup::default(),
+            batch: None,
+        })
+    }
+
+    /// Zbiera pliki mniejsze niż `limit` bajtów w partie do `limit` bajtów, kodowane
+    /// jedną ramką z jedną tabelą; katalog nadal wskazuje każdy plik osobno.
+    ///
+    /// Partia jest trzymana w pamięci do zapisu, a pliki z różnymi opcjami trafiają
+    /// do różnych partii. Trzeba wywołać przed pierwszym [`ArchiveWriter::add_member`].
+    pub fn with_batches(mut self, limit: u64) -> Result<Self> {
+        if self.added > 0 {
+            return Err(HuffmanError::InvalidOptions("partie trzeba włączyć przed dodaniem plików".to_string()));
+        }
+        // Katalog wersji 3 (i 5) jest dłuższy, więc zastępujemy zapisany już pusty katalog
+        self.archive.batched = true;
+        self.inner.seek(SeekFrom::Start(self.start))?;
+        write_directory(&mut self.inner, &self.archive)?;
+        self.batch = Some(Batch { limit, options: Options::default(), data: Vec::new(), members: Vec::new() });
+        Ok(self)
+    }
+
+    /// Kompresuje kolejny plik (dwoma przebiegami, jak [`compress_stream`]).
+    ///
+    /// Statystyki pliku, którego dane zastąpiło odwołanie, mają zerowe długości nagłówka i danych,
+    /// tak samo jak pliku odłożonego do partii - koszt partii trafia do statystyk z `finish`.
+    pub fn add_member<R: Read + Seek>(&mut self, input: &mut R, options: &Options) -> Result<Stats> {
+        if self.added >= self.archive.members.len() {
+            return Err(HuffmanError::InvalidOptions("dodano więcej plików niż zadeklarowano ścieżek".to_string()));
+        }
+        if let Some(limit) = self.batch.as_ref().map(|batch| batch.limit) {
+            let position = input.stream_position()?;
+            let len = input.seek(SeekFrom::End(0))? - position;
+            input.seek(SeekFrom::Start(position))?;
+            if len > 0 && len < limit {
+                return self.add_to_batch(input, len, options);
+            }
+        }
+        if self.written.is_some() {
+            return self.add_blocks(input, options);
+        }
+        let offset = self.inner.stream_position()? - self.start;
+        let stats = compress_stream(input, &mut self.inner, options)?;
+        let member = &mut self.archive.members[self.added];
+        member.offset = offset;
+        member.original_len = stats.original_len;
+        member.compressed_len = self.inner.stream_position()? - self.start - offset;
+        self.added += 1;
+        self.stats.merge(&stats);
+        Ok(stats)
+    }
+
+    // Koduje plik blokami i zapisuje tylko ramki, których nie ma jeszcze w archiwum
+    fn add_blocks<R: Read + Seek>(&mut self, input: &mut R, options: &Options) -> Result<Stats> {
+        let written = self.written.as_mut().expect("deduplikacja włączona");
+        let unit = options.unit as usize;
+        let block_size = (written.block_size - written.block_size % unit).max(unit);
+        let position = input.stream_position()?;
+        let len = input.seek(SeekFrom::End(0))? - position;
+        input.seek(SeekFrom::Start(position))?;
+        let count = len.div_ceil(block_size as u64).max(1);
+        let blocks = u32::try_from(count)
+            .map_err(|_| HuffmanError::InvalidOptions(format!("plik ma {} bloków, więcej niż mieści katalog", count)))?;
+
+        // Lista bloków leży przed ich nowymi ramkami i jest wypełniana po zakodowaniu
+        let list_offset = self.inner.stream_position()?;
+        if blocks > 1 {
+            self.inner.write_all(&vec![0u8; 16 * blocks as usize])?;
+        }
+        let mut list = Vec::with_capacity(blocks as usize);
+        let mut stats = Stats::default();
+        let mut reused = 0;
+        let (mut block, mut frame, mut scratch) = (Vec::new(), Vec::new(), EncoderScratch::new());
+        for i in 0..count {
+            block.clear();
+            let limit = if i + 1 == count { u64::MAX } else { block_size as u64 };
+            input.by_ref().take(limit).read_to_end(&mut block)?;
+            frame.clear();
+            let mut block_stats = compress_into(&block, options, &mut scratch, &mut frame)?;
+            let mut hasher = DefaultHasher::new();
+            hasher.write(&frame);
+            let key = (frame.len() as u64, crc32(&frame), hasher.finish());
+            let end = self.inner.stream_position()?;
+            let offset = match written.frames.get(&key) {
+                Some(&offset) if same_bytes(&mut self.inner, written.read_at, self.start + offset, &frame, end)? => {
+                    reused += 1;
+                    self.dedup.saved += frame.len() as u64;
+                    block_stats = Stats { header_len: 0, payload_len: 0, footer_len: 0, ..block_stats };
+                    offset
+                }
+                _ => {
+                    self.inner.write_all(&frame)?;
+                    *written.frames.entry(key).or_insert(end - self.start)
+                }
+            };
+            list.push((offset, frame.len() as u64));
+            stats.merge(&block_stats);
+        }
+
+        let member = &mut self.archive.members[self.added];
+        member.original_len = stats.original_len;
+        if blocks > 1 {
+            let end = self.inner.stream_position()?;
+            self.inner.seek(SeekFrom::Start(list_offset))?;
+            for (offset, len) in list {
+                self.inner.write_all(&offset.to_be_bytes())?;
+                self.inner.write_all(&len.to_be_bytes())?;
+            }
+            self.inner.seek(SeekFrom::Start(end))?;
+            (member.offset, member.compressed_len, member.blocks) = (list_offset - self.start, 16 * count, blocks);
+        } else {
+            (member.offset, member.compressed_len) = list[0];
+        }
+        if reused > 0 {
+            self.dedup.members += 1;
+            self.dedup.blocks += reused;
+        }
+        self.added += 1;
+        self.stats.merge(&stats);
+        Ok(stats)
+    }
+
+    fn add_to_batch<R: Read>(&mut self, input: &mut R, len: u64, options: &Options) -> Result<Stats> {
+        let batch = self.batch.as_ref().expect("partie włączone");
+        if !batch.members.is_empty() && (batch.data.len() as u64 + len > batch.limit || batch.options != *options) {
+            self.flush_batch()?;
+        }
+        let batch = self.batch.as_mut().expect("partie włączone");
+        batch.options = *options;
+        let member = &mut self.archive.members[self.added];
+        member.batch_offset = batch.data.len() as u64;
+        member.original_len = input.read_to_end(&mut batch.data)? as u64;
+        batch.members.push(self.added);
+        self.added += 1;
+        Ok(Stats { original_len: member.original_len, ..Stats::default() })
+    }
+
+    // Koduje odłożone pliki jedną ramką i wpisuje jej położenie każdemu z nich
+    fn flush_batch(&mut self) -> Result<()> {
+        let Some(batch) = self.batch.as_mut().filter(|batch| !batch.members.is_empty()) else {
+            return Ok(());
+        };
+        let mut frame = Vec::new();
+        let stats = compress_into(&batch.data, &batch.options, &mut EncoderScratch::new(), &mut frame)?;
+        let offset = self.inner.stream_position()? - self.start;
+        self.inner.write_all(&frame)?;
+        for index in batch.members.drain(..) {
+            let member = &mut self.archive.members[index];
+            member.offset = offset;
+            member.compressed_len = frame.len() as u64;
+        }
+        batch.data.clear();
+        self.stats.merge(&stats);
+        Ok(())
+    }
+
+    /// Bloki zapisane dotąd jako odwołania do danych wcześniejszych.
+    pub fn dedup(&self) -> Dedup {
+        self.dedup
+    }
+
+    /// Nadpisuje katalog prawdziwymi offsetami i zwraca `W` ustawione na końcu
+    /// archiwum razem ze statystykami zsumowanymi po plikach.
+    pub fn finish(mut self) -> Result<(W, Stats)> {
+        check_added(self.added, &self.archive)?;
+        self.flush_batch()?;
+        rewrite_directory(&mut self.inner, self.start, &self.archive)?;
+        Ok((self.inner, self.stats))
+    }
+}
+
+impl<W: Read + Write + Seek> ArchiveWriter<W> {
+    /// Koduje pliki blokami po `block_size` bajtów, każdy osobną ramką, i zapisuje tylko raz
+    /// ramkę powtarzającą się w tym i wcześniejszych plikach (ta sama zawartość bloku i opcje).
+    /// Plik z jednym blokiem wskazuje wtedy w katalogu wcześniejszą ramkę, a plik z wieloma -
+    /// listę bloków, w której powtórzone wskazują ramki zapisane wcześniej.
+    ///
+    /// Trafienie skrótu jest potwierdzane porównaniem z bajtami odczytanymi z `W`, dlatego `W`
+    /// musi dawać się czytać. Trzeba wywołać przed pierwszym [`ArchiveWriter::add_member`].
+    pub fn with_dedup(mut self, block_size: usize) -> Result<Self> {
+        if self.added > 0 || block_size == 0 {
+            return Err(HuffmanError::InvalidOptions(
+                "deduplikację trzeba włączyć przed dodaniem plików, z niezerowym rozmiarem bloku".to_string(),
+            ));
+        }
+        // Katalog wersji 4 jest dłuższy, więc zastępujemy zapisany już pusty katalog
+        self.archive.deduped = true;
+        self.inner.seek(SeekFrom::Start(self.start))?;
+        write_directory(&mut self.inner, &self.archive)?;
+        let read_at = |inner: &mut W, offset: u64, buf: &mut [u8]| {
+            inner.seek(SeekFrom::Start(offset))?;
+            inner.read_exact(buf)
+        };
+        self.written = Some(Written { block_size, frames: HashMap::new(), read_at });
+        Ok(self)
+    }
+}
+
+// Czy pod `offset` w `inner` leży już `frame`; potem wraca na koniec zapisanych danych
+fn same_bytes<W: Write + Seek>(
+    inner: &mut W,
+    read_at: fn(&mut W, u64, &mut [u8]) -> io::Result<()>,
+    offset: u64,
+    frame: &[u8],
+    end: u64,
+) -> io::Result<bool> {
+    inner.flush()?;
+    let mut stored = vec![0u8; frame.len()];
+    read_at(inner, offset, &mut stored)?;
+    inner.seek(SeekFrom::Start(end))?;
+    Ok(stored == frame)
+}
+
+/// Zapisuje archiwum solid: zawartość wszystkich plików jest łączona i kompresowana
+/// jako jedna ramka z jednym modelem.
+///
+/// Przy wielu małych, podobnych plikach oszczędza to tabelę kodów na każdy plik,
+/// ale wyciągnięcie jednego pliku wymaga zdekodowania strumienia od początku.
+/// Połączone dane są trzymane w pamięci do [`SolidArchiveWriter::finish`].
+pub struct SolidArchiveWriter<W: Write + Seek> {
+    inner: W,
+    start: u64,
+    archive: Archive,
+    added: usize,
+    options: Options,
+    data: Vec<u8>,
+}
+
+impl<W: Write + Seek> SolidArchiveWriter<W> {
+    pub fn new(mut inner: W, paths: Vec<String>, options: Options) -> Result<Self> {
+        let archive = placeholder_archive(paths, true)?;
+        let start = write_directory(&mut inner, &archive)?;
+        Ok(SolidArchiveWriter { inner, start, archive, added: 0, options, data: Vec::new() })
+    }
+
+    /// Dopisuje kolejny plik do wspólnego strumienia; zwraca jego długość.
+    pub fn add_member<R: Read>(&mut self, input: &mut R) -> Result<u64> {
+        let member = self.archive.members.get_mut(self.added).ok_or_else(|| {
+            HuffmanError::InvalidOptions("dodano więcej plików niż zadeklarowano ścieżek".to_string())
+        })?;
+        member.offset = self.data.len() as u64;
+        member.original_len = input.read_to_end(&mut self.data)? as u64;
+        self.added += 1;
+        Ok(member.original_len)
+    }
+
+    /// Kompresuje połączone dane, nadpisuje katalog i zwraca `W` ze statystykami.
+    pub fn finish(mut self) -> Result<(W, Stats)> {
+        check_added(self.added, &self.archive)?;
+        let mut frame = Vec::new();
+        let stats = compress_into(&self.data, &self.options, &mut EncoderScratch::new(), &mut frame)?;
+        self.inner.write_all(&frame)?;
+        rewrite_directory(&mut self.inner, self.start, &self.archive)?;
+        Ok((self.inner, stats))
+    }
+}
+
+fn placeholder_archive(paths: Vec<String>, solid: bool) -> Result<Archive> {
+    for path in &paths {
+        check_path(path).map_err(HuffmanError::InvalidOptions)?;
+    }
+    let members = paths
+        .into_iter()
+        .map(|path| Member { path, original_len: 0, offset: 0, compressed_len: 0, batch_offset: 0, blocks: 0 })
+        .collect();
+    Ok(Archive { members, solid, batched: false, deduped: false })
+}
+
+// Zapisuje katalog z zerowymi offsetami; zwraca pozycję początku archiwum
+fn write_directory<W: Write + Seek>(inner: &mut W, archive: &Archive) -> Result<u64> {
+    let start = inner.stream_position()?;
+    let mut placeholder = Vec::with_capacity(archive.byte_len());
+    archive.write_into(&mut placeholder);
+    inner.write_all(&placeholder)?;
+    Ok(start)
+}
+
+fn rewrite_directory<W: Write + Seek>(inner: &mut W, start: u64, archive: &Archive) -> Result<()> {
+    let end = inner.stream_position()?;
+    let mut directory = Vec::with_capacity(archive.byte_len());
+    archive.write_into(&mut directory);
+    inner.seek(SeekFrom::Start(start))?;
+    inner.write_all(&directory)?;
+    inner.seek(SeekFrom::Start(end))?;
+    inner.flush()?;
+    Ok(())
+}
+
+fn check_added(added: usize, archive: &Archive) -> Result<()> {
+    if added != archive.members.len() {
+        return Err(HuffmanError::InvalidOptions(format!(
+            "dodano {} z {} plików archiwum",
+            added,
+            archive.members.len()
+        )));
+    }
+    Ok(())
+}
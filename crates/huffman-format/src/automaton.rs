@@ -0,0 +1,162 @@
+use std::fmt;
+
+use crate::codec::header_code_tables;
+use crate::error::{HuffmanError, Result};
+use crate::header::{ESCAPE, Header};
+use crate::huffman::{FlatTree, Symbol, Walk};
+
+/// Dokąd prowadzi bit czytany w stanie automatu dekodera.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition {
+    /// Kod jeszcze się nie skończył: kolejny bit czyta stan o tym numerze.
+    State(u32),
+    /// Kod się skończył: symbol trafia do wyjścia, a następny kod czyta automat kontekstu
+    /// przesuniętego o ten symbol, od jego stanu początkowego.
+    Symbol(Symbol),
+    /// Kod ucieczki (model v6): za nim symbol zapisany wprost na `8 * unit` bitach.
+    Escape,
+    /// Bity nie zaczynają żadnego kodu; dekoder zgłasza wtedy `InvalidCode`.
+    Invalid,
+}
+
+/// Automat jednego kontekstu: stan 0 to korzeń drzewa kodów, a każdy stan ma przejście dla bitu 0 i 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextAutomaton {
+    pub context: Vec<u8>,
+    /// Przejście przed przeczytaniem pierwszego bitu: `State(0)`, a w kontekście z jedynym
+    /// symbolem o kodzie zerowej długości - od razu ten symbol, bez stanów.
+    pub start: Transition,
+    pub states: Vec<[Transition; 2]>,
+}
+
+impl ContextAutomaton {
+    /// Przejścia `Invalid` jako pary (stan, bit); kod jest pełny (nierówność Krafta z
+    /// równością), gdy nie ma żadnego, z wyjątkiem jedynego symbolu z kodem 1-bitowym.
+    pub fn incomplete(&self) -> Vec<(u32, u8)> {
+        let mut holes = Vec::new();
+        for (state, transitions) in self.states.iter().enumerate() {
+            for (bit, transition) in transitions.iter().enumerate() {
+                if *transition == Transition::Invalid {
+                    holes.push((state as u32, bit as u8));
+                }
+            }
+        }
+        holes
+    }
+}
+
+/// Automat dekodera z nagłówka ramki: dla każdego kontekstu drzewo kodów jako tabela
+/// przejść po jednym bicie, w postaci do sprawdzenia poza tą biblioteką (prefiksowość,
+/// pełność kodu) albo do wygenerowania z niej innego dekodera.
+///
+/// Automat opisuje dokładnie te kody, których używa dekoder, także niekanoniczne kody
+/// z drzewa w plikach v0. Kontekst na początku danych to `order * unit` zer; po symbolu
+/// kontekst przesuwa się o `unit` bajtów (w trybie 4-bitowym o jeden półbajt).
+/// W modelu z ucieczkami kontekst spoza [`DecodeAutomaton::contexts`] oznacza symbol
+/// zapisany wprost. [`fmt::Display`] wypisuje automat jako tabelę TSV.
+///
+/// ```
+/// use huffman_format::{DecodeAutomaton, Header, Options, Transition, compress};
+///
+/// // Bez `store_fallback`, bo tak krótkie dane ramka zapisałaby wprost, bez drzewa
+/// let packed = compress(b"abracadabra", Options { store_fallback: false, ..Options::default() })?;
+/// let automaton = DecodeAutomaton::from_header(&Header::parse(&packed)?.0)?;
+/// let root = &automaton.contexts[0];
+/// // Pięć symboli to cztery węzły wewnętrzne drzewa, a kod jest pełny
+/// assert_eq!(root.states.len(), 4);
+/// assert!(root.incomplete().is_empty());
+/// // Najczęstsze 'a' ma kod 1-bitowy
+/// assert!(root.states[0].contains(&Transition::Symbol(b"a".to_vec())));
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeAutomaton {
+    pub order: usize,
+    pub unit: u8,
+    pub nibble: bool,
+    pub escape: bool,
+    /// Automaty kontekstów w kolejności bajtów kontekstu.
+    pub contexts: Vec<ContextAutomaton>,
+}
+
+impl DecodeAutomaton {
+    /// Automat kodów z nagłówka, sprawdzonych tak jak przed dekodowaniem.
+    pub fn from_header(header: &Header) -> Result<Self> {
+        let mut code_tables: Vec<_> = header_code_tables(header)?.into_iter().collect();
+        code_tables.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut contexts = Vec::with_capacity(code_tables.len());
+        for (context, code_table) in code_tables {
+            let tree = FlatTree::from_codes(&code_table)
+                .map_err(|reason| HuffmanError::MalformedHeader(format!("kontekst {:?}: {}", context, reason)))?;
+            let transition = |walk| match walk {
+                Walk::Node(node) => Transition::State(node),
+                Walk::Leaf(leaf) => match tree.symbol(leaf).as_slice() {
+                    ESCAPE if header.escape => Transition::Escape,
+                    symbol => Transition::Symbol(symbol.to_vec()),
+                },
+                Walk::Missing => Transition::Invalid,
+            };
+            let states = (0..tree.node_count() as u32)
+                .map(|node| [transition(tree.next(node, false)), transition(tree.next(node, true))])
+                .collect();
+            contexts.push(ContextAutomaton { context, start: transition(tree.start()), states });
+        }
+        Ok(DecodeAutomaton {
+            order: header.order,
+            unit: header.unit,
+            nibble: header.nibble,
+            escape: header.escape,
+            contexts,
+        })
+    }
+
+    /// Łączna liczba stanów wszystkich kontekstów.
+    pub fn state_count(&self) -> usize {
+        self.contexts.iter().map(|c| c.states.len()).sum()
+    }
+}
+
+// Bajty szesnastkowo, pusty ciąg jako "-", żeby kolumny TSV nigdy nie były puste
+fn hex(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "-".to_string();
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::State(state) => write!(f, "stan {}", state),
+            Transition::Symbol(symbol) => write!(f, "symbol {}", hex(symbol)),
+            Transition::Escape => write!(f, "ucieczka"),
+            Transition::Invalid => write!(f, "błąd"),
+        }
+    }
+}
+
+/// Tabela TSV: nagłówek z parametrami modelu, potem wiersz `kontekst stan bit cel`
+/// na każde przejście; stan `start` to przejście przed pierwszym bitem (bit `-`).
+impl fmt::Display for DecodeAutomaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# rząd {}, symbol {} B{}{}",
+            self.order,
+            self.unit,
+            if self.nibble { ", 4-bitowy" } else { "" },
+            if self.escape { ", ucieczki" } else { "" }
+        )?;
+        writeln!(f, "kontekst\tstan\tbit\tcel")?;
+        for automaton in &self.contexts {
+            let context = hex(&automaton.context);
+            writeln!(f, "{}\tstart\t-\t{}", context, automaton.start)?;
+            for (state, transitions) in automaton.states.iter().enumerate() {
+                for (bit, transition) in transitions.iter().enumerate() {
+                    writeln!(f, "{}\t{}\t{}\t{}", context, state, bit, transition)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
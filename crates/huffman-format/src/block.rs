@@ -0,0 +1,84 @@
+use crate::codec::{DecodeOptions, Options, compress_with_stats, decompress_with};
+use crate::error::{HuffmanError, Result};
+
+/// Prosty interfejs kompresji pojedynczych buforów, do podpięcia pod formaty
+/// kolumnowe (np. jako eksperymentalny kodek buforów Arrow IPC).
+pub trait BlockCodec {
+    /// Dopisuje skompresowany `input` do `out`.
+    fn compress_block(&self, input: &[u8], out: &mut Vec<u8>) -> Result<()>;
+
+    /// Dopisuje do `out` bufor odtworzony z wyniku [`BlockCodec::compress_block`].
+    fn decompress_block(&self, input: &[u8], out: &mut Vec<u8>) -> Result<()>;
+}
+
+// Prefiks bufora jak w Arrow IPC: długość oryginału jako i64 little-endian,
+// -1 oznacza bufor zapisany bez kompresji
+const PREFIX_LEN: usize = 8;
+const UNCOMPRESSED: i64 = -1;
+
+/// Kodek Huffmana w konwencji buforów Arrow IPC.
+///
+/// Każdy bufor dostaje 8-bajtowy prefiks z długością oryginału (i64 LE), a za nim
+/// zwykłą ramkę `.huff`. Jeśli kompresja nie zmniejsza bufora, dane są zapisywane
+/// wprost z prefiksem -1, tak jak robią to kodeki LZ4 i ZSTD w Arrow.
+///
+/// ```
+/// use huffman_format::{BlockCodec, HuffmanBlockCodec};
+///
+/// let codec = HuffmanBlockCodec::default();
+/// let column = b"aaaabbbcccaaaabbbcccaaaabbbccc".repeat(10);
+/// let mut packed = Vec::new();
+/// codec.compress_block(&column, &mut packed)?;
+/// assert!(packed.len() < column.len());
+///
+/// let mut unpacked = Vec::new();
+/// codec.decompress_block(&packed, &mut unpacked)?;
+/// assert_eq!(unpacked, column);
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HuffmanBlockCodec {
+    pub options: Options,
+    pub decode_options: DecodeOptions,
+}
+
+impl BlockCodec for HuffmanBlockCodec {
+    fn compress_block(&self, input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        out.extend_from_slice(&(input.len() as i64).to_le_bytes());
+        if input.is_empty() {
+            return Ok(());
+        }
+        let (frame, _) = compress_with_stats(input, &self.options)?;
+        if frame.len() < input.len() {
+            out.extend_from_slice(&frame);
+        } else {
+            let prefix = out.len() - PREFIX_LEN;
+            out[prefix..].copy_from_slice(&UNCOMPRESSED.to_le_bytes());
+            out.extend_from_slice(input);
+        }
+        Ok(())
+    }
+
+    fn decompress_block(&self, input: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        let Some((prefix, body)) = input.split_first_chunk::<PREFIX_LEN>() else {
+            return Err(HuffmanError::MalformedHeader("bufor krótszy niż prefiks długości".to_string()));
+        };
+        let expected = i64::from_le_bytes(*prefix);
+        if expected == UNCOMPRESSED {
+            out.extend_from_slice(body);
+            return Ok(());
+        }
+        if expected < 0 {
+            return Err(HuffmanError::MalformedHeader(format!("ujemna długość bufora {}", expected)));
+        }
+        if expected == 0 {
+            return Ok(());
+        }
+        let decoded = decompress_with(body, &self.decode_options)?;
+        if decoded.len() as i64 != expected {
+            return Err(HuffmanError::TruncatedStream { decoded: decoded.len() as u64, expected: expected as u64 });
+        }
+        out.extend_from_slice(&decoded);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+//! Transformata Burrowsa-Wheelera i move-to-front, jak w bzip2: [`crate::Filter::Bwt`].
+//!
+//! BWT sortuje wszystkie obroty bloku i zapisuje ostatnią kolumnę, w której bajty
+//! z podobnych kontekstów leżą obok siebie; move-to-front zamienia je na małe liczby
+//! (głównie zera), więc model rzędu 0 koduje wynik dużo lepiej niż sam tekst.
+//!
+//! ```
+//! use huffman_format::Filter;
+//!
+//! // `u32` numer wiersza oryginału, a za nim ostatnia kolumna "nnbaaa" po move-to-front
+//! let filtered = Filter::Bwt.apply(b"banana");
+//! assert_eq!(filtered, [0, 0, 0, 3, b'n', 0, b'c', b'c', 0, 0]);
+//! assert_eq!(Filter::Bwt.revert(&filtered, 6)?, b"banana");
+//! # Ok::<(), huffman_format::HuffmanError>(())
+//! ```
+
+use crate::error::{HuffmanError, Result};
+
+/// Największy blok transformaty w bajtach (jak `bzip2 -9`); dłuższe dane są dzielone na bloki.
+pub const BWT_BLOCK: usize = 900_000;
+
+/// Ostatnia kolumna posortowanych obrotów `block` i numer wiersza, w którym stoi sam `block`.
+///
+/// Obroty są sortowane przez podwajanie prefiksów z sortowaniem przez zliczanie, więc czas
+/// jest `O(n log n)` także dla długich serii. Równe obroty (dane okresowe) dają tę samą
+/// ostatnią kolumnę w każdej kolejności.
+pub fn transform(block: &[u8]) -> (Vec<u8>, usize) {
+    let n = block.len();
+    if n == 0 {
+        return (Vec::new(), 0);
+    }
+    let rotations = sort_rotations(block);
+    let primary = rotations.iter().position(|&start| start == 0).expect("obrót 0 jest wśród obrotów");
+    let last = rotations.iter().map(|&start| block[(start + n - 1) % n]).collect();
+    (last, primary)
+}
+
+/// Odtwarza blok z ostatniej kolumny i numeru wiersza z [`transform`].
+pub fn inverse(last: &[u8], primary: usize) -> Result<Vec<u8>> {
+    let n = last.len();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    if primary >= n {
+        return Err(HuffmanError::MalformedHeader(format!(
+            "filtr bwt: wiersz {} poza blokiem {} bajtów",
+            primary, n
+        )));
+    }
+    // Pierwsza kolumna to posortowana ostatnia: LF[i] to wiersz obrotu o jeden bajt wcześniej
+    let mut starts = [0usize; 256];
+    for &byte in last {
+        starts[byte as usize] += 1;
+    }
+    let mut sum = 0;
+    for start in starts.iter_mut() {
+        (*start, sum) = (sum, sum + *start);
+    }
+    let lf: Vec<usize> = last
+        .iter()
+        .map(|&byte| {
+            let row = starts[byte as usize];
+            starts[byte as usize] += 1;
+            row
+        })
+        .collect();
+
+    let mut out = vec![0u8; n];
+    let mut row = primary;
+    for slot in out.iter_mut().rev() {
+        *slot = last[row];
+        row = lf[row];
+    }
+    Ok(out)
+}
+
+/// Move-to-front: każdy bajt zastępuje jego pozycja na liście ostatnio użytych bajtów.
+pub fn mtf_encode(data: &[u8]) -> Vec<u8> {
+    let mut recent: [u8; 256] = std::array::from_fn(|i| i as u8);
+    data.iter()
+        .map(|&byte| {
+            let pos = recent.iter().position(|&b| b == byte).expect("lista ma wszystkie bajty");
+            recent.copy_within(..pos, 1);
+            recent[0] = byte;
+            pos as u8
+        })
+        .collect()
+}
+
+/// Odwraca [`mtf_encode`].
+pub fn mtf_decode(data: &[u8]) -> Vec<u8> {
+    let mut recent: [u8; 256] = std::array::from_fn(|i| i as u8);
+    data.iter()
+        .map(|&pos| {
+            let byte = recent[pos as usize];
+            recent.copy_within(..pos as usize, 1);
+            recent[0] = byte;
+            byte
+        })
+        .collect()
+}
+
+// Początki obrotów w kolejności leksykograficznej. W kroku k obroty są już posortowane
+// po pierwszych k bajtach (klasy w `rank`); sortowanie stabilne po klasie obrotu
+// zaczynającego się k bajtów wcześniej porządkuje je po 2k bajtach
+fn sort_rotations(block: &[u8]) -> Vec<usize> {
+    let n = block.len();
+    let mut counts = vec![0usize; n.max(256)];
+    for &byte in block {
+        counts[byte as usize] += 1;
+    }
+    let mut order = counting_sort((0..n).collect(), |i| block[i] as usize, &mut counts[..256]);
+    let mut rank = vec![0usize; n];
+    for j in 1..n {
+        let step = block[order[j]] != block[order[j - 1]];
+        rank[order[j]] = rank[order[j - 1]] + step as usize;
+    }
+
+    let mut next = vec![0usize; n];
+    let mut k = 1;
+    while k < n && rank[order[n - 1]] < n - 1 {
+        let classes = rank[order[n - 1]] + 1;
+        let shifted: Vec<usize> = order.iter().map(|&i| (i + n - k) % n).collect();
+        let counts = &mut counts[..classes];
+        counts.fill(0);
+        for &i in &shifted {
+            counts[rank[i]] += 1;
+        }
+        order = counting_sort(shifted, |i| rank[i], counts);
+
+        next[order[0]] = 0;
+        for j in 1..n {
+            let (a, b) = (order[j], order[j - 1]);
+            let step = (rank[a], rank[(a + k) % n]) != (rank[b], rank[(b + k) % n]);
+            next[a] = next[b] + step as usize;
+        }
+        std::mem::swap(&mut rank, &mut next);
+        k *= 2;
+    }
+    order
+}
+
+// Stabilne sortowanie `items` po kluczu `key`; `counts` ma liczności kluczy
+fn counting_sort(items: Vec<usize>, key: impl Fn(usize) -> usize, counts: &mut [usize]) -> Vec<usize> {
+    let mut sum = 0;
+    for count in counts.iter_mut() {
+        (*count, sum) = (sum, sum + *count);
+    }
+    let mut sorted = vec![0usize; items.len()];
+    for item in items {
+        let slot = &mut counts[key(item)];
+        sorted[*slot] = item;
+        *slot += 1;
+    }
+    sorted
+}
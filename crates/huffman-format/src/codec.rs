@@ -0,0 +1,1595 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+
+use crate::adaptive::{ADAPTIVE_MAGIC, AdaptiveReader};
+use crate::alphabet::Alphabet;
+use crate::bitio::{BitReader, BitWriter};
+use crate::dict::Dictionary;
+use crate::error::{HuffmanError, Position, Resource, Result};
+use crate::filters::{self, FilterChain};
+use crate::checksum::{Crc32, crc32};
+use crate::header::{ChecksumKind, ESCAPE, Header, LegacyFrequencies, MAX_UNIT, Model};
+use crate::index::{SEEK_MAGIC, SeekTable};
+use crate::meta::{META_MAGIC, Metadata};
+use crate::lz77::{LZ77_MAGIC, decompress_lz77};
+use crate::reader::{INPUT_CHUNK, READER_OUTPUT};
+use crate::huffman::{
+    Code, DEFAULT_MAX_CODE_LEN, FreqTable, MarkovCodeLengths, MarkovCodeTable, MarkovFreqTable, assign_legacy_codes,
+    assign_tree_codes, canonical_code_table, check_code_lengths, code_lengths, entropy_from_freq,
+};
+
+// Symbol dekodera; przy szerokości 1 używany jest tylko pierwszy bajt
+type UnitSymbol = [u8; MAX_UNIT as usize];
+
+// Tablica odwrotna dekodera: kontekst -> (kod -> symbol)
+type MarkovDecodeTable = HashMap<Vec<u8>, HashMap<Code, UnitSymbol>>;
+
+/// Parametry kompresji.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Rząd modelu Markowa (liczba poprzednich bajtów tworzących kontekst), maks. 255.
+    pub order: usize,
+    /// Maksymalna długość kodu w bitach; dłuższe kody są skracane algorytmem package-merge.
+    /// Domyślnie 15: głębokie drzewa modeli rzędu 2 nie spowalniają dekodowania tablicami.
+    /// Alfabet większy niż `2^max_code_len` symboli podnosi limit do najmniejszego możliwego.
+    pub max_code_len: u8,
+    /// Jeśli ustawiony, wejście musi składać się wyłącznie z bajtów tego alfabetu.
+    pub alphabet: Option<Alphabet>,
+    /// Szerokość symbolu w bajtach, od 1 do [`MAX_UNIT`]: 2 np. dla tekstu UTF-16 (zob. [`detect_unit`]),
+    /// 4-8 dla rekordów stałej długości. Każdy symbol to wpis w nagłówku, więc szerokie
+    /// symbole warto łączyć z [`Options::max_symbols`].
+    pub unit: u8,
+    /// Suma kontrolna oryginału sprawdzana przy dekompresji.
+    pub checksum: ChecksumKind,
+    /// Najwyżej tyle par (kontekst, symbol) w tablicach modelu, zob. [`prune_symbols`].
+    /// Ogranicza nagłówek i pamięć dekodera przy wyższych rzędach kosztem rzadkich
+    /// symboli, które są zapisywane wprost za kodem ucieczki.
+    pub max_symbols: Option<usize>,
+    /// Symbole 4-bitowe: każdy bajt to dwa symbole (starsza połowa pierwsza), a rząd liczy
+    /// półbajty. Dla danych szesnastkowych i BCD alfabet ma 16 symboli, więc tablice są
+    /// mniejsze. Wymaga `unit` = 1; ramki mają nagłówek v7.
+    pub nibble: bool,
+    /// Gdy ramka z kodami byłaby dłuższa niż symbole zapisane wprost (np. JPEG albo zip),
+    /// zapisuje ramkę bez tablic, tylko z kodami ucieczki (zob. [`Header`], v6): wynik jest
+    /// wtedy najwyżej o nagłówek i stopkę dłuższy od wejścia. Domyślnie włączone.
+    pub store_fallback: bool,
+    /// Filtry przekształcające dane przed kodowaniem, po kolei, np. [`crate::Filter::Rle`] dla
+    /// długich serii (bitmapy, rzadkie pliki binarne) albo `delta:2,rle` dla próbek. Zapisywane
+    /// w nagłówku v7; dekoder odwraca je od końca, a suma kontrolna dotyczy oryginału, więc
+    /// wykrywa też uszkodzony parametr filtra.
+    pub filters: FilterChain,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            order: 0,
+            max_code_len: DEFAULT_MAX_CODE_LEN,
+            alphabet: None,
+            unit: 1,
+            checksum: ChecksumKind::default(),
+            max_symbols: None,
+            nibble: false,
+            store_fallback: true,
+            filters: FilterChain::default(),
+        }
+    }
+}
+
+impl Options {
+    // Bity symbolu zapisanego wprost za kodem ucieczki
+    pub(crate) fn raw_bits(&self) -> u8 {
+        if self.nibble { 4 } else { 8 * self.unit }
+    }
+
+    // Szerokość symbolu z check_unit, parametry filtrów, a przy symbolach 4-bitowych jednobajtowe dane
+    pub(crate) fn check_symbols(&self) -> Result<()> {
+        check_unit(self.unit)?;
+        self.filters.check()?;
+        if self.nibble && self.unit != 1 {
+            return Err(HuffmanError::InvalidOptions(format!(
+                "symbole 4-bitowe wymagają szerokości 1, a wybrano {}",
+                self.unit
+            )));
+        }
+        Ok(())
+    }
+
+    // Filtry przekształcają dane tylko w koderach, które mają całą ramkę w pamięci
+    pub(crate) fn check_unfiltered(&self, coder: &str) -> Result<()> {
+        if self.filters.is_empty() {
+            return Ok(());
+        }
+        Err(HuffmanError::InvalidOptions(format!("{} nie obsługuje filtrów {}", coder, self.filters)))
+    }
+
+    // Symbole do modelowania: same dane albo, z `nibble`, ich połówki
+    pub(crate) fn symbols<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.nibble {
+            Cow::Owned(data.iter().flat_map(|&byte| [byte >> 4, byte & 0x0F]).collect())
+        } else {
+            Cow::Borrowed(data)
+        }
+    }
+}
+
+/// Dobiera szerokość symbolu po znaczniku BOM: UTF-16 (LE lub BE) daje 2, reszta 1.
+pub fn detect_unit(data: &[u8]) -> u8 {
+    match data {
+        [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] => 2,
+        _ => 1,
+    }
+}
+
+/// Parametry dekompresji.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Przerywa dekodowanie, gdy wyjście przekroczyłoby N-krotność skompresowanego wejścia.
+    /// Chroni serwisy dekodujące pliki z niezaufanych źródeł przed "bombami" z zawyżoną
+    /// długością w nagłówku.
+    pub max_expansion_ratio: Option<u64>,
+    /// Ile bajtów skompresowanego wejścia [`crate::HuffmanReader`] pobiera jednym
+    /// wywołaniem `read` (domyślnie 8 KiB). Większe porcje pomagają na sieciowych
+    /// systemach plików, gdzie każde wywołanie jest kosztowne.
+    pub input_chunk: Option<usize>,
+    /// Dekodowanie bez dostępów do pamięci zależnych od danych: przy każdym bicie
+    /// przeglądana jest cała tablica kodów (wszystkich kontekstów), a pasujący wpis
+    /// jest wybierany maską zamiast wyszukiwania w tablicach dekodera. Dla danych tajnych,
+    /// gdy obawą są ataki przez pamięć podręczną.
+    ///
+    /// Koszt rośnie z liczbą wpisów tablicy: przy rzędzie 0 (najwyżej 256 wpisów)
+    /// dekodowanie jest kilkadziesiąt razy wolniejsze, przy rzędzie 1 ponad sto razy, a przy
+    /// rzędzie 2 jeszcze bardziej, więc opcja nadaje się do kluczy i tokenów, nie do dużych
+    /// plików. Liczba bitów każdego kodu nadal wpływa na czas (granice symboli), ale nie
+    /// na adresy odczytów. Kody ucieczki z [`Options::max_symbols`] są sprawdzane zwykłym
+    /// wyszukiwaniem.
+    pub constant_time: bool,
+    /// Najwięcej bajtów wyniku (wszystkich ramek razem). Sprawdzane z długości w nagłówkach,
+    /// zanim ramka zacznie się dekodować, więc mały spreparowany plik jest odrzucany od razu.
+    pub max_output_len: Option<u64>,
+    /// Najwięcej par (kontekst, symbol) w tablicy kodów jednej ramki; nagłówek z większą
+    /// tablicą jest odrzucany w trakcie czytania, zanim tablica zostanie zbudowana.
+    pub max_table_entries: Option<usize>,
+    /// Najwięcej pamięci dekodera w bajtach: szacunek tablic kodów ramki, a przy dekodowaniu
+    /// do pamięci ([`decompress_with`], [`crate::decompress_parallel`]) także bufora wyniku.
+    pub max_memory: Option<usize>,
+    /// Najwięcej bitów jednego poziomu tablic wyszukiwania dekodera, od 1 do
+    /// [`MAX_LOOKUP_BITS`] (domyślnie [`DEFAULT_LOOKUP_BITS`]). Większe tablice czytają więcej
+    /// kodów jednym odczytem, ale gorzej mieszczą się w pamięci podręcznej; najlepsza wartość
+    /// zależy od procesora (`huff encode --autotune`). Nie zmienia wyniku dekodowania.
+    pub lookup_bits: Option<u8>,
+    /// [`crate::HuffmanReader`] oddaje dane ramki dopiero po sprawdzeniu jej sumy kontrolnej,
+    /// więc uszkodzony blok przerywa odczyt, zanim cokolwiek z niego trafi do wyjścia. Bufor
+    /// mieści całą ramkę: przy [`crate::HuffmanWriter`] jeden blok, przy [`compress`] cały plik,
+    /// a [`DecodeOptions::max_memory`] ogranicza też jego rozmiar. Ramki bez sumy kontrolnej
+    /// ([`ChecksumKind::None`]) są oddawane bez sprawdzania.
+    pub verify_blocks: bool,
+}
+
+// Szacunkowa pamięć jednego wpisu tablic dekodera: klucz i długość w modelu z nagłówka,
+// kod i symbol w tablicy dekodowania, razem z narzutem HashMap, oraz wpisy tablicy
+// wyszukiwania (zwykle 1-2 po 12 B na kod, najwyżej 4 na każdym poziomie)
+pub(crate) const TABLE_ENTRY_BYTES: usize = 128;
+
+impl DecodeOptions {
+    // Sprawdza, czy `output` bajtów wyjścia mieści się w limicie dla `input` bajtów wejścia
+    pub(crate) fn check_expansion(&self, input: u64, output: u64) -> Result<()> {
+        match self.max_expansion_ratio {
+            Some(ratio) if output > input.saturating_mul(ratio) => {
+                Err(HuffmanError::ExpansionLimit { ratio, input, output })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Sprawdza długość wyniku; `in_memory`, gdy cały wynik trafi do jednego bufora
+    pub(crate) fn check_output(&self, output: u64, in_memory: bool) -> Result<()> {
+        if let Some(limit) = self.max_output_len
+            && output > limit
+        {
+            return Err(HuffmanError::ResourceLimit { resource: Resource::Output, limit, requested: output });
+        }
+        match self.max_memory {
+            Some(limit) if in_memory && output > limit as u64 => Err(HuffmanError::ResourceLimit {
+                resource: Resource::Memory,
+                limit: limit as u64,
+                requested: output,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Sprawdza liczbę wpisów tablicy kodów ramki, także jako szacunek pamięci
+    pub(crate) fn check_table(&self, entries: usize) -> Result<()> {
+        if let Some(limit) = self.max_table_entries
+            && entries > limit
+        {
+            return Err(HuffmanError::ResourceLimit {
+                resource: Resource::TableEntries,
+                limit: limit as u64,
+                requested: entries as u64,
+            });
+        }
+        let memory = entries.saturating_mul(TABLE_ENTRY_BYTES);
+        match self.max_memory {
+            Some(limit) if memory > limit => Err(HuffmanError::ResourceLimit {
+                resource: Resource::Memory,
+                limit: limit as u64,
+                requested: memory as u64,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Szacunek pamięci dekodowania ramki z [`estimate_decode_memory`], w bajtach.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// Tablice kodów i wyszukiwania dekodera; ten sam szacunek, który sprawdza
+    /// [`DecodeOptions::max_memory`].
+    pub tables: u64,
+    /// Bufory wejścia i wyjścia dekodera strumieniowego ([`crate::HuffmanReader`])
+    /// przy domyślnym [`DecodeOptions::input_chunk`].
+    pub buffers: u64,
+    /// Bufor wyniku przy dekodowaniu do pamięci ([`decompress_with`]): długość oryginału.
+    pub output: u64,
+}
+
+impl MemoryEstimate {
+    /// Pamięć dekodowania do pamięci: tablice, bufory i cały wynik.
+    pub fn total(&self) -> u64 {
+        self.tables.saturating_add(self.buffers).saturating_add(self.output)
+    }
+
+    /// Pamięć dekodowania strumieniowego, bez bufora na cały wynik.
+    pub fn streaming(&self) -> u64 {
+        self.tables.saturating_add(self.buffers)
+    }
+}
+
+/// Szacuje pamięć potrzebną do zdekodowania ramki o nagłówku `header`, bez budowania
+/// tablic dekodera - np. żeby odrzucić plik albo zaplanować dekodowania, zanim się zaczną.
+///
+/// ```
+/// use huffman_format::{Header, Options, compress, estimate_decode_memory};
+///
+/// let packed = compress(&b"abracadabra".repeat(100), Options::default())?;
+/// let memory = estimate_decode_memory(&Header::parse(&packed)?.0);
+/// assert_eq!(memory.output, 1100);
+/// assert!(memory.tables > 0 && memory.total() > memory.streaming());
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub fn estimate_decode_memory(header: &Header) -> MemoryEstimate {
+    MemoryEstimate {
+        tables: header.table_entries().saturating_mul(TABLE_ENTRY_BYTES) as u64,
+        buffers: (INPUT_CHUNK + READER_OUTPUT) as u64,
+        output: header.original_len,
+    }
+}
+
+/// Statystyki pojedynczej kompresji.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub original_len: u64,
+    pub header_len: usize,
+    pub payload_len: usize,
+    pub footer_len: usize,
+    /// Entropia warunkowa H(X|C) w bitach na bajt wejścia (przy `unit` 2 to połowa
+    /// entropii na symbol, przy symbolach 4-bitowych - podwojona entropia półbajtu).
+    pub entropy: f64,
+}
+
+impl Stats {
+    /// Procent zaoszczędzonego miejsca (ujemny, gdy wynik jest większy od wejścia).
+    pub fn compression_percent(&self) -> f64 {
+        if self.original_len == 0 {
+            return 0.0;
+        }
+        let total = (self.header_len + self.payload_len + self.footer_len) as f64;
+        100.0 * (1.0 - total / self.original_len as f64)
+    }
+
+    /// Najmniejsza długość strumienia w bajtach, na jaką pozwala entropia modelu
+    /// (`entropy` bitów na każdy bajt wejścia), bez nagłówka i stopki.
+    pub fn ideal_payload_len(&self) -> f64 {
+        self.entropy * self.original_len as f64 / 8.0
+    }
+
+    /// Narzut kodera: o ile procent strumień jest dłuższy od minimum z entropii.
+    ///
+    /// Kody Huffmana mają całkowite długości, więc narzut nigdy nie jest ujemny poza
+    /// zaokrągleniem do pełnego bajtu. Duży narzut przy niskiej entropii wskazuje koder
+    /// (np. rozkład z dominującym symbolem), a duża entropia przy małym narzucie - model,
+    /// któremu pomoże raczej inny rząd albo filtr. Po [`Stats::merge`] to narzut całości.
+    /// Pliki archiwum zapisane jako odwołania ([`crate::Dedup`]) nie mają własnego
+    /// strumienia, więc zaniżają narzut całego archiwum.
+    ///
+    /// ```
+    /// use huffman_format::{Options, compress_with_stats};
+    ///
+    /// // Dwa symbole po równo: kod 1-bitowy jest dokładnie tak długi jak entropia
+    /// let (_, stats) = compress_with_stats(&b"ab".repeat(512), &Options::default())?;
+    /// assert_eq!(stats.ideal_payload_len(), 128.0);
+    /// assert_eq!(stats.coding_overhead_percent(), 0.0);
+    /// # Ok::<(), huffman_format::HuffmanError>(())
+    /// ```
+    pub fn coding_overhead_percent(&self) -> f64 {
+        let ideal = self.ideal_payload_len();
+        if ideal <= 0.0 {
+            return 0.0;
+        }
+        100.0 * (self.payload_len as f64 / ideal - 1.0)
+    }
+
+    /// Dolicza statystyki kolejnej ramki; entropia jest ważona długością danych.
+    pub fn merge(&mut self, other: &Stats) {
+        let len = self.original_len + other.original_len;
+        if len > 0 {
+            self.entropy = (self.entropy * self.original_len as f64 + other.entropy * other.original_len as f64)
+                / len as f64;
+        }
+        self.original_len = len;
+        self.header_len += other.header_len;
+        self.payload_len += other.payload_len;
+        self.footer_len += other.footer_len;
+    }
+}
+
+// Symbole w porcji EncoderScratch::count_checked: porcja bajtów jest wielokrotnością szerokości
+// symbolu, a przy 1 B mieści się w pamięci podręcznej razem z licznikami
+const COUNT_CHUNK_SYMBOLS: usize = 16 * 1024;
+
+/// Bufory robocze enkodera wielokrotnego użytku.
+///
+/// Przy kompresji wielu małych porcji danych jeden `EncoderScratch` przekazywany do
+/// [`compress_into`] eliminuje alokacje w pętli kodowania: tablice liczników i bufor kontekstu zachowują pojemność między wywołaniami.
+#[derive(Debug, Default)]
+pub struct EncoderScratch {
+    counts: HashMap<Vec<u8>, Box<[u64; 256]>>,
+    // Liczniki dla symboli wielobajtowych - alfabet jest zbyt duży na tablicę
+    wide_counts: MarkovFreqTable,
+    context: Vec<u8>,
+}
+
+impl EncoderScratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn reset_context(&mut self, order: usize) {
+        self.context.clear();
+        self.context.resize(order, 0);
+    }
+
+    pub(crate) fn count(&mut self, raw_data: &[u8], order: usize, unit: usize) -> MarkovFreqTable {
+        self.begin_count(order, unit);
+        self.count_chunk(raw_data, order, unit);
+        self.frequencies(order, unit)
+    }
+
+    // Zlicza `symbols` (dane ramki po filtrach i `Options::symbols`) i zwraca też CRC-32
+    // oryginału `data`. Bez filtrów suma powstaje w pętli zliczania, porcjami jak w
+    // compress_stream, więc dane są czytane raz; po filtrach zliczanie nie widzi oryginału.
+    pub(crate) fn count_checked(
+        &mut self,
+        data: &[u8],
+        symbols: &[u8],
+        filtered: bool,
+        options: &Options,
+    ) -> (MarkovFreqTable, u32) {
+        let order = options.order.min(255);
+        let unit = options.unit as usize;
+        let mut crc = Crc32::new();
+        self.begin_count(order, unit);
+        if filtered {
+            self.count_chunk(symbols, order, unit);
+            crc.update(data);
+        } else {
+            let chunk_len = COUNT_CHUNK_SYMBOLS * unit;
+            let symbols_per_byte = if options.nibble { 2 } else { 1 };
+            for (chunk, symbols) in data.chunks(chunk_len).zip(symbols.chunks(chunk_len * symbols_per_byte)) {
+                self.count_chunk(symbols, order, unit);
+                crc.update(chunk);
+            }
+        }
+        (self.frequencies(order, unit), crc.finish())
+    }
+
+    pub(crate) fn begin_count(&mut self, order: usize, unit: usize) {
+        // Zerujemy zamiast czyścić mapę, żeby nie zwalniać tablic liczników
+        for counts in self.counts.values_mut() {
+            counts.fill(0);
+        }
+        self.wide_counts.clear();
+        self.reset_context(order * unit);
+    }
+
+    // Kontekst przechodzi między kolejnymi porcjami, więc dane można podawać kawałkami.
+    // Przy unit > 1 porcje muszą mieć długość podzielną przez unit (poza ostatnią).
+    pub(crate) fn count_chunk(&mut self, raw_data: &[u8], order: usize, unit: usize) {
+        if unit > 1 {
+            let ctx_len = self.context.len();
+            for chunk in raw_data.chunks(unit) {
+                let mut padded: UnitSymbol = [0; MAX_UNIT as usize];
+                padded[..chunk.len()].copy_from_slice(chunk);
+                let symbol = &padded[..unit];
+
+                *self
+                    .wide_counts
+                    .entry(self.context.clone())
+                    .or_default()
+                    .entry(symbol.to_vec())
+                    .or_insert(0) += 1;
+
+                if ctx_len > 0 {
+                    self.context.rotate_left(unit);
+                    self.context[ctx_len - unit..].copy_from_slice(symbol);
+                }
+            }
+            return;
+        }
+
+        for &byte in raw_data {
+            match self.counts.get_mut(self.context.as_slice()) {
+                Some(counts) => counts[byte as usize] += 1,
+                None => {
+                    let mut counts = Box::new([0u64; 256]);
+                    counts[byte as usize] = 1;
+                    self.counts.insert(self.context.clone(), counts);
+                }
+            }
+
+            if order > 0 {
+                self.context.rotate_left(1);
+                self.context[order - 1] = byte;
+            }
+        }
+    }
+
+    pub(crate) fn frequencies(&self, order: usize, unit: usize) -> MarkovFreqTable {
+        if unit > 1 {
+            return self.wide_counts.clone();
+        }
+        let mut markov_freqs = MarkovFreqTable::new();
+        for (ctx, counts) in &self.counts {
+            if ctx.len() != order || counts.iter().all(|&c| c == 0) {
+                continue;
+            }
+            let f_table = counts
+                .iter()
+                .enumerate()
+                .filter(|&(_, &c)| c > 0)
+                .map(|(sym, &c)| (vec![sym as u8], c))
+                .collect();
+            markov_freqs.insert(ctx.clone(), f_table);
+        }
+        markov_freqs
+    }
+
+    pub(crate) fn begin_encode(&mut self, order: usize, unit: usize) {
+        self.reset_context(order * unit);
+    }
+
+    // Kontekst i niepełne słowo w `bits` przechodzą do kolejnej porcji.
+    // Z `raw_bits` (model z ucieczkami) brakujące symbole i konteksty są zapisywane
+    // wprost na tylu bitach.
+    pub(crate) fn encode_chunk<W: Write>(
+        &mut self,
+        raw_data: &[u8],
+        m_code_table: &MarkovCodeTable,
+        unit: usize,
+        raw_bits: Option<u8>,
+        bits: &mut BitWriter<W>,
+    ) -> Result<()> {
+        let context = &mut self.context;
+        let ctx_len = context.len();
+
+        for chunk in raw_data.chunks(unit) {
+            // Niepełny ostatni symbol dopełniamy zerami; dekoder utnie go do długości oryginału
+            let mut padded: UnitSymbol = [0; MAX_UNIT as usize];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            let symbol = &padded[..unit];
+
+            let codes = match m_code_table.get(context.as_slice()) {
+                Some(codes) => Some(codes),
+                None if raw_bits.is_some() => None,
+                None => return Err(HuffmanError::UnknownContext(context.clone())),
+            };
+            match codes.map(|codes| (codes.get(symbol), codes.get(ESCAPE))) {
+                Some((Some(code), _)) => bits.write_code(*code)?,
+                Some((None, None)) => {
+                    return Err(HuffmanError::SymbolNotInTable { context: context.clone(), symbol: symbol.to_vec() });
+                }
+                escaped => {
+                    if let Some((_, Some(code))) = escaped {
+                        bits.write_code(*code)?;
+                    }
+                    let raw = symbol.iter().fold(0, |acc, &b| (acc << 8) | b as u64);
+                    bits.write_code(Code { bits: raw, len: raw_bits.unwrap_or(8 * unit as u8) })?;
+                }
+            }
+
+            if ctx_len > 0 {
+                context.rotate_left(unit);
+                context[ctx_len - unit..].copy_from_slice(symbol);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Zlicza częstotliwości symboli osobno dla każdego kontekstu rzędu `order`.
+pub fn collect_frequencies(raw_data: &[u8], order: usize) -> MarkovFreqTable {
+    EncoderScratch::new().count(raw_data, order, 1)
+}
+
+/// Wyznacza długości kodów dla wszystkich kontekstów.
+pub fn build_code_lengths(markov_freqs: &MarkovFreqTable, max_code_len: u8) -> Result<MarkovCodeLengths> {
+    markov_freqs
+        .iter()
+        .map(|(ctx, f_table)| Ok((ctx.clone(), code_lengths(f_table, max_code_len)?)))
+        .collect()
+}
+
+// Przydziela kody kanoniczne na podstawie długości
+pub(crate) fn canonical_code_tables(markov_lengths: &MarkovCodeLengths) -> MarkovCodeTable {
+    markov_lengths
+        .iter()
+        .map(|(ctx, l_table)| (ctx.clone(), canonical_code_table(l_table)))
+        .collect()
+}
+
+/// Dokładna długość strumienia bitów dla modelu: suma częstotliwości razy długości kodów.
+pub fn payload_bits(markov_freqs: &MarkovFreqTable, markov_lengths: &MarkovCodeLengths) -> u64 {
+    markov_freqs
+        .iter()
+        .map(|(ctx, f_table)| {
+            let lengths = &markov_lengths[ctx];
+            f_table.iter().map(|(sym, &freq)| freq * lengths[sym] as u64).sum::<u64>()
+        })
+        .sum()
+}
+
+/// Zostawia w modelu `max_symbols` najczęstszych par (kontekst, symbol); remisy
+/// rozstrzyga kolejność kontekstów i symboli, więc wynik nie zależy od `HashMap`.
+///
+/// Liczności odciętych symboli przechodzą na wpis [`ESCAPE`] ich kontekstu, a konteksty
+/// bez żadnej zachowanej pary znikają z modelu. Zwraca przycięty model i liczbę symboli,
+/// które trzeba zapisać wprost, albo `None`, gdy model mieści się w limicie.
+///
+/// ```
+/// use huffman_format::{collect_frequencies, prune_symbols};
+///
+/// let frequencies = collect_frequencies(b"abracadabra", 0);
+/// let (pruned, raw) = prune_symbols(&frequencies, 2).unwrap();
+/// assert_eq!(pruned[&vec![]].len(), 3); // 'a', 'b' i kod ucieczki
+/// assert_eq!(raw, 4); // dwa 'r', 'c' i 'd'
+/// ```
+pub fn prune_symbols(markov_freqs: &MarkovFreqTable, max_symbols: usize) -> Option<(MarkovFreqTable, u64)> {
+    let mut entries: Vec<(u64, &Vec<u8>, &Vec<u8>)> = markov_freqs
+        .iter()
+        .flat_map(|(ctx, f_table)| f_table.iter().map(move |(sym, &freq)| (freq, ctx, sym)))
+        .collect();
+    if entries.len() <= max_symbols {
+        return None;
+    }
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| (a.1, a.2).cmp(&(b.1, b.2))));
+
+    let mut pruned = MarkovFreqTable::new();
+    for &(freq, ctx, sym) in &entries[..max_symbols] {
+        pruned.entry(ctx.clone()).or_default().insert(sym.clone(), freq);
+    }
+    let mut raw = 0;
+    for &(freq, ctx, _) in &entries[max_symbols..] {
+        raw += freq;
+        if let Some(f_table) = pruned.get_mut(ctx) {
+            *f_table.entry(ESCAPE.to_vec()).or_insert(0) += freq;
+        }
+    }
+    Some((pruned, raw))
+}
+
+// Długości kodów ramki (z `max_symbols` przyciętych do limitu), znacznik modelu z ucieczkami
+// i dokładna liczba bitów danych
+pub(crate) fn frame_model(frequencies: &MarkovFreqTable, options: &Options) -> Result<(MarkovCodeLengths, bool, u64)> {
+    match options.max_symbols.and_then(|n| prune_symbols(frequencies, n)) {
+        Some((pruned, raw)) => {
+            let lengths = build_code_lengths(&pruned, options.max_code_len)?;
+            let bits = payload_bits(&pruned, &lengths) + raw * options.raw_bits() as u64;
+            Ok((lengths, true, bits))
+        }
+        None => {
+            let lengths = build_code_lengths(frequencies, options.max_code_len)?;
+            let bits = payload_bits(frequencies, &lengths);
+            Ok((lengths, false, bits))
+        }
+    }
+}
+
+// Nagłówek ramki bez tablic kodów, w której wszystkie symbole są zapisane wprost
+pub(crate) fn raw_header(original_len: u64, options: &Options) -> Header {
+    let symbols = if options.nibble { 2 * original_len } else { original_len.div_ceil(options.unit as u64) };
+    Header {
+        original_len,
+        order: options.order.min(255),
+        max_code_len: options.max_code_len,
+        unit: options.unit,
+        payload_bits: Some(symbols * options.raw_bits() as u64),
+        checksum: options.checksum,
+        escape: true,
+        nibble: options.nibble,
+        filters: Vec::new(),
+        model: Model::CodeLengths(MarkovCodeLengths::new()),
+    }
+}
+
+// [`raw_header`], jeśli z `options.store_fallback` ramka wychodzi krótsza niż z nagłówkiem
+// `header` (`header_len` bajtów)
+pub(crate) fn stored_header(header: &Header, header_len: usize, options: &Options) -> Option<Header> {
+    // Ramka bez tablic zapisuje wprost dane po filtrach, więc filtry zostają w nagłówku
+    let stored = Header {
+        original_len: header.original_len,
+        filters: header.filters.clone(),
+        ..raw_header(header.coded_len(), options)
+    };
+    let payload_len = |header: &Header| header.payload_bits.unwrap_or(0).div_ceil(8);
+    let stored_len = stored.to_bytes().len() as u64 + payload_len(&stored);
+    (options.store_fallback && stored_len < header_len as u64 + payload_len(header)).then_some(stored)
+}
+
+/// Entropia warunkowa H(X|C) modelu, ważona częstością kontekstów, w bitach na bajt
+/// `original_len`: każdy kontekst waży tyle, ile jego symbole na bajt oryginału.
+pub fn conditional_entropy(markov_freqs: &MarkovFreqTable, original_len: u64) -> f64 {
+    let mut weighted_entropy = 0.0;
+    for f_table in markov_freqs.values() {
+        let ctx_count: u64 = f_table.values().sum();
+        let prob_ctx = ctx_count as f64 / original_len as f64;
+        weighted_entropy += prob_ctx * entropy_from_freq(f_table);
+    }
+    weighted_entropy
+}
+
+// Kody formatu sprzed v0: drzewo z par w kolejności z pliku, bez limitu długości
+pub(crate) fn legacy_code_tables(frequencies: &LegacyFrequencies) -> Result<MarkovCodeTable> {
+    frequencies
+        .iter()
+        .map(|(context, pairs)| {
+            let code_table = assign_legacy_codes(pairs)
+                .map_err(|e| HuffmanError::MalformedHeader(format!("kontekst {:?}: {}", context, e)))?;
+            Ok((context.clone(), code_table))
+        })
+        .collect()
+}
+
+// Kody kontekstów zapisane w nagłówku, sprawdzone tak, jak sprawdza je dekoder
+pub(crate) fn header_code_tables(header: &Header) -> Result<MarkovCodeTable> {
+    Ok(match &header.model {
+        Model::Legacy(frequencies) => legacy_code_tables(frequencies)?,
+        // v0: kody z drzewa odbudowanego z częstotliwości
+        Model::Frequencies(frequencies) => frequencies
+            .iter()
+            .map(|(context, freq_table)| {
+                let code_table = assign_tree_codes(freq_table, header.max_code_len).map_err(|e| {
+                    HuffmanError::MalformedHeader(format!("kontekst {:?}: {}", context, e))
+                })?;
+                Ok((context.clone(), code_table))
+            })
+            .collect::<Result<MarkovCodeTable>>()?,
+        Model::CodeLengths(lengths) => {
+            for (context, l_table) in lengths {
+                check_code_lengths(l_table, header.max_code_len).map_err(|reason| {
+                    HuffmanError::MalformedHeader(format!("kontekst {:?}: {}", context, reason))
+                })?;
+            }
+            canonical_code_tables(lengths)
+        }
+    })
+}
+
+// Tablice odwrotne i kody ucieczki kontekstów (tylko w modelu v6)
+fn build_decode_tables(header: &Header) -> Result<(MarkovDecodeTable, HashMap<Vec<u8>, Code>)> {
+    let code_tables = header_code_tables(header)?;
+    let mut markov_tables = MarkovDecodeTable::new();
+    let mut escapes = HashMap::new();
+    for (context, code_table) in code_tables {
+        let mut reverse_table = HashMap::new();
+        for (sym_vec, code) in code_table {
+            if header.escape && sym_vec == ESCAPE {
+                escapes.insert(context.clone(), code);
+            }
+            // Filtrujemy dummy node (vec![])
+            if !sym_vec.is_empty() {
+                let mut symbol: UnitSymbol = [0; MAX_UNIT as usize];
+                let n = sym_vec.len().min(symbol.len());
+                symbol[..n].copy_from_slice(&sym_vec[..n]);
+                reverse_table.insert(code, symbol);
+            }
+        }
+        markov_tables.insert(context, reverse_table);
+    }
+    Ok((markov_tables, escapes))
+}
+
+// Symbole najwyżej tej szerokości są zliczane w tablicy indeksowanej wartością
+const DENSE_COUNT_UNIT: usize = 2;
+
+enum SymbolCounts {
+    Dense(Vec<u64>),
+    Sparse(HashMap<u64, u64>),
+}
+
+// Wszystkie wpisy tablic dekodera w płaskich tablicach, przeszukiwane w całości
+// przy każdym wyszukiwaniu (DecodeOptions::constant_time)
+struct ConstantTimeTable {
+    context_len: usize,
+    contexts: Vec<u8>,
+    codes: Vec<Code>,
+    symbols: Vec<UnitSymbol>,
+}
+
+impl ConstantTimeTable {
+    fn new(tables: &MarkovDecodeTable, context_len: usize) -> Self {
+        let mut table = ConstantTimeTable { context_len, contexts: Vec::new(), codes: Vec::new(), symbols: Vec::new() };
+        for (context, codes) in tables {
+            for (&code, &symbol) in codes {
+                table.contexts.extend_from_slice(context);
+                table.codes.push(code);
+                table.symbols.push(symbol);
+            }
+        }
+        table
+    }
+
+    fn lookup(&self, context: &[u8], code: Code) -> Option<UnitSymbol> {
+        let mut found = 0u8;
+        let mut symbol: UnitSymbol = [0; MAX_UNIT as usize];
+        for (i, entry) in self.codes.iter().enumerate() {
+            let mut diff = (entry.bits ^ code.bits) | (entry.len ^ code.len) as u64;
+            let entry_context = &self.contexts[i * self.context_len..(i + 1) * self.context_len];
+            for (a, b) in entry_context.iter().zip(context) {
+                diff |= (a ^ b) as u64;
+            }
+            // 0xff, gdy wpis pasuje, w przeciwnym razie 0 - bez rozgałęzienia zależnego od danych;
+            // black_box nie pozwala kompilatorowi zamienić maski z powrotem na skok
+            let mask = std::hint::black_box(((diff | diff.wrapping_neg()) >> 63) as u8).wrapping_sub(1);
+            for (out, &byte) in symbol.iter_mut().zip(&self.symbols[i]) {
+                *out |= byte & mask;
+            }
+            found |= mask;
+        }
+        (found != 0).then_some(symbol)
+    }
+}
+
+/// Domyślna liczba bitów indeksujących jeden poziom tablicy wyszukiwania dekodera.
+pub const DEFAULT_LOOKUP_BITS: u8 = 10;
+
+/// Największa wartość [`DecodeOptions::lookup_bits`]: 64 Ki wpisów na poziom.
+pub const MAX_LOOKUP_BITS: u8 = 16;
+
+// Indeks tablicy wyszukiwania dla kontekstu, którego nie ma w modelu
+const NO_TABLE: u32 = u32::MAX;
+
+// Co oznacza kod w tablicy wyszukiwania
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    // Symbol i tablica kontekstu, który po nim nastąpi, żeby nie szukać jej w HashMap
+    Symbol { symbol: UnitSymbol, next: u32 },
+    // Kod ucieczki: za nim symbol zapisany wprost
+    Escape,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LookupEntry {
+    // Bity nie zaczynają żadnego kodu
+    Invalid,
+    // Kod kończy się po `len` bitach tego poziomu
+    Code { target: Target, len: u8 },
+    // Kod jest dłuższy: po bitach tego poziomu kolejne `bits` bitów indeksuje podtablica od `start`
+    Sub { start: u32, bits: u8 },
+}
+
+// Tablica dekodowania kontekstu jak w zlib: korzeń indeksowany pierwszymi bitami kodu
+// daje symbol krótkiego kodu od razu, a dłuższe kody prowadzą do podtablic. Każdy poziom
+// ma najwyżej `max_bits` bitów i najwyżej 4 wpisy na kod, który do niego trafia, więc
+// pamięć rośnie z liczbą kodów, a nie z długością najdłuższego (nawet przy kodach 20+ bitów).
+struct LookupTable {
+    entries: Vec<LookupEntry>,
+    root_bits: u8,
+    // Kod zerowej długości (jedyny w kontekście), brany bez czytania bitów
+    empty: Option<Target>,
+}
+
+impl LookupTable {
+    fn new(codes: &[(Code, Target)], max_bits: u8) -> Self {
+        let mut table = LookupTable { entries: Vec::new(), root_bits: 0, empty: None };
+        let mut coded = Vec::with_capacity(codes.len());
+        for &(code, target) in codes {
+            match code.len {
+                0 => table.empty = Some(target),
+                _ => coded.push((code, target)),
+            }
+        }
+        if !coded.is_empty() {
+            table.root_bits = table.add_level(&coded, 0, max_bits);
+        }
+        table
+    }
+
+    // Dopisuje poziom dla kodów o wspólnych pierwszych `skip` bitach; zwraca liczbę bitów poziomu
+    fn add_level(&mut self, codes: &[(Code, Target)], skip: u8, max_bits: u8) -> u8 {
+        let longest = codes.iter().map(|(code, _)| code.len - skip).max().unwrap_or(1);
+        let fit = (usize::BITS - codes.len().leading_zeros()) as u8 + 1;
+        let bits = longest.min(fit).min(max_bits);
+        let start = self.entries.len();
+        self.entries.resize(start + (1 << bits), LookupEntry::Invalid);
+
+        let mut longer: BTreeMap<usize, Vec<(Code, Target)>> = BTreeMap::new();
+        for &(code, target) in codes {
+            let len = code.len - skip;
+            // Bity kodu za wspólnym początkiem
+            let rest = code.bits & (u64::MAX >> (64 - len));
+            if len <= bits {
+                let first = start + ((rest << (bits - len)) as usize);
+                self.entries[first..first + (1 << (bits - len))].fill(LookupEntry::Code { target, len });
+            } else {
+                longer.entry((rest >> (len - bits)) as usize).or_default().push((code, target));
+            }
+        }
+        for (index, group) in longer {
+            let sub_start = self.entries.len() as u32;
+            let sub_bits = self.add_level(&group, skip + bits, max_bits);
+            self.entries[start + index] = LookupEntry::Sub { start: sub_start, bits: sub_bits };
+        }
+        bits
+    }
+}
+
+// Tablice wyszukiwania wszystkich kontekstów i ich indeksy według bajtów kontekstu
+fn build_lookup(
+    tables: &MarkovDecodeTable,
+    escapes: &HashMap<Vec<u8>, Code>,
+    unit: usize,
+    max_bits: u8,
+) -> (Vec<LookupTable>, HashMap<Vec<u8>, u32>) {
+    let contexts: Vec<&Vec<u8>> = tables.keys().collect();
+    let lookup_index: HashMap<Vec<u8>, u32> =
+        contexts.iter().enumerate().map(|(i, &context)| (context.clone(), i as u32)).collect();
+    let lookup = contexts
+        .iter()
+        .map(|&context| {
+            let mut targets: Vec<_> = tables[context]
+                .iter()
+                .map(|(&code, &symbol)| {
+                    let mut next = context.clone();
+                    if !next.is_empty() {
+                        next.rotate_left(unit);
+                        let len = next.len();
+                        next[len - unit..].copy_from_slice(&symbol[..unit]);
+                    }
+                    let next = lookup_index.get(&next).copied().unwrap_or(NO_TABLE);
+                    (code, Target::Symbol { symbol, next })
+                })
+                .collect();
+            if let Some(&code) = escapes.get(context) {
+                targets.push((code, Target::Escape));
+            }
+            LookupTable::new(&targets, max_bits)
+        })
+        .collect();
+    (lookup, lookup_index)
+}
+
+/// Przyrostowy dekoder symboli: przyjmuje kolejne bajty strumienia bitów
+/// i dopisuje odtworzone dane aż do długości zapisanej w nagłówku.
+///
+/// Kody są szukane w tablicach `LookupTable` po kilka bitów naraz; tylko tryb stałego
+/// czasu dekoduje po jednym bicie i przegląda `ConstantTimeTable`.
+pub(crate) struct SymbolDecoder {
+    tables: MarkovDecodeTable,
+    escapes: HashMap<Vec<u8>, Code>,
+    lookup: Vec<LookupTable>,
+    lookup_index: HashMap<Vec<u8>, u32>,
+    // Tablica bieżącego kontekstu w `lookup` albo NO_TABLE
+    table: u32,
+    // Bity wejścia czekające na zdekodowanie (najstarszy najwyżej), gdy kody są szukane w `lookup`
+    window: u128,
+    window_len: u8,
+    bits_fed: u64,
+    // Model v6: kontekst spoza tablic oznacza symbol zapisany wprost
+    escape: bool,
+    // Bity symbolu zapisanego wprost, zebrane od kodu ucieczki
+    raw: Option<Code>,
+    raw_bits: u8,
+    unit: usize,
+    // Symbole 4-bitowe: starsza połowa bajtu czeka na młodszą
+    nibble: bool,
+    high_nibble: Option<u8>,
+    context: Vec<u8>,
+    current_code: Code,
+    bits_read: u64,
+    // Dokładna długość strumienia z nagłówka v3; bez niej dopełnienie ucina długość oryginału
+    payload_bits: Option<u64>,
+    checksum: ChecksumKind,
+    crc: Crc32,
+    decoded: u64,
+    original_len: u64,
+    // Liczniki odtworzonych symboli według wartości symbolu (big-endian)
+    counts: Option<SymbolCounts>,
+    constant_time: Option<ConstantTimeTable>,
+}
+
+impl SymbolDecoder {
+    pub(crate) fn new(header: &Header) -> Result<Self> {
+        let (tables, escapes) = build_decode_tables(header)?;
+        let unit = header.unit as usize;
+        let (lookup, lookup_index) = build_lookup(&tables, &escapes, unit, DEFAULT_LOOKUP_BITS);
+        let context = vec![0u8; header.order * unit];
+        let table = lookup_index.get(&context).copied().unwrap_or(NO_TABLE);
+        Ok(SymbolDecoder {
+            tables,
+            escapes,
+            lookup,
+            lookup_index,
+            table,
+            window: 0,
+            window_len: 0,
+            bits_fed: 0,
+            escape: header.escape,
+            raw: None,
+            raw_bits: if header.nibble { 4 } else { 8 * header.unit },
+            unit,
+            nibble: header.nibble,
+            high_nibble: None,
+            context,
+            current_code: Code::default(),
+            bits_read: 0,
+            payload_bits: header.payload_bits,
+            checksum: header.checksum,
+            crc: Crc32::new(),
+            decoded: 0,
+            original_len: header.coded_len(),
+            counts: None,
+            constant_time: None,
+        })
+    }
+
+    /// Ustawia dekoder zgodnie z `options`: tablice wyszukiwania mają poziomy po
+    /// [`DecodeOptions::lookup_bits`] bitów, a z [`DecodeOptions::constant_time`] kody są
+    /// wyszukiwane w płaskiej tablicy przeglądanej w całości.
+    pub(crate) fn configure(&mut self, options: &DecodeOptions) {
+        if let Some(bits) = options.lookup_bits.filter(|&bits| bits != DEFAULT_LOOKUP_BITS) {
+            let bits = bits.clamp(1, MAX_LOOKUP_BITS);
+            let (lookup, lookup_index) = build_lookup(&self.tables, &self.escapes, self.unit, bits);
+            self.table = lookup_index.get(&self.context).copied().unwrap_or(NO_TABLE);
+            (self.lookup, self.lookup_index) = (lookup, lookup_index);
+        }
+        if options.constant_time {
+            self.constant_time = Some(ConstantTimeTable::new(&self.tables, self.context.len()));
+        }
+    }
+
+    /// Włącza zliczanie odtworzonych symboli, zob. [`SymbolDecoder::add_counts`].
+    pub(crate) fn count_symbols(&mut self) {
+        self.counts = Some(if self.unit <= DENSE_COUNT_UNIT {
+            SymbolCounts::Dense(vec![0; 1 << (8 * self.unit)])
+        } else {
+            SymbolCounts::Sparse(HashMap::new())
+        });
+    }
+
+    /// Dopisuje do `table` liczności symboli odtworzonych do tej pory.
+    pub(crate) fn add_counts(&self, table: &mut FreqTable) {
+        let counts: Vec<(u64, u64)> = match &self.counts {
+            None => return,
+            Some(SymbolCounts::Dense(counts)) => {
+                counts.iter().enumerate().filter(|(_, count)| **count > 0).map(|(v, &c)| (v as u64, c)).collect()
+            }
+            Some(SymbolCounts::Sparse(counts)) => counts.iter().map(|(&v, &c)| (v, c)).collect(),
+        };
+        for (value, count) in counts {
+            let symbol = value.to_be_bytes()[8 - self.unit..].to_vec();
+            *table.entry(symbol).or_insert(0) += count;
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.decoded >= self.original_len
+    }
+
+    pub(crate) fn remaining(&self) -> u64 {
+        self.original_len - self.decoded
+    }
+
+    // Pozycja początku bieżącego kodu - do komunikatów o błędach
+    fn position(&self) -> Position {
+        Position {
+            block: None,
+            bit_offset: Some(self.bits_read - self.current_code.len as u64),
+            decoded: Some(self.decoded),
+        }
+    }
+
+    fn emit(&mut self, symbol: UnitSymbol, out: &mut Vec<u8>) {
+        if !self.nibble {
+            // Ostatni symbol może być dopełniony zerami poza długość oryginału
+            let n = (self.unit as u64).min(self.remaining()) as usize;
+            out.extend_from_slice(&symbol[..n]);
+            if self.checksum != ChecksumKind::None {
+                self.crc.update(&symbol[..n]);
+            }
+            self.decoded += n as u64;
+        } else if let Some(high) = self.high_nibble.take() {
+            let byte = [high << 4 | symbol[0]];
+            out.extend_from_slice(&byte);
+            if self.checksum != ChecksumKind::None {
+                self.crc.update(&byte);
+            }
+            self.decoded += 1;
+        } else {
+            self.high_nibble = Some(symbol[0]);
+        }
+        if let Some(counts) = &mut self.counts {
+            // Ucięty ostatni symbol liczy się z dopełnieniem zerami, jak w koderze
+            let value = symbol[..self.unit].iter().fold(0, |acc, &b| (acc << 8) | b as u64);
+            match counts {
+                SymbolCounts::Dense(counts) => counts[value as usize] += 1,
+                SymbolCounts::Sparse(counts) => *counts.entry(value).or_insert(0) += 1,
+            }
+        }
+
+        let ctx_len = self.context.len();
+        if ctx_len > 0 {
+            self.context.rotate_left(self.unit);
+            self.context[ctx_len - self.unit..].copy_from_slice(&symbol[..self.unit]);
+        }
+        self.current_code = Code::default();
+    }
+
+    fn current_table(&self) -> Result<&HashMap<Code, UnitSymbol>> {
+        self.tables.get(&self.context).ok_or_else(|| {
+            HuffmanError::UnknownContext(self.context.clone()).at(self.position())
+        })
+    }
+
+    // Symbol o kodzie `code` w bieżącym kontekście. W trybie stałego czasu nieznany
+    // kontekst nie jest zgłaszany osobno: żaden kod nie pasuje i kończy się to InvalidCode
+    fn lookup(&self, code: Code) -> Result<Option<UnitSymbol>> {
+        match &self.constant_time {
+            Some(table) => Ok(table.lookup(&self.context, code)),
+            None => Ok(self.current_table()?.get(&code).copied()),
+        }
+    }
+
+    // SPRAWDŹ, CZY SYMBOL JEST DETERMINISTYCZNY (kod zerowej długości)
+    // Jeśli w tabeli jest kod pusty, bierzemy go bez czytania bitów
+    pub(crate) fn drain_deterministic(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        while !self.is_done() && self.raw.is_none() {
+            if self.escape && !self.tables.contains_key(&self.context) {
+                self.raw = Some(Code::default());
+                break;
+            }
+            match self.lookup(Code::default())? {
+                Some(symbol) => self.emit(symbol, out),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Przetwarza jeden bajt strumienia; bity po osiągnięciu długości oryginału to dopełnienie.
+    pub(crate) fn feed(&mut self, byte: u8, out: &mut Vec<u8>) -> Result<()> {
+        if self.constant_time.is_none() {
+            if self.is_done() {
+                return Ok(());
+            }
+            // Bity za długością strumienia z nagłówka v3 to dopełnienie
+            let count = self.payload_bits.map_or(8, |bits| (bits - self.bits_fed).min(8)) as u8;
+            self.push_window((byte as u64) >> (8 - count), count);
+            self.decode_window(out)?;
+            if !self.is_done() && self.payload_bits == Some(self.bits_fed) {
+                return self.finish(out);
+            }
+            return Ok(());
+        }
+        for i in (0..8).rev() {
+            self.drain_deterministic(out)?;
+            if self.is_done() {
+                return Ok(());
+            }
+            if self.payload_bits == Some(self.bits_read) {
+                // Koniec prawdziwych danych - reszta bajtu to dopełnienie
+                return self.finish(out);
+            }
+            self.push_bit((byte >> i) & 1 == 1, out)?;
+        }
+        Ok(())
+    }
+
+    /// Dekoduje resztę ramki z `bits` i sprawdza jej koniec jak [`SymbolDecoder::finish`].
+    pub(crate) fn decode_from<R: Read>(&mut self, bits: &mut BitReader<R>, out: &mut Vec<u8>) -> Result<()> {
+        while self.constant_time.is_none() {
+            self.decode_window(out)?;
+            if self.is_done() {
+                return self.finish(out);
+            }
+            match bits.read_bits(32)? {
+                (_, 0) => return self.finish(out),
+                (value, count) => self.push_window(value, count),
+            }
+        }
+        loop {
+            self.drain_deterministic(out)?;
+            if self.is_done() {
+                break;
+            }
+            match bits.read_bit()? {
+                Some(bit) => self.push_bit(bit, out)?,
+                None => break,
+            }
+        }
+        self.finish(out)
+    }
+
+    fn push_bit(&mut self, bit: bool, out: &mut Vec<u8>) -> Result<()> {
+        if let Some(raw) = self.raw {
+            self.bits_read += 1;
+            let raw = raw.push(bit);
+            if raw.len == self.raw_bits {
+                let mut symbol: UnitSymbol = [0; MAX_UNIT as usize];
+                symbol[..self.unit].copy_from_slice(&raw.bits.to_be_bytes()[8 - self.unit..]);
+                self.raw = None;
+                self.emit(symbol, out);
+            } else {
+                self.raw = Some(raw);
+            }
+            return Ok(());
+        }
+        if self.current_code.len == 64 {
+            // Żaden kod nie jest dłuższy niż 64 bity
+            return Err(HuffmanError::InvalidCode {
+                context: self.context.clone(),
+                bits: self.current_code.to_string(),
+            }
+            .at(self.position()));
+        }
+
+        self.bits_read += 1;
+        self.current_code = self.current_code.push(bit);
+
+        if self.escapes.get(&self.context) == Some(&self.current_code) {
+            self.raw = Some(Code::default());
+            self.current_code = Code::default();
+        } else if let Some(symbol) = self.lookup(self.current_code)? {
+            self.emit(symbol, out);
+        }
+        Ok(())
+    }
+
+    fn push_window(&mut self, value: u64, count: u8) {
+        self.window = (self.window << count) | value as u128;
+        self.window_len += count;
+        self.bits_fed += count as u64;
+    }
+
+    // `count` bitów okna od bitu `skip` (0 to najstarszy), za końcem okna dopełnionych zerami
+    fn peek(&self, skip: u8, count: u8) -> u64 {
+        let end = skip as u32 + count as u32;
+        let len = self.window_len as u32;
+        let bits = if end <= len { self.window >> (len - end) } else { self.window << (end - len) };
+        (bits & ((1 << count) - 1)) as u64
+    }
+
+    fn consume(&mut self, count: u8) {
+        self.window_len -= count;
+        self.window &= (1 << self.window_len) - 1;
+        self.bits_read += count as u64;
+    }
+
+    // Dekoduje z okna wszystkie symbole, których bity już są; kod urwany na końcu okna
+    // czeka na kolejne bity, a jeśli ich nie będzie, finish zgłosi urwany strumień
+    fn decode_window(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        while !self.is_done() {
+            if self.raw.is_some() {
+                if self.window_len < self.raw_bits {
+                    return Ok(());
+                }
+                let bits = self.peek(0, self.raw_bits);
+                self.consume(self.raw_bits);
+                let mut symbol: UnitSymbol = [0; MAX_UNIT as usize];
+                symbol[..self.unit].copy_from_slice(&bits.to_be_bytes()[8 - self.unit..]);
+                self.raw = None;
+                self.emit(symbol, out);
+                self.table = self.lookup_index.get(&self.context).copied().unwrap_or(NO_TABLE);
+                continue;
+            }
+            let Some(table) = self.lookup.get(self.table as usize) else {
+                if self.escape {
+                    self.raw = Some(Code::default());
+                    continue;
+                }
+                return Err(HuffmanError::UnknownContext(self.context.clone()).at(self.position()));
+            };
+
+            let (mut start, mut bits, mut skip) = (0, table.root_bits, 0u8);
+            let found = match table.empty {
+                Some(target) => Some((target, 0)),
+                None => loop {
+                    match table.entries[start + self.peek(skip, bits) as usize] {
+                        LookupEntry::Code { target, len } => break Some((target, skip + len)),
+                        LookupEntry::Sub { start: sub_start, bits: sub_bits } => {
+                            skip += bits;
+                            (start, bits) = (sub_start as usize, sub_bits);
+                        }
+                        LookupEntry::Invalid if skip + bits > self.window_len => break None,
+                        LookupEntry::Invalid => {
+                            return Err(HuffmanError::InvalidCode {
+                                context: self.context.clone(),
+                                bits: Code { bits: self.peek(0, skip + bits), len: skip + bits }.to_string(),
+                            }
+                            .at(self.position()));
+                        }
+                    }
+                },
+            };
+            match found {
+                Some((target, len)) if len <= self.window_len => {
+                    self.consume(len);
+                    match target {
+                        Target::Symbol { symbol, next } => {
+                            self.emit(symbol, out);
+                            self.table = next;
+                        }
+                        Target::Escape => self.raw = Some(Code::default()),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Czytnik bitów ramki: z nagłówkiem v3+ kończy się dokładnie na ostatnim bajcie strumienia.
+    pub(crate) fn bit_reader<R: Read>(&self, inner: R) -> BitReader<R> {
+        match self.payload_bits {
+            Some(bits) => BitReader::with_limit(inner, bits),
+            None => BitReader::new(inner),
+        }
+    }
+
+    /// Długość stopki ramki, którą trzeba podać do [`SymbolDecoder::verify`].
+    pub(crate) fn footer_len(&self) -> usize {
+        self.checksum.footer_len()
+    }
+
+    /// Porównuje sumę kontrolną ze stopki z sumą odtworzonych danych. Suma ramki z filtrami
+    /// dotyczy danych po ich odwróceniu, więc wtedy `reverted` to wynik [`filters::revert`].
+    pub(crate) fn verify(&self, footer: &[u8], reverted: Option<&[u8]>) -> Result<()> {
+        if self.checksum == ChecksumKind::Crc32 {
+            let expected = u32::from_be_bytes(footer.try_into().expect("stopka CRC-32 ma 4 bajty"));
+            let actual = reverted.map_or_else(|| self.crc.finish(), crc32);
+            if expected != actual {
+                return Err(HuffmanError::ChecksumMismatch { expected, actual }.at(Position {
+                    decoded: Some(self.decoded),
+                    ..Position::default()
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wołane po wyczerpaniu strumienia bitów; zgłasza błąd, jeśli brakuje symboli.
+    pub(crate) fn finish(&mut self, out: &mut Vec<u8>) -> Result<()> {
+        match self.constant_time {
+            Some(_) => self.drain_deterministic(out)?,
+            None => self.decode_window(out)?,
+        }
+        if !self.is_done() {
+            return Err(HuffmanError::TruncatedStream {
+                decoded: self.decoded,
+                expected: self.original_len,
+            }
+            .at(self.position()));
+        }
+        if let Some(bits) = self.payload_bits.filter(|&bits| bits != self.bits_read) {
+            return Err(HuffmanError::MalformedHeader(format!(
+                "dane skończyły się po {} bitach, nagłówek deklaruje {}",
+                self.bits_read, bits
+            ))
+            .at(self.position()));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn write_footer(kind: ChecksumKind, crc: u32, out: &mut Vec<u8>) {
+    if kind == ChecksumKind::Crc32 {
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+}
+
+pub(crate) fn check_unit(unit: u8) -> Result<()> {
+    if unit == 0 || unit > MAX_UNIT {
+        return Err(HuffmanError::InvalidOptions(format!(
+            "szerokość symbolu {} (dozwolone 1..={})",
+            unit, MAX_UNIT
+        )));
+    }
+    Ok(())
+}
+
+/// Kompresuje `data` i zwraca gotowy plik `.huff` (nagłówek + strumień bitów).
+///
+/// Puste wejście daje ramkę z samym nagłówkiem: długość 0, tabela kodów bez symboli
+/// i pusty strumień bitów (plus stopka, jeśli jest włączona).
+///
+/// ```
+/// use huffman_format::{Options, compress, decompress};
+///
+/// let packed = compress(b"", Options::default())?;
+/// assert_eq!(decompress(&packed)?, b"");
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub fn compress(data: &[u8], options: Options) -> Result<Vec<u8>> {
+    Ok(compress_with_stats(data, &options)?.0)
+}
+
+/// Jak [`compress`], ale dodatkowo zwraca statystyki kompresji.
+pub fn compress_with_stats(data: &[u8], options: &Options) -> Result<(Vec<u8>, Stats)> {
+    let mut output = Vec::new();
+    let stats = compress_into(data, options, &mut EncoderScratch::new(), &mut output)?;
+    Ok((output, stats))
+}
+
+/// Kompresuje `data` do `out` (czyszcząc go wcześniej), używając buforów `scratch`.
+/// Przeznaczone do wielokrotnych wywołań, np. w serwisach kompresujących wiele porcji na sekundę.
+pub fn compress_into(
+    data: &[u8],
+    options: &Options,
+    scratch: &mut EncoderScratch,
+    out: &mut Vec<u8>,
+) -> Result<Stats> {
+    if let Some(alphabet) = &options.alphabet {
+        alphabet.check(data)?;
+    }
+    options.check_symbols()?;
+    let order = options.order.min(255);
+    let unit = options.unit as usize;
+    let original_len = data.len() as u64;
+    let (coded, stages) = options.filters.apply(data);
+
+    let symbols = options.symbols(&coded);
+    let (frequencies, crc) = scratch.count_checked(data, &symbols, !stages.is_empty(), options);
+    let (lengths, escape, bits) = frame_model(&frequencies, options)?;
+    let mut code_tables = canonical_code_tables(&lengths);
+
+    let weighted_entropy = conditional_entropy(&frequencies, original_len);
+
+    let mut header = Header {
+        original_len,
+        order,
+        max_code_len: options.max_code_len,
+        unit: options.unit,
+        payload_bits: Some(bits),
+        checksum: options.checksum,
+        escape,
+        nibble: options.nibble,
+        filters: stages,
+        model: Model::CodeLengths(lengths),
+    };
+    out.clear();
+    header.write_into(out);
+    if let Some(stored) = stored_header(&header, out.len(), options) {
+        header = stored;
+        code_tables.clear();
+        out.clear();
+        header.write_into(out);
+    }
+    let header_len = out.len();
+    scratch.begin_encode(order, unit);
+    let mut writer = BitWriter::new(&mut *out);
+    scratch.encode_chunk(&symbols, &code_tables, unit, header.escape.then_some(options.raw_bits()), &mut writer)?;
+    writer.finish()?;
+    let payload_len = out.len() - header_len;
+    debug_assert_eq!(Some(payload_len as u64), header.payload_bits.map(|bits| bits.div_ceil(8)));
+    write_footer(options.checksum, crc, out);
+
+    Ok(Stats {
+        original_len,
+        header_len,
+        payload_len,
+        footer_len: options.checksum.footer_len(),
+        entropy: weighted_entropy,
+    })
+}
+
+/// Statystyki, jakie dałby [`compress_with_stats`], bez kodowania bitów: długość danych
+/// to suma długości kodów zaokrąglona w górę do bajtu, a nagłówek jest budowany z tych
+/// samych długości kodów, więc wynik jest dokładny.
+///
+/// ```
+/// use huffman_format::{Options, compress_with_stats, estimate};
+///
+/// let options = Options { order: 1, ..Options::default() };
+/// let (_, stats) = compress_with_stats(b"abracadabra", &options)?;
+/// assert_eq!(estimate(b"abracadabra", &options)?, stats);
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub fn estimate(data: &[u8], options: &Options) -> Result<Stats> {
+    if let Some(alphabet) = &options.alphabet {
+        alphabet.check(data)?;
+    }
+    options.check_symbols()?;
+    estimate_with(data, options, &mut EncoderScratch::new())
+}
+
+fn estimate_with(data: &[u8], options: &Options, scratch: &mut EncoderScratch) -> Result<Stats> {
+    let order = options.order.min(255);
+    let original_len = data.len() as u64;
+    let (coded, stages) = options.filters.apply(data);
+    let frequencies = scratch.count(&options.symbols(&coded), order, options.unit as usize);
+    let (lengths, escape, bits) = frame_model(&frequencies, options)?;
+    let entropy = conditional_entropy(&frequencies, original_len);
+    let mut header = Header {
+        original_len,
+        order,
+        max_code_len: options.max_code_len,
+        unit: options.unit,
+        payload_bits: Some(bits),
+        checksum: options.checksum,
+        escape,
+        nibble: options.nibble,
+        filters: stages,
+        model: Model::CodeLengths(lengths),
+    };
+    let mut header_len = header.to_bytes().len();
+    if let Some(stored) = stored_header(&header, header_len, options) {
+        header = stored;
+        header_len = header.to_bytes().len();
+    }
+    Ok(Stats {
+        original_len,
+        header_len,
+        payload_len: header.payload_bits.unwrap_or(0).div_ceil(8) as usize,
+        footer_len: options.checksum.footer_len(),
+        entropy,
+    })
+}
+
+/// Dokładna długość ramki, jaką dałby [`compress_into`] dla `data`, bez kodowania bitów.
+pub(crate) fn frame_len(data: &[u8], options: &Options, scratch: &mut EncoderScratch) -> Result<usize> {
+    let stats = estimate_with(data, options, scratch)?;
+    Ok(stats.header_len + stats.payload_len + stats.footer_len)
+}
+
+/// Dekompresuje plik `.huff` wyprodukowany przez [`compress`].
+///
+/// Plik może składać się z kilku sklejonych ramek (np. z [`crate::HuffmanWriter`]);
+/// wynikiem jest konkatenacja ich zawartości.
+pub fn decompress(content: &[u8]) -> Result<Vec<u8>> {
+    decompress_with(content, &DecodeOptions::default())
+}
+
+/// Jak [`decompress`], ale z limitami z `options`.
+///
+/// Długość z nagłówka każdej ramki jest sprawdzana przed alokacją bufora wyniku.
+pub fn decompress_with(content: &[u8], options: &DecodeOptions) -> Result<Vec<u8>> {
+    decompress_frames(content, options, None)
+}
+
+/// Jak [`decompress_with`], ale zwraca też liczności odtworzonych symboli
+/// (zsumowane po ramkach), bez drugiego przebiegu po wyniku.
+///
+/// ```
+/// use huffman_format::{DecodeOptions, Options, compress, decompress_with_frequencies};
+///
+/// let packed = compress(b"abracadabra", Options::default())?;
+/// let (data, frequencies) = decompress_with_frequencies(&packed, &DecodeOptions::default())?;
+/// assert_eq!(data, b"abracadabra");
+/// assert_eq!(frequencies[&b"a".to_vec()], 5);
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub fn decompress_with_frequencies(content: &[u8], options: &DecodeOptions) -> Result<(Vec<u8>, FreqTable)> {
+    let mut frequencies = FreqTable::new();
+    let result = decompress_frames(content, options, Some(&mut frequencies))?;
+    Ok((result, frequencies))
+}
+
+// Liczności bajtów dla strumieni bez modelu symboli w nagłówku (adaptacyjny, LZ77)
+fn add_byte_counts(table: &mut FreqTable, data: &[u8]) {
+    for &byte in data {
+        *table.entry(vec![byte]).or_insert(0) += 1;
+    }
+}
+
+fn decompress_frames(
+    content: &[u8],
+    options: &DecodeOptions,
+    mut frequencies: Option<&mut FreqTable>,
+) -> Result<Vec<u8>> {
+    let mut result = Vec::new();
+    if content.starts_with(&ADAPTIVE_MAGIC) {
+        // Kod ma co najmniej bit na symbol, więc wynik jest najwyżej 8 razy dłuższy od wejścia
+        // Strumień nie deklaruje długości, więc czytamy o bajt ponad limit, żeby go wykryć
+        let limit = options.max_output_len.unwrap_or(u64::MAX).min(options.max_memory.map_or(u64::MAX, |m| m as u64));
+        let mut reader = AdaptiveReader::new(content)?.take(limit.saturating_add(1));
+        reader.read_to_end(&mut result).map_err(HuffmanError::from)?;
+        options.check_expansion(content.len() as u64, result.len() as u64)?;
+        options.check_output(result.len() as u64, true)?;
+        if let Some(table) = frequencies {
+            add_byte_counts(table, &result);
+        }
+        return Ok(result);
+    }
+    if content.starts_with(&LZ77_MAGIC) {
+        let result = decompress_lz77(content, options)?;
+        if let Some(table) = frequencies {
+            add_byte_counts(table, &result);
+        }
+        return Ok(result);
+    }
+    if let Some(id) = Dictionary::required_by(content) {
+        return Err(HuffmanError::InvalidOptions(format!(
+            "ramka zakodowana słownikiem {:08x} - dekoduje ją Dictionary::decompress",
+            id
+        )));
+    }
+    let mut pos = 0;
+    loop {
+        let (header, data_offset) =
+            Header::parse_bounded(&content[pos..], options).map_err(|e| e.at(Position::default()))?;
+        let output = (result.len() as u64).saturating_add(header.original_len);
+        options
+            .check_expansion(content.len() as u64, output)
+            .and_then(|()| options.check_output(output, true))
+            .map_err(|e| e.at(Position::default()))?;
+        let mut decoder = SymbolDecoder::new(&header).map_err(|e| e.at(Position::default()))?;
+        decoder.configure(options);
+        if frequencies.is_some() {
+            decoder.count_symbols();
+        }
+        pos += data_offset;
+
+        // Długość z nagłówka może być zmyślona: rezerwujemy najwyżej tyle, ile dałyby
+        // pozostałe bity przy kodach 1-bitowych, a resztę bufor dostanie w miarę dekodowania
+        let bound = (content.len() - pos) as u64 * 8 * header.unit as u64;
+        let start = result.len();
+        result.reserve(header.coded_len().min(bound) as usize);
+        let mut payload = &content[pos..];
+        decoder.decode_from(&mut decoder.bit_reader(&mut payload), &mut result)?;
+        pos = content.len() - payload.len();
+
+        let footer = content
+            .get(pos..pos + decoder.footer_len())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "brak sumy kontrolnej na końcu ramki"))?;
+        pos += footer.len();
+        if header.filters.is_empty() {
+            decoder.verify(footer, None)?;
+        } else {
+            let coded = result.split_off(start);
+            result.extend_from_slice(&filters::revert(&header.filters, coded, header.original_len)?);
+            decoder.verify(footer, Some(&result[start..]))?;
+        }
+        if let Some(table) = frequencies.as_deref_mut() {
+            decoder.add_counts(table);
+        }
+
+        if content[pos..].starts_with(&META_MAGIC) {
+            // Metadane leżą między ostatnią ramką a tablicą skoków
+            let (_, len) = Metadata::parse(&content[pos..]).map_err(|e| e.at(Position::default()))?;
+            pos += len;
+            if pos < content.len() && !content[pos..].starts_with(&SEEK_MAGIC) {
+                return Err(HuffmanError::MalformedHeader("dane za blokiem metadanych".to_string()));
+            }
+        }
+        if pos == content.len() {
+            return Ok(result);
+        }
+        if content[pos..].starts_with(&SEEK_MAGIC) {
+            // Tablica skoków kończy plik; dane już są, ale uszkodzoną tablicę zgłaszamy
+            SeekTable::parse(&content[pos..]).map_err(|e| e.at(Position::default()))?;
+            return Ok(result);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::adaptive::{AdaptivePreset, AdaptiveReader, AdaptiveWriter};
+use crate::codec::{DecodeOptions, EncoderScratch, Options, compress_into, decompress_with};
+use crate::error::Result;
+use crate::lz77::{Lz77Options, compress_lz77, decompress_lz77};
+use crate::reader::HuffmanReader;
+use crate::writer::DEFAULT_BLOCK_SIZE;
+
+/// Wspólny interfejs kodeka, który da się trzymać za `Box<dyn Codec>`, np. gdy
+/// aplikacja wybiera kodek z konfiguracji i obsługuje kilka formatów naraz.
+///
+/// Puste wejście daje pusty wynik (bez ramki z samym nagłówkiem, jaką zapisuje
+/// [`crate::compress`]), a pusty wynik dekompresuje się z powrotem do pustych danych.
+///
+/// ```
+/// use huffman_format::{AdaptiveCodec, Codec, HuffmanCodec, Lz77Codec};
+///
+/// let codecs: Vec<Box<dyn Codec>> =
+///     vec![Box::new(HuffmanCodec::default()), Box::new(AdaptiveCodec::default()), Box::new(Lz77Codec::default())];
+/// for codec in &codecs {
+///     let packed = codec.compress(b"abracadabra")?;
+///     assert_eq!(codec.decompress(&packed)?, b"abracadabra", "{}", codec.name());
+/// }
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub trait Codec {
+    /// Krótka nazwa kodeka, np. do konfiguracji albo nagłówka `Content-Encoding`.
+    fn name(&self) -> &str;
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>>;
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>>;
+
+    /// Kompresuje wszystko z `input` do `output`; zwraca liczbę zapisanych bajtów.
+    /// Domyślnie wczytuje całe wejście do pamięci.
+    fn compress_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<u64> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        let packed = self.compress(&data)?;
+        output.write_all(&packed)?;
+        Ok(packed.len() as u64)
+    }
+
+    /// Odwrotność [`Codec::compress_stream`]; zwraca liczbę odtworzonych bajtów.
+    fn decompress_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<u64> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        let decoded = self.decompress(&data)?;
+        output.write_all(&decoded)?;
+        Ok(decoded.len() as u64)
+    }
+}
+
+/// Format `.huff` jako [`Codec`]; strumienie są kodowane blokami po `block_size`
+/// bajtów, jak w [`crate::HuffmanWriter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HuffmanCodec {
+    pub options: Options,
+    pub decode_options: DecodeOptions,
+    pub block_size: usize,
+}
+
+impl Default for HuffmanCodec {
+    fn default() -> Self {
+        HuffmanCodec {
+            options: Options::default(),
+            decode_options: DecodeOptions::default(),
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+impl Codec for HuffmanCodec {
+    fn name(&self) -> &str {
+        "huffman"
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut packed = Vec::new();
+        HuffmanEncoder::with_block_size(input, self.options, self.block_size).read_to_end(&mut packed)?;
+        Ok(packed)
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+        decompress_with(input, &self.decode_options)
+    }
+
+    fn compress_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<u64> {
+        let mut encoder = HuffmanEncoder::with_block_size(input, self.options, self.block_size);
+        Ok(io::copy(&mut encoder, output)?)
+    }
+
+    fn decompress_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<u64> {
+        let mut input = BufReader::new(input);
+        if input.fill_buf()?.is_empty() {
+            return Ok(0);
+        }
+        Ok(io::copy(&mut HuffmanReader::with_options(input, self.decode_options)?, output)?)
+    }
+}
+
+/// Tryb adaptacyjny ([`AdaptiveWriter`]) jako [`Codec`]; z `preset` strumienie
+/// zaczynają od modelu startowego, który przy dekompresji musi być ten sam.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdaptiveCodec {
+    pub preset: Option<AdaptivePreset>,
+}
+
+impl Codec for AdaptiveCodec {
+    fn name(&self) -> &str {
+        "huffman-adaptive"
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut packed = Vec::new();
+        self.compress_stream(&mut &input[..], &mut packed)?;
+        Ok(packed)
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        self.decompress_stream(&mut &input[..], &mut decoded)?;
+        Ok(decoded)
+    }
+
+    fn compress_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<u64> {
+        let mut input = BufReader::new(input);
+        if input.fill_buf()?.is_empty() {
+            return Ok(0);
+        }
+        let mut writer = match &self.preset {
+            Some(preset) => AdaptiveWriter::with_preset(output, preset)?,
+            None => AdaptiveWriter::new(output)?,
+        };
+        io::copy(&mut input, &mut writer)?;
+        let (_, stats) = writer.finish()?;
+        Ok((stats.header_len + stats.payload_len + stats.footer_len) as u64)
+    }
+
+    fn decompress_stream(&self, input: &mut dyn Read, output: &mut dyn Write) -> Result<u64> {
+        let mut input = BufReader::new(input);
+        if input.fill_buf()?.is_empty() {
+            return Ok(0);
+        }
+        let mut reader = match &self.preset {
+            Some(preset) => AdaptiveReader::with_preset(input, preset)?,
+            None => AdaptiveReader::new(input)?,
+        };
+        Ok(io::copy(&mut reader, output)?)
+    }
+}
+
+/// Kodek LZ77 + Huffman ([`compress_lz77`]) jako [`Codec`]; całe wejście to jedna ramka.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Lz77Codec {
+    pub options: Lz77Options,
+    pub decode_options: DecodeOptions,
+}
+
+impl Codec for Lz77Codec {
+    fn name(&self) -> &str {
+        "huffman-lz77"
+    }
+
+    fn compress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+        compress_lz77(input, &self.options)
+    }
+
+    fn decompress(&self, input: &[u8]) -> Result<Vec<u8>> {
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+        decompress_lz77(input, &self.decode_options)
+    }
+}
+
+/// Koder w postaci [`Read`]: pobiera dane z `R` blokami i oddaje kolejne ramki `.huff`,
+/// odwrotnie niż [`crate::HuffmanWriter`]. Przydatny tam, gdzie API oczekuje źródła
+/// danych, np. jako ciało żądania HTTP albo wejście `io::copy`.
+///
+/// ```
+/// use std::io::Read;
+/// use huffman_format::{HuffmanEncoder, Options, decompress};
+///
+/// let mut packed = Vec::new();
+/// HuffmanEncoder::new(&b"abracadabra"[..], Options::default()).read_to_end(&mut packed)?;
+/// assert_eq!(decompress(&packed)?, b"abracadabra");
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub struct HuffmanEncoder<R: Read> {
+    inner: R,
+    options: Options,
+    block_size: usize,
+    scratch: EncoderScratch,
+    block: Vec<u8>,
+    frame: Vec<u8>,
+    frame_pos: usize,
+    finished: bool,
+}
+
+impl<R: Read> HuffmanEncoder<R> {
+    pub fn new(inner: R, options: Options) -> Self {
+        Self::with_block_size(inner, options, DEFAULT_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(inner: R, options: Options, block_size: usize) -> Self {
+        HuffmanEncoder {
+            inner,
+            options,
+            block_size: block_size.max(1),
+            scratch: EncoderScratch::new(),
+            block: Vec::new(),
+            frame: Vec::new(),
+            frame_pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Koduje kolejny blok wejścia; zwraca false, gdy wejście się skończyło
+    fn next_frame(&mut self) -> Result<bool> {
+        self.block.clear();
+        (&mut self.inner).take(self.block_size as u64).read_to_end(&mut self.block)?;
+        if self.block.len() < self.block_size {
+            self.finished = true;
+        }
+        if self.block.is_empty() {
+            return Ok(false);
+        }
+        compress_into(&self.block, &self.options, &mut self.scratch, &mut self.frame)?;
+        self.frame_pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for HuffmanEncoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.frame_pos == self.frame.len() {
+            if self.finished || !self.next_frame()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.frame.len() - self.frame_pos);
+        buf[..n].copy_from_slice(&self.frame[self.frame_pos..self.frame_pos + n]);
+        self.frame_pos += n;
+        Ok(n)
+    }
+}
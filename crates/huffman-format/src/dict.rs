@@ -0,0 +1,302 @@
+use std::io;
+
+use crate::checksum::crc32;
+use crate::codec::{
+    DecodeOptions, EncoderScratch, Options, Stats, SymbolDecoder, build_code_lengths, decompress_with, prune_symbols,
+};
+use crate::error::{HuffmanError, Position, Result};
+use crate::filters;
+use crate::header::{ChecksumKind, ESCAPE, Header, MAGIC, Model, parse_filters, write_filters};
+use crate::huffman::MarkovFreqTable;
+use crate::table::{Coverage, FixedTable};
+
+/// Sygnatura pliku słownika ([`Dictionary::to_bytes`]).
+pub const DICT_MAGIC: [u8; 4] = *b"HDIC";
+
+/// Sygnatura ramki zakodowanej słownikiem ([`Dictionary::compress`]).
+pub const DICT_FRAME_MAGIC: [u8; 4] = *b"HDCT";
+
+const DICT_VERSION: u8 = 1;
+
+// Ramka z filtrami: za stałym początkiem łańcuch filtrów zapisany jak w nagłówku v7
+const FILTERED_FRAME_VERSION: u8 = 2;
+
+// Sygnatura, wersja, `u32` identyfikator słownika, `u8` rodzaj sumy kontrolnej
+// i `u64` długość oryginału; strumień bitów kończy się tuż przed stopką
+const FRAME_PREFIX_LEN: usize = DICT_FRAME_MAGIC.len() + 1 + 4 + 1 + 8;
+
+// Liczności próbek wbudowanego słownika z build.rs: (kontekst, bajt, liczba wystąpień)
+struct PresetCounts {
+    name: &'static str,
+    order: usize,
+    counts: &'static [(&'static [u8], u8, u64)],
+}
+
+const PRESET_COUNTS: &[PresetCounts] = include!(concat!(env!("OUT_DIR"), "/presets.rs"));
+
+/// Wspólny model wytrenowany na wielu podobnych plikach (odpowiedzi API w JSON, linie logów),
+/// którym koduje się każdy z nich bez powtarzania tabeli kodów w wyniku.
+///
+/// Słownik to tabela kodów rzędu N z kodem ucieczki w każdym kontekście, więc koduje
+/// bezstratnie także dane, których nie było w próbkach. Ramka słownika ma tylko
+/// 18 bajtów nagłówka: sygnaturę `HDCT`, wersję, [`Dictionary::id`], rodzaj sumy kontrolnej
+/// i długość oryginału; strumień bitów sięga do stopki, takiej jak w ramce `.huff`.
+/// Ramka danych po filtrach ([`Options::filters`]) ma wersję 2 i łańcuch filtrów za tymi
+/// 18 bajtami, a suma kontrolna dotyczy oryginału. Dekoder musi dostać ten sam słownik,
+/// a inny jest odrzucany po `id`.
+///
+/// ```
+/// use huffman_format::{DecodeOptions, Dictionary, Options, compress};
+///
+/// let samples = [&br#"{"id":1,"status":"ok"}"#[..], br#"{"id":2,"status":"error"}"#];
+/// let options = Options { order: 1, ..Options::default() };
+/// let dict = Dictionary::train(samples, &options)?;
+///
+/// let message = br#"{"id":3,"status":"ok"}"#;
+/// let packed = dict.compress(message, &options)?;
+/// assert!(packed.len() < compress(message, options)?.len());
+/// let shared = Dictionary::from_bytes(&dict.to_bytes())?;
+/// assert_eq!(shared.decompress(&packed, &DecodeOptions::default())?, message);
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    table: FixedTable,
+    id: u32,
+}
+
+impl Dictionary {
+    /// Trenuje słownik na próbkach; z `options` brane są rząd, szerokość symbolu, limit
+    /// długości kodu, [`Options::max_symbols`] i filtry, przez które przechodzi każda próbka.
+    /// Każda próbka zaczyna się od kontekstu z samych zer, tak jak każdy kodowany później plik.
+    pub fn train<'a>(samples: impl IntoIterator<Item = &'a [u8]>, options: &Options) -> Result<Dictionary> {
+        options.check_symbols()?;
+        if options.nibble {
+            return Err(HuffmanError::InvalidOptions("słownik: symbole 4-bitowe nie są obsługiwane".to_string()));
+        }
+        let order = options.order.min(255);
+        let unit = options.unit as usize;
+        let mut scratch = EncoderScratch::new();
+        let mut frequencies = MarkovFreqTable::new();
+        for sample in samples {
+            let (coded, _) = options.filters.apply(sample);
+            for (context, f_table) in scratch.count(&coded, order, unit) {
+                let merged = frequencies.entry(context).or_default();
+                for (symbol, count) in f_table {
+                    *merged.entry(symbol).or_insert(0) += count;
+                }
+            }
+        }
+        Dictionary::from_frequencies(frequencies, options)
+    }
+
+    // Słownik z liczności próbek; `train` i słowniki wbudowane różnią się tylko ich źródłem
+    fn from_frequencies(mut frequencies: MarkovFreqTable, options: &Options) -> Result<Dictionary> {
+        if frequencies.is_empty() {
+            return Err(HuffmanError::InvalidOptions("słownik: próbki są puste".to_string()));
+        }
+        let order = options.order.min(255);
+        if let Some((pruned, _)) = options.max_symbols.and_then(|n| prune_symbols(&frequencies, n)) {
+            frequencies = pruned;
+        }
+        // Ucieczka z częstością 1 jest najrzadszym symbolem, więc wydłuża kody próbek najmniej
+        for f_table in frequencies.values_mut() {
+            f_table.entry(ESCAPE.to_vec()).or_insert(1);
+        }
+        let lengths = build_code_lengths(&frequencies, options.max_code_len)?;
+        let longest = lengths.values().flat_map(|l_table| l_table.values().copied()).max().unwrap_or(0);
+        let header = Header {
+            original_len: 0,
+            order,
+            max_code_len: options.max_code_len.max(longest),
+            unit: options.unit,
+            payload_bits: Some(0),
+            checksum: ChecksumKind::None,
+            escape: true,
+            nibble: false,
+            filters: Vec::new(),
+            model: Model::CodeLengths(lengths),
+        };
+        Dictionary::from_table(FixedTable::from_header(&header)?)
+    }
+
+    /// Wbudowany słownik o nazwie z [`Dictionary::presets`], wytrenowany przy budowie biblioteki
+    /// na próbkach z `presets/`. Dekoder rozpoznaje jego ramki po [`Dictionary::find_preset`].
+    pub fn preset(name: &str) -> Option<Dictionary> {
+        let preset = PRESET_COUNTS.iter().find(|preset| preset.name == name)?;
+        let mut frequencies = MarkovFreqTable::new();
+        for &(context, symbol, count) in preset.counts {
+            frequencies.entry(context.to_vec()).or_default().insert(vec![symbol], count);
+        }
+        let options = Options { order: preset.order, ..Options::default() };
+        Some(Dictionary::from_frequencies(frequencies, &options).expect("próbki wbudowanych słowników są poprawne"))
+    }
+
+    /// Nazwy wbudowanych słowników: `english`, `json`, `csv`, `base64`, `dna` i `logs`.
+    /// Słownik `logs` (rząd 2) wytrenowano na liniach logów po filtrze `timestamps:32`,
+    /// więc koduje najkrócej z tym samym filtrem ([`Options::filters`]).
+    pub fn presets() -> impl Iterator<Item = &'static str> {
+        PRESET_COUNTS.iter().map(|preset| preset.name)
+    }
+
+    /// Wbudowany słownik o identyfikatorze `id` (z [`Dictionary::required_by`]) razem z nazwą.
+    pub fn find_preset(id: u32) -> Option<(&'static str, Dictionary)> {
+        Dictionary::presets().find_map(|name| Some((name, Dictionary::preset(name)?)).filter(|(_, dict)| dict.id == id))
+    }
+
+    /// Słownik z gotowej tabeli; tabela bez kodów ucieczki dostaje je ([`FixedTable::with_escapes`]).
+    pub fn from_table(table: FixedTable) -> Result<Dictionary> {
+        let table = table.with_escapes()?;
+        let id = crc32(&table.header().to_bytes());
+        Ok(Dictionary { table, id })
+    }
+
+    /// Identyfikator zapisywany w ramkach: CRC-32 tabeli kodów.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Tabela kodów słownika.
+    pub fn table(&self) -> &FixedTable {
+        &self.table
+    }
+
+    /// Słownik w postaci do zapisania w pliku: `HDIC`, wersja i nagłówek `.huff` pustej ramki z tabelą.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = DICT_MAGIC.to_vec();
+        bytes.push(DICT_VERSION);
+        self.table.header().write_into(&mut bytes);
+        bytes
+    }
+
+    /// Odtwarza słownik zapisany przez [`Dictionary::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Dictionary> {
+        match bytes {
+            [m0, m1, m2, m3, DICT_VERSION, rest @ ..] if [*m0, *m1, *m2, *m3] == DICT_MAGIC => {
+                let (header, len) = Header::parse(rest)?;
+                if len != rest.len() {
+                    return Err(HuffmanError::MalformedHeader(format!(
+                        "słownik: {} bajtów za tabelą kodów",
+                        rest.len() - len
+                    )));
+                }
+                Dictionary::from_table(FixedTable::from_header(&header)?)
+            }
+            [m0, m1, m2, m3, version, ..] if [*m0, *m1, *m2, *m3] == DICT_MAGIC => Err(
+                HuffmanError::MalformedHeader(format!("nieobsługiwana wersja słownika {}", version)),
+            ),
+            _ => Err(HuffmanError::MalformedHeader("brak sygnatury HDIC - to nie jest słownik".to_string())),
+        }
+    }
+
+    /// Identyfikator słownika, którego wymaga ramka zaczynająca się od `prefix`;
+    /// `None`, gdy to nie ramka słownika.
+    pub fn required_by(prefix: &[u8]) -> Option<u32> {
+        match prefix {
+            [m0, m1, m2, m3, _, id @ ..] if [*m0, *m1, *m2, *m3] == DICT_FRAME_MAGIC => {
+                Some(u32::from_be_bytes(id.get(..4)?.try_into().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Koduje `data` do ramki słownika; z `options` używane są alfabet, filtry i suma kontrolna,
+    /// a rząd i szerokość muszą zgadzać się ze słownikiem. Dane, które słownik by wydłużył,
+    /// dostają zwykłą ramkę `.huff` zapisaną wprost ([`FixedTable::compress_with_stats`]).
+    pub fn compress(&self, data: &[u8], options: &Options) -> Result<Vec<u8>> {
+        Ok(self.compress_with_stats(data, options)?.0)
+    }
+
+    /// Jak [`Dictionary::compress`], ale zwraca też statystyki; nagłówkiem jest tu sam początek ramki.
+    pub fn compress_with_stats(&self, data: &[u8], options: &Options) -> Result<(Vec<u8>, Stats)> {
+        let (out, stats, _) = self.compress_with_coverage(data, options)?;
+        Ok((out, stats))
+    }
+
+    /// Jak [`Dictionary::compress_with_stats`], ale zwraca też pokrycie danych przez słownik:
+    /// ile symboli poszło za kodem ucieczki i ile strumienia to zajęło.
+    ///
+    /// ```
+    /// use huffman_format::{Dictionary, Options};
+    ///
+    /// let options = Options { order: 1, ..Options::default() };
+    /// let dict = Dictionary::train([&b"status=ok;status=error"[..]], &options)?;
+    /// let (_, _, seen) = dict.compress_with_coverage(b"status=ok", &options)?;
+    /// let (_, _, new) = dict.compress_with_coverage(b"STATUS=OK", &options)?;
+    /// assert_eq!(seen.ratio(), 1.0);
+    /// assert!(new.ratio() < 0.5 && new.escape_share() > 0.5);
+    /// # Ok::<(), huffman_format::HuffmanError>(())
+    /// ```
+    pub fn compress_with_coverage(&self, data: &[u8], options: &Options) -> Result<(Vec<u8>, Stats, Coverage)> {
+        self.table.compress_framed(data, options, |header, out| {
+            out.extend_from_slice(&DICT_FRAME_MAGIC);
+            out.push(if header.filters.is_empty() { DICT_VERSION } else { FILTERED_FRAME_VERSION });
+            out.extend_from_slice(&self.id.to_be_bytes());
+            out.push(header.checksum.to_byte());
+            out.extend_from_slice(&header.original_len.to_be_bytes());
+            if !header.filters.is_empty() {
+                write_filters(&header.filters, out);
+            }
+        })
+    }
+
+    /// Dekoduje ramkę z [`Dictionary::compress`] z limitami z `options`.
+    pub fn decompress(&self, frame: &[u8], options: &DecodeOptions) -> Result<Vec<u8>> {
+        // Ramka zapisana wprost nie potrzebuje słownika
+        if frame.starts_with(&MAGIC) {
+            return decompress_with(frame, options);
+        }
+        let prefix = frame.get(..FRAME_PREFIX_LEN).ok_or_else(|| {
+            HuffmanError::MalformedHeader(format!("ramka słownika krótsza niż {} bajtów", FRAME_PREFIX_LEN))
+        })?;
+        let Some(id) = Dictionary::required_by(prefix) else {
+            return Err(HuffmanError::MalformedHeader("brak sygnatury HDCT - to nie jest ramka słownika".to_string()));
+        };
+        let (stages, filters_len) = match prefix[4] {
+            DICT_VERSION => (Vec::new(), 0),
+            FILTERED_FRAME_VERSION => parse_filters(&frame[FRAME_PREFIX_LEN..]).map_err(|e| e.at(Position::default()))?,
+            version => {
+                return Err(HuffmanError::MalformedHeader(format!("nieobsługiwana wersja ramki słownika {}", version)));
+            }
+        };
+        if id != self.id {
+            return Err(HuffmanError::InvalidOptions(format!(
+                "słownik {:08x} nie pasuje do ramki (wymaga {:08x})",
+                self.id, id
+            )));
+        }
+        let header = Header {
+            original_len: u64::from_be_bytes(prefix[10..].try_into().expect("długość ma 8 bajtów")),
+            // Bity za ostatnim symbolem to dopełnienie, jak w plikach sprzed v3
+            payload_bits: None,
+            checksum: ChecksumKind::from_byte(prefix[9])?,
+            filters: stages,
+            ..self.table.header()
+        };
+        options
+            .check_expansion(frame.len() as u64, header.original_len)
+            .and_then(|()| options.check_output(header.original_len, true))
+            .and_then(|()| options.check_table(header.table_entries()))
+            .map_err(|e| e.at(Position::default()))?;
+
+        let mut decoder = SymbolDecoder::new(&header)?;
+        decoder.configure(options);
+        let rest = &frame[FRAME_PREFIX_LEN + filters_len..];
+        let payload_len = rest
+            .len()
+            .checked_sub(decoder.footer_len())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "brak sumy kontrolnej na końcu ramki"))?;
+        let (mut payload, footer) = rest.split_at(payload_len);
+        // Jak w decompress_with: rezerwujemy najwyżej tyle, ile dałyby bity przy kodach 1-bitowych
+        let bound = payload.len() as u64 * 8 * header.unit as u64;
+        let mut out = Vec::with_capacity(header.coded_len().min(bound) as usize);
+        decoder.decode_from(&mut decoder.bit_reader(&mut payload), &mut out)?;
+        if header.filters.is_empty() {
+            decoder.verify(footer, None)?;
+            return Ok(out);
+        }
+        let out = filters::revert(&header.filters, out, header.original_len)?;
+        decoder.verify(footer, Some(&out))?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,77 @@
+use crate::codec::Stats;
+
+/// Etap pracy kodera zgłaszany w [`Event::PhaseStarted`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Pierwszy przebieg: zliczanie częstotliwości.
+    Counting,
+    /// Budowa kodów i emisja bitów.
+    Encoding,
+}
+
+/// Zdarzenie zgłaszane przez koder do [`EventSink`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    PhaseStarted(Phase),
+    /// Ramka numer `n` (od 0) trafiła do wyjścia; `ratio` to stosunek
+    /// długości ramki do długości oryginału, a `payload_len` i `ideal_payload_len`
+    /// to długość strumienia i jej minimum z entropii ([`Stats::ideal_payload_len`]).
+    BlockEncoded {
+        n: u64,
+        original_len: u64,
+        compressed_len: u64,
+        ratio: f64,
+        payload_len: u64,
+        ideal_payload_len: f64,
+    },
+    Warning { message: String },
+}
+
+impl Event {
+    pub(crate) fn block(n: u64, stats: &Stats) -> Event {
+        let compressed_len = (stats.header_len + stats.payload_len + stats.footer_len) as u64;
+        let original_len = stats.original_len;
+        let ratio = if original_len == 0 { 0.0 } else { compressed_len as f64 / original_len as f64 };
+        Event::BlockEncoded {
+            n,
+            original_len,
+            compressed_len,
+            ratio,
+            payload_len: stats.payload_len as u64,
+            ideal_payload_len: stats.ideal_payload_len(),
+        }
+    }
+}
+
+/// Odbiorca zdarzeń zamiast logowania w bibliotece: CLI zamienia je na komunikaty,
+/// a nakładka graficzna może z nich rysować postęp.
+///
+/// Każde domknięcie `FnMut(&Event)` jest odbiorcą:
+///
+/// ```
+/// use std::io::Write;
+/// use huffman_format::{Event, HuffmanWriter, Options};
+///
+/// let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 4)
+///     .with_events(|event: &Event| println!("{:?}", event));
+/// writer.write_all(b"abracadabra")?;
+/// writer.finish()?;
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub trait EventSink {
+    fn event(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event)> EventSink for F {
+    fn event(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Odbiorca ignorujący wszystkie zdarzenia.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoEvents;
+
+impl EventSink for NoEvents {
+    fn event(&mut self, _: &Event) {}
+}
@@ -0,0 +1,354 @@
+use std::fmt::Write;
+
+use crate::codec::{EncoderScratch, check_unit};
+use crate::error::{HuffmanError, Result};
+use crate::freq::FreqTableExt;
+use crate::header::{ChecksumKind, Header, Model};
+use crate::huffman::{FreqTable, MarkovFreqTable};
+use crate::table::FixedTable;
+
+/// Sygnatura binarnej postaci modelu ([`FreqModel::to_bytes`]).
+pub const FREQ_MAGIC: [u8; 4] = *b"HFRQ";
+
+const FREQ_VERSION: u8 = 1;
+
+// Wartość pola "format" w JSON, żeby plik dało się rozpoznać bez rozszerzenia
+const JSON_FORMAT: &str = "huff-freq";
+
+/// Częstotliwości modelu rzędu N zapisywane poza plikiem `.huff`, np. do obejrzenia
+/// modelu w innym narzędziu albo do kodowania kolejnych plików tym samym modelem
+/// (`huff stats --export-freq`, `huff encode --freq`).
+///
+/// Konteksty i symbole są w JSON ciągami szesnastkowymi, a [`FreqModel::to_bytes`] to ta sama
+/// treść w zwartej postaci binarnej. Obie postacie mają stałą kolejność kontekstów i symboli,
+/// więc ten sam model daje zawsze te same bajty.
+///
+/// ```
+/// use huffman_format::{FreqModel, Options, decompress};
+///
+/// let model = FreqModel::count(b"abracadabra", 1, 1)?;
+/// let json = model.to_json();
+/// assert!(json.contains("\"61\": {\"62\": 2, \"63\": 1, \"64\": 1}"));
+///
+/// let imported = FreqModel::from_json(&json)?;
+/// assert_eq!(imported, FreqModel::from_bytes(&model.to_bytes())?);
+/// let table = imported.table(15)?;
+/// let (packed, _) = table.compress_with_stats(b"abracad", &Options { order: 1, ..Options::default() })?;
+/// assert_eq!(decompress(&packed)?, b"abracad");
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreqModel {
+    pub order: usize,
+    pub unit: u8,
+    /// Częstotliwości symboli w każdym kontekście.
+    pub tables: MarkovFreqTable,
+}
+
+impl FreqModel {
+    /// Zlicza symbole `data` tak, jak koder `.huff` z tym rzędem i szerokością symbolu.
+    pub fn count(data: &[u8], order: usize, unit: u8) -> Result<FreqModel> {
+        check_unit(unit)?;
+        if order > 255 {
+            return Err(HuffmanError::InvalidOptions(format!("rząd {} (dozwolone 0..=255)", order)));
+        }
+        let tables = EncoderScratch::new().count(data, order, unit as usize);
+        Ok(FreqModel { order, unit, tables })
+    }
+
+    /// Tabela kodów rzędu i szerokości modelu z kodami nie dłuższymi niż `max_code_len`.
+    pub fn table(&self, max_code_len: u8) -> Result<FixedTable> {
+        FixedTable::from_header(&Header {
+            original_len: 0,
+            order: self.order,
+            max_code_len,
+            unit: self.unit,
+            payload_bits: Some(0),
+            checksum: ChecksumKind::None,
+            escape: false,
+            nibble: false,
+            filters: Vec::new(),
+            model: Model::Frequencies(self.tables.clone()),
+        })
+    }
+
+    /// Model jako JSON: jeden wiersz na kontekst, konteksty i symbole w kolejności bajtów.
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            "{{\n  \"format\": \"{}\",\n  \"version\": {},\n  \"order\": {},\n  \"unit\": {},\n  \"contexts\": {{",
+            JSON_FORMAT, FREQ_VERSION, self.order, self.unit
+        );
+        for (i, (context, f_table)) in self.sorted().into_iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            let _ = write!(json, "{}\n    \"{}\": {{", separator, hex(context));
+            for (j, (symbol, freq)) in f_table.into_iter().enumerate() {
+                let _ = write!(json, "{}\"{}\": {}", if j == 0 { "" } else { ", " }, hex(symbol), freq);
+            }
+            json.push('}');
+        }
+        json.push_str("\n  }\n}\n");
+        json
+    }
+
+    /// Odczytuje model zapisany przez [`FreqModel::to_json`]; nieznane pola są pomijane.
+    pub fn from_json(text: &str) -> Result<FreqModel> {
+        let mut parser = JsonParser { bytes: text.as_bytes(), pos: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(parser.error("dane za końcem obiektu"));
+        }
+        let Json::Object(fields) = value else {
+            return Err(malformed("oczekiwano obiektu JSON"));
+        };
+        let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, value)| value);
+        match field("format") {
+            Some(Json::String(format)) if format == JSON_FORMAT => {}
+            _ => return Err(malformed(format!("brak pola \"format\": \"{}\"", JSON_FORMAT))),
+        }
+        match field("version") {
+            Some(Json::Number(version)) if *version == FREQ_VERSION as u64 => {}
+            Some(Json::Number(version)) => return Err(malformed(format!("nieobsługiwana wersja {}", version))),
+            _ => return Err(malformed("brak liczby w polu \"version\"")),
+        }
+        let number = |name: &str, max: u64| match field(name) {
+            Some(Json::Number(n)) if *n <= max => Ok(*n),
+            _ => Err(malformed(format!("pole \"{}\" musi być liczbą 0..={}", name, max))),
+        };
+        let order = number("order", 255)? as usize;
+        let unit = number("unit", 255)? as u8;
+        let Some(Json::Object(contexts)) = field("contexts") else {
+            return Err(malformed("brak obiektu \"contexts\""));
+        };
+        let mut tables = MarkovFreqTable::new();
+        for (context, symbols) in contexts {
+            let Json::Object(symbols) = symbols else {
+                return Err(malformed(format!("kontekst \"{}\" nie jest obiektem", context)));
+            };
+            let mut f_table = FreqTable::new();
+            for (symbol, freq) in symbols {
+                let Json::Number(freq) = freq else {
+                    return Err(malformed(format!("częstotliwość \"{}\" nie jest liczbą", symbol)));
+                };
+                f_table.insert(unhex(symbol)?, *freq);
+            }
+            tables.insert(unhex(context)?, f_table);
+        }
+        FreqModel { order, unit, tables }.checked()
+    }
+
+    /// Model w zwartej postaci binarnej: `HFRQ`, wersja, rząd, szerokość symbolu, `u32` liczba
+    /// kontekstów, a dla każdego kontekst, `u32` liczba symboli i pary symbol, `u64` częstotliwość.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = FREQ_MAGIC.to_vec();
+        bytes.extend_from_slice(&[FREQ_VERSION, self.order as u8, self.unit]);
+        bytes.extend_from_slice(&(self.tables.len() as u32).to_be_bytes());
+        for (context, f_table) in self.sorted() {
+            bytes.extend_from_slice(context);
+            bytes.extend_from_slice(&(f_table.len() as u32).to_be_bytes());
+            for (symbol, freq) in f_table {
+                bytes.extend_from_slice(symbol);
+                bytes.extend_from_slice(&freq.to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Odtwarza model zapisany przez [`FreqModel::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<FreqModel> {
+        let (order, unit, mut rest) = match bytes {
+            [m0, m1, m2, m3, FREQ_VERSION, order, unit, rest @ ..] if [*m0, *m1, *m2, *m3] == FREQ_MAGIC => {
+                (*order as usize, *unit, rest)
+            }
+            [m0, m1, m2, m3, version, ..] if [*m0, *m1, *m2, *m3] == FREQ_MAGIC => {
+                return Err(malformed(format!("nieobsługiwana wersja {}", version)));
+            }
+            _ => return Err(malformed("brak sygnatury HFRQ")),
+        };
+        check_unit(unit)?;
+        let mut take = |len: usize| {
+            let (taken, tail) = rest.split_at_checked(len).ok_or_else(|| malformed("plik jest urwany"))?;
+            rest = tail;
+            Ok::<_, HuffmanError>(taken)
+        };
+        let count = |bytes: &[u8]| u32::from_be_bytes(bytes.try_into().expect("licznik ma 4 bajty")) as usize;
+        let mut tables = MarkovFreqTable::new();
+        for _ in 0..count(take(4)?) {
+            let context = take(order * unit as usize)?.to_vec();
+            let mut f_table = FreqTable::new();
+            for _ in 0..count(take(4)?) {
+                let symbol = take(unit as usize)?.to_vec();
+                let freq = u64::from_be_bytes(take(8)?.try_into().expect("częstotliwość ma 8 bajtów"));
+                f_table.insert(symbol, freq);
+            }
+            tables.insert(context, f_table);
+        }
+        if !rest.is_empty() {
+            return Err(malformed(format!("{} bajtów za ostatnim kontekstem", rest.len())));
+        }
+        FreqModel { order, unit, tables }.checked()
+    }
+
+    /// Rozpoznaje postać po sygnaturze: [`FreqModel::from_bytes`] albo [`FreqModel::from_json`].
+    pub fn parse(bytes: &[u8]) -> Result<FreqModel> {
+        if bytes.starts_with(&FREQ_MAGIC) {
+            return FreqModel::from_bytes(bytes);
+        }
+        let text = std::str::from_utf8(bytes).map_err(|_| malformed("ani HFRQ, ani JSON w UTF-8"))?;
+        FreqModel::from_json(text)
+    }
+
+    // Konteksty i symbole posortowane bajtami, czyli kolejność obu postaci zapisu
+    fn sorted(&self) -> Vec<SortedContext<'_>> {
+        let mut contexts: Vec<_> = self
+            .tables
+            .iter()
+            .map(|(context, f_table)| {
+                let mut symbols: Vec<_> = f_table.iter().map(|(symbol, &freq)| (symbol, freq)).collect();
+                symbols.sort_unstable();
+                (context, symbols)
+            })
+            .collect();
+        contexts.sort_unstable_by_key(|&(context, _)| context);
+        contexts
+    }
+
+    // Model z pliku trafia wprost do budowy drzewa, więc sprawdzamy szerokości i wagi
+    fn checked(self) -> Result<FreqModel> {
+        check_unit(self.unit)?;
+        if self.tables.is_empty() {
+            return Err(HuffmanError::EmptyInput);
+        }
+        let context_len = self.order * self.unit as usize;
+        for (context, f_table) in &self.tables {
+            if context.len() != context_len {
+                return Err(malformed(format!(
+                    "kontekst {} ma {} bajtów zamiast {}",
+                    hex(context),
+                    context.len(),
+                    context_len
+                )));
+            }
+            if let Some(symbol) = f_table.keys().find(|symbol| symbol.len() != self.unit as usize) {
+                let message = format!("symbol {} ma {} bajtów zamiast {}", hex(symbol), symbol.len(), self.unit);
+                return Err(malformed(message));
+            }
+            f_table.validate()?;
+        }
+        Ok(self)
+    }
+}
+
+// Kontekst z symbolami i częstotliwościami w kolejności zapisu
+type SortedContext<'a> = (&'a Vec<u8>, Vec<(&'a Vec<u8>, u64)>);
+
+fn malformed(message: impl Into<String>) -> HuffmanError {
+    HuffmanError::MalformedHeader(format!("plik częstotliwości: {}", message.into()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Result<Vec<u8>> {
+    let digits = text.as_bytes();
+    if !digits.len().is_multiple_of(2) || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err(malformed(format!("\"{}\" nie jest ciągiem bajtów szesnastkowo", text)));
+    }
+    let digit = |d: u8| (d as char).to_digit(16).expect("sprawdzona cyfra") as u8;
+    Ok(digits.chunks(2).map(|pair| digit(pair[0]) << 4 | digit(pair[1])).collect())
+}
+
+// Tyle JSON, ile używa FreqModel::to_json: obiekty, ciągi i liczby całkowite
+enum Json {
+    Object(Vec<(String, Json)>),
+    String(String),
+    Number(u64),
+}
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self) -> Result<Json> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.object(),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("oczekiwano obiektu, ciągu albo liczby")),
+            None => Err(self.error("nieoczekiwany koniec danych")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return Err(self.error("oczekiwano ':'"));
+            }
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Ok(Json::Object(fields));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("oczekiwano ',' albo '}'"));
+            }
+        }
+    }
+
+    // Ciągi w tym formacie to nazwy pól i cyfry szesnastkowe, więc bez sekwencji ucieczki
+    fn string(&mut self) -> Result<String> {
+        if !self.eat(b'"') {
+            return Err(self.error("oczekiwano ciągu"));
+        }
+        let start = self.pos;
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            match byte {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(String::from_utf8_lossy(&self.bytes[start..self.pos - 1]).into_owned());
+                }
+                b'\\' => return Err(self.error("sekwencje ucieczki w ciągach nie są obsługiwane")),
+                _ => self.pos += 1,
+            }
+        }
+        Err(self.error("niezamknięty ciąg"))
+    }
+
+    fn number(&mut self) -> Result<Json> {
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.bytes[start..self.pos]).expect("cyfry ASCII");
+        digits.parse().map(Json::Number).map_err(|_| self.error("liczba poza zakresem u64"))
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.bytes.get(self.pos) == Some(&byte);
+        self.pos += found as usize;
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> HuffmanError {
+        malformed(format!("{} (bajt {})", message, self.pos))
+    }
+}
@@ -0,0 +1,616 @@
+use std::collections::HashSet;
+use std::io::{Cursor, ErrorKind, Read};
+use std::str::FromStr;
+
+use crate::codec::DecodeOptions;
+use crate::error::{HuffmanError, Result};
+use crate::filters::{Filter, MAX_FILTERS};
+use crate::huffman::{CodeLengths, FreqTable, MAX_CODE_LEN_LIMIT, MarkovCodeLengths, MarkovFreqTable, Symbol};
+
+// Czytnik pól nagłówka pamiętający offset - do komunikatów o błędach
+struct FieldReader<'a, R: Read> {
+    inner: &'a mut R,
+    pos: u64,
+}
+
+impl<R: Read> FieldReader<'_, R> {
+    // Urwany nagłówek to błąd formatu, a nie błąd wejścia/wyjścia
+    fn read(&mut self, buf: &mut [u8], what: &str) -> Result<()> {
+        match self.inner.read_exact(buf) {
+            Ok(()) => {
+                self.pos += buf.len() as u64;
+                Ok(())
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(HuffmanError::MalformedHeader(
+                format!("urwany przy polu '{}' (offset {})", what, self.pos),
+            )),
+            Err(e) => Err(HuffmanError::Io(e)),
+        }
+    }
+}
+
+// Łańcuch filtrów z flagą v7 (i w ramkach słownika z filtrami): `u8` liczba filtrów, a dla
+// każdego `u8` identyfikator z rejestru, `u8` parametr i `u64` długość danych po nim
+pub(crate) fn write_filters(filters: &[(Filter, u64)], bytes: &mut Vec<u8>) {
+    bytes.push(filters.len() as u8);
+    for &(filter, len) in filters {
+        bytes.extend_from_slice(&[filter.id(), filter.param()]);
+        bytes.extend_from_slice(&len.to_be_bytes());
+    }
+}
+
+fn read_filters<R: Read>(fields: &mut FieldReader<'_, R>) -> Result<Vec<(Filter, u64)>> {
+    let mut buf1 = [0u8; 1];
+    fields.read(&mut buf1, "liczba filtrów")?;
+    let count = buf1[0] as usize;
+    if count == 0 || count > MAX_FILTERS {
+        return Err(HuffmanError::MalformedHeader(format!("{} filtrów w łańcuchu (od 1 do {})", count, MAX_FILTERS)));
+    }
+    let mut buf2 = [0u8; 2];
+    let mut buf8 = [0u8; 8];
+    let mut filters = Vec::with_capacity(count);
+    for _ in 0..count {
+        fields.read(&mut buf2, "filtr")?;
+        let filter = Filter::from_header(buf2[0], buf2[1])?;
+        fields.read(&mut buf8, "długość po filtrze")?;
+        filters.push((filter, u64::from_be_bytes(buf8)));
+    }
+    Ok(filters)
+}
+
+// Łańcuch z [`write_filters`] na początku `bytes` i jego długość w bajtach
+pub(crate) fn parse_filters(mut bytes: &[u8]) -> Result<(Vec<(Filter, u64)>, usize)> {
+    let mut fields = FieldReader { inner: &mut bytes, pos: 0 };
+    let filters = read_filters(&mut fields)?;
+    Ok((filters, fields.pos as usize))
+}
+
+/// Sygnatura na początku pliku `.huff` (od v4).
+pub const MAGIC: [u8; 4] = *b"HUFF";
+
+/// Najnowsza wersja formatu, w której zapisuje enkoder.
+pub const FORMAT_VERSION: u8 = 7;
+
+// Wersja bez znaków ucieczki; v6 jest zapisywana tylko dla przyciętych modeli
+const PLAIN_VERSION: u8 = 5;
+const ESCAPE_VERSION: u8 = 6;
+
+// Bity bajtu flag w nagłówku v7
+const FLAG_ESCAPE: u8 = 1;
+const FLAG_NIBBLE: u8 = 2;
+const FLAG_FILTER: u8 = 4;
+
+/// Liczba wartości symbolu 4-bitowego ([`Header::nibble`]).
+pub const NIBBLE_SYMBOLS: usize = 16;
+
+// Wersje zapisywane bez sygnatury - rozpoznawane po pierwszym bajcie
+const LAST_BARE_VERSION: u8 = 3;
+
+/// Największa obsługiwana szerokość symbolu w bajtach.
+pub const MAX_UNIT: u8 = 8;
+
+/// Klucz kodu ucieczki w tablicach modelu v6: pusty symbol, który nie koliduje
+/// z żadnym prawdziwym symbolem.
+pub const ESCAPE: &[u8] = &[];
+
+// Liczba symboli kontekstu odczytana z nagłówka nie może przekraczać alfabetu
+fn check_symbol_count(count: usize, max: usize, context: &[u8]) -> Result<()> {
+    if count > max {
+        return Err(HuffmanError::MalformedHeader(format!(
+            "kontekst {:?} ma {} symboli, a alfabet tylko {}",
+            context, count, max
+        )));
+    }
+    Ok(())
+}
+
+// Pierwsze bajty nie pasują ani do sygnatury, ani do starszych układów
+fn not_huff(prefix: &[u8]) -> HuffmanError {
+    let hex: Vec<String> = prefix.iter().map(|b| format!("{:02x}", b)).collect();
+    HuffmanError::MalformedHeader(format!(
+        "brak sygnatury {} (początek pliku: {}) - to nie jest archiwum .huff",
+        String::from_utf8_lossy(&MAGIC),
+        hex.join(" ")
+    ))
+}
+
+/// Rodzaj sumy kontrolnej oryginalnych danych zapisywanej za strumieniem bitów ramki.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChecksumKind {
+    None,
+    /// CRC-32 (IEEE), 4 bajty big-endian.
+    #[default]
+    Crc32,
+}
+
+impl ChecksumKind {
+    /// Długość stopki z sumą kontrolną w bajtach.
+    pub fn footer_len(self) -> usize {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Crc32 => 4,
+        }
+    }
+
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            ChecksumKind::None => 0,
+            ChecksumKind::Crc32 => 1,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(ChecksumKind::None),
+            1 => Ok(ChecksumKind::Crc32),
+            other => Err(HuffmanError::MalformedHeader(format!("nieznany rodzaj sumy kontrolnej {}", other))),
+        }
+    }
+}
+
+impl FromStr for ChecksumKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "none" => Ok(ChecksumKind::None),
+            "crc32" => Ok(ChecksumKind::Crc32),
+            other => Err(format!("nieznana suma kontrolna '{}' (dostępne: crc32, none)", other)),
+        }
+    }
+}
+
+/// Tablice częstotliwości w kolejności z pliku: kontekst i jego pary (symbol, częstotliwość).
+pub type LegacyFrequencies = Vec<(Vec<u8>, Vec<(Symbol, u64)>)>;
+
+/// Model zapisany w nagłówku.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Model {
+    /// Format sprzed v0 (pierwsze programy `encode`/`decode`): częstotliwości bez limitu
+    /// długości kodu; drzewo jest budowane w kolejności par z pliku.
+    Legacy(LegacyFrequencies),
+    /// v0: tablice częstotliwości; dekoder odbudowuje z nich drzewo Huffmana.
+    Frequencies(MarkovFreqTable),
+    /// v1: same długości kodów kanonicznych dla każdego kontekstu.
+    CodeLengths(MarkovCodeLengths),
+}
+
+/// Nagłówek pliku `.huff`: długość oryginału, rząd modelu, limit długości kodu
+/// oraz model (tablice częstotliwości albo długości kodów) dla każdego kontekstu.
+///
+/// Układ v1: bajt wersji, `u64` długość, `u8` rząd, `u8` limit, `u32` liczba
+/// kontekstów, a dla każdego: bajty kontekstu, `u8` liczba symboli - 1
+/// i pary (`u8` symbol, `u8` długość kodu). Układ v0 nie ma bajtu wersji
+/// i zaczyna się od długości; rozpoznajemy go po zerowym pierwszym bajcie.
+///
+/// Format sprzed v0 ([`Model::Legacy`]) to v0 bez bajtu limitu: w miejscu limitu (zawsze
+/// co najmniej 1) stoi najstarszy bajt liczby kontekstów, czyli 0. Pary symboli są tam
+/// zapisane w kolejności, w jakiej enkoder wstawiał je na stertę, i dekoder buduje drzewo
+/// w tej samej kolejności, bo od niej zależy rozstrzyganie remisów.
+///
+/// Od v1 kody wynikają z samych długości (kody kanoniczne), bez częstotliwości i bez
+/// reguł rozstrzygania remisów przy budowie drzewa: w każdym kontekście symbole są
+/// sortowane po (długość, bajty symbolu), pierwszy dostaje kod z samych zer, a każdy
+/// następny kod poprzedniego plus 1, przesunięty w lewo o przyrost długości. Kod ucieczki
+/// (v6) jest pustym symbolem, więc wśród kodów swojej długości jest pierwszy. Tylko v0
+/// odbudowuje drzewo z częstotliwości i zależy od sposobu jego budowy.
+///
+/// Układ v2 (tylko dla symboli wielobajtowych) dodaje po limicie `u8` szerokość
+/// symbolu, a liczbę symboli w kontekście zapisuje jako `u32`; kontekst ma
+/// `rząd * szerokość` bajtów, a symbol `szerokość` bajtów.
+///
+/// Układ v3 to v2 z dokładną liczbą bitów danych (`u64`) zaraz po
+/// długości oryginału; liczba symboli jest zapisywana jak w v1 dla szerokości 1
+/// i jak w v2 dla większych.
+///
+/// Układ v4 poprzedza bajt wersji sygnaturą [`MAGIC`]; dalej jak v3.
+///
+/// Układ v5 dodaje po szerokości symbolu `u8` rodzaj sumy kontrolnej
+/// ([`ChecksumKind`]); sama suma jest zapisywana za strumieniem bitów ramki.
+///
+/// Układ v6 (tylko dla modeli przyciętych przez [`crate::Options::max_symbols`]) to v5,
+/// w którym każdy kontekst ma za parami symboli `u8` długość kodu ucieczki (0 - brak).
+/// Po kodzie ucieczki symbol jest zapisany wprost na `8 * szerokość` bitach; tak samo
+/// zapisywane są wszystkie symbole w kontekstach, których nie ma w nagłówku, więc v6 bez
+/// kontekstów to dane zapisane wprost ([`crate::Options::store_fallback`]).
+///
+/// Układ v7 (dla symboli 4-bitowych, [`crate::Options::nibble`], i filtrów) to v5 z `u8` flagami
+/// za rodzajem sumy kontrolnej: bit 0 - kody ucieczki jak w v6, bit 1 - symbole 4-bitowe.
+/// Symbole i konteksty mają wtedy po bajcie z wartością 0-15, a symbol zapisany wprost 4 bity.
+/// Bit 2 (filtry, [`crate::Options::filters`]) dodaje za flagami `u8` liczbę filtrów, a dla
+/// każdego w kolejności stosowania `u8` identyfikator z rejestru [`crate::Filter`], `u8` parametr
+/// i `u64` długość danych po nim; strumień bitów koduje dane po ostatnim, a suma kontrolna
+/// dotyczy oryginału.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub original_len: u64,
+    pub order: usize,
+    pub max_code_len: u8,
+    /// Szerokość symbolu w bajtach (1 lub 2); rząd liczony jest w symbolach.
+    pub unit: u8,
+    /// Dokładna długość strumienia bitów; bity za nią to dopełnienie ostatniego bajtu.
+    /// Brak w plikach sprzed v3.
+    pub payload_bits: Option<u64>,
+    /// Suma kontrolna w stopce ramki (od v5; wcześniejsze pliki jej nie mają).
+    pub checksum: ChecksumKind,
+    /// Model z kodami ucieczki ([`ESCAPE`]) i bez części kontekstów (v6).
+    pub escape: bool,
+    /// Symbole 4-bitowe (v7): każdy bajt danych to dwa symbole, starsza połowa pierwsza.
+    /// `unit` wynosi wtedy 1, a `original_len` nadal liczy bajty.
+    pub nibble: bool,
+    /// Łańcuch filtrów wstępnych (v7) w kolejności stosowania, każdy z długością danych po nim;
+    /// ostatnia to liczba bajtów zakodowanych w strumieniu, a `original_len` - długość przed filtrami.
+    pub filters: Vec<(Filter, u64)>,
+    pub model: Model,
+}
+
+impl Header {
+    /// Wersja formatu wynikająca z rodzaju modelu.
+    pub fn version(&self) -> u8 {
+        match self.model {
+            Model::Legacy(_) | Model::Frequencies(_) => 0,
+            Model::CodeLengths(_) if self.nibble || !self.filters.is_empty() => FORMAT_VERSION,
+            Model::CodeLengths(_) if self.escape => ESCAPE_VERSION,
+            Model::CodeLengths(_) if self.payload_bits.is_some() => PLAIN_VERSION,
+            Model::CodeLengths(_) if self.unit == 1 => 1,
+            Model::CodeLengths(_) => 2,
+        }
+    }
+
+    /// Wersja zapisana w pliku, odczytana z pierwszych bajtów ramki (v3 i v4
+    /// dają tu swoją wersję, a [`Header::version`] - bieżącą); `None`, gdy to nie ramka `.huff`.
+    pub fn peek_version(prefix: &[u8]) -> Option<u8> {
+        match prefix {
+            [version, ..] if *version <= LAST_BARE_VERSION => Some(*version),
+            [m0, m1, m2, m3, version, ..] if [*m0, *m1, *m2, *m3] == MAGIC => Some(*version),
+            _ => None,
+        }
+    }
+
+    /// Liczba bajtów zakodowanych w strumieniu bitów: długość po ostatnim filtrze albo `original_len`.
+    pub fn coded_len(&self) -> u64 {
+        self.filters.last().map_or(self.original_len, |&(_, len)| len)
+    }
+
+    /// Liczba par (kontekst, symbol) w modelu, razem z kodami ucieczki.
+    pub fn table_entries(&self) -> usize {
+        match &self.model {
+            Model::Legacy(tables) => tables.iter().map(|(_, t)| t.len()).sum(),
+            Model::Frequencies(tables) => tables.values().map(|t| t.len()).sum(),
+            Model::CodeLengths(tables) => tables.values().map(|t| t.len()).sum(),
+        }
+    }
+
+    /// Serializuje nagłówek do bajtów (big-endian).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_into(&mut bytes);
+        bytes
+    }
+
+    /// Dopisuje zserializowany nagłówek na koniec `bytes`.
+    pub fn write_into(&self, bytes: &mut Vec<u8>) {
+        let version = self.version();
+        if version > LAST_BARE_VERSION {
+            bytes.extend_from_slice(&MAGIC);
+        }
+        if version > 0 {
+            bytes.push(version);
+        }
+        bytes.extend_from_slice(&self.original_len.to_be_bytes());
+        if let (3.., Some(bits)) = (version, self.payload_bits) {
+            bytes.extend_from_slice(&bits.to_be_bytes());
+        }
+        bytes.push(self.order as u8);
+        if !matches!(self.model, Model::Legacy(_)) {
+            bytes.push(self.max_code_len);
+        }
+        if version >= 2 {
+            bytes.push(self.unit);
+        }
+        if version >= 5 {
+            bytes.push(self.checksum.to_byte());
+        }
+        if version >= 7 {
+            let escape = if self.escape { FLAG_ESCAPE } else { 0 };
+            let nibble = if self.nibble { FLAG_NIBBLE } else { 0 };
+            let filter = if self.filters.is_empty() { 0 } else { FLAG_FILTER };
+            bytes.push(escape | nibble | filter);
+            if !self.filters.is_empty() {
+                write_filters(&self.filters, bytes);
+            }
+        }
+
+        match &self.model {
+            Model::Legacy(frequencies) => {
+                bytes.extend_from_slice(&(frequencies.len() as u32).to_be_bytes());
+                for (context, pairs) in frequencies {
+                    bytes.extend_from_slice(context);
+                    bytes.extend_from_slice(&(pairs.len() as u32).to_be_bytes());
+                    for (symbol, freq) in pairs {
+                        bytes.push(symbol[0]);
+                        bytes.extend_from_slice(&freq.to_be_bytes());
+                    }
+                }
+            }
+            Model::Frequencies(frequencies) => {
+                bytes.extend_from_slice(&(frequencies.len() as u32).to_be_bytes());
+                for (context, f_table) in frequencies {
+                    bytes.extend_from_slice(context);
+                    bytes.extend_from_slice(&(f_table.len() as u32).to_be_bytes());
+
+                    for (symbol, freq) in f_table {
+                        bytes.push(symbol[0]);
+                        bytes.extend_from_slice(&freq.to_be_bytes());
+                    }
+                }
+            }
+            Model::CodeLengths(lengths) => {
+                bytes.extend_from_slice(&(lengths.len() as u32).to_be_bytes());
+
+                // Stała kolejność, żeby ten sam model dawał zawsze te same bajty
+                let mut contexts: Vec<_> = lengths.iter().collect();
+                contexts.sort_by_key(|&(context, _)| context);
+                for (context, l_table) in contexts {
+                    bytes.extend_from_slice(context);
+                    // Kod ucieczki nie jest zwykłym symbolem - ma własne pole za parami
+                    let mut symbols: Vec<_> = l_table.iter().filter(|(symbol, _)| !symbol.is_empty()).collect();
+                    if self.unit == 1 {
+                        bytes.push((symbols.len() - 1) as u8);
+                    } else {
+                        bytes.extend_from_slice(&(symbols.len() as u32).to_be_bytes());
+                    }
+
+                    symbols.sort();
+                    for (symbol, &len) in symbols {
+                        bytes.extend_from_slice(symbol);
+                        bytes.push(len);
+                    }
+                    if self.escape {
+                        bytes.push(l_table.get(ESCAPE).copied().unwrap_or(0));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parsuje nagłówek z początku `content`; zwraca go razem z offsetem początku danych.
+    pub fn parse(content: &[u8]) -> Result<(Header, usize)> {
+        Header::parse_bounded(content, &DecodeOptions::default())
+    }
+
+    // Jak parse, ale z limitami tablicy kodów z `limits`
+    pub(crate) fn parse_bounded(content: &[u8], limits: &DecodeOptions) -> Result<(Header, usize)> {
+        let mut cursor = Cursor::new(content);
+        let header = Header::read_bounded(&mut cursor, limits)?;
+        Ok((header, cursor.position() as usize))
+    }
+
+    /// Czyta nagłówek z dowolnego źródła, zostawiając je ustawione na początku danych.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Header> {
+        Header::read_bounded(reader, &DecodeOptions::default())
+    }
+
+    // Jak read_from; liczba wpisów tablicy jest sprawdzana z limitami przed wczytaniem
+    // każdego kontekstu, więc zmyślone liczności nie zdążą zająć pamięci
+    pub(crate) fn read_bounded<R: Read>(reader: &mut R, limits: &DecodeOptions) -> Result<Header> {
+        let mut fields = FieldReader { inner: reader, pos: 0 };
+
+        let mut buf1 = [0u8; 1];
+        let mut buf4 = [0u8; 4];
+        let mut buf8 = [0u8; 8];
+
+        // Pliki sprzed v4 nie mają sygnatury: v0 zaczyna się od długości w big-endian
+        // (pierwszy bajt 0), v1-v3 od bajtu wersji
+        fields.read(&mut buf1, "sygnatura")?;
+        let version = if buf1[0] == MAGIC[0] {
+            let mut rest = [0u8; 3];
+            fields.read(&mut rest, "sygnatura")?;
+            if rest != MAGIC[1..] {
+                return Err(not_huff([buf1[0], rest[0], rest[1], rest[2]].as_slice()));
+            }
+            fields.read(&mut buf1, "wersja")?;
+            buf1[0]
+        } else if buf1[0] <= LAST_BARE_VERSION {
+            buf1[0]
+        } else {
+            return Err(not_huff(&buf1));
+        };
+        if version == 0 {
+            buf8[0] = 0;
+            fields.read(&mut buf8[1..], "długość oryginału")?;
+        } else if version > FORMAT_VERSION {
+            return Err(HuffmanError::MalformedHeader(format!(
+                "nieobsługiwana wersja formatu {} (najnowsza znana: {})",
+                version, FORMAT_VERSION
+            )));
+        } else {
+            fields.read(&mut buf8, "długość oryginału")?;
+        }
+        let original_len = u64::from_be_bytes(buf8);
+
+        let payload_bits = if version >= 3 {
+            fields.read(&mut buf8, "liczba bitów danych")?;
+            Some(u64::from_be_bytes(buf8))
+        } else {
+            None
+        };
+
+        fields.read(&mut buf1, "rząd")?;
+        let order = buf1[0] as usize;
+        fields.read(&mut buf1, "limit długości kodu")?;
+        // Format sprzed v0 ma tu już najstarszy bajt liczby kontekstów
+        let legacy = version == 0 && buf1[0] == 0;
+        let max_code_len = if legacy { MAX_CODE_LEN_LIMIT } else { buf1[0] };
+
+        let unit = if version >= 2 {
+            fields.read(&mut buf1, "szerokość symbolu")?;
+            buf1[0]
+        } else {
+            1
+        };
+        if unit == 0 || unit > MAX_UNIT {
+            return Err(HuffmanError::MalformedHeader(format!("nieobsługiwana szerokość symbolu {}", unit)));
+        }
+        let unit_len = unit as usize;
+
+        let checksum = if version >= 5 {
+            fields.read(&mut buf1, "suma kontrolna")?;
+            ChecksumKind::from_byte(buf1[0])?
+        } else {
+            ChecksumKind::None
+        };
+
+        let (escape, nibble, filtered) = if version >= 7 {
+            fields.read(&mut buf1, "flagi")?;
+            if buf1[0] & !(FLAG_ESCAPE | FLAG_NIBBLE | FLAG_FILTER) != 0 {
+                return Err(HuffmanError::MalformedHeader(format!("nieznane flagi nagłówka {:#04x}", buf1[0])));
+            }
+            (buf1[0] & FLAG_ESCAPE != 0, buf1[0] & FLAG_NIBBLE != 0, buf1[0] & FLAG_FILTER != 0)
+        } else {
+            (version == ESCAPE_VERSION, false, false)
+        };
+        let filters = if filtered { read_filters(&mut fields)? } else { Vec::new() };
+        if nibble && unit != 1 {
+            return Err(HuffmanError::MalformedHeader(format!(
+                "symbole 4-bitowe przy szerokości symbolu {}",
+                unit
+            )));
+        }
+
+        if legacy {
+            buf4[0] = 0;
+            fields.read(&mut buf4[1..], "liczba kontekstów")?;
+        } else {
+            fields.read(&mut buf4, "liczba kontekstów")?;
+        }
+        let num_contexts = u32::from_be_bytes(buf4) as usize;
+        // Liczności z nagłówka ograniczają tylko pętle, ale zmyślone dają bezsensowne modele,
+        // więc odrzucamy je od razu: kontekstów i symboli nie może być więcej niż różnych wartości
+        let context_len = if version == 0 { order } else { order * unit_len };
+        let values = if nibble { NIBBLE_SYMBOLS } else { 256 };
+        let max_contexts = values.checked_pow(context_len as u32).unwrap_or(usize::MAX);
+        let max_symbols =
+            if nibble { NIBBLE_SYMBOLS } else { 1usize.checked_shl(8 * unit_len as u32).unwrap_or(usize::MAX) };
+        if num_contexts > max_contexts {
+            return Err(HuffmanError::MalformedHeader(format!(
+                "{} kontekstów, a rząd {} pozwala najwyżej na {}",
+                num_contexts, order, max_contexts
+            )));
+        }
+
+        let mut entries = 0usize;
+        let model = if version == 0 {
+            let mut frequencies = LegacyFrequencies::new();
+            let mut seen = HashSet::new();
+            for _ in 0..num_contexts {
+                let mut context_key = vec![0u8; order];
+                if order > 0 {
+                    fields.read(&mut context_key, "kontekst")?;
+                }
+
+                fields.read(&mut buf4, "liczba symboli")?;
+                let num_symbols = u32::from_be_bytes(buf4) as usize;
+                check_symbol_count(num_symbols, max_symbols, &context_key)?;
+                entries = entries.saturating_add(num_symbols);
+                limits.check_table(entries)?;
+
+                let mut pairs = Vec::with_capacity(num_symbols);
+                let mut symbols = [false; 256];
+                for _ in 0..num_symbols {
+                    fields.read(&mut buf1, "symbol")?;
+                    fields.read(&mut buf8, "częstotliwość")?;
+                    if std::mem::replace(&mut symbols[buf1[0] as usize], true) {
+                        return Err(HuffmanError::MalformedHeader(format!(
+                            "symbol {:?} powtórzony w kontekście {:?}",
+                            [buf1[0]],
+                            context_key
+                        )));
+                    }
+                    pairs.push((vec![buf1[0]], u64::from_be_bytes(buf8)));
+                }
+                if !seen.insert(context_key.clone()) {
+                    return Err(HuffmanError::MalformedHeader(format!("kontekst {:?} powtórzony", context_key)));
+                }
+                frequencies.push((context_key, pairs));
+            }
+            if legacy {
+                Model::Legacy(frequencies)
+            } else {
+                let tables = frequencies.into_iter().map(|(context, pairs)| (context, FreqTable::from_iter(pairs)));
+                Model::Frequencies(tables.collect())
+            }
+        } else {
+            let mut lengths = MarkovCodeLengths::new();
+            for _ in 0..num_contexts {
+                let mut context_key = vec![0u8; order * unit_len];
+                if order > 0 {
+                    fields.read(&mut context_key, "kontekst")?;
+                }
+                if nibble && context_key.iter().any(|&value| value as usize >= NIBBLE_SYMBOLS) {
+                    return Err(HuffmanError::MalformedHeader(format!(
+                        "kontekst {:?} nie składa się z symboli 4-bitowych",
+                        context_key
+                    )));
+                }
+
+                let num_symbols = if unit == 1 {
+                    fields.read(&mut buf1, "liczba symboli")?;
+                    buf1[0] as usize + 1
+                } else {
+                    fields.read(&mut buf4, "liczba symboli")?;
+                    u32::from_be_bytes(buf4) as usize
+                };
+                check_symbol_count(num_symbols, max_symbols, &context_key)?;
+                entries = entries.saturating_add(num_symbols);
+                limits.check_table(entries)?;
+
+                let mut l_table = CodeLengths::new();
+                for _ in 0..num_symbols {
+                    let mut symbol = vec![0u8; unit_len];
+                    fields.read(&mut symbol, "symbol")?;
+                    if nibble && symbol[0] as usize >= NIBBLE_SYMBOLS {
+                        return Err(HuffmanError::MalformedHeader(format!(
+                            "symbol {} w kontekście {:?} nie mieści się w 4 bitach",
+                            symbol[0], context_key
+                        )));
+                    }
+                    fields.read(&mut buf1, "długość kodu")?;
+                    if l_table.insert(symbol.clone(), buf1[0]).is_some() {
+                        return Err(HuffmanError::MalformedHeader(format!(
+                            "symbol {:?} powtórzony w kontekście {:?}",
+                            symbol, context_key
+                        )));
+                    }
+                }
+                if escape {
+                    fields.read(&mut buf1, "długość kodu ucieczki")?;
+                    if buf1[0] > 0 {
+                        l_table.insert(ESCAPE.to_vec(), buf1[0]);
+                    }
+                }
+                if lengths.insert(context_key.clone(), l_table).is_some() {
+                    return Err(HuffmanError::MalformedHeader(format!(
+                        "kontekst {:?} powtórzony",
+                        context_key
+                    )));
+                }
+            }
+            Model::CodeLengths(lengths)
+        };
+
+        Ok(Header {
+            original_len,
+            order,
+            max_code_len,
+            unit,
+            payload_bits,
+            checksum,
+            escape,
+            nibble,
+            filters,
+            model,
+        })
+    }
+}
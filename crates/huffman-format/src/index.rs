@@ -0,0 +1,290 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::bitio::BitReader;
+use crate::codec::SymbolDecoder;
+use crate::error::{HuffmanError, Position, Result};
+use crate::header::Header;
+use crate::meta::{META_MAGIC, Metadata};
+use crate::reader::HuffmanReader;
+
+/// Położenie jednej ramki w pliku `.huff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Offset pierwszego bajtu nagłówka ramki.
+    pub offset: u64,
+    /// Długość ramki w bajtach: nagłówek, strumień bitów i stopka.
+    pub len: u64,
+    /// Liczba bajtów po dekompresji.
+    pub original_len: u64,
+}
+
+/// Wyznacza granice wszystkich ramek bez dekompresji danych.
+///
+/// Ramki z nagłówkiem v3+ są przeskakiwane na podstawie `payload_bits`; starsze
+/// nie zapisują długości strumienia, więc ich bity trzeba zdekodować.
+/// Tablica skoków na końcu pliku i blok metadanych ([`crate::Metadata`]) nie są traktowane jako ramki.
+pub fn frame_index<R: Read + Seek>(reader: &mut R) -> Result<Vec<FrameInfo>> {
+    let end = match SeekTable::read_from_end(reader)? {
+        Some((_, table_offset)) => table_offset,
+        None => reader.seek(SeekFrom::End(0))?,
+    };
+    let mut offset = reader.seek(SeekFrom::Start(0))?;
+    let mut frames = Vec::new();
+
+    while offset < end {
+        let mut signature = Vec::with_capacity(META_MAGIC.len());
+        reader.by_ref().take(META_MAGIC.len() as u64).read_to_end(&mut signature)?;
+        reader.seek(SeekFrom::Start(offset))?;
+        if signature == META_MAGIC {
+            // Za metadanymi jest już tylko tablica skoków albo koniec pliku
+            Metadata::read_from(&mut *reader).map_err(|e| e.at(Position::default()))?;
+            if reader.stream_position()? != end {
+                return Err(HuffmanError::MalformedHeader("dane za blokiem metadanych".to_string()));
+            }
+            break;
+        }
+        let header = Header::read_from(reader).map_err(|e| e.at(Position::default()))?;
+        let payload_offset = reader.stream_position()?;
+        let payload_len = match header.payload_bits {
+            Some(bits) => bits.div_ceil(8),
+            None => skip_payload(reader, &header)?,
+        };
+        let next = payload_offset + payload_len + header.checksum.footer_len() as u64;
+        if next > end {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "ramka urwana przed końcem strumienia bitów").into());
+        }
+
+        frames.push(FrameInfo {
+            offset,
+            len: next - offset,
+            original_len: header.original_len,
+        });
+        offset = reader.seek(SeekFrom::Start(next))?;
+    }
+    Ok(frames)
+}
+
+/// Sygnatura tablicy skoków - na jej początku i na samym końcu pliku.
+pub const SEEK_MAGIC: [u8; 4] = *b"HSEK";
+
+// Sygnatura, liczba wpisów, długość danych, długość tablicy i końcowa sygnatura
+const SEEK_FIXED_LEN: usize = 4 + 4 + 8 + 4 + 4;
+
+/// Początek ramki w archiwum i w danych po dekompresji.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekEntry {
+    pub frame_offset: u64,
+    pub data_offset: u64,
+}
+
+/// Opcjonalna tablica skoków dopisywana za ostatnią ramką przez
+/// [`crate::HuffmanWriter::with_seek_table`].
+///
+/// Układ: `HSEK`, u32 liczba wpisów, wpisy (u64 offset ramki, u64 offset danych),
+/// u64 długość danych, u32 długość całej tablicy, `HSEK`. Końcowe pola pozwalają
+/// znaleźć tablicę od końca pliku bez czytania ramek.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeekTable {
+    pub entries: Vec<SeekEntry>,
+    /// Łączna długość danych po dekompresji.
+    pub total_len: u64,
+}
+
+impl SeekTable {
+    pub fn byte_len(&self) -> usize {
+        SEEK_FIXED_LEN + self.entries.len() * 16
+    }
+
+    pub fn write_into(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&SEEK_MAGIC);
+        out.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            out.extend_from_slice(&entry.frame_offset.to_be_bytes());
+            out.extend_from_slice(&entry.data_offset.to_be_bytes());
+        }
+        out.extend_from_slice(&self.total_len.to_be_bytes());
+        out.extend_from_slice(&(self.byte_len() as u32).to_be_bytes());
+        out.extend_from_slice(&SEEK_MAGIC);
+    }
+
+    /// Parsuje tablicę zajmującą dokładnie `bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<SeekTable> {
+        let malformed = |what: &str| HuffmanError::MalformedHeader(format!("tablica skoków: {}", what));
+        if bytes.len() < SEEK_FIXED_LEN || bytes[..4] != SEEK_MAGIC || bytes[bytes.len() - 4..] != SEEK_MAGIC {
+            return Err(malformed("brak sygnatury HSEK"));
+        }
+        let u32_at = |i: usize| u32::from_be_bytes(bytes[i..i + 4].try_into().expect("4 bajty"));
+        let u64_at = |i: usize| u64::from_be_bytes(bytes[i..i + 8].try_into().expect("8 bajtów"));
+
+        let count = u32_at(4) as usize;
+        if SEEK_FIXED_LEN + count.saturating_mul(16) != bytes.len() || u32_at(bytes.len() - 8) as usize != bytes.len() {
+            return Err(malformed("długość nie zgadza się z liczbą wpisów"));
+        }
+        let entries: Vec<SeekEntry> = (0..count)
+            .map(|i| SeekEntry { frame_offset: u64_at(8 + i * 16), data_offset: u64_at(16 + i * 16) })
+            .collect();
+        let total_len = u64_at(8 + count * 16);
+        let ordered = entries.windows(2).all(|w| w[0].frame_offset < w[1].frame_offset && w[0].data_offset <= w[1].data_offset);
+        if !ordered || entries.last().is_some_and(|e| e.data_offset > total_len) {
+            return Err(malformed("wpisy nie są uporządkowane"));
+        }
+        if entries.first().map_or(total_len > 0, |e| e.data_offset != 0) {
+            return Err(malformed("pierwsza ramka nie zaczyna się od początku danych"));
+        }
+        Ok(SeekTable { entries, total_len })
+    }
+
+    /// Szuka tablicy na końcu pliku; zwraca ją razem z offsetem jej początku.
+    pub fn read_from_end<R: Read + Seek>(reader: &mut R) -> Result<Option<(SeekTable, u64)>> {
+        let end = reader.seek(SeekFrom::End(0))?;
+        if end < SEEK_FIXED_LEN as u64 {
+            return Ok(None);
+        }
+        let mut tail = [0u8; 8];
+        reader.seek(SeekFrom::End(-8))?;
+        reader.read_exact(&mut tail)?;
+        let len = u32::from_be_bytes(tail[..4].try_into().expect("4 bajty")) as u64;
+        if tail[4..] != SEEK_MAGIC || len < SEEK_FIXED_LEN as u64 || len > end {
+            return Ok(None);
+        }
+
+        let start = end - len;
+        let mut bytes = vec![0u8; len as usize];
+        reader.seek(SeekFrom::Start(start))?;
+        reader.read_exact(&mut bytes)?;
+        if bytes[..4] != SEEK_MAGIC {
+            return Ok(None);
+        }
+        let table = SeekTable::parse(&bytes)?;
+        if table.entries.last().is_some_and(|e| e.frame_offset >= start) {
+            return Err(HuffmanError::MalformedHeader("tablica skoków wskazuje poza ramki".to_string()));
+        }
+        Ok(Some((table, start)))
+    }
+}
+
+/// Dekompresuje tylko `len` bajtów danych od offsetu `start`.
+///
+/// Z tablicą skoków dekodowane są wyłącznie ramki pokrywające zakres; bez niej
+/// granice ramek wyznacza [`frame_index`] (czytając same nagłówki). Zakres
+/// wychodzący poza koniec danych jest przycinany.
+pub fn decompress_range<R: Read + Seek>(reader: &mut R, start: u64, len: u64) -> Result<Vec<u8>> {
+    let (entries, total_len, frames_end) = match SeekTable::read_from_end(reader)? {
+        Some((table, table_offset)) => (table.entries, table.total_len, table_offset),
+        None => {
+            let frames = frame_index(reader)?;
+            let mut data_offset = 0u64;
+            let mut entries = Vec::with_capacity(frames.len());
+            for frame in &frames {
+                entries.push(SeekEntry { frame_offset: frame.offset, data_offset });
+                data_offset = data_offset.checked_add(frame.original_len).ok_or_else(|| {
+                    HuffmanError::MalformedHeader("suma długości ramek przekracza u64".to_string())
+                })?;
+            }
+            let frames_end = frames.last().map_or(0, |f| f.offset + f.len);
+            (entries, data_offset, frames_end)
+        }
+    };
+
+    let stop = start.saturating_add(len).min(total_len);
+    if start >= stop {
+        return Ok(Vec::new());
+    }
+    let first = entries.partition_point(|e| e.data_offset <= start) - 1;
+    let after = entries.partition_point(|e| e.data_offset < stop);
+    let from = entries[first].frame_offset;
+    let to = entries.get(after).map_or(frames_end, |e| e.frame_offset);
+
+    reader.seek(SeekFrom::Start(from))?;
+    let mut frames = HuffmanReader::new(reader.take(to - from))?;
+    io::copy(&mut (&mut frames).take(start - entries[first].data_offset), &mut io::sink())?;
+    // Długości pochodzą z pliku, więc bufor rośnie dopiero z odtwarzanymi danymi
+    let mut out = Vec::new();
+    frames.take(stop - start).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+// Dekoduje strumień bitów tylko po to, żeby poznać jego długość w bajtach
+fn skip_payload<R: Read>(reader: &mut R, header: &Header) -> Result<u64> {
+    let mut decoder = SymbolDecoder::new(header)?;
+    // Bez payload_bits czytnik bitów pobiera bajty pojedynczo, więc ich liczba to długość strumienia
+    let mut bits = BitReader::new(reader);
+    decoder.decode_from(&mut bits, &mut Vec::new())?;
+    Ok(bits.bits_read().div_ceil(8))
+}
+
+/// Sygnatura pliku z indeksem linii.
+pub const LINE_INDEX_MAGIC: [u8; 4] = *b"HIDX";
+
+const LINE_INDEX_VERSION: u8 = 1;
+
+/// Punkt kontrolny indeksu linii: ramka zaczynająca się od linii `line`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCheckpoint {
+    /// Numer pierwszej linii ramki, liczony od 0.
+    pub line: u64,
+    /// Offset ramki w archiwum.
+    pub frame_offset: u64,
+    /// Offset pierwszego bajtu ramki w danych po dekompresji.
+    pub data_offset: u64,
+}
+
+/// Indeks linii zapisywany obok archiwum (`plik.huff.idx`): co `interval` linii
+/// zaczyna się nowa ramka, więc zakres linii można odczytać od najbliższej
+/// ramki zamiast od początku pliku. Liczby zapisywane są w big-endian.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineIndex {
+    pub interval: u64,
+    pub checkpoints: Vec<LineCheckpoint>,
+}
+
+impl LineIndex {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(17 + self.checkpoints.len() * 24);
+        out.extend_from_slice(&LINE_INDEX_MAGIC);
+        out.push(LINE_INDEX_VERSION);
+        out.extend_from_slice(&self.interval.to_be_bytes());
+        out.extend_from_slice(&(self.checkpoints.len() as u32).to_be_bytes());
+        for point in &self.checkpoints {
+            out.extend_from_slice(&point.line.to_be_bytes());
+            out.extend_from_slice(&point.frame_offset.to_be_bytes());
+            out.extend_from_slice(&point.data_offset.to_be_bytes());
+        }
+        out
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<LineIndex> {
+        let mut magic = [0u8; 5];
+        reader.read_exact(&mut magic)?;
+        if magic[..4] != LINE_INDEX_MAGIC || magic[4] != LINE_INDEX_VERSION {
+            return Err(HuffmanError::MalformedHeader("to nie jest indeks linii .huff.idx".to_string()));
+        }
+        let mut buf8 = [0u8; 8];
+        let mut buf4 = [0u8; 4];
+        reader.read_exact(&mut buf8)?;
+        let interval = u64::from_be_bytes(buf8);
+        reader.read_exact(&mut buf4)?;
+        let count = u32::from_be_bytes(buf4);
+
+        let mut checkpoints = Vec::new();
+        for _ in 0..count {
+            let mut fields = [0u64; 3];
+            for field in &mut fields {
+                reader.read_exact(&mut buf8)?;
+                *field = u64::from_be_bytes(buf8);
+            }
+            checkpoints.push(LineCheckpoint {
+                line: fields[0],
+                frame_offset: fields[1],
+                data_offset: fields[2],
+            });
+        }
+        Ok(LineIndex { interval, checkpoints })
+    }
+
+    /// Ostatni punkt kontrolny nie późniejszy niż linia `line` (liczona od 0).
+    pub fn checkpoint_for(&self, line: u64) -> Option<&LineCheckpoint> {
+        let after = self.checkpoints.partition_point(|point| point.line <= line);
+        after.checked_sub(1).map(|i| &self.checkpoints[i])
+    }
+}
@@ -0,0 +1,81 @@
+//! Format kontenera `.huff`: nagłówek z modelem kontekstowym rzędu N
+//! i strumień bitów, razem z API kompresji i dekompresji.
+//!
+//! Najprostsze użycie to para [`compress`] / [`decompress`]:
+//!
+//! ```
+//! use huffman_format::{Options, compress, decompress};
+//!
+//! let data = b"abracadabra";
+//! let packed = compress(data, Options { order: 1, ..Options::default() })?;
+//! assert_eq!(decompress(&packed)?, data);
+//! # Ok::<(), huffman_format::HuffmanError>(())
+//! ```
+//!
+//! Wszystkie operacje, które mogą się nie powieść, zwracają [`Result`] z [`HuffmanError`].
+//! Aplikacje obsługujące kilka formatów mogą używać tego kodeka przez [`Codec`].
+//!
+//! Algorytmy (budowa drzewa, tabele kodów, pakowanie bitów) pochodzą z `huffman-core`
+//! i są tu ponownie eksportowane jako [`huffman`], [`freq`], [`bitio`] i [`checksum`];
+//! [`testdata`] generuje powtarzalne korpusy do testów i pomiarów.
+//!
+//! Stabilną, wspieraną częścią API jest [`prelude`]; wyliczenia błędów i parametrów
+//! są `#[non_exhaustive]`, więc nowe warianty nie psują kodu, który je dopasowuje.
+//! Moduły implementacji są prywatne: wszystko poza nimi jest eksportowane tutaj, w korzeniu.
+
+mod adaptive;
+mod archive;
+mod automaton;
+mod block;
+mod bwt;
+mod codec;
+mod compressor;
+mod dict;
+mod events;
+mod filters;
+mod freqfile;
+mod header;
+mod index;
+mod lz77;
+mod meta;
+mod message;
+mod parallel;
+pub mod prelude;
+mod reader;
+mod split;
+mod stream;
+mod table;
+mod timestamps;
+mod writer;
+
+pub use huffman_core::{alphabet, bitio, checksum, error, freq, huffman, testdata};
+
+pub use adaptive::{ADAPTIVE_MAGIC, AdaptivePreset, AdaptiveReader, AdaptiveWriter};
+pub use archive::{
+    ARCHIVE_MAGIC, Archive, ArchiveWriter, Dedup, Member, MemberFrames, MemberInfo, MemberReader, SolidArchiveWriter,
+};
+pub use automaton::{DecodeAutomaton, Transition};
+pub use block::{BlockCodec, HuffmanBlockCodec};
+pub use codec::{
+    DEFAULT_LOOKUP_BITS, DecodeOptions, EncoderScratch, MAX_LOOKUP_BITS, MemoryEstimate, Options, Stats,
+    build_code_lengths, collect_frequencies, compress, compress_into, compress_with_stats, conditional_entropy,
+    decompress, decompress_with, decompress_with_frequencies, detect_unit, estimate, estimate_decode_memory,
+    payload_bits, prune_symbols,
+};
+pub use compressor::{AdaptiveCodec, Codec, HuffmanCodec, HuffmanEncoder, Lz77Codec};
+pub use dict::{DICT_FRAME_MAGIC, DICT_MAGIC, Dictionary};
+pub use events::{Event, EventSink, NoEvents, Phase};
+pub use filters::{Filter, FilterChain, MAX_FILTERS};
+pub use freqfile::{FREQ_MAGIC, FreqModel};
+pub use header::{ChecksumKind, ESCAPE, FORMAT_VERSION, Header, MAGIC, MAX_UNIT, Model};
+pub use index::{FrameInfo, LineCheckpoint, LineIndex, SeekEntry, SeekTable, decompress_range, frame_index};
+pub use lz77::{LZ77_MAGIC, Lz77Options, compress_lz77, compress_lz77_with_stats, decompress_lz77};
+pub use message::{MessageModel, MessageModelSet};
+pub use meta::{META_MAGIC, Metadata, read_metadata, set_metadata};
+pub use parallel::{compress_parallel, decompress_parallel, default_threads};
+pub use huffman_core::{Alphabet, HuffmanError, Position, Resource, Result};
+pub use reader::HuffmanReader;
+pub use split::{DEFAULT_GRANULE, compress_optimal_split, optimal_split};
+pub use stream::{INCOMPRESSIBLE_ENTROPY, compress_stream, compress_stream_with_events, sample_entropy, store_stream};
+pub use table::{Coverage, FixedTable};
+pub use writer::{DEFAULT_BLOCK_SIZE, HuffmanWriter};
@@ -0,0 +1,439 @@
+//! Kodek LZ77 + Huffman, jak deflate: powtórzenia w oknie przesuwnym zastępują pary
+//! (długość, odległość), a literały, długości i odległości kodują tabele Huffmana bloku.
+//!
+//! Model rzędu 0 widzi tylko częstości bajtów, więc w tekście i kodzie źródłowym zostawia
+//! całe powtarzające się słowa i linie; LZ77 koduje je kilkunastoma bitami.
+//!
+//! ```
+//! use huffman_format::{Lz77Options, Options, compress, compress_lz77, decompress};
+//!
+//! let text = b"fn main() { println!(\"abrakadabra\"); }\n".repeat(50);
+//! let packed = compress_lz77(&text, &Lz77Options::default())?;
+//! assert!(packed.len() * 5 < compress(&text, Options::default())?.len());
+//! assert_eq!(decompress(&packed)?, text);
+//! # Ok::<(), huffman_format::HuffmanError>(())
+//! ```
+
+use std::io::{self, Write};
+
+use crate::bitio::{BitReader, BitWriter};
+use crate::checksum::{Crc32, crc32};
+use crate::codec::{DecodeOptions, Stats, write_footer};
+use crate::error::{HuffmanError, Position, Result};
+use crate::header::ChecksumKind;
+use crate::huffman::{
+    Code, CodeLengths, DEFAULT_MAX_CODE_LEN, FlatTree, FreqTable, Walk, canonical_code_table, check_code_lengths,
+    entropy_from_freq, package_merge_lengths,
+};
+
+/// Sygnatura ramki LZ77 ([`compress_lz77`]).
+pub const LZ77_MAGIC: [u8; 4] = *b"HLZ7";
+
+/// Okno przesuwne: najdalsze odwołanie wstecz w bajtach, jak w deflate.
+pub const LZ77_WINDOW: usize = 32 * 1024;
+
+const LZ77_VERSION: u8 = 1;
+// Sygnatura, wersja, `u8` rodzaj sumy kontrolnej i `u64` długość oryginału
+const PREFIX_LEN: usize = LZ77_MAGIC.len() + 1 + 1 + 8;
+// Każdy blok tylu bajtów wejścia ma własne tabele kodów
+const BLOCK_LEN: usize = 256 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+
+// Alfabet literałów i długości: bajty 0-255, koniec bloku 256, kody długości od 257
+const END_OF_BLOCK: usize = 256;
+const FIRST_LENGTH: usize = 257;
+const LITLEN_SYMBOLS: usize = 286;
+const DIST_SYMBOLS: usize = 30;
+// Długość kodu w tabeli bloku zajmuje 4 bity (0 - symbolu nie ma w bloku), a za zerem
+// 5 bitów mówi, ile kolejnych symboli też nie ma
+const LENGTH_BITS: u8 = 4;
+const ZERO_RUN_BITS: u8 = 5;
+const MAX_ZERO_RUN: usize = (1 << ZERO_RUN_BITS) - 1;
+
+// Kody długości i odległości z deflate (RFC 1951): początek przedziału i liczba bitów dodatkowych
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] =
+    [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Parametry [`compress_lz77`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lz77Options {
+    pub checksum: ChecksumKind,
+    /// Ile wcześniejszych wystąpień tego samego początku sprawdza szukanie dopasowania;
+    /// więcej to dłuższe dopasowania i wolniejsze kodowanie (`gzip -9` sprawdza 4096).
+    pub max_chain: usize,
+    /// Leniwe dopasowanie jak w gzip: bajt idzie jako literał, gdy od następnego
+    /// zaczyna się dłuższe dopasowanie.
+    pub lazy: bool,
+}
+
+impl Default for Lz77Options {
+    fn default() -> Self {
+        Lz77Options { checksum: ChecksumKind::default(), max_chain: 128, lazy: true }
+    }
+}
+
+/// Koduje `data` do ramki LZ77.
+///
+/// Układ ramki: `HLZ7`, `u8` wersja, `u8` rodzaj sumy kontrolnej, `u64` długość oryginału,
+/// strumień bitów i stopka jak w ramce `.huff`. Strumień to bloki po 256 KiB wejścia: każdy
+/// zaczyna się 4-bitowymi długościami kodów 286 symboli literałów i długości oraz 30 symboli
+/// odległości (kody kanoniczne do 15 bitów; za zerem 5 bitów z liczbą dalszych zer),
+/// a kończy symbolem końca bloku.
+pub fn compress_lz77(data: &[u8], options: &Lz77Options) -> Result<Vec<u8>> {
+    Ok(compress_lz77_with_stats(data, options)?.0)
+}
+
+/// Jak [`compress_lz77`], ale zwraca też statystyki; entropia jest tu rzędu 0, więc LZ77
+/// schodzi zwykle poniżej jej minimum.
+pub fn compress_lz77_with_stats(data: &[u8], options: &Lz77Options) -> Result<(Vec<u8>, Stats)> {
+    let mut out = Vec::with_capacity(PREFIX_LEN + data.len() / 2);
+    out.extend_from_slice(&LZ77_MAGIC);
+    out.push(LZ77_VERSION);
+    out.push(options.checksum.to_byte());
+    out.extend_from_slice(&(data.len() as u64).to_be_bytes());
+
+    // Suma kontrolna i liczności dla entropii powstają blok po bloku, razem z szukaniem dopasowań
+    let mut finder = MatchFinder::new(options);
+    let mut tokens = Vec::new();
+    let mut crc = Crc32::new();
+    let mut counts = [0u64; 256];
+    let mut writer = BitWriter::new(&mut out);
+    for start in (0..data.len()).step_by(BLOCK_LEN) {
+        let end = (start + BLOCK_LEN).min(data.len());
+        tokens.clear();
+        finder.tokenize(data, start, end, &mut tokens);
+        write_block(&tokens, &mut writer)?;
+        crc.update(&data[start..end]);
+        for &byte in &data[start..end] {
+            counts[byte as usize] += 1;
+        }
+    }
+    writer.finish()?;
+    let payload_len = out.len() - PREFIX_LEN;
+    write_footer(options.checksum, crc.finish(), &mut out);
+
+    let frequencies: FreqTable =
+        (0..=255u8).filter(|&byte| counts[byte as usize] > 0).map(|byte| (vec![byte], counts[byte as usize])).collect();
+    let stats = Stats {
+        original_len: data.len() as u64,
+        header_len: PREFIX_LEN,
+        payload_len,
+        footer_len: options.checksum.footer_len(),
+        entropy: if data.is_empty() { 0.0 } else { entropy_from_freq(&frequencies) },
+    };
+    Ok((out, stats))
+}
+
+/// Dekoduje ramkę z [`compress_lz77`] z limitami z `options`; [`crate::decompress`] rozpoznaje
+/// ją po sygnaturze sam.
+pub fn decompress_lz77(frame: &[u8], options: &DecodeOptions) -> Result<Vec<u8>> {
+    let prefix = frame
+        .get(..PREFIX_LEN)
+        .ok_or_else(|| HuffmanError::MalformedHeader(format!("ramka LZ77 krótsza niż {} bajtów", PREFIX_LEN)))?;
+    if !prefix.starts_with(&LZ77_MAGIC) {
+        return Err(HuffmanError::MalformedHeader("brak sygnatury HLZ7 - to nie jest ramka LZ77".to_string()));
+    }
+    if prefix[4] != LZ77_VERSION {
+        return Err(HuffmanError::MalformedHeader(format!("nieobsługiwana wersja ramki LZ77 {}", prefix[4])));
+    }
+    let checksum = ChecksumKind::from_byte(prefix[5])?;
+    let original_len = u64::from_be_bytes(prefix[6..].try_into().expect("długość ma 8 bajtów"));
+    options
+        .check_expansion(frame.len() as u64, original_len)
+        .and_then(|()| options.check_output(original_len, true))
+        .map_err(|e| e.at(Position::default()))?;
+
+    let rest = &frame[PREFIX_LEN..];
+    let payload_len = rest
+        .len()
+        .checked_sub(checksum.footer_len())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "brak sumy kontrolnej na końcu ramki"))?;
+    let (payload, footer) = rest.split_at(payload_len);
+    // Symbol ma co najmniej bit, a dopasowanie daje najwyżej MAX_MATCH bajtów
+    let bound = payload.len() as u64 * 8 * MAX_MATCH as u64;
+    let mut out = Vec::with_capacity(original_len.min(bound) as usize);
+    let mut bits = BitReader::with_limit(payload, payload.len() as u64 * 8);
+    let mut block = 0;
+    while (out.len() as u64) < original_len {
+        decode_block(&mut bits, &mut out, original_len).map_err(|e| {
+            e.at(Position { block: Some(block), bit_offset: Some(bits.bits_read()), decoded: Some(out.len() as u64) })
+        })?;
+        block += 1;
+    }
+    if bits.bits_read().div_ceil(8) != payload.len() as u64 {
+        return Err(HuffmanError::MalformedHeader(format!(
+            "{} bajtów za końcem strumienia LZ77",
+            payload.len() as u64 - bits.bits_read().div_ceil(8)
+        )));
+    }
+    if checksum == ChecksumKind::Crc32 {
+        let expected = u32::from_be_bytes(footer.try_into().expect("stopka CRC-32 ma 4 bajty"));
+        let actual = crc32(&out);
+        if expected != actual {
+            return Err(HuffmanError::ChecksumMismatch { expected, actual }
+                .at(Position { decoded: Some(out.len() as u64), ..Position::default() }));
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    Literal(u8),
+    Match { len: u16, dist: u16 },
+}
+
+// Łańcuchy haszy jak w gzip: `head` wskazuje ostatnią pozycję z danym haszem trzech bajtów,
+// a `prev` poprzednią z tym samym haszem w obrębie okna; pozycje są zapisane jako 1 + pozycja
+struct MatchFinder {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+    max_chain: usize,
+    lazy: bool,
+}
+
+impl MatchFinder {
+    fn new(options: &Lz77Options) -> Self {
+        MatchFinder {
+            head: vec![0; 1 << HASH_BITS],
+            prev: vec![0; LZ77_WINDOW],
+            max_chain: options.max_chain.max(1),
+            lazy: options.lazy,
+        }
+    }
+
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        if let Some(bytes) = data.get(pos..pos + MIN_MATCH) {
+            let hash = hash(bytes);
+            self.prev[pos % LZ77_WINDOW] = self.head[hash];
+            self.head[hash] = pos + 1;
+        }
+    }
+
+    // Najdłuższe dopasowanie od `pos` kończące się najpóźniej na `end`: (długość, odległość),
+    // albo (0, 0), gdy nie ma dopasowania z co najmniej MIN_MATCH bajtów
+    fn longest(&self, data: &[u8], pos: usize, end: usize) -> (usize, usize) {
+        let max_len = (end - pos).min(MAX_MATCH);
+        if max_len < MIN_MATCH {
+            return (0, 0);
+        }
+        let target = &data[pos..pos + max_len];
+        let (mut best_len, mut best_dist) = (0, 0);
+        let mut candidate = self.head[hash(target)];
+        for _ in 0..self.max_chain {
+            let Some(start) = candidate.checked_sub(1) else { break };
+            if pos - start > LZ77_WINDOW {
+                break;
+            }
+            // Wcześniejsze dane sięgają za `pos`, więc mają co najmniej `max_len` bajtów; kandydat
+            // dłuższy od najlepszego musi się zgadzać na jego ostatnim bajcie i za nim
+            let earlier = &data[start..];
+            if earlier[best_len] == target[best_len] {
+                let len = earlier.iter().zip(target).take_while(|(a, b)| a == b).count();
+                if len > best_len {
+                    (best_len, best_dist) = (len, pos - start);
+                    if len == max_len {
+                        break;
+                    }
+                }
+            }
+            let next = self.prev[start % LZ77_WINDOW];
+            // Ogniwo nadpisane przez pozycję sprzed pełnego okna prowadzi do przodu: koniec łańcucha
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        if best_len < MIN_MATCH { (0, 0) } else { (best_len, best_dist) }
+    }
+
+    // Literały i dopasowania bloku `data[start..end]`; dopasowania sięgają też do wcześniejszych bloków
+    fn tokenize(&mut self, data: &[u8], start: usize, end: usize, tokens: &mut Vec<Token>) {
+        let mut pos = start;
+        while pos < end {
+            let (len, dist) = self.longest(data, pos, end);
+            self.insert(data, pos);
+            let deferred = self.lazy && len > 0 && len < MAX_MATCH && self.longest(data, pos + 1, end).0 > len;
+            if len == 0 || deferred {
+                tokens.push(Token::Literal(data[pos]));
+                pos += 1;
+                continue;
+            }
+            tokens.push(Token::Match { len: len as u16, dist: dist as u16 });
+            for inner in pos + 1..pos + len {
+                self.insert(data, inner);
+            }
+            pos += len;
+        }
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+    (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+// Ostatni przedział, który zaczyna się nie dalej niż `value`
+fn slot(bases: &[u16], value: u16) -> usize {
+    bases.partition_point(|&base| base <= value) - 1
+}
+
+fn write_block(tokens: &[Token], writer: &mut BitWriter<impl Write>) -> Result<()> {
+    let mut litlen = [0u64; LITLEN_SYMBOLS];
+    let mut dist = [0u64; DIST_SYMBOLS];
+    litlen[END_OF_BLOCK] = 1;
+    for token in tokens {
+        match *token {
+            Token::Literal(byte) => litlen[byte as usize] += 1,
+            Token::Match { len, dist: distance } => {
+                litlen[FIRST_LENGTH + slot(&LENGTH_BASE, len)] += 1;
+                dist[slot(&DIST_BASE, distance)] += 1;
+            }
+        }
+    }
+    let litlen_codes = block_codes(&litlen)?;
+    let dist_codes = block_codes(&dist)?;
+    write_lengths(&litlen_codes, writer)?;
+    write_lengths(&dist_codes, writer)?;
+
+    for token in tokens {
+        match *token {
+            Token::Literal(byte) => writer.write_code(litlen_codes[byte as usize])?,
+            Token::Match { len, dist } => {
+                let length = slot(&LENGTH_BASE, len);
+                writer.write_code(litlen_codes[FIRST_LENGTH + length])?;
+                writer.write_code(Code { bits: (len - LENGTH_BASE[length]) as u64, len: LENGTH_EXTRA[length] })?;
+                let distance = slot(&DIST_BASE, dist);
+                writer.write_code(dist_codes[distance])?;
+                writer.write_code(Code { bits: (dist - DIST_BASE[distance]) as u64, len: DIST_EXTRA[distance] })?;
+            }
+        }
+    }
+    writer.write_code(litlen_codes[END_OF_BLOCK])?;
+    Ok(())
+}
+
+fn write_lengths(codes: &[Code], writer: &mut BitWriter<impl Write>) -> Result<()> {
+    let mut symbol = 0;
+    while let Some(code) = codes.get(symbol) {
+        writer.write_code(Code { bits: code.len as u64, len: LENGTH_BITS })?;
+        symbol += 1;
+        if code.len == 0 {
+            let run = codes[symbol..].iter().take(MAX_ZERO_RUN).take_while(|code| code.len == 0).count();
+            writer.write_code(Code { bits: run as u64, len: ZERO_RUN_BITS })?;
+            symbol += run;
+        }
+    }
+    Ok(())
+}
+
+// Symbole alfabetów bloku w tablicach kodów są zapisane jako `u16` big-endian,
+// więc kolejność kodów kanonicznych to kolejność numerów symboli
+fn symbol_key(symbol: usize) -> Vec<u8> {
+    (symbol as u16).to_be_bytes().to_vec()
+}
+
+fn symbol_index(key: &[u8]) -> usize {
+    u16::from_be_bytes([key[0], key[1]]) as usize
+}
+
+// Kody kanoniczne symboli z licznościami `counts`; symbole bez wystąpień mają kod długości 0
+fn block_codes(counts: &[u64]) -> Result<Vec<Code>> {
+    let frequencies: FreqTable =
+        counts.iter().enumerate().filter(|&(_, &count)| count > 0).map(|(s, &count)| (symbol_key(s), count)).collect();
+    let mut codes = vec![Code::default(); counts.len()];
+    if frequencies.is_empty() {
+        return Ok(codes);
+    }
+    let lengths = package_merge_lengths(&frequencies, DEFAULT_MAX_CODE_LEN)?;
+    for (symbol, code) in canonical_code_table(&lengths) {
+        codes[symbol_index(&symbol)] = code;
+    }
+    Ok(codes)
+}
+
+fn decode_block(bits: &mut BitReader<&[u8]>, out: &mut Vec<u8>, original_len: u64) -> Result<()> {
+    let litlen = read_tree(bits, LITLEN_SYMBOLS)?;
+    let dist = read_tree(bits, DIST_SYMBOLS)?;
+    loop {
+        let symbol = read_symbol(&litlen, bits)?;
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+        if symbol < END_OF_BLOCK {
+            out.push(symbol as u8);
+        } else {
+            let length = symbol - FIRST_LENGTH;
+            let len = LENGTH_BASE[length] as usize + read_extra(bits, LENGTH_EXTRA[length])?;
+            let distance = read_symbol(&dist, bits)?;
+            let back = DIST_BASE[distance] as usize + read_extra(bits, DIST_EXTRA[distance])?;
+            let start = out.len().checked_sub(back).ok_or_else(|| {
+                HuffmanError::MalformedHeader(format!("odwołanie {} bajtów wstecz po {} bajtach", back, out.len()))
+            })?;
+            // Dopasowanie może zachodzić na siebie (seria), więc kopiujemy bajt po bajcie
+            for i in start..start + len {
+                out.push(out[i]);
+            }
+        }
+        if out.len() as u64 > original_len {
+            return Err(HuffmanError::MalformedHeader(format!(
+                "strumień LZ77 daje więcej niż {} bajtów z nagłówka",
+                original_len
+            )));
+        }
+    }
+}
+
+// Tabela kodów z długości na początku bloku ([`write_lengths`])
+fn read_tree(bits: &mut BitReader<&[u8]>, symbols: usize) -> Result<FlatTree> {
+    let mut lengths = CodeLengths::new();
+    let mut symbol = 0;
+    while symbol < symbols {
+        match read_extra(bits, LENGTH_BITS)? as u8 {
+            0 => symbol += 1 + read_extra(bits, ZERO_RUN_BITS)?,
+            len => {
+                lengths.insert(symbol_key(symbol), len);
+                symbol += 1;
+            }
+        }
+    }
+    if symbol > symbols {
+        return Err(HuffmanError::MalformedHeader(format!("tabela kodów ma {} symboli z {}", symbol, symbols)));
+    }
+    check_code_lengths(&lengths, DEFAULT_MAX_CODE_LEN).map_err(HuffmanError::MalformedHeader)?;
+    FlatTree::from_codes(&canonical_code_table(&lengths)).map_err(HuffmanError::MalformedHeader)
+}
+
+fn read_symbol(tree: &FlatTree, bits: &mut BitReader<&[u8]>) -> Result<usize> {
+    let mut walk = tree.start();
+    let mut code = Code::default();
+    loop {
+        match walk {
+            Walk::Leaf(leaf) => return Ok(symbol_index(tree.symbol(leaf))),
+            Walk::Missing => return Err(HuffmanError::InvalidCode { context: Vec::new(), bits: code.to_string() }),
+            Walk::Node(node) => {
+                let bit = read_extra(bits, 1)? == 1;
+                code = code.push(bit);
+                walk = tree.next(node, bit);
+            }
+        }
+    }
+}
+
+fn read_extra(bits: &mut BitReader<&[u8]>, count: u8) -> Result<usize> {
+    let (value, got) = bits.read_bits(count)?;
+    if got < count {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "strumień LZ77 urwany w środku bloku").into());
+    }
+    Ok(value as usize)
+}
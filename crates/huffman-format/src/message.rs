@@ -0,0 +1,271 @@
+use crate::bitio::{BitReader, BitWriter};
+use crate::error::{HuffmanError, Result};
+use crate::huffman::{
+    Code, CodeLengths, CodeTable, DEFAULT_MAX_CODE_LEN, FlatTree, FreqTable, Walk, canonical_code_table,
+    check_code_lengths, code_lengths,
+};
+
+/// Największa wiadomość, której długość mieści się w 2-bajtowym nagłówku ramki.
+pub const MAX_MESSAGE_LEN: usize = u16::MAX as usize;
+
+// Najdłuższy kod w odczytywanym modelu; modele trenowane przed obniżeniem
+// domyślnego limitu do 15 bitów mogą mieć kody do 32 bitów
+const MODEL_MAX_CODE_LEN: u8 = 32;
+
+/// Wspólny model do kompresji wielu krótkich, podobnych wiadomości
+/// (np. telemetrii zserializowanej przez postcard albo CBOR).
+///
+/// Model jest trenowany raz na próbkach i rozsyłany do nadawców i odbiorców
+/// ([`MessageModel::to_bytes`]), więc ramka wiadomości nie niesie tabeli kodów:
+/// to tylko 2 bajty długości (big-endian) i strumień bitów. Każdy bajt ma kod,
+/// także taki, którego nie było w próbkach.
+///
+/// ```
+/// use huffman_format::MessageModel;
+///
+/// let samples: Vec<Vec<u8>> = (0..50u8).map(|i| [0xa2, 0x61, b't', 0x18, i, 0x61, b'h', 0x10].to_vec()).collect();
+/// let model = MessageModel::train(samples.iter().map(Vec::as_slice))?;
+///
+/// let message = [0xa2, 0x61, b't', 0x18, 21, 0x61, b'h', 0x10];
+/// let frame = model.encode(&message)?;
+/// assert!(frame.len() < message.len());
+/// assert_eq!(model.decode(&frame)?, message);
+///
+/// let shared = MessageModel::from_bytes(&model.to_bytes())?;
+/// assert_eq!(shared.decode(&frame)?, message);
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+///
+/// Długo działająca usługa może odświeżać model z bieżącego ruchu: [`MessageModel::update`]
+/// tylko zlicza bajty, a kody zmienia dopiero [`MessageModel::rebuild_codes`]. Ramki starego
+/// i nowego modelu nie są zgodne, więc nowy model dostaje nowy numer w [`MessageModelSet`].
+#[derive(Debug, Clone)]
+pub struct MessageModel {
+    lengths: [u8; 256],
+    // Liczniki bajtów z treningu i update, z których rebuild_codes buduje kody
+    counts: [u64; 256],
+    codes: CodeTable,
+    // Drzewo kodów w płaskiej tablicy, przechodzone po bicie przy dekodowaniu
+    tree: FlatTree,
+}
+
+impl MessageModel {
+    /// Trenuje model rzędu 0 na próbkach wiadomości.
+    pub fn train<'a>(samples: impl IntoIterator<Item = &'a [u8]>) -> Result<MessageModel> {
+        // Każdy bajt dostaje licznik co najmniej 1, żeby dało się zakodować dowolną wiadomość
+        let mut counts = [1u64; 256];
+        for sample in samples {
+            for &byte in sample {
+                counts[byte as usize] += 1;
+            }
+        }
+        let lengths = lengths_from_counts(&counts)?;
+        Ok(MessageModel::from_lengths(&lengths, counts))
+    }
+
+    /// Dolicza bajty `data` do liczników modelu; kody pozostają bez zmian do
+    /// [`MessageModel::rebuild_codes`], więc ramki kodowane w międzyczasie są nadal zgodne.
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.counts[byte as usize] = self.counts[byte as usize].saturating_add(1);
+        }
+    }
+
+    /// Buduje kody od nowa z liczników: próbek z [`MessageModel::train`] i wszystkich
+    /// [`MessageModel::update`]. Model z [`MessageModel::from_bytes`] nie ma liczników
+    /// próbek, więc jego nowe kody wynikają tylko z danych z `update`.
+    ///
+    /// ```
+    /// use huffman_format::MessageModel;
+    ///
+    /// let mut model = MessageModel::train([&b"temp=21.5"[..]])?;
+    /// let before = model.encoded_len(b"{\"user\":\"ala\"}");
+    /// for _ in 0..100 {
+    ///     model.update(b"{\"user\":\"ola\"}");
+    /// }
+    /// assert_eq!(model.encoded_len(b"{\"user\":\"ala\"}"), before);
+    /// model.rebuild_codes()?;
+    /// assert!(model.encoded_len(b"{\"user\":\"ala\"}") < before);
+    /// # Ok::<(), huffman_format::HuffmanError>(())
+    /// ```
+    pub fn rebuild_codes(&mut self) -> Result<()> {
+        *self = MessageModel::from_lengths(&lengths_from_counts(&self.counts)?, self.counts);
+        Ok(())
+    }
+
+    /// Model w postaci do rozesłania: 256 długości kodów, po jednym bajcie.
+    pub fn to_bytes(&self) -> [u8; 256] {
+        self.lengths
+    }
+
+    /// Odtwarza model zapisany przez [`MessageModel::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<MessageModel> {
+        if bytes.len() != 256 {
+            return Err(HuffmanError::MalformedHeader(format!(
+                "model wiadomości ma {} bajtów zamiast 256",
+                bytes.len()
+            )));
+        }
+        let lengths: CodeLengths = (0..=255u8).map(|b| (vec![b], bytes[b as usize])).collect();
+        check_code_lengths(&lengths, MODEL_MAX_CODE_LEN)
+            .map_err(|reason| HuffmanError::MalformedHeader(format!("model wiadomości: {}", reason)))?;
+        if lengths.values().any(|&len| len == 0) {
+            return Err(HuffmanError::MalformedHeader("model wiadomości: bajt bez kodu".to_string()));
+        }
+        Ok(MessageModel::from_lengths(&lengths, [1; 256]))
+    }
+
+    fn from_lengths(lengths: &CodeLengths, counts: [u64; 256]) -> MessageModel {
+        let codes = canonical_code_table(lengths);
+        let tree = FlatTree::from_codes(&codes).expect("kody kanoniczne są prefiksowe");
+        let mut table = [0u8; 256];
+        for (symbol, &len) in lengths {
+            table[symbol[0] as usize] = len;
+        }
+        MessageModel { lengths: table, counts, codes, tree }
+    }
+
+    /// Długość ramki z [`MessageModel::encode`] w bajtach, bez kodowania.
+    pub fn encoded_len(&self, message: &[u8]) -> usize {
+        let bits: u64 = message.iter().map(|&byte| self.lengths[byte as usize] as u64).sum();
+        2 + bits.div_ceil(8) as usize
+    }
+
+    /// Koduje wiadomość do ramki: `u16` długość i strumień bitów dopełniony do bajtu.
+    pub fn encode(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut frame = Vec::new();
+        self.encode_into(message, &mut frame)?;
+        Ok(frame)
+    }
+
+    /// Jak [`MessageModel::encode`], ale dopisuje ramkę do `out`.
+    pub fn encode_into(&self, message: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(HuffmanError::InvalidOptions(format!(
+                "wiadomość ma {} bajtów (maks. {})",
+                message.len(),
+                MAX_MESSAGE_LEN
+            )));
+        }
+        out.extend_from_slice(&(message.len() as u16).to_be_bytes());
+        let mut bits = BitWriter::new(out);
+        for byte in message {
+            bits.write_code(self.codes[&[*byte][..]])?;
+        }
+        bits.finish()?;
+        Ok(())
+    }
+
+    /// Dekoduje ramkę z [`MessageModel::encode`].
+    pub fn decode(&self, frame: &[u8]) -> Result<Vec<u8>> {
+        let Some((len, payload)) = frame.split_first_chunk::<2>() else {
+            return Err(HuffmanError::MalformedHeader("ramka krótsza niż 2 bajty".to_string()));
+        };
+        let expected = u16::from_be_bytes(*len) as u64;
+        let mut out = Vec::with_capacity(expected as usize);
+        let mut bits = BitReader::new(payload);
+        // Model ma kody wszystkich 256 bajtów, więc korzeń drzewa jest węzłem
+        let Walk::Node(root) = self.tree.start() else { unreachable!("model bez kodów") };
+        let mut node = root;
+        let mut code = Code::default();
+        while (out.len() as u64) < expected {
+            let Some(bit) = bits.read_bit()? else {
+                return Err(HuffmanError::TruncatedStream { decoded: out.len() as u64, expected });
+            };
+            code = code.push(bit);
+            match self.tree.next(node, bit) {
+                Walk::Node(next) => node = next,
+                Walk::Leaf(leaf) => {
+                    out.push(self.tree.symbol(leaf)[0]);
+                    node = root;
+                    code = Code::default();
+                }
+                Walk::Missing => {
+                    return Err(HuffmanError::InvalidCode { context: Vec::new(), bits: code.to_string() });
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn lengths_from_counts(counts: &[u64; 256]) -> Result<CodeLengths> {
+    let frequencies: FreqTable = (0..=255u8).map(|b| (vec![b], counts[b as usize])).collect();
+    code_lengths(&frequencies, DEFAULT_MAX_CODE_LEN)
+}
+
+/// Lista modeli próbowanych po kolei: każda wiadomość jest kodowana tym, który
+/// daje najkrótszą ramkę, a numer modelu trafia do pierwszego bajtu ramki.
+///
+/// Przy niejednorodnym ruchu (np. JSON z dwóch usług) nie trzeba ręcznie
+/// kierować wiadomości do modelu; odbiorca musi znać te same modele w tej samej kolejności.
+///
+/// ```
+/// use huffman_format::{MessageModel, MessageModelSet};
+///
+/// let json = MessageModel::train([&b"{\"id\":1,\"ok\":true}"[..]])?;
+/// let digits = MessageModel::train([&b"0123456789,0123456789"[..]])?;
+/// let models = MessageModelSet::new(vec![json, digits])?;
+///
+/// let frame = models.encode(b"31415,92653")?;
+/// assert_eq!(frame[0], 1);
+/// assert_eq!(models.decode(&frame)?, b"31415,92653");
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MessageModelSet {
+    models: Vec<MessageModel>,
+}
+
+impl MessageModelSet {
+    /// Przyjmuje od 1 do 256 modeli; numer modelu to jego pozycja na liście.
+    pub fn new(models: Vec<MessageModel>) -> Result<MessageModelSet> {
+        if models.is_empty() || models.len() > 256 {
+            return Err(HuffmanError::InvalidOptions(format!(
+                "zestaw modeli musi mieć od 1 do 256 modeli, podano {}",
+                models.len()
+            )));
+        }
+        Ok(MessageModelSet { models })
+    }
+
+    pub fn models(&self) -> &[MessageModel] {
+        &self.models
+    }
+
+    /// Dodaje model na końcu listy i zwraca jego numer, np. odświeżony przez
+    /// [`MessageModel::rebuild_codes`]: stare ramki nadal dekodują się starym modelem.
+    /// Odbiorcy muszą dostać nowy model, zanim zobaczą jego ramki.
+    pub fn push(&mut self, model: MessageModel) -> Result<u8> {
+        if self.models.len() == 256 {
+            return Err(HuffmanError::InvalidOptions("zestaw ma już 256 modeli".to_string()));
+        }
+        self.models.push(model);
+        Ok((self.models.len() - 1) as u8)
+    }
+
+    /// Numer modelu dającego najkrótszą ramkę; przy remisie wygrywa wcześniejszy.
+    pub fn best_model(&self, message: &[u8]) -> u8 {
+        let best = (0..self.models.len()).min_by_key(|&i| self.models[i].encoded_len(message));
+        best.expect("zestaw ma co najmniej jeden model") as u8
+    }
+
+    /// Koduje wiadomość do ramki: `u8` numer modelu i ramka [`MessageModel::encode`].
+    pub fn encode(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let id = self.best_model(message);
+        let mut frame = vec![id];
+        self.models[id as usize].encode_into(message, &mut frame)?;
+        Ok(frame)
+    }
+
+    /// Dekoduje ramkę z [`MessageModelSet::encode`].
+    pub fn decode(&self, frame: &[u8]) -> Result<Vec<u8>> {
+        let Some((&id, rest)) = frame.split_first() else {
+            return Err(HuffmanError::MalformedHeader("pusta ramka".to_string()));
+        };
+        let model = self.models.get(id as usize).ok_or_else(|| {
+            HuffmanError::MalformedHeader(format!("nieznany model {} (zestaw ma {})", id, self.models.len()))
+        })?;
+        model.decode(rest)
+    }
+}
@@ -0,0 +1,167 @@
+//! Metadane pliku `.huff`: komentarz i nazwa oryginału w bloku TLV za ostatnią ramką.
+//!
+//! Blok leży między ramkami a tablicą skoków, więc jego zmiana ([`set_metadata`]) nie przesuwa
+//! żadnej ramki: offsety w tablicy skoków i w indeksie linii zostają ważne, a skompresowane
+//! bloki nie są nawet czytane. Dekodery przeskakują blok, a wpisy o nieznanym typie są
+//! zachowywane przy ponownym zapisie, więc nowe rodzaje metadanych nie zmieniają formatu.
+//!
+//! ```
+//! use std::io::Cursor;
+//! use huffman_format::{Metadata, Options, compress, decompress, read_metadata, set_metadata};
+//!
+//! let mut file = Cursor::new(compress(b"abracadabra", Options::default())?);
+//! let metadata = Metadata { name: Some("abra.txt".to_string()), ..Metadata::default() };
+//! let len = set_metadata(&mut file, &metadata)?;
+//! file.get_mut().truncate(len as usize);
+//! assert_eq!(read_metadata(&mut file)?, metadata);
+//! assert_eq!(decompress(file.get_ref())?, b"abracadabra");
+//! # Ok::<(), huffman_format::HuffmanError>(())
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::error::{HuffmanError, Result};
+use crate::index::{SeekTable, frame_index};
+
+/// Sygnatura bloku metadanych.
+pub const META_MAGIC: [u8; 4] = *b"HMET";
+
+const META_VERSION: u8 = 1;
+
+/// Najdłuższy blok metadanych; dekoder nie rezerwuje pamięci na zmyśloną długość.
+pub const MAX_METADATA_LEN: usize = 1 << 20;
+
+// Sygnatura, `u32` długość całego bloku i wersja; dalej wpisy `u8` typ, `u32` długość, wartość
+const META_PREFIX_LEN: usize = META_MAGIC.len() + 4 + 1;
+
+const TAG_COMMENT: u8 = 1;
+const TAG_NAME: u8 = 2;
+
+/// Metadane pliku `.huff` z bloku TLV za ostatnią ramką ([`read_metadata`], [`set_metadata`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Dowolny opis pliku.
+    pub comment: Option<String>,
+    /// Nazwa pliku przed kompresją.
+    pub name: Option<String>,
+    /// Wpisy (typ, wartość) nieznane tej wersji, przepisywane bez zmian.
+    pub other: Vec<(u8, Vec<u8>)>,
+}
+
+impl Metadata {
+    /// Czy nie ma żadnego wpisu; pusty zestaw nie zajmuje miejsca w pliku.
+    pub fn is_empty(&self) -> bool {
+        self.comment.is_none() && self.name.is_none() && self.other.is_empty()
+    }
+
+    /// Blok metadanych do zapisania za ostatnią ramką.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = META_MAGIC.to_vec();
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.push(META_VERSION);
+        let known = [(TAG_COMMENT, &self.comment), (TAG_NAME, &self.name)];
+        let known = known.into_iter().filter_map(|(tag, value)| Some((tag, value.as_deref()?.as_bytes())));
+        for (tag, value) in known.chain(self.other.iter().map(|(tag, value)| (*tag, value.as_slice()))) {
+            bytes.push(tag);
+            bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(value);
+        }
+        let len = bytes.len() as u32;
+        bytes[META_MAGIC.len()..META_MAGIC.len() + 4].copy_from_slice(&len.to_be_bytes());
+        bytes
+    }
+
+    /// Czyta z `reader` dokładnie jeden blok metadanych, od sygnatury.
+    pub fn read_from<R: Read>(reader: R) -> Result<Metadata> {
+        let malformed = |what: &str| HuffmanError::MalformedHeader(format!("metadane: {}", what));
+        let mut prefix = [0u8; META_PREFIX_LEN];
+        let mut reader = reader.take(META_PREFIX_LEN as u64);
+        reader.read_exact(&mut prefix).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => malformed("blok urwany przed wpisami"),
+            _ => e.into(),
+        })?;
+        if prefix[..4] != META_MAGIC {
+            return Err(malformed("brak sygnatury HMET"));
+        }
+        if prefix[8] != META_VERSION {
+            return Err(malformed(&format!("nieobsługiwana wersja {}", prefix[8])));
+        }
+        let len = u32::from_be_bytes(prefix[4..8].try_into().expect("4 bajty")) as usize;
+        if !(META_PREFIX_LEN..=MAX_METADATA_LEN).contains(&len) {
+            return Err(malformed(&format!("długość bloku {} poza zakresem", len)));
+        }
+        let mut body = Vec::new();
+        reader.set_limit((len - META_PREFIX_LEN) as u64);
+        reader.read_to_end(&mut body)?;
+        if body.len() != len - META_PREFIX_LEN {
+            return Err(malformed("blok urwany przed końcem"));
+        }
+
+        let mut metadata = Metadata::default();
+        let mut rest = &body[..];
+        while !rest.is_empty() {
+            let (tag, value_len) = match rest {
+                [tag, l0, l1, l2, l3, ..] => (*tag, u32::from_be_bytes([*l0, *l1, *l2, *l3]) as usize),
+                _ => return Err(malformed("urwany wpis")),
+            };
+            let value = rest.get(5..5 + value_len).ok_or_else(|| malformed("wpis dłuższy niż blok"))?;
+            rest = &rest[5 + value_len..];
+            let text = || String::from_utf8(value.to_vec()).map_err(|_| malformed("tekst nie jest w UTF-8"));
+            match tag {
+                TAG_COMMENT => metadata.comment = Some(text()?),
+                TAG_NAME => metadata.name = Some(text()?),
+                _ => metadata.other.push((tag, value.to_vec())),
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Blok metadanych na początku `bytes` i jego długość.
+    pub fn parse(bytes: &[u8]) -> Result<(Metadata, usize)> {
+        let mut rest = bytes;
+        let metadata = Metadata::read_from(&mut rest)?;
+        Ok((metadata, bytes.len() - rest.len()))
+    }
+}
+
+// Koniec ostatniej ramki: tu zaczyna się blok metadanych albo tablica skoków
+fn frames_end<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+    Ok(frame_index(reader)?.last().map_or(0, |frame| frame.offset + frame.len))
+}
+
+/// Metadane pliku `.huff`; plik bez bloku ma puste metadane.
+pub fn read_metadata<R: Read + Seek>(reader: &mut R) -> Result<Metadata> {
+    let end = frames_end(reader)?;
+    let mut signature = Vec::with_capacity(META_MAGIC.len());
+    reader.seek(SeekFrom::Start(end))?;
+    reader.by_ref().take(META_MAGIC.len() as u64).read_to_end(&mut signature)?;
+    if signature != META_MAGIC {
+        return Ok(Metadata::default());
+    }
+    reader.seek(SeekFrom::Start(end))?;
+    Metadata::read_from(reader)
+}
+
+/// Zapisuje `metadata` w pliku `.huff` zamiast dotychczasowych (pusty zestaw usuwa blok).
+///
+/// Zmienia się tylko koniec pliku od ostatniej ramki: blok metadanych i przepisana za nim
+/// tablica skoków. Skrócenie pliku po krótszym bloku należy do wołającego
+/// (`File::set_len` z długością z wyniku), bo `Write + Seek` nie umie obciąć pliku.
+pub fn set_metadata<F: Read + Write + Seek>(file: &mut F, metadata: &Metadata) -> Result<u64> {
+    let end = frames_end(file)?;
+    let seek_table = SeekTable::read_from_end(file)?.map(|(table, _)| table);
+    let mut tail = if metadata.is_empty() { Vec::new() } else { metadata.to_bytes() };
+    if tail.len() > MAX_METADATA_LEN {
+        return Err(HuffmanError::InvalidOptions(format!(
+            "metadane zajmują {} bajtów (najwyżej {})",
+            tail.len(),
+            MAX_METADATA_LEN
+        )));
+    }
+    if let Some(table) = seek_table {
+        table.write_into(&mut tail);
+    }
+    file.seek(SeekFrom::Start(end))?;
+    file.write_all(&tail)?;
+    Ok(end + tail.len() as u64)
+}
@@ -0,0 +1,112 @@
+use std::io::Cursor;
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::codec::{DecodeOptions, EncoderScratch, Options, Stats, compress_into, compress_with_stats, decompress_with};
+use crate::error::{Position, Result};
+use crate::index::frame_index;
+
+/// Liczba wątków, gdy użytkownik poda 0: tyle, ile rdzeni widzi system.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Kompresuje `data` blokami po `block_size` bajtów na `threads` wątkach.
+///
+/// Każdy blok staje się osobną ramką z własnym modelem, jak w
+/// [`crate::HuffmanWriter`], więc wynik odczytuje zwykły [`crate::decompress`],
+/// a [`decompress_parallel`] dekoduje ramki równolegle. Wynik nie zależy od
+/// liczby wątków.
+pub fn compress_parallel(
+    data: &[u8],
+    options: &Options,
+    block_size: usize,
+    threads: usize,
+) -> Result<(Vec<u8>, Stats)> {
+    if data.is_empty() {
+        // Bez bloków nie byłoby żadnej ramki; pusta ramka jak w `compress`
+        return compress_with_stats(data, options);
+    }
+    // Alfabet sprawdzamy raz dla całości, żeby offset błędu dotyczył całego wejścia
+    if let Some(alphabet) = &options.alphabet {
+        alphabet.check(data)?;
+    }
+    let options = Options { alphabet: None, ..*options };
+    // Granice bloków nie mogą przecinać symboli wielobajtowych
+    let unit = options.unit.max(1) as usize;
+    let block_size = (block_size.max(1).div_ceil(unit)) * unit;
+
+    let blocks: Vec<&[u8]> = data.chunks(block_size).collect();
+
+    let frames = map_parallel(&blocks, threads, EncoderScratch::new, |scratch, block| {
+        let mut frame = Vec::new();
+        let stats = compress_into(block, &options, scratch, &mut frame)?;
+        Ok((frame, stats))
+    })?;
+
+    let mut out = Vec::new();
+    let mut stats = Stats::default();
+    for (frame, block_stats) in &frames {
+        out.extend_from_slice(frame);
+        stats.merge(block_stats);
+    }
+    Ok((out, stats))
+}
+/// Dekompresuje archiwum, dekodując jego ramki równolegle na `threads` wątkach.
+///
+/// Granice ramek wyznacza [`crate::frame_index`]; limit z
+/// [`DecodeOptions::max_expansion_ratio`] i [`DecodeOptions::max_output_len`] są
+/// sprawdzane dla sumy długości z nagłówków, zanim cokolwiek zostanie zaalokowane.
+pub fn decompress_parallel(content: &[u8], options: &DecodeOptions, threads: usize) -> Result<Vec<u8>> {
+    if content.is_empty() {
+        // Ten sam błąd co w dekoderze sekwencyjnym
+        return decompress_with(content, options);
+    }
+    let frames = frame_index(&mut Cursor::new(content))?;
+    let total = frames.iter().fold(0u64, |total, f| total.saturating_add(f.original_len));
+    options
+        .check_expansion(content.len() as u64, total)
+        .and_then(|()| options.check_output(total, true))
+        .map_err(|e| e.at(Position::default()))?;
+    // Pojedyncza ramka jest już sprawdzona wyżej, na poziomie całego pliku
+    let frame_options = DecodeOptions { max_expansion_ratio: None, max_output_len: None, ..*options };
+
+    let decoded = map_parallel(&frames, threads, || (), |_, frame| {
+        let start = frame.offset as usize;
+        decompress_with(&content[start..start + frame.len as usize], &frame_options)
+    })?;
+
+    let mut out = Vec::with_capacity(decoded.iter().map(Vec::len).sum());
+    for chunk in &decoded {
+        out.extend_from_slice(chunk);
+    }
+    Ok(out)
+}
+
+// Wątek `t` przetwarza elementy t, t + threads, t + 2 * threads, ...; wyniki wracają
+// w kolejności wejścia, a pierwszy błąd (w tej kolejności) przerywa całość
+fn map_parallel<T: Sync, S, R: Send>(
+    items: &[T],
+    threads: usize,
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, &T) -> Result<R> + Sync,
+) -> Result<Vec<R>> {
+    let threads = threads.clamp(1, items.len().max(1));
+    let per_thread: Vec<Vec<Result<R>>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|t| {
+                let (init, f) = (&init, &f);
+                scope.spawn(move || {
+                    let mut state = init();
+                    items.iter().skip(t).step_by(threads).map(|item| f(&mut state, item)).collect()
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().expect("wątek roboczy spanikował")).collect()
+    });
+
+    let mut iters: Vec<_> = per_thread.into_iter().map(Vec::into_iter).collect();
+    (0..items.len())
+        .map(|i| iters[i % threads].next().expect("każdy element ma wynik"))
+        .collect()
+}
@@ -0,0 +1,38 @@
+//! Wspierana powierzchnia API: typy potrzebne do zwykłej kompresji i dekompresji.
+//!
+//! `use huffman_format::prelude::*;` daje [`HuffmanCodec`] z interfejsem [`Codec`],
+//! parametry ([`Config`], [`DecodeOptions`]), statystyki ([`Stats`]) i błędy ([`Error`]),
+//! a także [`Symbol`], [`Code`] i drzewo kodów [`Tree`] do przeglądania modeli oraz
+//! [`Archive`] z [`MemberInfo`] i [`MemberReader`] do czytania plików z archiwów.
+//! Nazwy stąd zmieniają się tylko przy zmianie wersji zgodnie z semver; reszta eksportów
+//! z korzenia (np. [`crate::Model`], [`crate::MAGIC`]) udostępnia szczegóły formatu dla `huff`
+//! i narzędzi, a w wersjach 0.x może się zmienić w każdym wydaniu podnoszącym numer minor.
+//!
+//! ```
+//! use huffman_format::prelude::*;
+//!
+//! let codec = HuffmanCodec { options: Config { order: 1, ..Config::default() }, ..HuffmanCodec::default() };
+//! let packed = codec.compress(b"abracadabra")?;
+//! assert_eq!(codec.decompress(&packed)?, b"abracadabra");
+//!
+//! let (_, stats): (_, Stats) = compress_with_stats(b"abracadabra", &Config::default())?;
+//! assert_eq!(stats.original_len, 11);
+//! // Wyliczenie błędów jest #[non_exhaustive], więc dopasowanie potrzebuje gałęzi `_`
+//! match decompress(b"nie .huff").unwrap_err().root() {
+//!     Error::MalformedHeader(_) => {}
+//!     other => panic!("{}", other),
+//! }
+//! # Ok::<(), Error>(())
+//! ```
+
+pub use crate::archive::{Archive, MemberInfo, MemberReader};
+pub use crate::codec::{
+    DecodeOptions, Options as Config, Stats, compress, compress_with_stats, decompress, decompress_with,
+};
+pub use crate::compressor::{Codec, HuffmanCodec};
+pub use crate::filters::{Filter, FilterChain};
+pub use crate::header::ChecksumKind;
+pub use crate::reader::HuffmanReader;
+pub use crate::writer::HuffmanWriter;
+pub use huffman_core::huffman::{Code, Symbol, Tree, TreeNode};
+pub use huffman_core::{HuffmanError as Error, Result};
@@ -0,0 +1,284 @@
+use std::io::{self, ErrorKind, Read};
+
+use crate::codec::{DecodeOptions, SymbolDecoder};
+use crate::error::{HuffmanError, Position, Result};
+use crate::filters::{self, Filter};
+use crate::header::Header;
+use crate::index::{SEEK_MAGIC, SeekTable};
+use crate::meta::{META_MAGIC, Metadata};
+
+// Ile bajtów skompresowanego strumienia pobieramy naraz z wejścia, gdy
+// DecodeOptions::input_chunk nie mówi inaczej
+pub(crate) const INPUT_CHUNK: usize = 8 * 1024;
+
+// Początkowa pojemność bufora odtworzonych bajtów
+pub(crate) const READER_OUTPUT: usize = INPUT_CHUNK * 8;
+
+// Liczy bajty przeczytane przy parsowaniu nagłówka
+struct Counted<'a, R: Read> {
+    inner: &'a mut R,
+    count: &'a mut u64,
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        *self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Dekoder strumieniowy: czyta plik `.huff` z `R` i oddaje odtworzone bajty
+/// przez [`Read`], bez trzymania całego wyniku w pamięci.
+///
+/// Nagłówek pierwszej ramki jest czytany w [`HuffmanReader::new`]; kolejne
+/// sklejone ramki (np. z [`crate::HuffmanWriter`]) są dekodowane po kolei.
+/// Wynik jest oddawany w kolejności danych, porcjami odpowiadającymi przeczytanemu
+/// wejściu, więc odczyt początku (np. przez [`Read::take`]) nie dekoduje reszty pliku.
+/// Błędy dekodowania są zwracane jako `io::Error` z [`crate::HuffmanError`]
+/// w środku (`HuffmanError::from(io_error)` odtwarza oryginał).
+///
+/// Limit z [`DecodeOptions::max_expansion_ratio`] jest tu sprawdzany na bieżąco
+/// względem wejścia przeczytanego do tej pory, bo pełna długość strumienia nie jest znana.
+/// [`DecodeOptions::max_output_len`] jest sprawdzany z nagłówka każdej ramki, zanim ramka
+/// zacznie się dekodować, a [`DecodeOptions::max_memory`] dotyczy tu tylko tablic kodów
+/// (i bufora ramki przy [`DecodeOptions::verify_blocks`]).
+///
+/// Ramki z filtrami ([`crate::Options::filters`]) są dekodowane w całości do bufora,
+/// bo filtry odwraca się dopiero na pełnych danych ramki.
+pub struct HuffmanReader<R: Read> {
+    inner: R,
+    options: DecodeOptions,
+    decoder: SymbolDecoder,
+    original_len: u64,
+    filters: Vec<(Filter, u64)>,
+    read_in: u64,
+    produced: u64,
+    input: Box<[u8]>,
+    input_pos: usize,
+    input_len: usize,
+    output: Vec<u8>,
+    output_pos: usize,
+    footer_checked: bool,
+    finished: bool,
+}
+
+impl<R: Read> HuffmanReader<R> {
+    pub fn new(inner: R) -> Result<Self> {
+        Self::with_options(inner, DecodeOptions::default())
+    }
+
+    pub fn with_options(mut inner: R, options: DecodeOptions) -> Result<Self> {
+        let mut read_in = 0;
+        let header = Header::read_bounded(&mut Counted { inner: &mut inner, count: &mut read_in }, &options)
+            .and_then(|header| {
+                options.check_output(header.original_len, options.verify_blocks || !header.filters.is_empty())?;
+                Ok(header)
+            })
+            .map_err(|e| e.at(Position::default()))?;
+        let mut decoder = SymbolDecoder::new(&header).map_err(|e| e.at(Position::default()))?;
+        decoder.configure(&options);
+        // Pusty bufor oznaczałby koniec danych już przy pierwszym odczycie
+        let input_chunk = options.input_chunk.unwrap_or(INPUT_CHUNK).max(1);
+        Ok(HuffmanReader {
+            inner,
+            options,
+            decoder,
+            original_len: header.original_len,
+            filters: header.filters,
+            read_in,
+            produced: 0,
+            input: vec![0u8; input_chunk].into_boxed_slice(),
+            input_pos: 0,
+            input_len: 0,
+            output: Vec::with_capacity(READER_OUTPUT),
+            output_pos: 0,
+            footer_checked: false,
+            finished: false,
+        })
+    }
+
+    /// Długość danych po dekompresji zapisana w nagłówku bieżącej ramki.
+    pub fn original_len(&self) -> u64 {
+        self.original_len
+    }
+
+    /// Ile bajtów zostało jeszcze do odtworzenia w bieżącej ramce.
+    pub fn remaining(&self) -> u64 {
+        // Ramka z filtrami trafia do bufora w całości, więc do tego czasu zostaje cała
+        if !self.filters.is_empty() && !self.decoder.is_done() {
+            return self.original_len;
+        }
+        self.decoder.remaining() + (self.output.len() - self.output_pos) as u64
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Dociąga kolejną porcję wejścia; zwraca false na końcu danych
+    fn fill_input(&mut self) -> Result<bool> {
+        let n = loop {
+            match self.inner.read(&mut self.input) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        self.input_pos = 0;
+        self.input_len = n;
+        self.read_in += n as u64;
+        Ok(n > 0)
+    }
+
+    // Czyta stopkę bieżącej ramki (sumę kontrolną) i porównuje z odtworzonymi danymi; suma
+    // ramki z filtrami dotyczy oryginału, więc najpierw odwraca je na buforze wyjściowym
+    fn check_footer(&mut self) -> Result<()> {
+        let mut footer = [0u8; 8];
+        let footer = &mut footer[..self.decoder.footer_len()];
+        let mut leftover = &self.input[self.input_pos..self.input_len];
+        let mut inner = Counted { inner: &mut self.inner, count: &mut self.read_in };
+        (&mut leftover).chain(&mut inner).read_exact(footer).map_err(|e| {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                io::Error::new(ErrorKind::UnexpectedEof, "brak sumy kontrolnej na końcu ramki")
+            } else {
+                e
+            }
+        })?;
+        self.input_pos = self.input_len - leftover.len();
+        self.footer_checked = true;
+        if self.filters.is_empty() {
+            return self.decoder.verify(footer, None);
+        }
+        let coded = std::mem::take(&mut self.output);
+        self.output = filters::revert(&self.filters, coded, self.original_len)?;
+        self.decoder.verify(footer, Some(&self.output))
+    }
+
+    // Czyta nagłówek następnej ramki z resztki bufora i dalszej części wejścia;
+    // metadane albo tablica skoków zamiast nagłówka kończą strumień
+    fn next_frame(&mut self) -> Result<()> {
+        let mut leftover = &self.input[self.input_pos..self.input_len];
+        let mut inner = Counted { inner: &mut self.inner, count: &mut self.read_in };
+        let mut signature = Vec::with_capacity(SEEK_MAGIC.len());
+        (&mut leftover).chain(&mut inner).take(SEEK_MAGIC.len() as u64).read_to_end(&mut signature)?;
+        if signature == META_MAGIC {
+            Metadata::read_from((&signature[..]).chain(&mut leftover).chain(&mut inner))
+                .map_err(|e| e.at(Position::default()))?;
+            signature.clear();
+            (&mut leftover).chain(&mut inner).take(SEEK_MAGIC.len() as u64).read_to_end(&mut signature)?;
+            if signature.is_empty() {
+                self.input_pos = self.input_len;
+                self.finished = true;
+                return Ok(());
+            }
+            if signature != SEEK_MAGIC {
+                let err = HuffmanError::MalformedHeader("dane za blokiem metadanych".to_string());
+                return Err(err.at(Position::default()));
+            }
+        }
+        if signature == SEEK_MAGIC {
+            let mut table = signature;
+            (&mut leftover).chain(&mut inner).read_to_end(&mut table)?;
+            SeekTable::parse(&table).map_err(|e| e.at(Position::default()))?;
+            self.input_pos = self.input_len;
+            self.finished = true;
+            return Ok(());
+        }
+
+        let header = Header::read_bounded(&mut (&signature[..]).chain(&mut leftover).chain(&mut inner), &self.options)
+            .and_then(|header| {
+                self.options.check_output(self.produced.saturating_add(header.original_len), false)?;
+                let buffered = self.options.verify_blocks || !header.filters.is_empty();
+                self.options.check_output(header.original_len, buffered)?;
+                Ok(header)
+            })
+            .map_err(|e| e.at(Position::default()))?;
+        self.input_pos = self.input_len - leftover.len();
+        self.decoder = SymbolDecoder::new(&header).map_err(|e| e.at(Position::default()))?;
+        self.decoder.configure(&self.options);
+        self.original_len = header.original_len;
+        self.filters = header.filters;
+        self.footer_checked = false;
+        Ok(())
+    }
+
+    // Dekoduje kolejną porcję wejścia do bufora wyjściowego
+    fn refill(&mut self) -> Result<()> {
+        self.output.clear();
+        self.output_pos = 0;
+
+        if self.decoder.is_done() {
+            if !self.footer_checked {
+                return self.finish_frame(self.produced);
+            }
+            if self.input_pos == self.input_len && !self.fill_input()? {
+                self.finished = true;
+                return Ok(());
+            }
+            return self.next_frame();
+        }
+
+        if self.options.verify_blocks || !self.filters.is_empty() {
+            // Cała ramka trafia do bufora i czeka na stopkę; błąd sumy nie wypuszcza z niej niczego
+            let produced = self.produced;
+            while !self.decoder.is_done() {
+                self.decode_chunk()?;
+            }
+            return self.finish_frame(produced).inspect_err(|_| self.output.clear());
+        }
+        self.decode_chunk()
+    }
+
+    // Sprawdza stopkę ramki, odwracając jej filtry na buforze wyjściowym; `produced` to licznik
+    // odtworzonych bajtów sprzed ramki
+    fn finish_frame(&mut self, produced: u64) -> Result<()> {
+        self.check_footer()?;
+        if !self.filters.is_empty() {
+            self.produced = produced + self.output.len() as u64;
+            self.options.check_expansion(self.read_in, self.produced)?;
+        }
+        Ok(())
+    }
+
+    // Dekoduje kolejną porcję wejścia, dopisując wynik do bufora wyjściowego
+    fn decode_chunk(&mut self) -> Result<()> {
+        if self.input_pos == self.input_len && !self.fill_input()? {
+            // finish zgłosi urwany strumień; pełna ramka wraca tu po stopkę
+            return self.decoder.finish(&mut self.output);
+        }
+        let before = self.output.len();
+
+        for &byte in &self.input[self.input_pos..self.input_len] {
+            if self.decoder.is_done() {
+                break;
+            }
+            self.decoder.feed(byte, &mut self.output)?;
+            self.input_pos += 1;
+        }
+        self.produced += (self.output.len() - before) as u64;
+        self.options.check_expansion(self.read_in, self.produced).map_err(|e| {
+            e.at(Position {
+                decoded: Some(self.produced),
+                ..Position::default()
+            })
+        })
+    }
+}
+
+impl<R: Read> Read for HuffmanReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.refill()?;
+        }
+
+        let available = &self.output[self.output_pos..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.output_pos += n;
+        Ok(n)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::codec::{EncoderScratch, Options, Stats, check_unit, compress_into, compress_with_stats, frame_len};
+use crate::error::{HuffmanError, Result};
+
+/// Domyślna ziarnistość kandydatów na granice bloków w [`optimal_split`].
+pub const DEFAULT_GRANULE: usize = 64 * 1024;
+
+/// Wybiera granice bloków minimalizujące łączną długość ramek.
+///
+/// Kandydaci na granice leżą co `granule` bajtów, a blok ma najwyżej
+/// `max_granules` ziaren. Programowanie dynamiczne liczy dla każdego prefiksu
+/// najkrótsze pokrycie ramkami, gdzie koszt ramki to dokładna długość nagłówka
+/// (z tabelą kodów), strumienia bitów i stopki. Kosztuje to około
+/// `max_granules²/2` przebiegów zliczania po danych. Zwraca długości kolejnych bloków.
+pub fn optimal_split(data: &[u8], options: &Options, granule: usize, max_granules: usize) -> Result<Vec<usize>> {
+    if data.is_empty() {
+        return Err(HuffmanError::EmptyInput);
+    }
+    check_unit(options.unit)?;
+    // Granice nie mogą przecinać symboli wielobajtowych
+    let unit = options.unit as usize;
+    let granule = granule.max(1).div_ceil(unit) * unit;
+    let max_granules = max_granules.max(1);
+    let points = data.len().div_ceil(granule);
+    let offset = |i: usize| (i * granule).min(data.len());
+
+    let mut scratch = EncoderScratch::new();
+    // best[j] = (najmniejszy koszt pokrycia pierwszych j ziaren, początek ostatniego bloku)
+    let mut best = vec![(0usize, 0usize); points + 1];
+    for j in 1..=points {
+        best[j] = (usize::MAX, 0);
+        for i in j.saturating_sub(max_granules)..j {
+            let cost = best[i].0 + frame_len(&data[offset(i)..offset(j)], options, &mut scratch)?;
+            if cost < best[j].0 {
+                best[j] = (cost, i);
+            }
+        }
+    }
+
+    let mut lengths = Vec::new();
+    let mut j = points;
+    while j > 0 {
+        let i = best[j].1;
+        lengths.push(offset(j) - offset(i));
+        j = i;
+    }
+    lengths.reverse();
+    Ok(lengths)
+}
+
+/// Kompresuje `data` ramkami o granicach z [`optimal_split`].
+///
+/// Wynik to zwykłe sklejone ramki, jak z [`crate::HuffmanWriter`].
+pub fn compress_optimal_split(
+    data: &[u8],
+    options: &Options,
+    granule: usize,
+    max_granules: usize,
+) -> Result<(Vec<u8>, Stats)> {
+    if let Some(alphabet) = &options.alphabet {
+        alphabet.check(data)?;
+    }
+    if data.is_empty() {
+        return compress_with_stats(data, options);
+    }
+    let options = Options { alphabet: None, ..*options };
+    let lengths = optimal_split(data, &options, granule, max_granules)?;
+
+    let mut out = Vec::new();
+    let mut frame = Vec::new();
+    let mut scratch = EncoderScratch::new();
+    let mut stats = Stats::default();
+    let mut start = 0;
+    for len in lengths {
+        let block = compress_into(&data[start..start + len], &options, &mut scratch, &mut frame)?;
+        out.extend_from_slice(&frame);
+        stats.merge(&block);
+        start += len;
+    }
+    Ok((out, stats))
+}
@@ -0,0 +1,258 @@
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use crate::codec::{
+    EncoderScratch, Options, Stats, canonical_code_tables, conditional_entropy, frame_model, raw_header,
+    stored_header, write_footer,
+};
+use crate::bitio::BitWriter;
+use crate::checksum::Crc32;
+use crate::error::{HuffmanError, Result};
+use crate::events::{Event, EventSink, NoEvents, Phase};
+use crate::header::{Header, Model};
+use crate::testdata::Rng;
+
+// Rozmiar porcji czytanej z wejścia w obu przebiegach (podzielny przez każdą szerokość symbolu)
+const STREAM_CHUNK: usize = 64 * 1024;
+
+/// Entropia próbki (bity na bajt), od której [`sample_entropy`] uznaje dane za nieściśliwe:
+/// kody Huffmana nie zejdą wtedy wyraźnie poniżej 8 bitów, a dojdzie tabela.
+pub const INCOMPRESSIBLE_ENTROPY: f64 = 7.9;
+
+// Porcje próbki: 16 x 4 KiB z losowych miejsc wejścia
+const SAMPLE_CHUNKS: u64 = 16;
+const SAMPLE_CHUNK: usize = 4096;
+
+// Czyta do zapełnienia bufora albo końca danych
+fn read_chunk<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Dwuprzebiegowa kompresja strumieniowa: pierwszy przebieg zlicza częstotliwości,
+/// drugi (po przewinięciu wejścia) emituje bity porcjami do `output`.
+///
+/// Zużycie pamięci zależy od rozmiaru modelu, a nie od długości wejścia, więc
+/// można kompresować pliki większe niż dostępna pamięć. Wynik jest identyczny
+/// z [`crate::compress`]. Bity trafiają do `output` słowami po 8 bajtów, więc
+/// plik warto opakować w `BufWriter`.
+pub fn compress_stream<R: Read + Seek, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    options: &Options,
+) -> Result<Stats> {
+    compress_stream_with_events(input, output, options, &mut NoEvents)
+}
+
+/// [`compress_stream`] zgłaszający do `events` początek obu przebiegów
+/// i zapisaną ramkę.
+pub fn compress_stream_with_events<R: Read + Seek, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    options: &Options,
+    events: &mut dyn EventSink,
+) -> Result<Stats> {
+    options.check_symbols()?;
+    options.check_unfiltered("compress_stream")?;
+    let order = options.order.min(255);
+    let unit = options.unit as usize;
+    let mut scratch = EncoderScratch::new();
+    let mut buf = vec![0u8; STREAM_CHUNK];
+    let start = input.stream_position()?;
+
+    // 1. Zbieranie statystyk
+    events.event(&Event::PhaseStarted(Phase::Counting));
+    scratch.begin_count(order, unit);
+    let mut crc = Crc32::new();
+    let mut original_len = 0u64;
+    loop {
+        let n = read_chunk(input, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if let Some(alphabet) = &options.alphabet {
+            alphabet.check_at(&buf[..n], original_len)?;
+        }
+        scratch.count_chunk(&options.symbols(&buf[..n]), order, unit);
+        crc.update(&buf[..n]);
+        original_len += n as u64;
+    }
+
+    // 2. Budowa drzew Huffmana i zapis nagłówka
+    events.event(&Event::PhaseStarted(Phase::Encoding));
+    let frequencies = scratch.frequencies(order, unit);
+    let (lengths, escape, bits) = frame_model(&frequencies, options)?;
+    let mut code_tables = canonical_code_tables(&lengths);
+    let entropy = conditional_entropy(&frequencies, original_len);
+
+    let mut header = Header {
+        original_len,
+        order,
+        max_code_len: options.max_code_len,
+        unit: options.unit,
+        payload_bits: Some(bits),
+        checksum: options.checksum,
+        escape,
+        nibble: options.nibble,
+        filters: Vec::new(),
+        model: Model::CodeLengths(lengths),
+    };
+    let mut out = header.to_bytes();
+    if let Some(stored) = stored_header(&header, out.len(), options) {
+        header = stored;
+        code_tables.clear();
+        out = header.to_bytes();
+    }
+    let header_len = out.len();
+    output.write_all(&out)?;
+
+    // 3. Kodowanie drugim przebiegiem, bity idą prosto do `output`
+    input.seek(SeekFrom::Start(start))?;
+    scratch.begin_encode(order, unit);
+    let mut bits = BitWriter::new(&mut *output);
+    let mut seen = 0u64;
+    loop {
+        let n = read_chunk(input, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        seen += n as u64;
+        let raw_bits = header.escape.then_some(options.raw_bits());
+        scratch.encode_chunk(&options.symbols(&buf[..n]), &code_tables, unit, raw_bits, &mut bits)?;
+    }
+    let payload_len = bits.bits_written().div_ceil(8) as usize;
+    bits.finish()?;
+    out.clear();
+    write_footer(options.checksum, crc.finish(), &mut out);
+    output.write_all(&out)?;
+    output.flush()?;
+
+    if seen != original_len {
+        return Err(HuffmanError::Io(io::Error::other(format!(
+            "wejście zmieniło się między przebiegami ({} -> {} bajtów)",
+            original_len, seen
+        ))));
+    }
+
+    let stats = Stats {
+        original_len,
+        header_len,
+        payload_len,
+        footer_len: options.checksum.footer_len(),
+        entropy,
+    };
+    events.event(&Event::block(0, &stats));
+    Ok(stats)
+}
+
+/// Entropia rzędu 0 (bity na bajt) kilku porcji z losowych miejsc wejścia, bez czytania
+/// całości; krótsze wejście jest czytane w całości. Po pomiarze wejście wraca na pozycję początkową.
+///
+/// Miejsca porcji zależą tylko od długości wejścia, więc wynik jest powtarzalny.
+///
+/// ```
+/// use std::io::Cursor;
+/// use huffman_format::{INCOMPRESSIBLE_ENTROPY, sample_entropy};
+/// use huffman_format::testdata::Corpus;
+///
+/// let random = Corpus::Random.generate(1 << 20, 1);
+/// assert!(sample_entropy(&mut Cursor::new(&random))? > INCOMPRESSIBLE_ENTROPY);
+/// let text = Corpus::Markov.generate(1 << 20, 1);
+/// assert!(sample_entropy(&mut Cursor::new(&text))? < INCOMPRESSIBLE_ENTROPY);
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+pub fn sample_entropy<R: Read + Seek>(input: &mut R) -> Result<f64> {
+    let start = input.stream_position()?;
+    let len = input.seek(SeekFrom::End(0))? - start;
+    let mut counts = [0u64; 256];
+    let mut buf = vec![0u8; SAMPLE_CHUNK];
+    let mut count = |input: &mut R, at: u64| -> Result<()> {
+        input.seek(SeekFrom::Start(start + at))?;
+        let n = read_chunk(input, &mut buf)?;
+        for &byte in &buf[..n] {
+            counts[byte as usize] += 1;
+        }
+        Ok(())
+    };
+    if len <= SAMPLE_CHUNKS * SAMPLE_CHUNK as u64 {
+        for at in (0..len).step_by(SAMPLE_CHUNK) {
+            count(input, at)?;
+        }
+    } else {
+        let mut rng = Rng::new(len);
+        for _ in 0..SAMPLE_CHUNKS {
+            count(input, rng.below(len - SAMPLE_CHUNK as u64 + 1))?;
+        }
+    }
+    input.seek(SeekFrom::Start(start))?;
+    let total: u64 = counts.iter().sum();
+    Ok(counts
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum())
+}
+
+/// Zapisuje wejście jednym przebiegiem jako ramkę bez tablic kodów, taką jak przy
+/// [`Options::store_fallback`]: bajty są kopiowane wprost, bez zliczania symboli.
+/// Długość oryginału do nagłówka pochodzi z `Seek`.
+///
+/// Entropia w statystykach to 8 bitów na bajt, bo modelu nie liczymy.
+pub fn store_stream<R: Read + Seek, W: Write>(input: &mut R, output: &mut W, options: &Options) -> Result<Stats> {
+    options.check_symbols()?;
+    options.check_unfiltered("store_stream")?;
+    let start = input.stream_position()?;
+    let original_len = input.seek(SeekFrom::End(0))? - start;
+    input.seek(SeekFrom::Start(start))?;
+    let header = raw_header(original_len, options);
+    let mut out = header.to_bytes();
+    let header_len = out.len();
+    output.write_all(&out)?;
+
+    let mut buf = vec![0u8; STREAM_CHUNK];
+    let mut crc = Crc32::new();
+    let mut seen = 0u64;
+    loop {
+        let n = read_chunk(input, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if let Some(alphabet) = &options.alphabet {
+            alphabet.check_at(&buf[..n], seen)?;
+        }
+        crc.update(&buf[..n]);
+        output.write_all(&buf[..n])?;
+        seen += n as u64;
+    }
+    if seen != original_len {
+        return Err(HuffmanError::Io(io::Error::other(format!(
+            "wejście zmieniło się w trakcie zapisu ({} -> {} bajtów)",
+            original_len, seen
+        ))));
+    }
+    // Niepełny ostatni symbol koder dopełnia zerami do pełnej szerokości
+    let payload_len = header.payload_bits.unwrap_or(0).div_ceil(8) as usize;
+    output.write_all(&vec![0u8; payload_len - original_len as usize])?;
+    out.clear();
+    write_footer(options.checksum, crc.finish(), &mut out);
+    output.write_all(&out)?;
+    output.flush()?;
+
+    Ok(Stats {
+        original_len,
+        header_len,
+        payload_len,
+        footer_len: options.checksum.footer_len(),
+        entropy: 8.0,
+    })
+}
@@ -0,0 +1,416 @@
+use std::borrow::Cow;
+
+use crate::bitio::BitWriter;
+use crate::codec::{
+    EncoderScratch, Options, Stats, build_code_lengths, canonical_code_tables, check_unit, conditional_entropy,
+    legacy_code_tables, stored_header, write_footer,
+};
+use crate::error::{HuffmanError, Result};
+use crate::header::{ChecksumKind, ESCAPE, Header, MAX_UNIT, Model};
+use crate::huffman::{FreqTable, MarkovCodeLengths, MarkovCodeTable, MarkovFreqTable, Tree, check_code_lengths};
+
+/// Jak gotowa tabela pokryła zakodowane dane ([`crate::Dictionary::compress_with_coverage`]):
+/// ile symboli miało własny kod, a ile poszło wprost za kodem ucieczki i ile bitów to kosztowało.
+///
+/// Symbol bez kodu kosztuje kod ucieczki i pełną szerokość w bitach, więc niskie pokrycie
+/// znaczy, że dane odeszły od próbek tabeli i warto ją wytrenować od nowa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Coverage {
+    /// Wszystkie symbole ramki (po filtrach).
+    pub symbols: u64,
+    /// Symbole zapisane wprost za kodem ucieczki albo w kontekście spoza tabeli.
+    pub escaped: u64,
+    /// Bity strumienia na te symbole, razem z kodami ucieczki.
+    pub escape_bits: u64,
+    /// Wszystkie bity strumienia.
+    pub payload_bits: u64,
+}
+
+impl Coverage {
+    /// Ułamek symboli z własnym kodem; puste dane są pokryte w całości.
+    pub fn ratio(&self) -> f64 {
+        if self.symbols == 0 {
+            return 1.0;
+        }
+        1.0 - self.escaped as f64 / self.symbols as f64
+    }
+
+    /// Ułamek strumienia zajęty przez symbole bez kodu.
+    pub fn escape_share(&self) -> f64 {
+        if self.payload_bits == 0 {
+            return 0.0;
+        }
+        self.escape_bits as f64 / self.payload_bits as f64
+    }
+}
+
+// Ramka przed kodowaniem: nagłówek, liczności i pokrycie symboli, dane po filtrach
+// do strumienia bitów i CRC-32 oryginału do stopki
+struct Frame<'a> {
+    header: Header,
+    frequencies: MarkovFreqTable,
+    coverage: Coverage,
+    coded: Cow<'a, [u8]>,
+    crc: u32,
+}
+
+/// Gotowa tabela kodów, którą koduje się dane zamiast modelu zbudowanego z nich samych,
+/// np. nagłówek wcześniejszego pliku tego samego rodzaju (`huff encode --use-table`).
+///
+/// Tabela pochodzi z zewnątrz, więc [`FixedTable::from_header`] od razu sprawdza, czy kody
+/// są prefiksowe, a konteksty i symbole mają szerokość wynikającą z rzędu i szerokości
+/// symbolu. [`FixedTable::check_sample`] przed długim kodowaniem sprawdza, czy każdy
+/// symbol próbki wejścia ma kod. Ramki są zwykłymi ramkami `.huff` z tą tabelą w nagłówku.
+/// Tabela, której brakuje symboli wejścia, może dostać kody ucieczki ([`FixedTable::with_escapes`]).
+///
+/// ```
+/// use huffman_format::{FixedTable, Header, Options, compress, decompress};
+///
+/// let reference = compress(b"abracadabra", Options { order: 1, store_fallback: false, ..Options::default() })?;
+/// let table = FixedTable::from_header(&Header::parse(&reference)?.0)?;
+/// let options = Options { order: table.order(), ..Options::default() };
+///
+/// table.check_sample(b"abracad")?;
+/// let (packed, _) = table.compress_with_stats(b"abracad", &options)?;
+/// assert_eq!(decompress(&packed)?, b"abracad");
+/// assert!(table.check_sample(b"abrakadabra").is_err()); // 'k' nie ma kodu
+/// # Ok::<(), huffman_format::HuffmanError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedTable {
+    order: usize,
+    unit: u8,
+    max_code_len: u8,
+    escape: bool,
+    lengths: MarkovCodeLengths,
+    codes: MarkovCodeTable,
+}
+
+impl FixedTable {
+    /// Tabela z nagłówka ramki; modele z częstotliwościami (v0 i starsze) są zamieniane na długości kodów.
+    pub fn from_header(header: &Header) -> Result<Self> {
+        check_unit(header.unit)?;
+        if header.nibble {
+            return Err(HuffmanError::InvalidOptions("tabela: symbole 4-bitowe nie są obsługiwane".to_string()));
+        }
+        let lengths = match &header.model {
+            // Kody tego drzewa nie są kanoniczne, ale ich długości dają ten sam rozmiar danych
+            Model::Legacy(frequencies) => legacy_code_tables(frequencies)?
+                .into_iter()
+                .map(|(context, codes)| (context, codes.into_iter().map(|(symbol, code)| (symbol, code.len)).collect()))
+                .collect(),
+            Model::Frequencies(frequencies) => build_code_lengths(frequencies, header.max_code_len)?,
+            Model::CodeLengths(lengths) => lengths.clone(),
+        };
+        let unit = header.unit as usize;
+        let context_len = header.order * unit;
+        for (context, l_table) in &lengths {
+            if context.len() != context_len {
+                return Err(HuffmanError::InvalidOptions(format!(
+                    "tabela: kontekst {:?} ma {} bajtów, a rząd {} przy symbolu {} B wymaga {}",
+                    context,
+                    context.len(),
+                    header.order,
+                    unit,
+                    context_len
+                )));
+            }
+            let escape_entry = |symbol: &Vec<u8>| header.escape && symbol.as_slice() == ESCAPE;
+            if let Some(symbol) = l_table.keys().find(|&s| s.len() != unit && !escape_entry(s)) {
+                return Err(HuffmanError::InvalidOptions(format!(
+                    "tabela: symbol {:?} w kontekście {:?} ma {} bajtów zamiast {}",
+                    symbol,
+                    context,
+                    symbol.len(),
+                    unit
+                )));
+            }
+            // Kody kanoniczne z długości spełniających nierówność Krafta są prefiksowe
+            check_code_lengths(l_table, header.max_code_len).map_err(|reason| {
+                let message = format!("tabela: kody kontekstu {:?} nie są prefiksowe: {}", context, reason);
+                HuffmanError::InvalidOptions(message)
+            })?;
+        }
+        let codes = canonical_code_tables(&lengths);
+        Ok(FixedTable {
+            order: header.order,
+            unit: header.unit,
+            max_code_len: header.max_code_len,
+            escape: header.escape,
+            lengths,
+            codes,
+        })
+    }
+
+    /// Rząd modelu, dla którego zbudowano tabelę.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Szerokość symbolu tabeli w bajtach.
+    pub fn unit(&self) -> u8 {
+        self.unit
+    }
+
+    /// Czy tabela ma kody ucieczki (model v6), czyli koduje każde wejście.
+    pub fn has_escapes(&self) -> bool {
+        self.escape
+    }
+
+    /// Ta sama tabela z kodem ucieczki w każdym kontekście: symbol bez kodu, także w kontekście
+    /// spoza tabeli, jest zapisywany wprost za nim, więc tabela koduje bezstratnie dowolne dane.
+    ///
+    /// Długości kodów są budowane od nowa z częstości wynikających z dotychczasowych długości
+    /// (`2^-len`), z ucieczką jako najrzadszym symbolem: kody znanych symboli wydłużają się
+    /// w sumie o jak najmniej. Tabela, która ma już ucieczki, jest zwracana bez zmian.
+    ///
+    /// ```
+    /// use huffman_format::{FixedTable, Header, Options, compress, decompress};
+    ///
+    /// let reference = compress(b"abracadabra", Options { store_fallback: false, ..Options::default() })?;
+    /// let table = FixedTable::from_header(&Header::parse(&reference)?.0)?;
+    /// assert!(table.check_sample(b"abrakadabra").is_err());
+    ///
+    /// let table = table.with_escapes()?;
+    /// let (packed, _) = table.compress_with_stats(b"abrakadabra", &Options::default())?;
+    /// assert_eq!(decompress(&packed)?, b"abrakadabra");
+    /// # Ok::<(), huffman_format::HuffmanError>(())
+    /// ```
+    pub fn with_escapes(&self) -> Result<FixedTable> {
+        if self.escape {
+            return Ok(self.clone());
+        }
+        let mut frequencies = MarkovFreqTable::new();
+        for (context, l_table) in &self.lengths {
+            let longest = l_table.values().copied().max().unwrap_or(0);
+            let mut f_table: FreqTable =
+                l_table.iter().map(|(symbol, &len)| (symbol.clone(), 1u64 << (longest - len))).collect();
+            f_table.insert(ESCAPE.to_vec(), 1);
+            frequencies.insert(context.clone(), f_table);
+        }
+        let lengths = build_code_lengths(&frequencies, self.max_code_len)?;
+        let longest = lengths.values().flat_map(|l_table| l_table.values().copied()).max().unwrap_or(0);
+        Ok(FixedTable {
+            order: self.order,
+            unit: self.unit,
+            max_code_len: self.max_code_len.max(longest),
+            escape: true,
+            codes: canonical_code_tables(&lengths),
+            lengths,
+        })
+    }
+
+    /// Liczba par (kontekst, symbol) z kodem.
+    pub fn symbols(&self) -> usize {
+        self.lengths.values().map(|l_table| l_table.len()).sum()
+    }
+
+    /// Drzewo kodów kontekstu `context` do przejścia wizytorem; kod ucieczki to liść z pustym
+    /// symbolem ([`ESCAPE`]). `None`, gdy tabela nie ma takiego kontekstu.
+    pub fn tree(&self, context: &[u8]) -> Option<Tree> {
+        let codes = self.codes.get(context)?;
+        Some(Tree::from_codes(codes).expect("kody tabeli są prefiksowe (sprawdzone w from_header)"))
+    }
+
+    /// Sprawdza, czy `options` pasują do tabeli: rząd i szerokość symbolu muszą być te same,
+    /// a przycinanie modelu ([`Options::max_symbols`]) nie ma tu czego przycinać.
+    /// Filtry ([`Options::filters`]) działają jak w zwykłej ramce, ale tabela powinna
+    /// pochodzić z danych po tych samych filtrach.
+    pub fn check_options(&self, options: &Options) -> Result<()> {
+        if options.order.min(255) != self.order {
+            return Err(HuffmanError::InvalidOptions(format!(
+                "tabela ma rząd {}, a wybrano rząd {}",
+                self.order, options.order
+            )));
+        }
+        if options.unit != self.unit {
+            return Err(HuffmanError::InvalidOptions(format!(
+                "tabela ma symbole {}-bajtowe, a wybrano szerokość {}",
+                self.unit, options.unit
+            )));
+        }
+        if options.nibble {
+            return Err(HuffmanError::InvalidOptions("symbole 4-bitowe nie działają z gotową tabelą".to_string()));
+        }
+        if options.max_symbols.is_some() {
+            return Err(HuffmanError::InvalidOptions("max_symbols nie działa z gotową tabelą".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Sprawdza, czy każdy symbol `sample` ma kod: wprost, przez kod ucieczki kontekstu
+    /// albo, w tabeli z ucieczkami, jako symbol zapisany wprost w kontekście spoza tabeli.
+    ///
+    /// Błąd podaje pierwszy symbol bez kodu z jego pozycją oraz liczbę takich symboli w próbce.
+    pub fn check_sample(&self, sample: &[u8]) -> Result<()> {
+        let unit = self.unit as usize;
+        let mut context = vec![0u8; self.order * unit];
+        let mut first = None;
+        let mut missing = 0u64;
+        for (i, chunk) in sample.chunks(unit).enumerate() {
+            let mut padded = [0u8; MAX_UNIT as usize];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            let symbol = &padded[..unit];
+
+            let covered = match self.lengths.get(&context) {
+                Some(l_table) => l_table.contains_key(symbol) || (self.escape && l_table.contains_key(ESCAPE)),
+                None => self.escape,
+            };
+            if !covered {
+                missing += 1;
+                first.get_or_insert_with(|| (i * unit, context.clone(), symbol.to_vec()));
+            }
+            if !context.is_empty() {
+                context.rotate_left(unit);
+                let len = context.len();
+                context[len - unit..].copy_from_slice(symbol);
+            }
+        }
+        match first {
+            None => Ok(()),
+            Some((offset, context, symbol)) => Err(HuffmanError::InvalidOptions(format!(
+                "tabela nie ma kodu dla {} z {} symboli próbki; pierwszy to {:?} w kontekście {:?} (bajt {})",
+                missing,
+                sample.len().div_ceil(unit),
+                symbol,
+                context,
+                offset
+            ))),
+        }
+    }
+
+    /// Jak [`crate::compress_with_stats`], ale kodem z tej tabeli; z `options` używane są
+    /// alfabet, filtry i suma kontrolna, a rząd i szerokość muszą zgadzać się z tabelą.
+    /// Całe wejście (po filtrach) jest sprawdzane ([`FixedTable::check_sample`]) przed zapisaniem czegokolwiek.
+    ///
+    /// Gdy tabela wydłużyłaby dane ponad ich zapis wprost, wynikiem jest, jak przy
+    /// [`Options::store_fallback`], ramka bez tablic z danymi przepisanymi bez kodowania.
+    ///
+    /// ```
+    /// use huffman_format::{FixedTable, Header, Options, compress, decompress};
+    ///
+    /// let options = Options { store_fallback: false, ..Options::default() };
+    /// let text = compress(b"abracadabra", options)?;
+    /// let table = FixedTable::from_header(&Header::parse(&text)?.0)?.with_escapes()?;
+    /// let noise: Vec<u8> = (0..=255).collect();
+    /// let (packed, stats) = table.compress_with_stats(&noise, &Options::default())?;
+    /// assert!(packed.len() <= noise.len() + 33 && stats.payload_len == noise.len());
+    /// assert_eq!(decompress(&packed)?, noise);
+    /// # Ok::<(), huffman_format::HuffmanError>(())
+    /// ```
+    pub fn compress_with_stats(&self, data: &[u8], options: &Options) -> Result<(Vec<u8>, Stats)> {
+        let (out, stats, _) = self.compress_framed(data, options, |header, out| header.write_into(out))?;
+        Ok((out, stats))
+    }
+
+    // Koduje `data` tą tabelą; `write_header` zapisuje przed strumieniem bitów początek ramki.
+    // Dane, którym tabela nie pasuje, idą wprost w zwykłej ramce bez tablic, jak w compress_into
+    pub(crate) fn compress_framed(
+        &self,
+        data: &[u8],
+        options: &Options,
+        write_header: impl FnOnce(&Header, &mut Vec<u8>),
+    ) -> Result<(Vec<u8>, Stats, Coverage)> {
+        let Frame { header, frequencies, coverage, coded, crc } = self.frame(data, options)?;
+        let mut out = Vec::new();
+        write_header(&header, &mut out);
+        let stored = stored_header(&header, out.len(), options);
+        let (header, codes) = match stored {
+            Some(stored) => {
+                out.clear();
+                stored.write_into(&mut out);
+                (stored, &MarkovCodeTable::new())
+            }
+            None => (header, &self.codes),
+        };
+        let header_len = out.len();
+        let mut scratch = EncoderScratch::new();
+        scratch.begin_encode(self.order, self.unit as usize);
+        let mut writer = BitWriter::new(&mut out);
+        let raw_bits = header.escape.then_some(8 * self.unit);
+        scratch.encode_chunk(&coded, codes, self.unit as usize, raw_bits, &mut writer)?;
+        writer.finish()?;
+        let payload_len = out.len() - header_len;
+        write_footer(options.checksum, crc, &mut out);
+
+        let stats = Stats {
+            original_len: header.original_len,
+            header_len,
+            payload_len,
+            footer_len: options.checksum.footer_len(),
+            entropy: conditional_entropy(&frequencies, header.original_len),
+        };
+        Ok((out, stats, coverage))
+    }
+
+    /// Statystyki, jakie dałby [`FixedTable::compress_with_stats`], bez kodowania bitów.
+    pub fn estimate(&self, data: &[u8], options: &Options) -> Result<Stats> {
+        let Frame { header, frequencies, .. } = self.frame(data, options)?;
+        let header_len = header.to_bytes().len();
+        let header = stored_header(&header, header_len, options).unwrap_or(header);
+        let bits = header.payload_bits.expect("nagłówek z liczbą bitów");
+        Ok(Stats {
+            original_len: header.original_len,
+            header_len: header.to_bytes().len(),
+            payload_len: bits.div_ceil(8) as usize,
+            footer_len: options.checksum.footer_len(),
+            entropy: conditional_entropy(&frequencies, header.original_len),
+        })
+    }
+
+    fn frame<'a>(&self, data: &'a [u8], options: &Options) -> Result<Frame<'a>> {
+        if let Some(alphabet) = &options.alphabet {
+            alphabet.check(data)?;
+        }
+        self.check_options(options)?;
+        options.filters.check()?;
+        let (coded, stages) = options.filters.apply(data);
+        self.check_sample(&coded)?;
+
+        let unit = self.unit as usize;
+        let (frequencies, crc) = EncoderScratch::new().count_checked(data, &coded, !stages.is_empty(), options);
+        let raw_bits = 8 * unit as u64;
+        let mut coverage = Coverage::default();
+        for (context, f_table) in &frequencies {
+            let l_table = self.lengths.get(context);
+            for (symbol, &freq) in f_table {
+                // Po check_sample każdy symbol ma kod albo drogę ucieczki
+                let bits = match l_table.map(|l| (l.get(symbol), l.get(ESCAPE))) {
+                    Some((Some(&len), _)) => freq * len as u64,
+                    Some((None, Some(&escape))) => freq * (escape as u64 + raw_bits),
+                    _ => freq * raw_bits,
+                };
+                coverage.symbols += freq;
+                coverage.payload_bits += bits;
+                if !l_table.is_some_and(|l| l.contains_key(symbol)) {
+                    coverage.escaped += freq;
+                    coverage.escape_bits += bits;
+                }
+            }
+        }
+        let header = Header {
+            original_len: data.len() as u64,
+            payload_bits: Some(coverage.payload_bits),
+            checksum: options.checksum,
+            filters: stages,
+            ..self.header()
+        };
+        Ok(Frame { header, frequencies, coverage, coded, crc })
+    }
+
+    /// Nagłówek pustej ramki z tą tabelą: zerowa długość danych i bez sumy kontrolnej.
+    pub(crate) fn header(&self) -> Header {
+        Header {
+            original_len: 0,
+            order: self.order,
+            max_code_len: self.max_code_len,
+            unit: self.unit,
+            payload_bits: Some(0),
+            checksum: ChecksumKind::None,
+            escape: self.escape,
+            nibble: false,
+            filters: Vec::new(),
+            model: Model::CodeLengths(self.lengths.clone()),
+        }
+    }
+}
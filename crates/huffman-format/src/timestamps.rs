@@ -0,0 +1,59 @@
+//! Różnice znaczników czasu w kolejnych liniach logu: [`crate::Filter::Timestamps`].
+//!
+//! Każda cyfra z początku linii staje się różnicą (modulo 10) od cyfry w tej samej kolumnie
+//! poprzedniej linii. Kolejne znaczniki czasu różnią się zwykle tylko ostatnimi cyframi,
+//! więc zostają z nich głównie zera, a model z kontekstem koduje je prawie za darmo.
+//! Cyfry zostają cyframi, a inne bajty się nie zmieniają, więc dekoder wie, które
+//! pozycje odwrócić, bez dodatkowych danych.
+//!
+//! Moduł nie zależy od reszty biblioteki: ten sam plik dołącza `build.rs`, który
+//! przepuszcza przez filtr próbkę wbudowanego słownika `logs`.
+//!
+//! ```
+//! use huffman_format::Filter;
+//!
+//! let log = b"12:00:01 start\n12:00:03 ready\n12:00:09 stop\n";
+//! let filter = Filter::Timestamps { columns: 32 };
+//! let encoded = filter.apply(log);
+//! assert_eq!(encoded, b"12:00:01 start\n00:00:02 ready\n00:00:06 stop\n");
+//! assert_eq!(filter.revert(&encoded, log.len() as u64)?, log);
+//! # Ok::<(), huffman_format::HuffmanError>(())
+//! ```
+
+/// Zamienia cyfry z pierwszych `columns` bajtów każdej linii na różnice od cyfr nad nimi.
+pub fn encode(data: &[u8], columns: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous: &[u8] = &[];
+    for line in data.split_inclusive(|&byte| byte == b'\n') {
+        for (column, &byte) in line.iter().enumerate() {
+            out.push(match previous.get(column) {
+                Some(&above) if column < columns && byte.is_ascii_digit() && above.is_ascii_digit() => {
+                    b'0' + (byte + 10 - above) % 10
+                }
+                _ => byte,
+            });
+        }
+        previous = line;
+    }
+    out
+}
+
+/// Odwraca [`encode`]: cyfry nad pozycją bierze z odtworzonej już poprzedniej linii.
+pub fn decode(data: &[u8], columns: usize) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(data.len());
+    let mut previous = 0..0;
+    for line in data.split_inclusive(|&byte| byte == b'\n') {
+        let start = out.len();
+        for (column, &byte) in line.iter().enumerate() {
+            let above = out[previous.clone()].get(column).copied();
+            out.push(match above {
+                Some(above) if column < columns && byte.is_ascii_digit() && above.is_ascii_digit() => {
+                    b'0' + (byte - b'0' + above - b'0') % 10
+                }
+                _ => byte,
+            });
+        }
+        previous = start..out.len();
+    }
+    out
+}
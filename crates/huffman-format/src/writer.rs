@@ -0,0 +1,189 @@
+use std::io::{self, Write};
+
+use crate::codec::{EncoderScratch, Options, Stats, compress_into};
+use crate::error::Result;
+use crate::events::{Event, EventSink};
+use crate::index::{SeekEntry, SeekTable};
+
+/// Domyślny rozmiar bloku buforowanego przez [`HuffmanWriter`].
+pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Koder strumieniowy: bajty zapisane przez [`Write`] są kompresowane
+/// i przekazywane do `W`.
+///
+/// Model Huffmana wymaga znajomości częstotliwości przed emisją pierwszego bitu,
+/// więc dane są buforowane w blokach po `block_size` bajtów. Każdy pełny blok
+/// trafia do `W` jako samodzielna ramka (nagłówek + bity), a ostatni, niepełny
+/// blok jest zapisywany w [`HuffmanWriter::finish`]. Ramki są po prostu sklejane;
+/// [`crate::decompress`] i [`crate::HuffmanReader`] odczytują je po kolei.
+/// Bez żadnych danych `finish` zapisuje jedną pustą ramkę, jak [`crate::compress`].
+///
+/// [`Write::flush`] zamyka bieżący blok przedwcześnie (kosztem dodatkowego
+/// nagłówka). Jeśli writer zostanie porzucony bez `finish`, resztka jest
+/// zapisywana w `Drop`, a ewentualny błąd ginie.
+pub struct HuffmanWriter<W: Write> {
+    inner: Option<W>,
+    options: Options,
+    block_size: usize,
+    buffer: Vec<u8>,
+    scratch: EncoderScratch,
+    frame: Vec<u8>,
+    stats: Stats,
+    blocks: u64,
+    seek_table: Option<SeekTable>,
+    events: Option<Box<dyn EventSink + Send>>,
+}
+
+impl<W: Write> HuffmanWriter<W> {
+    pub fn new(inner: W, options: Options) -> Self {
+        Self::with_block_size(inner, options, DEFAULT_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(inner: W, options: Options, block_size: usize) -> Self {
+        let block_size = block_size.max(1);
+        HuffmanWriter {
+            inner: Some(inner),
+            options,
+            block_size,
+            buffer: Vec::with_capacity(block_size),
+            scratch: EncoderScratch::new(),
+            frame: Vec::new(),
+            stats: Stats::default(),
+            blocks: 0,
+            seek_table: None,
+            events: None,
+        }
+    }
+
+    /// Dopisuje w [`HuffmanWriter::finish`] tablicę skoków, dzięki której
+    /// [`crate::decompress_range`] dekoduje tylko potrzebne ramki.
+    pub fn with_seek_table(mut self) -> Self {
+        self.seek_table = Some(SeekTable::default());
+        self
+    }
+
+    /// Zgłasza do `sink` każdą zapisaną ramkę ([`Event::BlockEncoded`]) i ramki,
+    /// które nie zmniejszyły danych ([`Event::Warning`]).
+    pub fn with_events<S: EventSink + Send + 'static>(mut self, sink: S) -> Self {
+        self.events = Some(Box::new(sink));
+        self
+    }
+
+    /// Liczba bajtów przyjętych do kompresji.
+    pub fn total_in(&self) -> u64 {
+        self.stats.original_len + self.buffer.len() as u64
+    }
+
+    /// Liczba bajtów zapisanych do `W`.
+    pub fn total_out(&self) -> u64 {
+        (self.stats.header_len + self.stats.payload_len + self.stats.footer_len) as u64
+    }
+
+    /// Liczba zapisanych ramek.
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Statystyki zsumowane po zapisanych ramkach; entropia jest ważona długością bloków.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Zmienia parametry kompresji dla kolejnych bloków (już zbuforowane dane też je dostaną).
+    pub fn set_options(&mut self, options: Options) {
+        self.options = options;
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("writer użyty po finish")
+    }
+
+    /// Kompresuje resztę bufora, dopisuje tablicę skoków (jeśli włączona), opróżnia `W` i go zwraca.
+    pub fn finish(self) -> Result<W> {
+        Ok(self.finish_with_stats()?.0)
+    }
+
+    /// Jak [`HuffmanWriter::finish`], ale zwraca też statystyki obejmujące ostatni blok.
+    pub fn finish_with_stats(mut self) -> Result<(W, Stats)> {
+        self.write_last_block()?;
+        let mut inner = self.inner.take().expect("writer użyty po finish");
+        if let Some(mut table) = self.seek_table.take() {
+            table.total_len = self.stats.original_len;
+            let mut bytes = Vec::with_capacity(table.byte_len());
+            table.write_into(&mut bytes);
+            inner.write_all(&bytes)?;
+        }
+        inner.flush()?;
+        Ok((inner, self.stats))
+    }
+
+    // Zapisuje zbuforowane dane jako jedną ramkę
+    fn write_block(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.write_frame()
+    }
+
+    // Jak write_block, ale strumień bez danych dostaje ramkę z samym nagłówkiem
+    fn write_last_block(&mut self) -> Result<()> {
+        if self.blocks == 0 { self.write_frame() } else { self.write_block() }
+    }
+
+    fn write_frame(&mut self) -> Result<()> {
+        if let Some(alphabet) = &self.options.alphabet {
+            alphabet.check_at(&self.buffer, self.stats.original_len)?;
+        }
+        let entry = SeekEntry {
+            frame_offset: self.total_out(),
+            data_offset: self.stats.original_len,
+        };
+        let block = compress_into(&self.buffer, &self.options, &mut self.scratch, &mut self.frame)?;
+        if let Some(table) = &mut self.seek_table {
+            table.entries.push(entry);
+        }
+        let inner = self.inner.as_mut().expect("writer użyty po finish");
+        inner.write_all(&self.frame)?;
+        if let Some(sink) = &mut self.events {
+            let (n, len) = (self.blocks, self.buffer.len() as u64);
+            sink.event(&Event::block(n, &block));
+            if len > 0 && self.frame.len() as u64 >= len {
+                let message = format!("ramka {} nie zmniejszyła danych ({} bajtów)", n, len);
+                sink.event(&Event::Warning { message });
+            }
+        }
+
+        self.stats.merge(&block);
+        self.blocks += 1;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HuffmanWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.block_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == self.block_size {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.as_mut().expect("writer użyty po finish").flush()
+    }
+}
+
+impl<W: Write> Drop for HuffmanWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.write_last_block();
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use huffman_format::{
+    Archive, ArchiveWriter, Dedup, HuffmanError, HuffmanReader, Member, Options, SolidArchiveWriter, Stats,
+};
+
+// Każdy plik archiwum (także pusty) odczytuje się z katalogu bez dekodowania pozostałych
+#[test]
+fn archive_members_roundtrip() {
+    let code = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let files: [(&str, &[u8]); 3] = [("code.py", &code), ("dir/empty", b""), ("dir/a.txt", b"abracadabra")];
+
+    let paths = files.iter().map(|(path, _)| path.to_string()).collect();
+    let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), paths).unwrap();
+    for (_, data) in &files {
+        writer.add_member(&mut Cursor::new(data), &Options::default()).unwrap();
+    }
+    let (mut packed, _) = writer.finish().unwrap();
+
+    packed.rewind().unwrap();
+    let archive = Archive::read_from(&mut packed).unwrap();
+    assert_eq!(archive.members.len(), files.len());
+    assert_eq!(Archive::try_from(&packed.get_ref()[..]).unwrap(), archive);
+    for (member, (path, data)) in archive.members.iter().zip(files) {
+        assert_eq!(member.path, path);
+        assert_eq!(member.original_len, data.len() as u64);
+        let mut decoded = Vec::new();
+        if member.compressed_len > 0 {
+            packed.seek(SeekFrom::Start(member.offset)).unwrap();
+            let frames = (&mut packed).take(member.compressed_len);
+            HuffmanReader::new(frames).unwrap().read_to_end(&mut decoded).unwrap();
+        }
+        assert_eq!(decoded, data);
+    }
+}
+
+// Ścieżki wychodzące poza katalog docelowy są odrzucane przy zapisie i przy odczycie
+#[test]
+fn archive_rejects_escaping_paths() {
+    for path in ["../x", "/etc/passwd", "a/./b", "C:/x"] {
+        let err = ArchiveWriter::new(Cursor::new(Vec::new()), vec![path.to_string()]).err();
+        assert!(matches!(err, Some(HuffmanError::InvalidOptions(_))), "{}", path);
+    }
+
+    let mut directory = Vec::new();
+    let path = "a/../../x".to_string();
+    let member = Member { path, original_len: 0, offset: 0, compressed_len: 0, batch_offset: 0, blocks: 0 };
+    Archive { members: vec![member], ..Archive::default() }.write_into(&mut directory);
+    assert!(matches!(Archive::read_from(&mut &directory[..]), Err(HuffmanError::MalformedHeader(_))));
+}
+
+// Archiwum solid: jeden strumień, a pliki wyznaczają offsety w zdekodowanych danych
+#[test]
+fn solid_archive_shares_one_stream() {
+    let files: [(&str, &[u8]); 3] =
+        [("a.json", br#"{"id": 1, "ok": true}"#), ("pusty", b""), ("b.json", br#"{"id": 2, "ok": false}"#)];
+    let paths = files.iter().map(|(path, _)| path.to_string()).collect();
+    let mut writer = SolidArchiveWriter::new(Cursor::new(Vec::new()), paths, Options::default()).unwrap();
+    for (_, data) in &files {
+        writer.add_member(&mut &data[..]).unwrap();
+    }
+    let (mut packed, stats) = writer.finish().unwrap();
+    assert_eq!(stats.original_len, files.iter().map(|(_, data)| data.len() as u64).sum::<u64>());
+
+    packed.rewind().unwrap();
+    let archive = Archive::read_from(&mut packed).unwrap();
+    assert!(archive.solid);
+    let mut stream = Vec::new();
+    HuffmanReader::new(&mut packed).unwrap().read_to_end(&mut stream).unwrap();
+    for (member, (path, data)) in archive.members.iter().zip(files) {
+        assert_eq!(member.path, path);
+        let start = member.offset as usize;
+        assert_eq!(&stream[start..start + member.original_len as usize], data);
+    }
+}
+
+// Pliki o tej samej zawartości wskazują w katalogu jedne dane, a każdy odczytuje się jak zwykle
+#[test]
+fn dedup_archive_stores_repeated_members_once() {
+    let license = b"MIT License, abracadabra";
+    let files: [(&str, &[u8]); 4] = [("a/LICENSE", license), ("b/LICENSE", license), ("pusty", b""), ("c", b"abc")];
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.to_string()).collect();
+    let write = |dedup: bool| {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap();
+        if dedup {
+            writer = writer.with_dedup(1024).unwrap();
+        }
+        for (_, data) in &files {
+            writer.add_member(&mut Cursor::new(data), &Options::default()).unwrap();
+        }
+        let shared = writer.dedup();
+        let (packed, stats) = writer.finish().unwrap();
+        (packed.into_inner(), stats, shared)
+    };
+    let (plain, plain_stats, _) = write(false);
+    let (packed, stats, shared) = write(true);
+
+    let archive = Archive::try_from(&packed[..]).unwrap();
+    let [first, second, ..] = &archive.members[..] else { unreachable!() };
+    assert_eq!((first.offset, first.compressed_len), (second.offset, second.compressed_len));
+    assert_eq!(shared, Dedup { members: 1, blocks: 1, saved: first.compressed_len });
+    // Katalog z deduplikacją ma dłuższe wpisy, więc porównujemy dane za nim
+    let data_len = |packed: &[u8]| (packed.len() - Archive::try_from(packed).unwrap().byte_len()) as u64;
+    assert_eq!(data_len(&packed) + shared.saved, data_len(&plain));
+    assert_eq!(stats.original_len, plain_stats.original_len);
+    let written = |stats: &Stats| (stats.header_len + stats.payload_len + stats.footer_len) as u64;
+    assert_eq!(written(&stats) + shared.saved, written(&plain_stats));
+
+    for (member, (_, data)) in archive.members.iter().zip(files) {
+        let start = member.offset as usize;
+        let frames = &packed[start..start + member.compressed_len as usize];
+        let mut decoded = Vec::new();
+        if member.compressed_len > 0 {
+            HuffmanReader::new(frames).unwrap().read_to_end(&mut decoded).unwrap();
+        }
+        assert_eq!(decoded, data);
+    }
+}
+
+// Powtórzone bloki (także w środku większych plików) są zapisane raz, a każde trafienie skrótu
+// jest sprawdzane z bajtami odczytanymi z archiwum
+#[test]
+fn dedup_archive_shares_repeated_blocks() {
+    let log: Vec<u8> = (0..64).flat_map(|i| format!("linia {:03}: status=ok\n", i * 7 % 64).into_bytes()).collect();
+    let edited = [&log[..3 * 256], b"dopisana linia\n"].concat();
+    let files: [(&str, &[u8]); 4] =
+        [("v1.log", &log), ("v2.log", &edited), ("kopia.log", &log), ("glowa", &log[..256])];
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.to_string()).collect();
+    let write = |inner: Forgetful| {
+        let mut writer = ArchiveWriter::new(inner, paths.clone()).unwrap().with_dedup(256).unwrap();
+        for (_, data) in &files {
+            writer.add_member(&mut Cursor::new(data), &Options::default()).unwrap();
+        }
+        let shared = writer.dedup();
+        (writer.finish().unwrap().0 .0.into_inner(), shared)
+    };
+    let (packed, shared) = write(Forgetful(Cursor::new(Vec::new()), false));
+    assert_eq!((shared.members, shared.blocks), (3, 3 + log.len().div_ceil(256) + 1));
+    let archive = Archive::try_from(&packed[..]).unwrap();
+    assert!(archive.deduped);
+    let blocks = log.len().div_ceil(256) as u32;
+    assert_eq!(archive.members.iter().map(|m| m.blocks).collect::<Vec<_>>(), [blocks, 4, blocks, 0]);
+    // Jednoblokowy plik wskazuje wprost pierwszą ramkę z listy bloków v1.log
+    let first = &archive.members[0];
+    let list = first.read_blocks(&mut &packed[first.offset as usize..]).unwrap();
+    assert_eq!((archive.members[3].offset, archive.members[3].compressed_len), list[0]);
+
+    // Odczyt, który nie zwraca zapisanych bajtów, nie potwierdza żadnego trafienia
+    let (unshared, none) = write(Forgetful(Cursor::new(Vec::new()), true));
+    assert_eq!(none, Dedup::default());
+    assert!(packed.len() as u64 + shared.saved <= unshared.len() as u64);
+
+    for packed in [packed, unshared] {
+        let mut packed = Cursor::new(packed);
+        let archive = Archive::read_from(&mut packed).unwrap();
+        for (member, (path, data)) in archive.members.iter().zip(files) {
+            let mut content = Vec::new();
+            archive.read_member_streaming(&mut packed, path).unwrap().read_to_end(&mut content).unwrap();
+            assert_eq!(content, data, "{}", path);
+            let mut frames = Vec::new();
+            let mut reader = HuffmanReader::new(archive.member_frames(&mut packed, member).unwrap()).unwrap();
+            reader.read_to_end(&mut frames).unwrap();
+            assert_eq!(frames, data, "{}", path);
+        }
+    }
+
+    let mut started = ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap();
+    started.add_member(&mut Cursor::new(&log), &Options::default()).unwrap();
+    assert!(matches!(started.with_dedup(256).err(), Some(HuffmanError::InvalidOptions(_))));
+}
+
+// Archiwum w pamięci, którego odczyt może zwracać zera zamiast zapisanych bajtów
+struct Forgetful(Cursor<Vec<u8>>, bool);
+
+impl Read for Forgetful {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.read(buf)?;
+        if self.1 {
+            buf[..n].fill(0);
+        }
+        Ok(n)
+    }
+}
+
+impl Write for Forgetful {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Seek for Forgetful {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+// Małe pliki dzielą ramkę partii, duży i pusty zostają osobno; katalog wskazuje każdy plik
+#[test]
+fn batched_archive_shares_tables_between_small_members() {
+    let big = vec![b'x'; 300];
+    let files: Vec<(String, Vec<u8>)> = (0..6)
+        .map(|i| (format!("m{}.json", i), format!(r#"{{"id": {}, "name": "member {}"}}"#, i, i).into_bytes()))
+        .chain([("pusty".to_string(), Vec::new()), ("duzy".to_string(), big)])
+        .collect();
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.clone()).collect();
+    let write = |batch: Option<u64>| {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap();
+        if let Some(limit) = batch {
+            writer = writer.with_batches(limit).unwrap();
+        }
+        for (_, data) in &files {
+            writer.add_member(&mut Cursor::new(data), &Options::default()).unwrap();
+        }
+        let (packed, stats) = writer.finish().unwrap();
+        (packed.into_inner(), stats)
+    };
+    let (plain, _) = write(None);
+    let (packed, stats) = write(Some(100));
+    assert!(packed.len() < plain.len(), "{} >= {}", packed.len(), plain.len());
+    assert_eq!(stats.original_len, files.iter().map(|(_, data)| data.len() as u64).sum());
+
+    let archive = Archive::try_from(&packed[..]).unwrap();
+    assert!(archive.batched);
+    let frames: std::collections::BTreeSet<_> = archive.members.iter().map(|m| m.offset).collect();
+    // Limit 100 bajtów mieści po trzy pliki JSON: dwie partie oraz osobne ramki pustego i dużego pliku
+    assert_eq!(frames.len(), 4, "{:?}", archive.members);
+    for (member, (_, data)) in archive.members.iter().zip(&files) {
+        let start = member.offset as usize;
+        let mut decoded = Vec::new();
+        if member.compressed_len > 0 {
+            let frame = &packed[start..start + member.compressed_len as usize];
+            HuffmanReader::new(frame).unwrap().read_to_end(&mut decoded).unwrap();
+        }
+        let from = member.batch_offset as usize;
+        assert_eq!(&decoded[from..from + member.original_len as usize], &data[..], "{}", member.path);
+    }
+
+    let mut after_start = ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap();
+    after_start.add_member(&mut Cursor::new(&files[0].1), &Options::default()).unwrap();
+    assert!(matches!(after_start.with_batches(100).err(), Some(HuffmanError::InvalidOptions(_))));
+}
+
+// Narzędzia zewnętrzne czytają pliki po nazwie bez znajomości układu: zwykłe, solid,
+// partie małych plików i powtórzona zawartość
+#[test]
+fn members_stream_by_name_in_every_layout() {
+    let license = b"MIT License, abracadabra";
+    let files: [(&str, &[u8]); 4] = [("a/LICENSE", license), ("b/LICENSE", license), ("pusty", b""), ("c", b"abc")];
+    let paths: Vec<String> = files.iter().map(|(path, _)| path.to_string()).collect();
+    let plain = |mut writer: ArchiveWriter<Cursor<Vec<u8>>>| {
+        for (_, data) in &files {
+            writer.add_member(&mut Cursor::new(data), &Options::default()).unwrap();
+        }
+        writer.finish().unwrap().0
+    };
+    let mut solid = SolidArchiveWriter::new(Cursor::new(Vec::new()), paths.clone(), Options::default()).unwrap();
+    for (_, data) in &files {
+        solid.add_member(&mut Cursor::new(data)).unwrap();
+    }
+    let layouts = [
+        plain(ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap()),
+        plain(ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap().with_dedup(1024).unwrap()),
+        plain(ArchiveWriter::new(Cursor::new(Vec::new()), paths.clone()).unwrap().with_batches(1024).unwrap()),
+        solid.finish().unwrap().0,
+    ];
+    for (layout, mut packed) in layouts.into_iter().enumerate() {
+        packed.set_position(0);
+        let archive = Archive::read_from(&mut packed).unwrap();
+        let infos: Vec<_> = archive.members().collect();
+        assert_eq!(infos.iter().map(|m| m.path).collect::<Vec<_>>(), paths);
+        // Tylko zwykłe archiwum ma osobne dane każdego pliku
+        assert_eq!(infos[0].compressed_len.is_some(), layout == 0, "układ {}", layout);
+        for (path, data) in files {
+            let mut content = Vec::new();
+            archive.read_member_streaming(&mut packed, path).unwrap().read_to_end(&mut content).unwrap();
+            assert_eq!(content, data, "układ {}: {}", layout, path);
+        }
+        let missing = archive.read_member_streaming(&mut packed, "brak").err();
+        assert!(matches!(missing, Some(HuffmanError::InvalidOptions(_))));
+
+        // Ucięte archiwum daje błąd zamiast krótszego pliku
+        let mut cut = packed.into_inner();
+        cut.truncate(cut.len() - 6);
+        let mut cut = Cursor::new(cut);
+        let read = archive.read_member_streaming(&mut cut, "c").and_then(|mut member| {
+            member.read_to_end(&mut Vec::new())?;
+            Ok(())
+        });
+        assert!(read.is_err(), "układ {}", layout);
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::Path;
+
+use huffman_format::checksum::crc32;
+use huffman_format::huffman::{CodeLengths, MarkovCodeLengths};
+use huffman_format::testdata::{Corpus, DEFAULT_SEED};
+use huffman_format::{ChecksumKind, ESCAPE, Header, Model, Options, compress, decompress};
+
+// Dekoduje każde archiwum wzorcowe ze wszystkich katalogów wersji w tests/compat
+#[test]
+fn golden_archives_still_decode() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat");
+    let mut checked = 0;
+
+    for version_dir in fs::read_dir(&root).unwrap() {
+        let version_dir = version_dir.unwrap().path();
+        if !version_dir.is_dir() || version_dir.ends_with("originals") {
+            continue;
+        }
+
+        for entry in fs::read_dir(&version_dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+            let Some(stem) = name.strip_suffix(".huff") else { continue };
+            let (original_name, _order) = stem.rsplit_once(".o").unwrap();
+
+            let original = fs::read(root.join("originals").join(original_name)).unwrap();
+            let decoded = decompress(&fs::read(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!(decoded, original, "{}", path.display());
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 0, "brak archiwów wzorcowych");
+}
+
+// Korpusy syntetyczne muszą być identyczne na każdej maszynie, inaczej wyniki
+// `huff bench` nie byłyby porównywalne; sumy przypinają dokładne bajty
+#[test]
+fn synthetic_corpora_are_reproducible() {
+    let expected = [
+        (Corpus::Zipf, 0x5ec8_e0c7),
+        (Corpus::Markov, 0xc982_db08),
+        (Corpus::Runs, 0x3437_ef6e),
+        (Corpus::Random, 0x277c_86ac),
+    ];
+    for (corpus, checksum) in expected {
+        let data = corpus.generate(100_000, DEFAULT_SEED);
+        assert_eq!(data.len(), 100_000);
+        assert_eq!(crc32(&data), checksum, "{}", corpus);
+        assert_ne!(data, corpus.generate(100_000, DEFAULT_SEED + 1), "{}", corpus);
+
+        let packed = compress(&data, Options { order: 1, ..Options::default() }).unwrap();
+        assert_eq!(decompress(&packed).unwrap(), data, "{}", corpus);
+    }
+}
+
+// Wersja odczytana z pierwszych bajtów zgadza się z katalogiem pliku wzorcowego
+#[test]
+fn golden_archives_report_their_version() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat");
+    for version in [0, 1, 2, 3, 4, 5, 7u8] {
+        for entry in fs::read_dir(root.join(format!("v{}", version))).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "huff") {
+                assert_eq!(Header::peek_version(&fs::read(&path).unwrap()), Some(version), "{}", path.display());
+            }
+        }
+    }
+    assert_eq!(Header::peek_version(b"HARC"), None);
+}
+
+// Pliki pierwszego enkodera: układ v0 bez bajtu limitu, a drzewo z par w kolejności z pliku
+#[test]
+fn legacy_frames_keep_their_pair_order() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/legacy");
+    for entry in fs::read_dir(root).unwrap() {
+        let bytes = fs::read(entry.unwrap().path()).unwrap();
+        let (header, offset) = Header::parse(&bytes).unwrap();
+        assert!(matches!(header.model, Model::Legacy(_)));
+        assert_eq!(header.to_bytes(), bytes[..offset]);
+    }
+
+    // b (1) schodzi ze sterty pierwsze i dostaje 0, a (2) dostaje 1: "aab" to 1 1 0
+    let mut frame = 3u64.to_be_bytes().to_vec();
+    frame.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 2]);
+    for (symbol, freq) in [(b'a', 2u64), (b'b', 1)] {
+        frame.push(symbol);
+        frame.extend_from_slice(&freq.to_be_bytes());
+    }
+    frame.push(0b1100_0000);
+    assert_eq!(decompress(&frame).unwrap(), b"aab");
+}
+
+// Ramka złożona ręcznie według opisu formatu: kody kanoniczne wynikają z samych długości,
+// więc niezależny dekoder odtworzy je bez wiedzy, jak enkoder budował drzewo
+#[test]
+fn canonical_codes_follow_from_lengths() {
+    let frame = |lengths: &[(&[u8], u8)], original_len, payload_bits, payload: &[u8]| {
+        let l_table: CodeLengths = lengths.iter().map(|&(symbol, len)| (symbol.to_vec(), len)).collect();
+        let header = Header {
+            original_len,
+            order: 0,
+            max_code_len: 32,
+            unit: 1,
+            payload_bits: Some(payload_bits),
+            checksum: ChecksumKind::None,
+            escape: l_table.contains_key(ESCAPE),
+            nibble: false,
+            filters: Vec::new(),
+            model: Model::CodeLengths(MarkovCodeLengths::from([(Vec::new(), l_table)])),
+        };
+        decompress(&[header.to_bytes(), payload.to_vec()].concat()).unwrap()
+    };
+
+    // a=0, b=10, c=110, d=111: "abacad" to 0 10 0 110 0 111
+    let lengths: [(&[u8], u8); 4] = [(b"d", 3), (b"c", 3), (b"b", 2), (b"a", 1)];
+    assert_eq!(frame(&lengths, 6, 11, &[0b0100_1100, 0b1110_0000]), b"abacad");
+
+    // Kod ucieczki przed 'b' tej samej długości: a=0, ucieczka=10, b=11, potem 'z' na 8 bitach
+    let lengths: [(&[u8], u8); 3] = [(b"b", 2), (ESCAPE, 2), (b"a", 1)];
+    assert_eq!(frame(&lengths, 3, 13, &[0b0111_0011, 0b1101_0000]), b"abz");
+}
@@ -0,0 +1,642 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::path::Path;
+
+use huffman_format::{
+    AdaptiveCodec, ChecksumKind, Codec, DecodeOptions, Event, Filter, FilterChain, HuffmanCodec, HuffmanEncoder,
+    HuffmanError, HuffmanReader, HuffmanWriter, Header, LineCheckpoint, LineIndex, Lz77Codec, Lz77Options, Metadata,
+    Options, Phase, compress, compress_lz77, compress_optimal_split, compress_parallel, compress_stream,
+    compress_stream_with_events, compress_with_stats, decompress, decompress_lz77, decompress_parallel,
+    decompress_range, decompress_with_frequencies, estimate, frame_index, read_metadata, set_metadata, store_stream,
+};
+use huffman_format::testdata::{Corpus, Rng};
+
+// Wiele ramek z HuffmanWriter musi dać się odczytać zarówno w pamięci, jak i strumieniowo
+#[test]
+fn writer_frames_roundtrip() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let options = Options { order: 1, ..Options::default() };
+
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), options, 300);
+    for piece in original.chunks(77) {
+        writer.write_all(piece).unwrap();
+    }
+    let packed = writer.finish().unwrap();
+
+    assert_eq!(decompress(&packed).unwrap(), original);
+
+    let mut streamed = Vec::new();
+    HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, original);
+}
+
+// W tekście szesnastkowym po każdym znaku może wystąpić każdy z 16, a po półbajcie tylko kilka
+// półbajtów: tablica kodów jest kilka razy mniejsza, a każda ścieżka kodowania daje ramki v7
+#[test]
+fn nibble_symbols_roundtrip() {
+    let hex: Vec<u8> = (0..4000u32)
+        .flat_map(|i| format!("{:02x}", (i.wrapping_mul(2_654_435_761) >> 13) as u8).into_bytes())
+        .collect();
+    let bytes = Options { order: 1, ..Options::default() };
+    let nibbles = Options { nibble: true, ..bytes };
+    let (packed, stats) = compress_with_stats(&hex, &nibbles).unwrap();
+    let full = compress_with_stats(&hex, &bytes).unwrap().1;
+    assert!(stats.header_len * 4 < full.header_len, "{:?} {:?}", stats, full);
+    assert_eq!(Header::parse(&packed).unwrap().0.version(), 7);
+    let estimated = estimate(&hex, &nibbles).unwrap();
+    assert_eq!((estimated.header_len, estimated.payload_len), (stats.header_len, stats.payload_len));
+    assert_eq!(decompress(&packed).unwrap(), hex);
+    // Entropia jest w bitach na bajt wejścia niezależnie od szerokości symbolu
+    let pairs = compress_with_stats(&hex, &Options { unit: 2, ..bytes }).unwrap().1;
+    for stats in [stats, pairs] {
+        assert!(stats.entropy <= 8.0 && stats.ideal_payload_len() <= stats.payload_len as f64, "{:?}", stats);
+    }
+
+    let mut streamed = Vec::new();
+    compress_stream(&mut Cursor::new(&hex), &mut streamed, &nibbles).unwrap();
+    assert_eq!(streamed, packed);
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), nibbles, 999);
+    writer.write_all(&hex).unwrap();
+    let framed = writer.finish().unwrap();
+    let mut decoded = Vec::new();
+    HuffmanReader::new(&framed[..]).unwrap().read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, hex);
+    assert_eq!(decompress_parallel(&framed, &DecodeOptions::default(), 3).unwrap(), hex);
+
+    // Z kodami ucieczki symbole spoza tablicy zajmują 4 bity
+    let pruned = Options { order: 2, max_symbols: Some(40), ..nibbles };
+    let packed = compress(&hex, pruned).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), hex);
+    assert!(compress(&hex, Options { nibble: true, unit: 2, ..Options::default() }).is_err());
+}
+
+// Puste wejście to ramka z samym nagłówkiem, także obok ramek z danymi
+#[test]
+fn empty_input_roundtrip() {
+    let options = Options { order: 2, ..Options::default() };
+    let empty = compress(b"", options).unwrap();
+    assert_eq!(decompress(&empty).unwrap(), b"");
+    assert_eq!(compress_parallel(b"", &options, 64, 2).unwrap().0, empty);
+    assert_eq!(compress_optimal_split(b"", &options, 64, 4).unwrap().0, empty);
+    assert_eq!(HuffmanWriter::new(Vec::new(), options).finish().unwrap(), empty);
+
+    let mut streamed = Vec::new();
+    compress_stream_with_events(&mut Cursor::new(b""), &mut streamed, &options, &mut |_: &Event| {}).unwrap();
+    assert_eq!(decompress(&streamed).unwrap(), b"");
+
+    let mut packed = empty.clone();
+    packed.extend(compress(b"abracadabra", options).unwrap());
+    packed.extend(&empty);
+    let mut decoded = Vec::new();
+    HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, b"abracadabra");
+    assert_eq!(frame_index(&mut Cursor::new(&packed)).unwrap().len(), 3);
+}
+
+// Jedyny symbol w kontekście dostaje kod 1-bitowy, więc seria zer zajmuje bit na bajt
+// (bez zapisu wprost, który dla jednego bajtu dałby krótszą ramkę)
+#[test]
+fn single_symbol_input_roundtrip() {
+    for data in [vec![0u8], vec![0u8; 1000], vec![b'x'; 4097]] {
+        for (order, unit) in [(0, 1), (2, 1), (1, 2)] {
+            let options = Options { order, unit, store_fallback: false, ..Options::default() };
+            let (packed, stats) = compress_with_stats(&data, &options).unwrap();
+            assert_eq!(stats.payload_len, data.len().div_ceil(8 * unit as usize));
+            assert_eq!(decompress(&packed).unwrap(), data);
+
+            let mut streamed = Vec::new();
+            HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+            assert_eq!(streamed, data);
+        }
+    }
+}
+
+// Dane bez redundancji nie rosną o tabelę kodów: ramka zapisuje symbole wprost, a dekoder
+// czyta ją jak model z samymi kodami ucieczki
+#[test]
+fn incompressible_input_is_stored() {
+    let data = Corpus::Random.generate(4001, 7);
+    let constant_time = DecodeOptions { constant_time: true, ..DecodeOptions::default() };
+    for options in [
+        Options { order: 1, ..Options::default() },
+        Options { unit: 2, ..Options::default() },
+        Options { nibble: true, ..Options::default() },
+    ] {
+        let (packed, stats) = compress_with_stats(&data, &options).unwrap();
+        assert!(packed.len() <= data.len() + 40, "{} -> {}", data.len(), packed.len());
+        let (header, _) = Header::parse(&packed).unwrap();
+        assert!(header.escape && header.table_entries() == 0, "{:?}", options);
+        let estimated = estimate(&data, &options).unwrap();
+        assert_eq!((estimated.header_len, estimated.payload_len), (stats.header_len, stats.payload_len));
+        assert_eq!(decompress(&packed).unwrap(), data);
+        let mut decoded = Vec::new();
+        HuffmanReader::with_options(&packed[..], constant_time).unwrap().read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, data);
+
+        let mut streamed = Vec::new();
+        compress_stream(&mut Cursor::new(&data), &mut streamed, &options).unwrap();
+        assert_eq!(streamed, packed);
+        // Kopia bez zliczania (--skip-incompressible) daje tę samą ramkę
+        let mut copied = Vec::new();
+        let copy_stats = store_stream(&mut Cursor::new(&data), &mut copied, &options).unwrap();
+        assert_eq!(copied, packed);
+        assert_eq!((copy_stats.header_len, copy_stats.payload_len), (stats.header_len, stats.payload_len));
+        let coded = compress(&data, Options { store_fallback: false, ..options }).unwrap();
+        assert!(coded.len() > packed.len());
+    }
+}
+
+// Filtr RLE z nagłówka odwracają wszystkie dekodery, także przy wielu ramkach
+#[test]
+fn rle_filter_roundtrips_through_every_decoder() {
+    let data = Corpus::Runs.generate(20_000, 3);
+    let options = Options { filters: Filter::Rle.into(), ..Options::default() };
+    let (packed, stats) = compress_with_stats(&data, &options).unwrap();
+    assert!(packed.len() * 3 < compress(&data, Options::default()).unwrap().len());
+    assert_eq!(stats.original_len, data.len() as u64);
+    let estimated = estimate(&data, &options).unwrap();
+    assert_eq!((estimated.header_len, estimated.payload_len), (stats.header_len, stats.payload_len));
+
+    let (header, _) = Header::parse(&packed).unwrap();
+    assert_eq!(header.original_len, data.len() as u64);
+    assert!(matches!(header.filters[..], [(Filter::Rle, coded)] if coded == header.coded_len() && coded < 5000));
+    assert_eq!(decompress(&packed).unwrap(), data);
+    let mut streamed = Vec::new();
+    let small_chunks = DecodeOptions { input_chunk: Some(7), ..DecodeOptions::default() };
+    HuffmanReader::with_options(&packed[..], small_chunks).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, data);
+
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), options, 3000);
+    writer.write_all(&data).unwrap();
+    let blocks = writer.finish().unwrap();
+    assert_eq!(decompress(&blocks).unwrap(), data);
+    assert_eq!(decompress_parallel(&blocks, &DecodeOptions::default(), 3).unwrap(), data);
+    let mut streamed = Vec::new();
+    HuffmanReader::new(&blocks[..]).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, data);
+
+    assert_eq!(decompress(&compress(b"", options).unwrap()).unwrap(), b"");
+    // Dwa przebiegi po strumieniu nie mają gdzie trzymać danych po filtrze
+    let err = compress_stream(&mut Cursor::new(&data), &mut Vec::new(), &options).unwrap_err();
+    assert!(matches!(err, HuffmanError::InvalidOptions(_)), "{}", err);
+}
+
+// BWT z move-to-front ściska tekst lepiej niż sam model rzędu 0 i wraca do oryginału
+// także dla danych okresowych, w których wiele obrotów jest równych
+#[test]
+fn bwt_filter_beats_plain_order_zero_on_text() {
+    let text = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let options = Options { filters: Filter::Bwt.into(), ..Options::default() };
+    let packed = compress(&text, options).unwrap();
+    assert!(packed.len() * 10 < compress(&text, Options::default()).unwrap().len() * 8);
+    assert_eq!(decompress(&packed).unwrap(), text);
+    let mut streamed = Vec::new();
+    HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, text);
+
+    let periodic: Vec<u8> = b"abcab".iter().copied().cycle().take(5000).collect();
+    for data in [&b""[..], b"x", &[0u8; 3000], &periodic, &Corpus::Random.generate(5000, 11)] {
+        let packed = compress(data, options).unwrap();
+        assert_eq!(decompress(&packed).unwrap(), data, "{} bajtów", data.len());
+    }
+}
+
+// Różnice próbek wolnego sygnału są małe, więc kodują się dużo krócej niż same próbki
+#[test]
+fn delta_filter_flattens_slow_signals() {
+    // Sygnał z szumem, żeby próbki się nie powtarzały
+    let mut rng = Rng::new(9);
+    let mut wave: Vec<u8> = (0..20_000)
+        .map(|i| (i as f64 / 300.0).sin() * 12_000.0 + rng.below(16) as f64)
+        .flat_map(|sample| (sample as i16).to_le_bytes())
+        .collect();
+    wave.push(0x7F);
+    let plain = Options { unit: 2, ..Options::default() };
+    let delta = Options { filters: "delta:2".parse().unwrap(), ..plain };
+    let packed = compress(&wave, delta).unwrap();
+    assert!(packed.len() * 2 < compress(&wave, plain).unwrap().len());
+    assert_eq!(decompress(&packed).unwrap(), wave);
+    assert_eq!(Header::parse(&packed).unwrap().0.filters, [(Filter::Delta { width: 2 }, wave.len() as u64)]);
+
+    let data = Corpus::Markov.generate(3001, 5);
+    for width in [1, 2, 4] {
+        let filter = Filter::Delta { width };
+        assert_eq!(filter.to_string().parse::<Filter>().unwrap(), filter);
+        assert_eq!(filter.revert(&filter.apply(&data), data.len() as u64).unwrap(), data);
+    }
+    assert_eq!("delta".parse::<Filter>().unwrap(), Filter::Delta { width: 1 });
+    assert!("delta:3".parse::<Filter>().is_err());
+    let invalid = Options { filters: Filter::Delta { width: 3 }.into(), ..Options::default() };
+    let err = compress(&data, invalid).unwrap_err();
+    assert!(matches!(err, HuffmanError::InvalidOptions(_)), "{}", err);
+}
+
+// Znaczniki czasu kolejnych linii różnią się ostatnimi cyframi, więc po filtrze timestamps
+// log koduje się krócej, a dane z cyframi w przypadkowych miejscach wracają bez zmian
+#[test]
+fn timestamps_filter_shrinks_log_frames() {
+    let log: Vec<u8> = (0..3000u32)
+        .map(|i| format!("2024-06-11 09:{:02}:{:02}.{:03} GET /items/{} 200\n", i / 60, i % 60, i * 7 % 1000, i % 97))
+        .flat_map(String::into_bytes)
+        .collect();
+    let plain = Options { order: 1, ..Options::default() };
+    let filtered = Options { filters: "timestamps".parse().unwrap(), ..plain };
+    let packed = compress(&log, filtered).unwrap();
+    let unfiltered = compress(&log, plain).unwrap();
+    assert!(packed.len() * 10 < unfiltered.len() * 9, "{} / {}", packed.len(), unfiltered.len());
+    assert_eq!(decompress(&packed).unwrap(), log);
+    assert_eq!("timestamps".parse::<Filter>().unwrap(), Filter::Timestamps { columns: 32 });
+    assert!("timestamps:0".parse::<Filter>().is_err());
+
+    let mut mixed = Corpus::Random.generate(5000, 6);
+    for (i, byte) in mixed.iter_mut().enumerate() {
+        match i % 37 {
+            0 => *byte = b'\n',
+            n if n % 3 == 0 => *byte = b'0' + *byte % 10,
+            _ => {}
+        }
+    }
+    for columns in [1, 8, 255] {
+        let filter = Filter::Timestamps { columns };
+        assert_eq!(filter.revert(&filter.apply(&mixed), mixed.len() as u64).unwrap(), mixed);
+    }
+}
+
+// Suma kontrolna dotyczy oryginału, więc zmieniony parametr filtra, który daje dane tej samej
+// długości, oba dekodery zgłaszają zamiast oddać inne dane
+#[test]
+fn tampered_filter_param_fails_checksum() {
+    let data = Corpus::Markov.generate(2000, 4);
+    let packed = compress(&data, Options { filters: "delta:1".parse().unwrap(), ..Options::default() }).unwrap();
+    // Etap w nagłówku: identyfikator delta, szerokość i długość danych po filtrze
+    let mut stage = vec![3, 1];
+    stage.extend_from_slice(&(data.len() as u64).to_be_bytes());
+    let pos = packed.windows(stage.len()).position(|w| w == stage).unwrap();
+    let mut tampered = packed.clone();
+    tampered[pos + 1] = 2;
+
+    let err = decompress(&tampered).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+    let mut streamed = Vec::new();
+    let err = HuffmanError::from(HuffmanReader::new(&tampered[..]).unwrap().read_to_end(&mut streamed).unwrap_err());
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+    assert!(streamed.is_empty());
+}
+
+// Łańcuch filtrów zapisuje w nagłówku każdy etap z jego długością, a dekodery odwracają
+// etapy od ostatniego
+#[test]
+fn filter_chain_reverts_stages_in_reverse_order() {
+    // Próbki rosnące schodkami: różnice to długie serie zer przerywane skokami
+    let steps: Vec<u8> = (0..30_000u32).flat_map(|i| ((i / 500 * 37) as u16).to_le_bytes()).collect();
+    let chain: FilterChain = "delta:2,rle".parse().unwrap();
+    assert_eq!(chain.iter().collect::<Vec<_>>(), [Filter::Delta { width: 2 }, Filter::Rle]);
+    let options = Options { filters: chain, ..Options::default() };
+    let packed = compress(&steps, options).unwrap();
+    let single = compress(&steps, Options { filters: Filter::Delta { width: 2 }.into(), ..options }).unwrap();
+    assert!(packed.len() * 4 < single.len(), "{} / {}", packed.len(), single.len());
+
+    let (header, _) = Header::parse(&packed).unwrap();
+    assert!(matches!(header.filters[..], [(Filter::Delta { width: 2 }, 60_000), (Filter::Rle, coded)] if coded < 5000));
+    assert_eq!(decompress(&packed).unwrap(), steps);
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), options, 7000);
+    writer.write_all(&steps).unwrap();
+    let blocks = writer.finish().unwrap();
+    let mut streamed = Vec::new();
+    HuffmanReader::new(&blocks[..]).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, steps);
+
+    let text = Corpus::Markov.generate(4000, 2);
+    let packed = compress(&text, Options { filters: "bwt,rle".parse().unwrap(), ..Options::default() }).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), text);
+    assert_eq!("bwt, delta:4".parse::<FilterChain>().unwrap().to_string(), "bwt,delta:4");
+    assert!("rle,rle,rle,rle,rle".parse::<FilterChain>().is_err());
+    assert!("rle,lz".parse::<FilterChain>().is_err());
+
+    // Identyfikator spoza rejestru to uszkodzony nagłówek, a nie inne dane
+    let header_bytes = header.to_bytes();
+    let mut stage = vec![3, 2];
+    stage.extend_from_slice(&60_000u64.to_be_bytes());
+    let at = header_bytes.windows(stage.len()).position(|w| w == stage).unwrap();
+    let mut forged = compress(&steps, options).unwrap();
+    forged[at] = 200;
+    let err = decompress(&forged).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::MalformedHeader(_)) && err.to_string().contains("200"), "{}", err);
+}
+
+// Indeks ramek pozwala zdekodować dowolną ramkę bez czytania poprzednich
+#[test]
+fn frame_index_locates_blocks() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 500);
+    writer.write_all(&original).unwrap();
+    let packed = writer.finish().unwrap();
+
+    let frames = frame_index(&mut Cursor::new(&packed)).unwrap();
+    assert_eq!(frames.len(), original.len().div_ceil(500));
+    assert_eq!(frames.iter().map(|f| f.len).sum::<u64>(), packed.len() as u64);
+
+    let last = frames.last().unwrap();
+    let tail = decompress(&packed[last.offset as usize..]).unwrap();
+    assert_eq!(tail, original[original.len() - last.original_len as usize..]);
+}
+
+// Wynik kodowania równoległego nie zależy od liczby wątków i jest zgodny z dekoderem sekwencyjnym
+#[test]
+fn parallel_blocks_roundtrip() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let options = Options { order: 1, ..Options::default() };
+
+    let (single, _) = compress_parallel(&original, &options, 400, 1).unwrap();
+    let (packed, stats) = compress_parallel(&original, &options, 400, 3).unwrap();
+    assert_eq!(single, packed);
+    assert_eq!(stats.original_len, original.len() as u64);
+
+    assert_eq!(decompress(&packed).unwrap(), original);
+    assert_eq!(decompress_parallel(&packed, &DecodeOptions::default(), 3).unwrap(), original);
+}
+
+#[test]
+fn line_index_roundtrip() {
+    let point = |line, frame_offset| LineCheckpoint { line, frame_offset, data_offset: line * 40 };
+    let index = LineIndex { interval: 100, checkpoints: vec![point(0, 0), point(100, 812), point(200, 1630)] };
+
+    let read = LineIndex::read_from(&mut &index.to_bytes()[..]).unwrap();
+    assert_eq!(read, index);
+    assert_eq!(read.checkpoint_for(99).map(|p| p.line), Some(0));
+    assert_eq!(read.checkpoint_for(100).map(|p| p.line), Some(100));
+    assert_eq!(read.checkpoint_for(5000).map(|p| p.line), Some(200));
+}
+
+// Tablica skoków nie przeszkadza zwykłym dekoderom, a zakres dekoduje się z samych potrzebnych ramek
+#[test]
+fn seek_table_range_decode() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 256).with_seek_table();
+    writer.write_all(&original).unwrap();
+    let packed = writer.finish().unwrap();
+
+    assert_eq!(decompress(&packed).unwrap(), original);
+    let mut streamed = Vec::new();
+    HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, original);
+    assert_eq!(frame_index(&mut Cursor::new(&packed)).unwrap().len(), original.len().div_ceil(256));
+
+    for (start, len) in [(0, 10), (250, 300), (1000, 1), (original.len() as u64 - 5, 100)] {
+        let range = decompress_range(&mut Cursor::new(&packed), start, len).unwrap();
+        let end = (start + len).min(original.len() as u64) as usize;
+        assert_eq!(range, original[start as usize..end]);
+    }
+}
+
+// Zmiana metadanych przepisuje tylko koniec pliku: ramki, tablica skoków i dekodery zostają nietknięte
+#[test]
+fn metadata_rewrites_only_the_tail() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 256).with_seek_table();
+    writer.write_all(&original).unwrap();
+    let packed = writer.finish().unwrap();
+    let frames = frame_index(&mut Cursor::new(&packed)).unwrap();
+    let frames_end = frames.last().map(|f| (f.offset + f.len) as usize).unwrap();
+
+    let mut file = Cursor::new(packed.clone());
+    let name = Some("code.py".to_string());
+    let mut metadata = Metadata { comment: Some("próbka".to_string()), name, ..Default::default() };
+    for round in 0..3 {
+        let len = set_metadata(&mut file, &metadata).unwrap();
+        file.get_mut().truncate(len as usize);
+        assert_eq!(read_metadata(&mut file).unwrap(), metadata);
+        let edited = file.get_ref();
+        assert_eq!(edited[..frames_end], packed[..frames_end], "runda {}", round);
+        assert_eq!(frame_index(&mut Cursor::new(edited)).unwrap(), frames);
+        assert_eq!(decompress(edited).unwrap(), original);
+        let mut streamed = Vec::new();
+        HuffmanReader::new(&edited[..]).unwrap().read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, original);
+        assert_eq!(decompress_range(&mut Cursor::new(edited), 250, 300).unwrap(), original[250..550]);
+
+        match round {
+            0 => metadata.comment = Some("dłuższy komentarz, który zajmuje więcej miejsca".to_string()),
+            _ => metadata = Metadata::default(),
+        }
+    }
+    // Pusty zestaw usuwa blok, więc plik wraca do postaci sprzed zmian
+    assert_eq!(file.get_ref(), &packed);
+
+    let mut trailing = packed[..frames_end].to_vec();
+    trailing.extend_from_slice(&Metadata { name: Some("a".to_string()), ..Default::default() }.to_bytes());
+    trailing.extend_from_slice(&packed[..frames.first().unwrap().len as usize]);
+    let err = decompress(&trailing).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::MalformedHeader(_)), "{}", err);
+    assert!(frame_index(&mut Cursor::new(&trailing)).is_err());
+}
+
+// Liczności z dekodera to te same liczby, które dałoby zliczenie całego wyniku
+#[test]
+fn decoder_reports_symbol_frequencies() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options { unit: 2, ..Options::default() }, 300);
+    writer.write_all(&original).unwrap();
+    let packed = writer.finish().unwrap();
+
+    let (decoded, frequencies) = decompress_with_frequencies(&packed, &DecodeOptions::default()).unwrap();
+    assert_eq!(decoded, original);
+    let mut expected = HashMap::new();
+    for block in original.chunks(300) {
+        for symbol in block.chunks(2) {
+            let mut symbol = symbol.to_vec();
+            symbol.resize(2, 0);
+            *expected.entry(symbol).or_insert(0u64) += 1;
+        }
+    }
+    assert_eq!(frequencies, expected);
+}
+
+// Dane o dwóch różnych rozkładach: podział na granicy rozkładów wygrywa z jedną ramką
+#[test]
+fn optimal_split_beats_single_frame() {
+    let mut data = b"aaaaaaabbbc".repeat(300);
+    data.extend((0..3000u32).map(|i| b"xyz"[(i * 7 % 3) as usize]));
+
+    let (packed, stats) = compress_optimal_split(&data, &Options::default(), 512, 16).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), data);
+    assert_eq!(stats.original_len, data.len() as u64);
+    assert!(packed.len() < compress(&data, Options::default()).unwrap().len());
+    assert!(frame_index(&mut Cursor::new(&packed)).unwrap().len() > 1);
+}
+
+// Początek pliku da się odczytać, zanim dekoder dojdzie do (tu uszkodzonej) końcówki
+#[test]
+fn reader_yields_prefix_progressively() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 500);
+    writer.write_all(&original).unwrap();
+    let mut packed = writer.finish().unwrap();
+    let last = packed.len() - 1;
+    packed[last] ^= 0xff;
+
+    let mut prefix = Vec::new();
+    HuffmanReader::new(&packed[..]).unwrap().take(600).read_to_end(&mut prefix).unwrap();
+    assert_eq!(prefix, original[..600]);
+}
+
+// Z verify_blocks uszkodzona ramka nie oddaje żadnego bajtu, a poprzednie są oddane w całości
+#[test]
+fn reader_verifies_blocks_before_output() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 500);
+    writer.write_all(&original).unwrap();
+    let mut packed = writer.finish().unwrap();
+    let clean = packed.clone();
+    let frames = frame_index(&mut Cursor::new(&packed)).unwrap();
+    // Ostatni bajt stopki trzeciej ramki: dane ramki dekodują się poprawnie, nie zgadza się tylko suma
+    let third = &frames[2];
+    packed[(third.offset + third.len - 1) as usize] ^= 0x01;
+
+    let read_until_error = |options: DecodeOptions| {
+        let mut reader = HuffmanReader::with_options(&packed[..], options).unwrap();
+        let (mut out, mut buf) = (Vec::new(), [0u8; 64]);
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => panic!("uszkodzona ramka przeszła"),
+                Ok(n) => out.extend_from_slice(&buf[..n]),
+                Err(_) => return out,
+            }
+        }
+    };
+    let options = DecodeOptions { verify_blocks: true, ..DecodeOptions::default() };
+    assert_eq!(read_until_error(options), original[..1000]);
+    assert!(read_until_error(DecodeOptions::default()).len() > 1000);
+
+    let mut all = Vec::new();
+    HuffmanReader::with_options(&clean[..], options).unwrap().read_to_end(&mut all).unwrap();
+    assert_eq!(all, original);
+}
+
+// Każda ramka jest zgłaszana raz, z numerem i długościami zgodnymi z indeksem ramek;
+// minima z entropii ramek sumują się do minimum ze statystyk całego strumienia
+#[test]
+fn writer_reports_encoded_blocks() {
+    let original = b"abracadabra ".repeat(100);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 500)
+        .with_events(move |event: &Event| sink.lock().unwrap().push(event.clone()));
+    writer.write_all(&original).unwrap();
+    let (packed, stats) = writer.finish_with_stats().unwrap();
+
+    let frames = frame_index(&mut Cursor::new(&packed)).unwrap();
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), frames.len());
+    let (mut payload, mut ideal) = (0, 0.0);
+    for (n, (event, frame)) in events.iter().zip(&frames).enumerate() {
+        let Event::BlockEncoded { n: block, original_len, compressed_len, ratio, payload_len, ideal_payload_len } =
+            *event
+        else {
+            panic!("nieoczekiwane zdarzenie {:?}", event);
+        };
+        assert_eq!((block, original_len, compressed_len), (n as u64, frame.original_len, frame.len));
+        assert!(ratio < 1.0);
+        // Kod Huffmana nie schodzi poniżej entropii, a przekracza ją o mniej niż bit na symbol
+        assert!(payload_len as f64 + 1.0 > ideal_payload_len && ideal_payload_len > 0.0, "{:?}", event);
+        assert!(((payload_len * 8) as f64) < ideal_payload_len * 8.0 + original_len as f64 + 8.0);
+        (payload, ideal) = (payload + payload_len, ideal + ideal_payload_len);
+    }
+    assert_eq!(payload, stats.payload_len as u64);
+    assert!((ideal - stats.ideal_payload_len()).abs() < 1e-6, "{} != {}", ideal, stats.ideal_payload_len());
+    assert!(stats.coding_overhead_percent() > 0.0 && stats.coding_overhead_percent() < 100.0);
+
+    let mut events = Vec::new();
+    let mut output = Vec::new();
+    compress_stream_with_events(&mut Cursor::new(&original), &mut output, &Options::default(), &mut |event: &Event| {
+        events.push(event.clone())
+    })
+    .unwrap();
+    assert_eq!(events[..2], [Event::PhaseStarted(Phase::Counting), Event::PhaseStarted(Phase::Encoding)]);
+    let packed_len = output.len() as u64;
+    assert!(matches!(events[2], Event::BlockEncoded { n: 0, compressed_len, .. } if compressed_len == packed_len));
+}
+
+// Rozmiar porcji wejścia zmienia tylko wielkość odczytów, nie wynik - także gdy porcja
+// kończy się w środku nagłówka albo stopki ramki
+#[test]
+fn reader_input_chunk_bounds_reads() {
+    struct Recorder<'a> {
+        data: &'a [u8],
+        largest: usize,
+    }
+    impl Read for Recorder<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.largest = self.largest.max(buf.len());
+            self.data.read(buf)
+        }
+    }
+
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options { order: 1, ..Options::default() }, 300);
+    writer.write_all(&original).unwrap();
+    let packed = writer.finish().unwrap();
+
+    for chunk in [1, 7, 64 * 1024] {
+        let options = DecodeOptions { input_chunk: Some(chunk), ..DecodeOptions::default() };
+        let mut source = Recorder { data: &packed, largest: 0 };
+        let mut decoded = Vec::new();
+        HuffmanReader::with_options(&mut source, options).unwrap().read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, original, "porcja {}", chunk);
+        assert!(source.largest <= chunk.max(8), "porcja {}: odczyt {} bajtów", chunk, source.largest);
+    }
+}
+
+// HuffmanEncoder daje te same ramki co HuffmanWriter, a kodeki za `dyn Codec`
+// obsługują puste dane i strumienie tak samo jak bufory
+#[test]
+fn encoder_and_dyn_codecs_roundtrip() {
+    let original = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 300);
+    writer.write_all(&original).unwrap();
+    let written = writer.finish().unwrap();
+    let mut read = Vec::new();
+    HuffmanEncoder::with_block_size(&original[..], Options::default(), 300).read_to_end(&mut read).unwrap();
+    assert_eq!(read, written);
+
+    let huffman = HuffmanCodec { block_size: 500, ..HuffmanCodec::default() };
+    let codecs: [Box<dyn Codec>; 3] =
+        [Box::new(huffman), Box::new(AdaptiveCodec::default()), Box::new(Lz77Codec::default())];
+    for codec in &codecs {
+        assert_eq!(codec.compress(b"").unwrap(), b"");
+        assert_eq!(codec.decompress(b"").unwrap(), b"");
+
+        let mut packed = Vec::new();
+        let packed_len = codec.compress_stream(&mut &original[..], &mut packed).unwrap();
+        assert_eq!(packed_len, packed.len() as u64, "{}", codec.name());
+        assert_eq!(codec.decompress(&packed).unwrap(), original, "{}", codec.name());
+        let mut decoded = Vec::new();
+        codec.decompress_stream(&mut &codec.compress(&original).unwrap()[..], &mut decoded).unwrap();
+        assert_eq!(decoded, original, "{}", codec.name());
+    }
+}
+
+// Ramka LZ77 odtwarza dane przez decompress_lz77 i zwykłe decompress, także bez leniwego
+// dopasowania, bez sumy kontrolnej i ponad jeden blok, a powtórzenia w tekście kodują się
+// krócej niż modelem rzędu 0
+#[test]
+fn lz77_frames_roundtrip() {
+    let code = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compat/originals/code.py")).unwrap();
+    let packed = compress_lz77(&code, &Lz77Options::default()).unwrap();
+    assert!(packed.len() * 3 < compress(&code, Options::default()).unwrap().len() * 2);
+    assert_eq!(decompress(&packed).unwrap(), code);
+
+    let periodic: Vec<u8> = b"abcab".iter().copied().cycle().take(5000).collect();
+    let long = Corpus::Markov.generate(300_000, 5);
+    let corpora = [&b""[..], b"x", &[0u8; 3000], &periodic, &Corpus::Random.generate(5000, 11), &long];
+    let fast = Lz77Options { checksum: ChecksumKind::None, max_chain: 4, lazy: false };
+    for options in [Lz77Options::default(), fast] {
+        for data in corpora {
+            let packed = compress_lz77(data, &options).unwrap();
+            assert_eq!(decompress_lz77(&packed, &DecodeOptions::default()).unwrap(), data, "{} bajtów", data.len());
+            assert_eq!(decompress(&packed).unwrap(), data, "{} bajtów", data.len());
+        }
+    }
+}
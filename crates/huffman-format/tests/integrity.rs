@@ -0,0 +1,567 @@
+use std::io::{Read, Write};
+
+use huffman_format::huffman::{Code, CodeLengths, FreqTable, MarkovCodeLengths, Tree, TreeNode};
+use huffman_format::testdata::Corpus;
+use huffman_format::{
+    AdaptivePreset, AdaptiveReader, AdaptiveWriter, BlockCodec, ChecksumKind, DecodeAutomaton, DecodeOptions,
+    Dictionary, FixedTable, FreqModel, Header, HuffmanBlockCodec, HuffmanError, HuffmanReader, HuffmanWriter,
+    Lz77Options, MessageModel, MessageModelSet, Model, Options, Transition, compress, compress_lz77, decompress,
+    decompress_lz77, decompress_with, estimate,
+};
+
+// Uszkodzona suma kontrolna musi zostać wykryta przez oba dekodery
+#[test]
+fn corrupted_checksum_is_reported() {
+    let mut packed = compress(b"abracadabra", Options { order: 1, ..Options::default() }).unwrap();
+    *packed.last_mut().unwrap() ^= 0x01;
+
+    let err = decompress(&packed).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+
+    let mut out = Vec::new();
+    let err = HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut out).unwrap_err();
+    let err = HuffmanError::from(err);
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+}
+
+// Bufor, którego nie da się zmniejszyć, jest zapisywany wprost z prefiksem -1
+#[test]
+fn block_codec_stores_incompressible_buffers() {
+    let codec = HuffmanBlockCodec::default();
+    let noise: Vec<u8> = (0..=255).collect();
+    let mut packed = Vec::new();
+    codec.compress_block(&noise, &mut packed).unwrap();
+    assert_eq!(packed[..8], (-1i64).to_le_bytes());
+    assert_eq!(packed[8..], noise);
+
+    let mut unpacked = Vec::new();
+    codec.decompress_block(&packed, &mut unpacked).unwrap();
+    assert_eq!(unpacked, noise);
+
+    let err = codec.decompress_block(&packed[..5], &mut unpacked).unwrap_err();
+    assert!(matches!(err, HuffmanError::MalformedHeader(_)), "{}", err);
+}
+
+// Ucięta ramka wiadomości i model z dziurą w kodach są odrzucane, a nie dekodowane na ślepo
+#[test]
+fn message_model_rejects_bad_input() {
+    let model = MessageModel::train([&b"temp=21.5;hum=40"[..], b"temp=22.0;hum=41"]).unwrap();
+    let frame = model.encode(b"temp=21.7;hum=39").unwrap();
+    let err = model.decode(&frame[..frame.len() - 2]).unwrap_err();
+    assert!(matches!(err, HuffmanError::TruncatedStream { expected: 16, .. }), "{}", err);
+
+    let mut bytes = model.to_bytes();
+    bytes[0] = 0;
+    assert!(matches!(MessageModel::from_bytes(&bytes), Err(HuffmanError::MalformedHeader(_))));
+}
+
+// Tabela z zewnątrz jest sprawdzana przed kodowaniem: kody prefiksowe, szerokości
+// zgodne z rzędem, pasujące opcje i kod dla każdego symbolu wejścia; krótka próbka
+// bez `store_fallback`, żeby jej ramka miała tablice
+#[test]
+fn fixed_table_is_validated_before_encoding() {
+    let options = Options { order: 1, store_fallback: false, ..Options::default() };
+    let header = Header::parse(&compress(b"temp=21.5;hum=40", options).unwrap()).unwrap().0;
+    let table = FixedTable::from_header(&header).unwrap();
+    let (packed, stats) = table.compress_with_stats(b"temp=40", &options).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), b"temp=40");
+    assert_eq!(table.estimate(b"temp=40", &options).unwrap().payload_len, stats.payload_len);
+
+    let err = table.check_sample(b"temp=41.5").unwrap_err();
+    assert!(err.to_string().contains("1 z 9 symboli") && err.to_string().contains("bajt 6"), "{}", err);
+    assert!(table.compress_with_stats(b"temp=41.5", &options).is_err());
+    assert!(table.check_options(&Options { order: 2, ..options }).is_err());
+
+    for broken in [|symbol: &mut Vec<u8>, _: &mut u8| symbol.push(0), |_: &mut Vec<u8>, len: &mut u8| *len = 1] {
+        let mut header = header.clone();
+        let Model::CodeLengths(lengths) = &mut header.model else { unreachable!() };
+        let l_table = lengths.get_mut(&b"t"[..]).unwrap();
+        let (mut symbol, mut len) = l_table.drain().next().unwrap();
+        broken(&mut symbol, &mut len);
+        l_table.extend([(symbol, len), (b"x".to_vec(), 1), (b"y".to_vec(), 1)]);
+        assert!(matches!(FixedTable::from_header(&header), Err(HuffmanError::InvalidOptions(_))));
+    }
+
+    // Tabela z kodami ucieczki pokrywa każde wejście
+    let pruned = compress(&Corpus::Markov.generate(4096, 1), Options { max_symbols: Some(20), ..options }).unwrap();
+    let table = FixedTable::from_header(&Header::parse(&pruned).unwrap().0).unwrap();
+    let data = Corpus::Random.generate(4096, 2);
+    let (packed, stats) = table.compress_with_stats(&data, &options).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), data);
+    assert!(stats.header_len < estimate(&data, &options).unwrap().header_len);
+}
+
+// Nieaktualna tabela po dodaniu ucieczek koduje dowolne wejście, a znane symbole tracą najwyżej bit
+#[test]
+fn stale_table_gets_escape_codes() {
+    let options = Options { order: 1, ..Options::default() };
+    let sample = Corpus::Markov.generate(4096, 1);
+    let table = FixedTable::from_header(&Header::parse(&compress(&sample, options).unwrap()).unwrap().0).unwrap();
+    assert!(!table.has_escapes());
+    let data = Corpus::Random.generate(4096, 2);
+    assert!(table.check_sample(&data).is_err());
+
+    let escaped = table.with_escapes().unwrap();
+    assert!(escaped.has_escapes() && escaped.symbols() > table.symbols());
+    escaped.check_sample(&data).unwrap();
+    let (packed, _) = escaped.compress_with_stats(&data, &options).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), data);
+
+    let (_, plain) = table.compress_with_stats(&sample, &options).unwrap();
+    let (packed, stats) = escaped.compress_with_stats(&sample, &options).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), sample);
+    assert!(stats.payload_len <= plain.payload_len + sample.len() / 8);
+    assert_eq!(escaped.with_escapes().unwrap().symbols(), escaped.symbols());
+}
+
+// Drzewo kontekstu z tabeli pliku ma liść na każdy kod, a kody liści wypełniają je całe
+#[test]
+fn table_tree_visits_every_code() {
+    let sample = Corpus::Markov.generate(4096, 4);
+    let table = FixedTable::from_header(&Header::parse(&compress(&sample, Options::default()).unwrap()).unwrap().0)
+        .unwrap()
+        .with_escapes()
+        .unwrap();
+    let tree = table.tree(&[]).unwrap();
+    let (mut leaves, mut kraft, mut escapes) = (0, 0.0, 0);
+    let mut previous: Option<Code> = None;
+    tree.visit_leaves(|symbol, code| {
+        leaves += 1;
+        kraft += 0.5f64.powi(code.len as i32);
+        escapes += symbol.is_empty() as usize;
+        // Liście od lewej: kody rosną leksykograficznie
+        if let Some(previous) = previous {
+            assert!(previous.bits << (64 - previous.len) < code.bits << (64 - code.len));
+        }
+        previous = Some(code);
+    });
+    assert_eq!(leaves, table.symbols());
+    assert_eq!((kraft, escapes), (1.0, 1));
+    assert!(table.tree(b"x").is_none());
+
+    let mut frequencies = FreqTable::new();
+    for &byte in &sample {
+        *frequencies.entry(vec![byte]).or_insert(0) += 1;
+    }
+    let weighted = Tree::from_frequencies(&frequencies, 15).unwrap();
+    let mut nodes = Vec::new();
+    weighted.visit_preorder(|node| nodes.push(node));
+    assert!(matches!(nodes[0], TreeNode::Internal { weight: Some(w), .. } if w == sample.len() as u64));
+    let internal = nodes.iter().filter(|node| matches!(node, TreeNode::Internal { .. })).count();
+    assert_eq!(internal + 1, nodes.len() - internal);
+}
+
+// Pliki kodowane słownikiem nie niosą tabeli, a dekoder z innym słownikiem (albo bez niego) odmawia
+#[test]
+fn dictionary_frames_need_the_same_dictionary() {
+    let options = Options { order: 2, ..Options::default() };
+    let samples: Vec<Vec<u8>> = (0..100)
+        .map(|i| format!("{{\"id\":{},\"status\":\"{}\"}}", i, ["ok", "error"][i % 2]).into_bytes())
+        .collect();
+    let dict = Dictionary::train(samples.iter().map(Vec::as_slice), &options).unwrap();
+    let shared = Dictionary::from_bytes(&dict.to_bytes()).unwrap();
+    assert_eq!(shared.id(), dict.id());
+
+    let message = b"{\"id\":1234,\"status\":\"ok\"}";
+    let (packed, stats) = dict.compress_with_stats(message, &options).unwrap();
+    assert_eq!(Dictionary::required_by(&packed), Some(dict.id()));
+    assert!(packed.len() < compress(message, options).unwrap().len() / 2);
+    assert_eq!(stats.header_len + stats.payload_len + stats.footer_len, packed.len());
+    assert_eq!(shared.decompress(&packed, &DecodeOptions::default()).unwrap(), message);
+
+    // Dane spoza próbek idą za kodami ucieczki, co widać w pokryciu
+    let noise = Corpus::Random.generate(1000, 3);
+    let (packed_noise, noise_stats, noise_coverage) = dict.compress_with_coverage(&noise, &options).unwrap();
+    assert_eq!(dict.decompress(&packed_noise, &DecodeOptions::default()).unwrap(), noise);
+    assert_eq!(noise_coverage.symbols, 1000);
+    assert_eq!(noise_coverage.payload_bits.div_ceil(8), noise_stats.payload_len as u64);
+    assert!(noise_coverage.ratio() < 0.1 && noise_coverage.escape_share() > 0.9, "{:?}", noise_coverage);
+    let (_, _, coverage) = dict.compress_with_coverage(message, &options).unwrap();
+    assert!(coverage.ratio() > 0.9 && coverage.escaped < noise_coverage.escaped, "{:?}", coverage);
+
+    let other = Dictionary::train([&b"temp=21.5;hum=40"[..]], &options).unwrap();
+    let err = other.decompress(&packed, &DecodeOptions::default()).unwrap_err();
+    assert!(matches!(err, HuffmanError::InvalidOptions(_)), "{}", err);
+    assert!(matches!(decompress(&packed).unwrap_err().root(), HuffmanError::InvalidOptions(_)));
+    assert!(dict.decompress(&packed[..packed.len() - 5], &DecodeOptions::default()).is_err());
+    let mut corrupted = packed.clone();
+    corrupted[20] ^= 0x10;
+    assert!(dict.decompress(&corrupted, &DecodeOptions::default()).is_err());
+    assert!(Dictionary::train([&b""[..]], &options).is_err());
+}
+
+// Wbudowane słowniki mają różne identyfikatory, a dekoder znajduje je po samej ramce
+#[test]
+fn builtin_presets_beat_frames_with_tables() {
+    let samples: [(&str, &[u8]); 6] = [
+        ("english", b"Thank you for your order, we will contact you soon."),
+        ("json", br#"{"id":17,"name":"alice","active":true}"#),
+        ("csv", b"1201,2024-06-11,Grace,Paris,120.50,EUR,ok\n"),
+        ("base64", b"U29tZSBzaG9ydCBwYXlsb2FkIGZvciB0aGUgdGVzdA=="),
+        ("dna", b"ACGTTGCAAGGCTTACGATCGATCGGATTACA\n"),
+        ("logs", b"2024-06-11T09:30:12.408Z WARN  [db.pool] connection acquired pool=primary active=18\n"),
+    ];
+    assert_eq!(Dictionary::presets().collect::<Vec<_>>(), samples.map(|(name, _)| name));
+    let mut ids = Vec::new();
+    for (name, sample) in samples {
+        let dict = Dictionary::preset(name).unwrap();
+        let options = Options { order: dict.table().order(), ..Options::default() };
+        let packed = dict.compress(sample, &options).unwrap();
+        assert!(packed.len() < compress(sample, options).unwrap().len(), "{}", name);
+
+        let (found, builtin) = Dictionary::find_preset(Dictionary::required_by(&packed).unwrap()).unwrap();
+        assert_eq!(found, name);
+        assert_eq!(builtin.decompress(&packed, &DecodeOptions::default()).unwrap(), sample);
+        ids.push(dict.id());
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), samples.len());
+    assert!(Dictionary::preset("xml").is_none());
+}
+
+// Słownik logs to próbka po filtrze timestamps, a ramka słownika z filtrem zapisuje łańcuch
+// i sumę kontrolną oryginału, więc wykrywa też zmieniony parametr filtra
+#[test]
+fn logs_preset_codes_filtered_frames() {
+    let options = Options { order: 2, filters: "timestamps:32".parse().unwrap(), ..Options::default() };
+    let dict = Dictionary::preset("logs").unwrap();
+    let trained = Dictionary::train([&include_bytes!("../presets/logs.txt")[..]], &options).unwrap();
+    assert_eq!(trained.id(), dict.id());
+
+    let log: Vec<u8> = (0..40)
+        .map(|i| format!("2024-06-11T09:{:02}:{:02}.{:03}Z INFO  [worker] ok\n", i / 6, i % 6 * 9, i * 37 % 1000))
+        .flat_map(String::into_bytes)
+        .collect();
+    let (packed, stats) = dict.compress_with_stats(&log, &options).unwrap();
+    let unfiltered = dict.compress(&log, &Options { filters: Default::default(), ..options }).unwrap();
+    assert!(packed.len() < unfiltered.len(), "{} >= {}", packed.len(), unfiltered.len());
+    assert_eq!(stats.header_len + stats.payload_len + stats.footer_len, packed.len());
+    assert_eq!(dict.decompress(&packed, &DecodeOptions::default()).unwrap(), log);
+    assert_eq!(dict.decompress(&unfiltered, &DecodeOptions::default()).unwrap(), log);
+
+    let stage = [&[4u8, 32][..], &(log.len() as u64).to_be_bytes()].concat();
+    let at = packed.windows(stage.len()).position(|w| w == stage).unwrap();
+    let mut tampered = packed.clone();
+    tampered[at + 1] = 1;
+    let err = dict.decompress(&tampered, &DecodeOptions::default()).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+}
+
+// Gotowa tabela i słowniki nie wydłużają danych, którym nie pasują: losowe dane dostają ramkę
+// zapisaną wprost, czytelną bez słownika, a tekst spoza słownika najwyżej tyle samo
+#[test]
+fn tables_fall_back_to_stored_frames() {
+    let noise = Corpus::Random.generate(20_000, 11);
+    let source = &include_bytes!("../src/codec.rs")[..20_000];
+    let text = compress(&Corpus::Markov.generate(4000, 5), Options { store_fallback: false, ..Options::default() });
+    let table = FixedTable::from_header(&Header::parse(&text.unwrap()).unwrap().0).unwrap().with_escapes().unwrap();
+    let (packed, stats) = table.compress_with_stats(&noise, &Options::default()).unwrap();
+    assert_eq!((stats.payload_len, packed.len()), (noise.len(), noise.len() + 33));
+    assert_eq!(decompress(&packed).unwrap(), noise);
+
+    let trained = Dictionary::train([&b"status=ok;status=error"[..]], &Options::default()).unwrap();
+    let logs = Options { order: 2, filters: "timestamps:32".parse().unwrap(), ..Options::default() };
+    let english = Dictionary::preset("english").unwrap();
+    let english_options = Options { order: english.table().order(), ..Options::default() };
+    let cases = [
+        ("--dict", &trained, &noise[..], Options::default()),
+        ("english", &english, &noise[..], english_options),
+        ("english", &english, source, english_options),
+        ("logs", &Dictionary::preset("logs").unwrap(), &noise[..], logs),
+    ];
+    for (name, dict, data, options) in cases {
+        let packed = dict.compress(data, &options).unwrap();
+        // Nagłówek, stopka i łańcuch filtrów to kilkadziesiąt bajtów, nie kilkanaście procent
+        assert!(packed.len() <= data.len() + 64, "{}: {} z {}", name, packed.len(), data.len());
+        assert_eq!(dict.decompress(&packed, &DecodeOptions::default()).unwrap(), data, "{}", name);
+        if data == &noise[..] {
+            assert_eq!(Dictionary::required_by(&packed), None, "{}", name);
+            assert!(packed.len() <= compress(data, options).unwrap().len(), "{}", name);
+            assert_eq!(decompress(&packed).unwrap(), data, "{}", name);
+        }
+    }
+}
+
+// Model z pliku częstotliwości daje przy każdym imporcie te same bajty i te same ramki
+#[test]
+fn frequency_model_export_is_deterministic() {
+    let data = Corpus::Markov.generate(4000, 7);
+    let model = FreqModel::count(&data, 2, 1).unwrap();
+    let json = model.to_json();
+    let from_json = FreqModel::from_json(&json).unwrap();
+    let from_bytes = FreqModel::parse(&model.to_bytes()).unwrap();
+    assert_eq!(from_json, model);
+    assert_eq!(from_bytes, model);
+    assert_eq!(from_json.to_json(), json);
+    assert_eq!(from_bytes.to_bytes(), model.to_bytes());
+
+    let options = Options { order: 2, ..Options::default() };
+    let packed = from_json.table(15).unwrap().compress_with_stats(&data, &options).unwrap().0;
+    assert_eq!(packed, from_bytes.table(15).unwrap().compress_with_stats(&data, &options).unwrap().0);
+    assert_eq!(decompress(&packed).unwrap(), data);
+
+    let wide = FreqModel::count(&data, 1, 2).unwrap();
+    assert_eq!(FreqModel::parse(wide.to_json().as_bytes()).unwrap(), wide);
+    for broken in [
+        json.replace("\"order\": 2", "\"order\": 1"),
+        json.replace("\"version\": 1", "\"version\": 2"),
+        json.replace("huff-freq", "inny"),
+        json[..json.len() / 2].to_string(),
+    ] {
+        assert!(FreqModel::from_json(&broken).is_err(), "{}", broken);
+    }
+    assert!(FreqModel::from_bytes(&model.to_bytes()[..20]).is_err());
+}
+
+// Każda wiadomość dostaje model z najkrótszą ramką, a nieznany numer modelu jest odrzucany
+#[test]
+fn message_model_set_picks_smallest_frame() {
+    let sensors = MessageModel::train([&b"temp=21.5;hum=40"[..], b"temp=22.0;hum=41"]).unwrap();
+    let events = MessageModel::train([&b"{\"event\":\"login\",\"user\":\"ala\"}"[..]]).unwrap();
+    let models = MessageModelSet::new(vec![sensors.clone(), events.clone()]).unwrap();
+
+    for message in [&b"temp=19.9;hum=55"[..], b"{\"event\":\"logout\",\"user\":\"ola\"}"] {
+        let frame = models.encode(message).unwrap();
+        let best = sensors.encoded_len(message).min(events.encoded_len(message));
+        assert_eq!(frame.len(), 1 + best);
+        assert_eq!(models.decode(&frame).unwrap(), message);
+    }
+    assert_eq!(models.best_model(b"temp=0.0;hum=0"), 0);
+    assert_eq!(models.best_model(b"{\"user\":\"ewa\"}"), 1);
+
+    let mut frame = models.encode(b"temp=1").unwrap();
+    frame[0] = 2;
+    assert!(matches!(models.decode(&frame), Err(HuffmanError::MalformedHeader(_))));
+    assert!(MessageModelSet::new(Vec::new()).is_err());
+}
+
+// Odświeżony model dostaje nowy numer: nowe wiadomości idą nim, a ramki starego modelu
+// nadal się dekodują; liczniki z update nie zmieniają kodów przed rebuild_codes
+#[test]
+fn message_model_refresh_is_hot_swapped() {
+    let sensors = MessageModel::train([&b"temp=21.5;hum=40"[..]]).unwrap();
+    let mut models = MessageModelSet::new(vec![sensors.clone()]).unwrap();
+    let message = b"{\"event\":\"login\",\"user\":\"ala\"}";
+    let old_frame = models.encode(message).unwrap();
+
+    let mut refreshed = sensors.clone();
+    for user in ["ala", "ola", "ewa"] {
+        refreshed.update(format!("{{\"event\":\"login\",\"user\":\"{}\"}}", user).as_bytes());
+    }
+    assert_eq!(refreshed.to_bytes(), sensors.to_bytes());
+    refreshed.rebuild_codes().unwrap();
+    assert!(refreshed.encoded_len(message) < sensors.encoded_len(message));
+
+    assert_eq!(models.push(refreshed).unwrap(), 1);
+    let frame = models.encode(message).unwrap();
+    assert_eq!(frame[0], 1);
+    assert_eq!(models.decode(&frame).unwrap(), message);
+    assert_eq!(models.decode(&old_frame).unwrap(), message);
+
+    // Model odczytany z bajtów buduje kody tylko z nowych danych
+    let mut shared = MessageModel::from_bytes(&sensors.to_bytes()).unwrap();
+    shared.rebuild_codes().unwrap();
+    assert!(shared.to_bytes().iter().all(|&len| len == 8));
+
+    let mut full = MessageModelSet::new(vec![sensors.clone(); 256]).unwrap();
+    assert!(full.push(sensors).is_err());
+}
+
+// Strumień adaptacyjny odczytuje zwykłe decompress, a zmieniona suma kontrolna jest wykrywana
+#[test]
+fn adaptive_stream_roundtrip_and_checksum() {
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * i % 251) as u8).chain(0..=255).collect();
+    let mut writer = AdaptiveWriter::new(Vec::new()).unwrap();
+    writer.write_all(&data).unwrap();
+    let (mut packed, stats) = writer.finish().unwrap();
+    assert_eq!(stats.original_len, data.len() as u64);
+    assert_eq!(decompress(&packed).unwrap(), data);
+
+    *packed.last_mut().unwrap() ^= 0x01;
+    let err = decompress(&packed).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+}
+
+// Krótki strumień z modelem startowym jest krótszy niż bez niego, a dekoder bez
+// tego samego presetu zgłasza błąd zamiast dekodować śmieci
+#[test]
+fn warm_started_adaptive_stream_needs_matching_preset() {
+    let sample = b"GET /api/users 200 12ms\nPOST /api/login 401 3ms\n".repeat(40);
+    let message = b"GET /api/orders 200 8ms\n";
+    let preset = AdaptivePreset::from_sample(&sample);
+
+    let encode = |mut writer: AdaptiveWriter<Vec<u8>>| {
+        writer.write_all(message).unwrap();
+        writer.finish().unwrap().0
+    };
+    let cold = encode(AdaptiveWriter::new(Vec::new()).unwrap());
+    let warm = encode(AdaptiveWriter::with_preset(Vec::new(), &preset).unwrap());
+    assert!(warm.len() < cold.len(), "{} >= {}", warm.len(), cold.len());
+
+    let mut decoded = Vec::new();
+    AdaptiveReader::with_preset(&warm[..], &preset).unwrap().read_to_end(&mut decoded).unwrap();
+    assert_eq!(decoded, message);
+
+    assert!(matches!(decompress(&warm), Err(HuffmanError::InvalidOptions(_))));
+    let other = AdaptivePreset::from_sample(b"zupelnie inna probka");
+    assert!(matches!(AdaptiveReader::with_preset(&warm[..], &other), Err(HuffmanError::InvalidOptions(_))));
+}
+
+// Dekoder stałego czasu daje ten sam wynik co zwykły, także dla kodów zerowej długości,
+// symboli dwubajtowych i wielu ramek, a uszkodzone dane nadal są odrzucane
+#[test]
+fn constant_time_decode_matches_table_decode() {
+    let options = DecodeOptions { constant_time: true, ..DecodeOptions::default() };
+    let text = Corpus::Markov.generate(1500, 5);
+    let cases = [
+        (text.clone(), Options { order: 1, ..Options::default() }),
+        (text.clone(), Options { unit: 2, ..Options::default() }),
+        (b"aaaaaaaa".to_vec(), Options { order: 1, ..Options::default() }),
+    ];
+    for (data, encode_options) in cases {
+        let packed = compress(&data, encode_options).unwrap();
+        assert_eq!(decompress_with(&packed, &options).unwrap(), data, "{:?}", encode_options);
+    }
+
+    let mut writer = HuffmanWriter::with_block_size(Vec::new(), Options::default(), 700);
+    writer.write_all(&text).unwrap();
+    let packed = writer.finish().unwrap();
+    let mut streamed = Vec::new();
+    HuffmanReader::with_options(&packed[..], options).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, text);
+
+    let mut corrupted = compress(&text, Options { order: 1, ..Options::default() }).unwrap();
+    let middle = corrupted.len() / 2;
+    corrupted[middle] ^= 0x5a;
+    assert!(decompress_with(&corrupted, &options).is_err());
+}
+
+// Kody dłuższe niż korzeń tablicy dekodera (tu ponad 20 bitów przy rzędzie 2 i limicie podniesionym
+// ponad domyślne 15) przechodzą przez podtablice, a bity, które nie zaczynają żadnego kodu, dają InvalidCode
+#[test]
+fn long_codes_decode_through_sub_tables() {
+    // Po "ab" symbole o licznościach Fibonacciego: najrzadsze dostają najdłuższe kody
+    let mut data = Vec::new();
+    let (mut count, mut next) = (1, 1);
+    for symbol in b'A'..b'A' + 24 {
+        for _ in 0..count {
+            data.extend_from_slice(&[b'a', b'b', symbol]);
+        }
+        (count, next) = (next, count + next);
+    }
+    let packed = compress(&data, Options { order: 2, max_code_len: 32, ..Options::default() }).unwrap();
+    let Model::CodeLengths(lengths) = Header::parse(&packed).unwrap().0.model else { panic!("model v1+") };
+    assert!(lengths[&b"ab".to_vec()].values().any(|&len| len > 20), "{:?}", lengths);
+    assert_eq!(decompress(&packed).unwrap(), data);
+    let mut streamed = Vec::new();
+    HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+    assert_eq!(streamed, data);
+
+    // Kody niepełne: a=0, b=10, a 11 nie zaczyna żadnego kodu
+    let frame = |original_len, payload_bits, payload: u8| {
+        let l_table: CodeLengths = [(b"a".to_vec(), 1), (b"b".to_vec(), 2)].into();
+        let header = Header {
+            original_len,
+            order: 0,
+            max_code_len: 32,
+            unit: 1,
+            payload_bits: Some(payload_bits),
+            checksum: ChecksumKind::None,
+            escape: false,
+            nibble: false,
+            filters: Vec::new(),
+            model: Model::CodeLengths(MarkovCodeLengths::from([(Vec::new(), l_table)])),
+        };
+        decompress(&[header.to_bytes(), vec![payload]].concat())
+    };
+    assert_eq!(frame(2, 3, 0b0100_0000).unwrap(), b"ab");
+    let err = frame(3, 5, 0b0101_1000).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::InvalidCode { bits, .. } if bits == "11"), "{}", err);
+    let err = frame(2, 2, 0b0100_0000).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::TruncatedStream { decoded: 1, .. }), "{}", err);
+    // Zmyślona długość strumienia nie zatrzymuje czytania bitów na jej dalekim końcu
+    let err = frame(2, 1 << 32, 0b0100_0000).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::MalformedHeader(_)), "{}", err);
+}
+
+// Dekoder napisany wyłącznie z tabeli przejść automatu odtwarza dane tak jak biblioteka,
+// także z ucieczkami i kontekstami spoza modelu
+#[test]
+fn exported_automaton_decodes_frames() {
+    let data = Corpus::Markov.generate(5000, 11);
+    for options in [
+        Options { order: 1, ..Options::default() },
+        Options { order: 2, max_symbols: Some(200), ..Options::default() },
+    ] {
+        let packed = compress(&data, options).unwrap();
+        let (header, data_offset) = Header::parse(&packed).unwrap();
+        let automaton = DecodeAutomaton::from_header(&header).unwrap();
+        assert_eq!(automaton.escape, options.max_symbols.is_some());
+        // Jedyny symbol kontekstu ma kod 1-bitowy, więc drugi bit korzenia nie prowadzi nigdzie
+        assert!(automaton.contexts.iter().all(|c| c.incomplete().is_empty() || c.incomplete() == [(0, 1)]));
+
+        let payload = &packed[data_offset..];
+        let mut pos = 0usize;
+        let mut bit = || {
+            let value = payload[pos / 8] >> (7 - pos % 8) & 1;
+            pos += 1;
+            value as usize
+        };
+        let mut decoded = Vec::new();
+        let mut context = vec![0u8; automaton.order];
+        while decoded.len() < data.len() {
+            let symbol = match automaton.contexts.iter().find(|c| c.context == context) {
+                Some(table) => {
+                    let mut transition = &table.start;
+                    while let Transition::State(state) = transition {
+                        transition = &table.states[*state as usize][bit()];
+                    }
+                    match transition {
+                        Transition::Symbol(symbol) => symbol[0],
+                        Transition::Escape => (0..8).fold(0, |byte, _| byte << 1 | bit() as u8),
+                        other => panic!("{:?}", other),
+                    }
+                }
+                None => (0..8).fold(0, |byte, _| byte << 1 | bit() as u8),
+            };
+            decoded.push(symbol);
+            context.rotate_left(1);
+            if let Some(last) = context.last_mut() {
+                *last = symbol;
+            }
+        }
+        assert_eq!(decoded, data);
+    }
+}
+
+// Uszkodzona albo ucięta ramka LZ77 daje błąd zamiast innych danych, a limit wyniku
+// działa przed dekodowaniem
+#[test]
+fn lz77_frame_damage_is_detected() {
+    let data = Corpus::Markov.generate(20_000, 3);
+    let packed = compress_lz77(&data, &Lz77Options::default()).unwrap();
+    let options = DecodeOptions::default();
+
+    // Ostatni bajt strumienia może mieć bity wypełnienia, których dekoder nie czyta
+    for pos in (0..packed.len() - 5).step_by(97) {
+        let mut damaged = packed.clone();
+        damaged[pos] ^= 0x10;
+        assert!(decompress_lz77(&damaged, &options).is_err(), "bajt {}", pos);
+    }
+    let mut damaged = packed.clone();
+    *damaged.last_mut().unwrap() ^= 0x01;
+    let err = decompress_lz77(&damaged, &options).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ChecksumMismatch { .. }), "{}", err);
+
+    for len in [0, 5, 13, 14, packed.len() / 2, packed.len() - 1] {
+        assert!(decompress_lz77(&packed[..len], &options).is_err(), "{} bajtów", len);
+    }
+    let mut longer = packed.clone();
+    longer.push(0);
+    assert!(decompress_lz77(&longer, &options).is_err());
+
+    let limited = |limit| DecodeOptions { max_output_len: Some(limit), ..DecodeOptions::default() };
+    assert!(decompress_lz77(&packed, &limited(data.len() as u64 - 1)).is_err());
+    assert_eq!(decompress_lz77(&packed, &limited(data.len() as u64)).unwrap(), data);
+}
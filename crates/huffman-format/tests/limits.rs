@@ -0,0 +1,296 @@
+use std::io::{Cursor, Read};
+
+use huffman_format::huffman::{FreqTable, MarkovFreqTable, assign_tree_codes};
+use huffman_format::testdata::{Corpus, Rng};
+use huffman_format::{
+    ChecksumKind, DecodeOptions, Header, HuffmanError, HuffmanReader, Model, Options, Resource, compress,
+    compress_with_stats, decompress, decompress_parallel, decompress_range, decompress_with,
+    decompress_with_frequencies, estimate_decode_memory, frame_index,
+};
+
+// Nagłówek deklarujący 1 TiB danych nie może doprowadzić do alokacji takiego bufora
+#[test]
+fn lying_header_hits_expansion_limit() {
+    let mut packed = compress(b"abracadabra", Options::default()).unwrap();
+    packed[5..13].copy_from_slice(&(1u64 << 40).to_be_bytes());
+
+    let options = DecodeOptions { max_expansion_ratio: Some(100), ..DecodeOptions::default() };
+    let err = decompress_with(&packed, &options).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ExpansionLimit { ratio: 100, .. }), "{}", err);
+}
+
+// Jeden symbol ma kod zerowej długości, więc kilkadziesiąt bajtów może zadeklarować 1 TiB;
+// limity odrzucają taki plik przed dekodowaniem, także bez limitu krotności
+#[test]
+fn resource_limits_reject_bombs() {
+    let mut bomb = compress(&[b'a'; 1000], Options::default()).unwrap();
+    bomb[5..13].copy_from_slice(&(1u64 << 40).to_be_bytes());
+    let options = DecodeOptions { max_output_len: Some(1 << 20), ..DecodeOptions::default() };
+    let output_limit = |err: &HuffmanError| {
+        matches!(err.root(), HuffmanError::ResourceLimit { resource: Resource::Output, limit: 1048576, .. })
+    };
+    let err = decompress_with(&bomb, &options).unwrap_err();
+    assert!(output_limit(&err), "{}", err);
+    assert!(output_limit(&decompress_parallel(&bomb, &options, 2).unwrap_err()));
+    assert!(output_limit(&HuffmanReader::with_options(&bomb[..], options).err().unwrap()));
+    let memory = DecodeOptions { max_memory: Some(1 << 20), ..DecodeOptions::default() };
+    let err = decompress_with(&bomb, &memory).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ResourceLimit { resource: Resource::Memory, .. }), "{}", err);
+
+    // Limit obejmuje wszystkie ramki razem, także w czytniku strumieniowym
+    let frame = compress(&[b'a'; 1000], Options::default()).unwrap();
+    let two_frames = [frame.clone(), frame].concat();
+    let options = DecodeOptions { max_output_len: Some(1500), ..DecodeOptions::default() };
+    assert!(decompress_with(&two_frames, &options).is_err());
+    let err = HuffmanReader::with_options(&two_frames[..], options).unwrap().read_to_end(&mut Vec::new());
+    let err = HuffmanError::from(err.unwrap_err());
+    assert!(matches!(err.root(), HuffmanError::ResourceLimit { requested: 2000, .. }), "{}", err);
+    let options = DecodeOptions { max_output_len: Some(2000), ..DecodeOptions::default() };
+    assert_eq!(decompress_with(&two_frames, &options).unwrap().len(), 2000);
+
+    // Duża tablica kodów jest odrzucana podczas czytania nagłówka (bez zapisu wprost, który by jej nie zapisał)
+    let data = Corpus::Random.generate(20_000, 7);
+    let packed = compress(&data, Options { order: 1, store_fallback: false, ..Options::default() }).unwrap();
+    let entries = DecodeOptions { max_table_entries: Some(1000), ..DecodeOptions::default() };
+    let err = decompress_with(&packed, &entries).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::ResourceLimit { resource: Resource::TableEntries, .. }), "{}", err);
+    assert!(HuffmanReader::with_options(&packed[..], entries).is_err());
+    let memory = DecodeOptions { max_memory: Some(64 * 1024), ..DecodeOptions::default() };
+    assert!(decompress_with(&packed, &memory).is_err());
+    let roomy = DecodeOptions {
+        max_output_len: Some(20_000),
+        max_table_entries: Some(1 << 20),
+        max_memory: Some(1 << 30),
+        ..DecodeOptions::default()
+    };
+    assert_eq!(decompress_with(&packed, &roomy).unwrap(), data);
+}
+
+// Szacunek pamięci z nagłówka to dokładnie to, co sprawdza max_memory przed dekodowaniem
+#[test]
+fn decode_memory_estimate_matches_limits() {
+    let data = Corpus::Random.generate(20_000, 7);
+    let packed = compress(&data, Options { order: 1, store_fallback: false, ..Options::default() }).unwrap();
+    let memory = estimate_decode_memory(&Header::parse(&packed).unwrap().0);
+    assert_eq!(memory.output, 20_000);
+    assert_eq!(memory.total(), memory.streaming() + 20_000);
+
+    let limit = |bytes: u64| DecodeOptions { max_memory: Some(bytes as usize), ..DecodeOptions::default() };
+    assert!(HuffmanReader::with_options(&packed[..], limit(memory.tables)).is_ok());
+    let err = HuffmanReader::with_options(&packed[..], limit(memory.tables - 1)).err().unwrap();
+    assert!(matches!(err.root(), HuffmanError::ResourceLimit { resource: Resource::Memory, .. }), "{}", err);
+    assert_eq!(decompress_with(&packed, &limit(memory.tables.max(memory.output))).unwrap(), data);
+}
+
+// Limit niższy niż log2 liczby symboli jest podnoszony przy kodowaniu, więc dekoder musi to zaakceptować
+#[test]
+fn infeasible_code_length_limit_is_raised() {
+    let data: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    let packed = compress(&data, Options { max_code_len: 4, ..Options::default() }).unwrap();
+    assert_eq!(decompress(&packed).unwrap(), data);
+}
+
+// Częstotliwości Fibonacciego dają drzewo głębokie na liczbę symboli bez jednego; domyślny
+// limit przycina je do 15 bitów, a jawnie podany wyższy limit zostawia długie kody
+#[test]
+fn default_limit_caps_deep_trees() {
+    let (mut a, mut b) = (1usize, 1usize);
+    let mut data = Vec::new();
+    for symbol in 0..24u8 {
+        data.extend(std::iter::repeat_n(symbol, a));
+        (a, b) = (b, a + b);
+    }
+    let longest = |packed: &[u8]| {
+        let (header, _) = Header::parse(packed).unwrap();
+        let Model::CodeLengths(lengths) = header.model else { unreachable!() };
+        (header.max_code_len, lengths[&Vec::new()].values().copied().max().unwrap())
+    };
+
+    let packed = compress(&data, Options::default()).unwrap();
+    assert_eq!(longest(&packed), (15, 15));
+    assert_eq!(decompress(&packed).unwrap(), data);
+
+    let packed = compress(&data, Options { max_code_len: 32, ..Options::default() }).unwrap();
+    assert_eq!(longest(&packed), (32, 23));
+    assert_eq!(decompress(&packed).unwrap(), data);
+}
+
+// Rozmiar poziomów tablic wyszukiwania nie zmienia wyniku: od kodów czytanych po bicie
+// po korzeń mieszczący każdy kod, także poza dozwolonym zakresem (przycinanym)
+#[test]
+fn lookup_bits_do_not_change_output() {
+    let data = Corpus::Markov.generate(30_000, 4);
+    let packed = [
+        compress(&data, Options { order: 1, max_code_len: 32, ..Options::default() }).unwrap(),
+        compress(&data, Options { order: 1, max_symbols: Some(40), ..Options::default() }).unwrap(),
+    ]
+    .concat();
+    let expected = [data.clone(), data].concat();
+    for lookup_bits in [0, 1, 3, 8, 16, 40] {
+        let options = DecodeOptions { lookup_bits: Some(lookup_bits), ..DecodeOptions::default() };
+        assert_eq!(decompress_with(&packed, &options).unwrap(), expected, "{}", lookup_bits);
+        let mut streamed = Vec::new();
+        HuffmanReader::with_options(&packed[..], options).unwrap().read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, expected);
+    }
+}
+
+// Częstotliwości v0 mogą opisać drzewo o głębokości 62 bez żadnych danych; kody
+// czyta się z niego bez rekurencji, a najgłębszy symbol dekoduje się jak każdy inny
+#[test]
+fn deep_v0_tree_decodes() {
+    let (mut a, mut b) = (1u64, 1u64);
+    let mut frequencies = FreqTable::new();
+    for symbol in 0..63u8 {
+        frequencies.insert(vec![symbol], a);
+        (a, b) = (b, a + b);
+    }
+    let codes = assign_tree_codes(&frequencies, 64).unwrap();
+    let deepest = codes[&vec![0u8]];
+    assert_eq!(deepest.len, 62);
+
+    let header = Header {
+        original_len: 1,
+        order: 0,
+        max_code_len: 64,
+        unit: 1,
+        payload_bits: None,
+        checksum: ChecksumKind::None,
+        escape: false,
+        nibble: false,
+        filters: Vec::new(),
+        model: Model::Frequencies(MarkovFreqTable::from([(Vec::new(), frequencies)])),
+    };
+    let payload = (deepest.bits << (64 - deepest.len)).to_be_bytes();
+    assert_eq!(decompress(&[header.to_bytes(), payload.to_vec()].concat()).unwrap(), [0]);
+}
+
+// Przycięty model mieści się w limicie, a rzadkie symbole i brakujące konteksty wracają ze znaków ucieczki
+#[test]
+fn max_symbols_bounds_header() {
+    let data = Corpus::Random.generate(20_000, 5);
+    for unit in [1, 2] {
+        let coded = Options { order: 2, unit, store_fallback: false, ..Options::default() };
+        let full = compress_with_stats(&data, &coded).unwrap().1;
+        let options = Options { max_symbols: Some(50), ..coded };
+        let (packed, stats) = compress_with_stats(&data, &options).unwrap();
+        assert!(stats.header_len < 1000 && stats.header_len * 50 < full.header_len, "{:?}", stats);
+        assert_eq!(Header::parse(&packed).unwrap().0.version(), 6);
+        assert_eq!(decompress(&packed).unwrap(), data);
+
+        let mut streamed = Vec::new();
+        HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, data);
+    }
+
+    // Model mieszczący się w limicie nie zmienia formatu
+    let packed = compress(b"abracadabra", Options { max_symbols: Some(5), ..Options::default() }).unwrap();
+    assert_eq!(packed, compress(b"abracadabra", Options::default()).unwrap());
+}
+
+// Uszkodzone i spreparowane ramki dają błąd, a nie panikę ani alokację według zmyślonych długości
+#[test]
+fn malformed_frames_never_panic() {
+    let data = Corpus::Markov.generate(300, 3);
+    let mut seeds = Vec::new();
+    for (order, unit) in [(0, 1), (1, 1), (2, 2)] {
+        seeds.push(compress(&data, Options { order, unit, ..Options::default() }).unwrap());
+        seeds.push(compress(&data, Options { order, unit, max_symbols: Some(10), ..Options::default() }).unwrap());
+    }
+
+    let limited = DecodeOptions { max_expansion_ratio: Some(64), ..DecodeOptions::default() };
+    let mut rng = Rng::new(7);
+    for _ in 0..300 {
+        let mut input = seeds[rng.below(seeds.len() as u64) as usize].clone();
+        for _ in 0..1 + rng.below(3) {
+            let i = rng.below(input.len() as u64) as usize;
+            match rng.below(3) {
+                0 => input[i] ^= 1 << rng.below(8),
+                1 => input[i] = [0x00, 0x01, 0x7f, 0xff][rng.below(4) as usize],
+                _ => input.truncate(i.max(1)),
+            }
+        }
+        let _ = decompress_with(&input, &limited);
+        let _ = decompress_parallel(&input, &limited, 2);
+        let _ = frame_index(&mut Cursor::new(&input));
+        if let Ok(mut reader) = HuffmanReader::with_options(&input[..], limited) {
+            let _ = reader.read_to_end(&mut Vec::new());
+        }
+    }
+
+    // Długość 4 EiB bez limitu ekspansji: dekoder kończy się na brakujących bitach, zanim coś zaalokuje
+    let mut packed = compress(b"ab", Options::default()).unwrap();
+    packed[5..13].copy_from_slice(&(1u64 << 62).to_be_bytes());
+    let err = decompress(&packed).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::TruncatedStream { .. }), "{}", err);
+    let two = [&packed[..], &packed[..]].concat();
+    assert!(decompress_range(&mut Cursor::new(&two), 0, u64::MAX).is_err());
+
+    // Częstotliwości v0, których suma nie mieści się w u64
+    let frequencies: FreqTable = [(b"a".to_vec(), u64::MAX), (b"b".to_vec(), 2)].into_iter().collect();
+    let header = Header {
+        original_len: 2,
+        order: 0,
+        max_code_len: 32,
+        unit: 1,
+        payload_bits: None,
+        checksum: ChecksumKind::None,
+        escape: false,
+        nibble: false,
+        filters: Vec::new(),
+        model: Model::Frequencies(MarkovFreqTable::from([(Vec::new(), frequencies)])),
+    };
+    let err = decompress(&[header.to_bytes(), vec![0x40]].concat()).unwrap_err();
+    assert!(matches!(err.root(), HuffmanError::MalformedHeader(_)) && err.to_string().contains("u64"), "{}", err);
+
+    // Nagłówek obiecujący więcej symboli niż ma alfabet jest odrzucany od razu
+    let options = Options { unit: 2, store_fallback: false, ..Options::default() };
+    let mut packed = compress(b"abracadabra", options).unwrap();
+    let (header, data_offset) = Header::parse(&packed).unwrap();
+    let Model::CodeLengths(lengths) = header.model else { unreachable!() };
+    let count_at = data_offset - lengths[&Vec::new()].len() * 3 - 4;
+    packed[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    let err = Header::parse(&packed).unwrap_err();
+    assert!(err.to_string().contains("alfabet"), "{}", err);
+}
+
+// Symbole do 8 bajtów: rekordy stałej długości z niepełnym ostatnim rekordem, także
+// z ucieczkami zapisującymi cały 64-bitowy symbol wprost
+#[test]
+fn wide_units_round_trip() {
+    let mut rng = Rng::new(11);
+    let records: Vec<Vec<u8>> = (0..40).map(|_| (0..8).map(|_| rng.below(256) as u8).collect()).collect();
+    let mut data = Vec::new();
+    for _ in 0..3000 {
+        data.extend_from_slice(&records[rng.below(records.len() as u64) as usize]);
+    }
+    data.truncate(data.len() - 3);
+    let constant_time = DecodeOptions { constant_time: true, ..DecodeOptions::default() };
+    for unit in 3..=8 {
+        for (order, max_symbols) in [(0, None), (1, None), (0, Some(10)), (1, Some(30))] {
+            let options = Options { order, unit, max_symbols, ..Options::default() };
+            let (packed, stats) = compress_with_stats(&data, &options).unwrap();
+            assert_eq!(Header::parse(&packed).unwrap().0.unit, unit);
+            // Bez przycinania symbol, którego szerokość dzieli rekord, ma najwyżej 80 wartości
+            if 8 % unit == 0 && max_symbols.is_none() {
+                assert!(stats.payload_len < data.len() / 4, "{:?} {:?}", options, stats);
+            }
+            assert_eq!(decompress(&packed).unwrap(), data, "{:?}", options);
+            // Dekoder stałoczasowy przegląda całą tablicę kodów, więc tylko z przyciętym modelem
+            if max_symbols.is_some() {
+                assert_eq!(decompress_with(&packed, &constant_time).unwrap(), data);
+            }
+            assert_eq!(decompress_parallel(&packed, &DecodeOptions::default(), 2).unwrap(), data);
+            let mut streamed = Vec::new();
+            HuffmanReader::new(&packed[..]).unwrap().read_to_end(&mut streamed).unwrap();
+            assert_eq!(streamed, data);
+        }
+    }
+
+    // Liczności symboli 8-bajtowych, z ostatnim rekordem dopełnionym zerami
+    let packed = compress(&data, Options { unit: 8, ..Options::default() }).unwrap();
+    let (_, frequencies) = decompress_with_frequencies(&packed, &DecodeOptions::default()).unwrap();
+    assert_eq!(frequencies.values().sum::<u64>(), 3000);
+    assert!(frequencies.len() <= 41 && frequencies.keys().all(|symbol| symbol.len() == 8));
+    assert!(compress(&data, Options { unit: 9, ..Options::default() }).is_err());
+}
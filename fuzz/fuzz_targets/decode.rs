@@ -0,0 +1,44 @@
+// Dowolne bajty podane wszystkim dekoderom: każdy ma zwrócić błąd albo dane, nigdy panikę.
+// Uruchomienie: cargo +nightly fuzz run decode
+#![no_main]
+
+use std::io::{Cursor, Read};
+
+use huffman_format::{
+    AdaptiveReader, Archive, DecodeOptions, Header, HuffmanReader, LineIndex, SeekTable, decompress_parallel,
+    decompress_range, decompress_with, frame_index,
+};
+use libfuzzer_sys::fuzz_target;
+
+// Bez limitów mały plik z kodami zerowej długości mógłby legalnie rozwinąć się w gigabajty
+const LIMITS: DecodeOptions = DecodeOptions {
+    max_expansion_ratio: Some(256),
+    input_chunk: None,
+    constant_time: false,
+    max_output_len: Some(1 << 28),
+    max_table_entries: Some(1 << 20),
+    max_memory: Some(1 << 30),
+    lookup_bits: None,
+    verify_blocks: false,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Header::parse(data);
+    let _ = decompress_with(data, &LIMITS);
+    let _ = decompress_with(data, &DecodeOptions { constant_time: true, ..LIMITS });
+    let _ = decompress_parallel(data, &LIMITS, 2);
+    if let Ok(reader) = HuffmanReader::with_options(data, LIMITS) {
+        let _ = reader.take(1 << 24).read_to_end(&mut Vec::new());
+    }
+    if let Ok(reader) = HuffmanReader::with_options(data, DecodeOptions { verify_blocks: true, ..LIMITS }) {
+        let _ = reader.take(1 << 24).read_to_end(&mut Vec::new());
+    }
+    let _ = frame_index(&mut Cursor::new(data));
+    let _ = decompress_range(&mut Cursor::new(data), 1, 4096);
+    let _ = SeekTable::read_from_end(&mut Cursor::new(data));
+    let _ = Archive::read_from(&mut Cursor::new(data));
+    let _ = LineIndex::read_from(&mut Cursor::new(data));
+    if let Ok(reader) = AdaptiveReader::new(data) {
+        let _ = reader.take(1 << 24).read_to_end(&mut Vec::new());
+    }
+});
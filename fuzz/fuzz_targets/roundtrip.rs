@@ -0,0 +1,146 @@
+// Losowe poprawne parametry i losowe dane: każdy enkoder ma dać plik, który każdy dekoder
+// odtwarza bajt w bajt. Łapie interakcje opcji (rząd, szerokość symbolu, ucieczki, bloki),
+// których nie pokrywają pojedyncze testy. Uruchomienie: cargo +nightly fuzz run roundtrip
+#![no_main]
+
+use std::io::{Cursor, Read, Write};
+
+use huffman_format::{
+    ChecksumKind, DecodeOptions, FilterChain, FixedTable, Header, HuffmanEncoder, HuffmanError, HuffmanReader,
+    HuffmanWriter, Options, compress_optimal_split, compress_parallel, compress_stream, compress_with_stats, decompress,
+    decompress_parallel, decompress_with,
+};
+use libfuzzer_sys::fuzz_target;
+
+// Liczba bajtów na początku wejścia, z których wybierane są parametry
+const CONFIG_LEN: usize = 6;
+
+// Sposób zapisu; każdy musi dawać ramki czytane przez wszystkie dekodery
+#[derive(Debug, Clone, Copy)]
+enum Encoder {
+    Whole,
+    Writer,
+    Pull,
+    Parallel,
+    OptimalSplit,
+    Stream,
+    FixedTable,
+}
+
+const ENCODERS: [Encoder; 7] = [
+    Encoder::Whole,
+    Encoder::Writer,
+    Encoder::Pull,
+    Encoder::Parallel,
+    Encoder::OptimalSplit,
+    Encoder::Stream,
+    Encoder::FixedTable,
+];
+
+// Pusty napis to brak filtrów
+const FILTERS: [&str; 7] = ["", "rle", "bwt", "delta:2", "delta:2,rle", "bwt,rle", "timestamps:8"];
+
+#[derive(Debug)]
+struct Config {
+    options: Options,
+    block_size: usize,
+    encoder: Encoder,
+}
+
+fn config(bytes: &[u8; CONFIG_LEN]) -> Config {
+    let flags = bytes[2];
+    let nibble = flags & 2 != 0;
+    let options = Options {
+        order: bytes[0] as usize % 4,
+        // Symbole dwubajtowe potrzebują dłuższych kodów niż 8 bitów przy dużym alfabecie
+        max_code_len: 17 + bytes[1] % 16,
+        alphabet: None,
+        unit: if flags & 1 != 0 && !nibble { 2 } else { 1 },
+        checksum: if flags & 4 != 0 { ChecksumKind::None } else { ChecksumKind::Crc32 },
+        max_symbols: (flags & 8 != 0).then_some(1 + bytes[3] as usize),
+        nibble,
+        store_fallback: flags & 16 == 0,
+        filters: FILTERS[(flags >> 5) as usize % FILTERS.len()].parse::<FilterChain>().unwrap_or_default(),
+    };
+    Config {
+        options,
+        block_size: 1 + bytes[4] as usize * 8,
+        encoder: ENCODERS[bytes[5] as usize % ENCODERS.len()],
+    }
+}
+
+fn encode(data: &[u8], config: &Config) -> huffman_format::Result<Vec<u8>> {
+    let Config { options, block_size, encoder } = config;
+    Ok(match encoder {
+        Encoder::Whole => compress_with_stats(data, options)?.0,
+        Encoder::Writer => {
+            let mut writer = HuffmanWriter::with_block_size(Vec::new(), *options, *block_size);
+            // Zapis w nierównych kawałkach, żeby granice bloków nie pokrywały się z wywołaniami
+            for chunk in data.chunks(1 + block_size / 3) {
+                writer.write_all(chunk)?;
+            }
+            writer.finish()?
+        }
+        Encoder::Pull => {
+            let mut packed = Vec::new();
+            HuffmanEncoder::with_block_size(data, *options, *block_size).read_to_end(&mut packed)?;
+            packed
+        }
+        Encoder::Parallel => compress_parallel(data, options, *block_size, 3)?.0,
+        Encoder::OptimalSplit => compress_optimal_split(data, options, *block_size, 4)?.0,
+        Encoder::Stream => {
+            let mut packed = Vec::new();
+            compress_stream(&mut Cursor::new(data), &mut packed, options)?;
+            packed
+        }
+        Encoder::FixedTable => {
+            // Tabela z własnego pliku pokrywa wszystkie symbole danych; przycięty model
+            // daje tabelę z kodami ucieczki, a samej tabeli nie ma już czego przycinać
+            let reference = compress_with_stats(data, options)?.0;
+            let table = FixedTable::from_header(&Header::parse(&reference)?.0)?;
+            table.compress_with_stats(data, &Options { max_symbols: None, ..*options })?.0
+        }
+    })
+}
+
+fuzz_target!(|input: &[u8]| {
+    let Some((bytes, data)) = input.split_first_chunk::<CONFIG_LEN>() else { return };
+    let config = config(bytes);
+
+    // Gotowa tabela nie obsługuje symboli 4-bitowych ani filtrów, a strumień z dwoma przebiegami
+    // filtrów - to jedyne dopuszczalne odmowy
+    let filter = !config.options.filters.is_empty();
+    let unsupported = match config.encoder {
+        Encoder::FixedTable => config.options.nibble || filter,
+        Encoder::Stream => filter,
+        _ => false,
+    };
+    let packed = match encode(data, &config) {
+        Ok(packed) => packed,
+        Err(HuffmanError::InvalidOptions(_)) if unsupported => return,
+        Err(e) => panic!("{:?}: kodowanie nie powiodło się: {}", config, e),
+    };
+    // HuffmanEncoder, jak każdy Codec, zamienia puste wejście w pusty wynik bez ramki
+    if packed.is_empty() {
+        assert!(data.is_empty() && matches!(config.encoder, Encoder::Pull), "{:?}: pusty wynik", config);
+        return;
+    }
+
+    let check = |decoder: &str, decoded: huffman_format::Result<Vec<u8>>| match decoded {
+        Ok(decoded) => assert!(decoded == data, "{:?}: {} zwrócił inne dane", config, decoder),
+        Err(e) => panic!("{:?}: {} nie odczytał wyniku: {}", config, decoder, e),
+    };
+    check("decompress", decompress(&packed));
+    let constant_time = DecodeOptions { constant_time: true, ..DecodeOptions::default() };
+    check("constant_time", decompress_with(&packed, &constant_time));
+    check("decompress_parallel", decompress_parallel(&packed, &DecodeOptions::default(), 2));
+    let chunked = DecodeOptions { input_chunk: Some(7), ..DecodeOptions::default() };
+    check(
+        "HuffmanReader",
+        HuffmanReader::with_options(&packed[..], chunked).and_then(|mut reader| {
+            let mut decoded = Vec::new();
+            reader.read_to_end(&mut decoded)?;
+            Ok(decoded)
+        }),
+    );
+});